@@ -0,0 +1,105 @@
+use serde_json::Value;
+
+/// A security advisory affecting a locked package, from `composer audit` or
+/// the packagist advisories API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advisory {
+    pub package: String,
+    pub title: String,
+    pub cve: Option<String>,
+    pub link: Option<String>,
+}
+
+/// Reads the advisories from the output of `composer audit --format=json`.
+pub fn parse_advisories(output: &str) -> Vec<Advisory> {
+    let contents: Value = match serde_json::from_str(output) {
+        Ok(contents) => contents,
+        Err(error) => {
+            log::warn!("Can't parse the composer audit output: {}", error);
+            return vec![];
+        }
+    };
+
+    let packages = match contents
+        .get("advisories")
+        .and_then(|advisories| advisories.as_object())
+    {
+        Some(packages) => packages,
+        None => return vec![],
+    };
+
+    let mut advisories = vec![];
+    for (package, entries) in packages {
+        // The advisories of a package are either a list or an object keyed
+        // by their index.
+        let entries: Vec<&Value> = match entries {
+            Value::Array(entries) => entries.iter().collect(),
+            Value::Object(entries) => entries.values().collect(),
+            _ => continue,
+        };
+
+        for entry in entries {
+            let field = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(|value| value.as_str())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+
+            advisories.push(Advisory {
+                package: package.to_string(),
+                title: field("title").unwrap_or_else(|| "Security advisory".to_string()),
+                cve: field("cve"),
+                link: field("link"),
+            });
+        }
+    }
+
+    advisories
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audit::{parse_advisories, Advisory};
+
+    #[test]
+    fn it_can_parse_advisories() {
+        let output = r#"{
+            "advisories": {
+                "guzzlehttp/psr7": [
+                    {
+                        "advisoryId": "PKSA-1",
+                        "packageName": "guzzlehttp/psr7",
+                        "title": "Improper header validation",
+                        "cve": "CVE-2023-29197",
+                        "link": "https://github.com/guzzle/psr7/security/advisories/GHSA-wxmh-65f7-jcvw"
+                    }
+                ],
+                "symfony/http-kernel": {
+                    "0": { "title": "Cookie injection", "cve": null, "link": "" }
+                }
+            },
+            "abandoned": {}
+        }"#;
+        let advisories = parse_advisories(output);
+
+        assert_eq!(2, advisories.len());
+        assert_eq!(Some("CVE-2023-29197".to_string()), advisories[0].cve);
+        assert_eq!(
+            Advisory {
+                package: "symfony/http-kernel".to_string(),
+                title: "Cookie injection".to_string(),
+                cve: None,
+                link: None,
+            },
+            advisories[1]
+        );
+    }
+
+    #[test]
+    fn it_ignores_an_empty_audit() {
+        assert!(parse_advisories(r#"{"advisories": [], "abandoned": []}"#).is_empty());
+        assert!(parse_advisories("not json").is_empty());
+    }
+}
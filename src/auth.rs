@@ -0,0 +1,221 @@
+use reqwest::{RequestBuilder, Url};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::global;
+
+/// Credentials for a private repository or mirror, from composer's auth.json.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer(String),
+    /// A GitHub OAuth token, from "github-oauth".
+    GitHub(String),
+    /// A GitLab private token, from "gitlab-token".
+    GitLab(String),
+}
+
+impl Credentials {
+    /// Adds the credentials to the request, the way composer sends them.
+    pub fn apply(self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credentials::Basic { username, password } => {
+                request.basic_auth(username, Some(password))
+            }
+            Credentials::Bearer(token) => request.bearer_auth(token),
+            Credentials::GitHub(token) => {
+                request.header("Authorization", format!("token {}", token))
+            }
+            Credentials::GitLab(token) => request.header("PRIVATE-TOKEN", token),
+        }
+    }
+}
+
+/// The parsed contents of the auth sources of a project, in lookup order.
+type Sources = Arc<Vec<Value>>;
+
+/// The auth sources read so far, by the directory of composer.json, so the
+/// files are only read once and not on every request.
+static SOURCES: Mutex<Vec<(Option<PathBuf>, Sources)>> = Mutex::new(vec![]);
+
+/// Forgets the auth sources read so far, so changed auth.json files are read
+/// again.
+pub fn clear_cache() {
+    SOURCES.lock().unwrap().clear();
+}
+
+/// The credentials for the host, looked up like composer does: in the
+/// COMPOSER_AUTH environment variable, the auth.json next to composer.json,
+/// the global auth.json and the "config" of the global config.json.
+pub fn credentials(host: &str, project_dir: Option<&Path>) -> Option<Credentials> {
+    sources(project_dir)
+        .iter()
+        .find_map(|contents| find_credentials(contents, host))
+}
+
+fn sources(project_dir: Option<&Path>) -> Sources {
+    let mut cached = SOURCES.lock().unwrap();
+    if let Some((_, sources)) = cached.iter().find(|(dir, _)| dir.as_deref() == project_dir) {
+        return sources.clone();
+    }
+
+    let sources = Arc::new(read_sources(project_dir));
+    cached.push((project_dir.map(|dir| dir.to_path_buf()), sources.clone()));
+
+    sources
+}
+
+fn read_sources(project_dir: Option<&Path>) -> Vec<Value> {
+    let mut sources = vec![];
+    if let Some(contents) = env::var("COMPOSER_AUTH")
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+    {
+        sources.push(contents);
+    }
+    let mut files = vec![];
+    files.extend(project_dir.map(|dir| dir.join("auth.json")));
+    files.extend(global::home_dir().map(|home| home.join("auth.json")));
+    sources.extend(
+        files
+            .iter()
+            .filter_map(|file| fs::read_to_string(file).ok())
+            .filter_map(|text| serde_json::from_str(&text).ok()),
+    );
+    if let Some(config) = global::config().and_then(|config| config.get("config").cloned()) {
+        sources.push(config);
+    }
+
+    sources
+}
+
+/// Adds the credentials of the URL's host to the request, if there are any.
+pub fn authorize(request: RequestBuilder, url: &str, project_dir: Option<&Path>) -> RequestBuilder {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()));
+
+    match host.and_then(|host| credentials(&host, project_dir)) {
+        Some(credentials) => credentials.apply(request),
+        None => request,
+    }
+}
+
+/// Reads the "http-basic", "bearer", "github-oauth", "gitlab-token" or
+/// "gitlab-oauth" credentials of the host. GitHub tokens are stored for
+/// github.com, but also used for its API and raw content hosts.
+fn find_credentials(contents: &Value, host: &str) -> Option<Credentials> {
+    let token = |key: &str, host: &str| {
+        contents
+            .get(key)
+            .and_then(|tokens| tokens.get(host))
+            .and_then(|token| token.as_str())
+            .map(|token| token.to_string())
+    };
+
+    if let Some(basic) = contents.get("http-basic").and_then(|basic| basic.get(host)) {
+        let field = |name: &str| {
+            basic
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+        };
+
+        return Some(Credentials::Basic {
+            username: field("username")?,
+            password: field("password").unwrap_or_default(),
+        });
+    }
+
+    let github_host = match host {
+        "api.github.com" | "raw.githubusercontent.com" | "codeload.github.com" => "github.com",
+        host => host,
+    };
+    // GitLab tokens can be plain strings or objects with the token and its
+    // type.
+    let gitlab_token = contents
+        .get("gitlab-token")
+        .and_then(|tokens| tokens.get(host))
+        .and_then(|token| {
+            token
+                .as_str()
+                .or_else(|| token.get("token").and_then(|token| token.as_str()))
+        });
+
+    token("bearer", host)
+        .map(Credentials::Bearer)
+        .or_else(|| token("github-oauth", github_host).map(Credentials::GitHub))
+        .or_else(|| gitlab_token.map(|token| Credentials::GitLab(token.to_string())))
+        .or_else(|| token("gitlab-oauth", host).map(Credentials::Bearer))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::auth::{find_credentials, Credentials};
+
+    #[test]
+    fn it_can_find_the_credentials_of_a_host() {
+        let contents = json!({
+            "http-basic": {
+                "repo.acme.com": { "username": "acme", "password": "secret" }
+            },
+            "bearer": {
+                "satis.acme.com": "token"
+            }
+        });
+
+        assert_eq!(
+            Some(Credentials::Basic {
+                username: "acme".to_string(),
+                password: "secret".to_string(),
+            }),
+            find_credentials(&contents, "repo.acme.com")
+        );
+        assert_eq!(
+            Some(Credentials::Bearer("token".to_string())),
+            find_credentials(&contents, "satis.acme.com")
+        );
+        assert_eq!(None, find_credentials(&contents, "repo.packagist.org"));
+        assert_eq!(
+            None,
+            find_credentials(&json!("not an object"), "repo.acme.com")
+        );
+    }
+
+    #[test]
+    fn it_can_find_github_and_gitlab_tokens() {
+        let contents = json!({
+            "github-oauth": { "github.com": "gh-token" },
+            "gitlab-token": {
+                "gitlab.com": "gl-token",
+                "gitlab.acme.com": { "username": "acme", "token": "acme-token" }
+            },
+            "gitlab-oauth": { "gitlab.example.com": "oauth-token" }
+        });
+
+        assert_eq!(
+            Some(Credentials::GitHub("gh-token".to_string())),
+            find_credentials(&contents, "api.github.com")
+        );
+        assert_eq!(
+            Some(Credentials::GitLab("gl-token".to_string())),
+            find_credentials(&contents, "gitlab.com")
+        );
+        assert_eq!(
+            Some(Credentials::GitLab("acme-token".to_string())),
+            find_credentials(&contents, "gitlab.acme.com")
+        );
+        assert_eq!(
+            Some(Credentials::Bearer("oauth-token".to_string())),
+            find_credentials(&contents, "gitlab.example.com")
+        );
+    }
+}
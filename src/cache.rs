@@ -0,0 +1,387 @@
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+/// How long cached metadata is used without asking packagist whether it
+/// changed.
+const FRESH_FOR: Duration = Duration::from_secs(10 * 60);
+
+/// How long the cached list of all package names is used.
+const PACKAGE_LIST_FRESH_FOR: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The directory composer caches packagist.org metadata in.
+const PACKAGIST_REPO_DIR: &str = "https---repo.packagist.org";
+
+/// The cache-dir of composer's effective configuration, which can also be
+/// set in the global config, by the directory of composer.json, as every
+/// project can configure its own.
+static CONFIGURED_DIRS: RwLock<Vec<(PathBuf, PathBuf)>> = RwLock::new(vec![]);
+
+/// How long cached responses are used, from the settings.
+static POLICY: RwLock<CachePolicy> = RwLock::new(CachePolicy {
+    metadata_ttl: FRESH_FOR,
+    package_list_ttl: PACKAGE_LIST_FRESH_FOR,
+    offline: false,
+});
+
+/// How long cached responses are used before revalidating them, and whether
+/// packagist may be asked at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachePolicy {
+    pub metadata_ttl: Duration,
+    pub package_list_ttl: Duration,
+    /// Only use cached responses, however old they are.
+    pub offline: bool,
+}
+
+/// A cached response, from composer's cache or the server's own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedMetadata {
+    pub text: String,
+    /// The ETag header of the response, to revalidate the metadata.
+    pub etag: Option<String>,
+    /// The Last-Modified header of the response, to revalidate the metadata.
+    pub last_modified: Option<String>,
+    /// Whether the metadata is recent enough to skip revalidating it.
+    pub is_fresh: bool,
+}
+
+pub fn set_policy(policy: CachePolicy) {
+    *POLICY.write().unwrap() = policy;
+}
+
+pub fn policy() -> CachePolicy {
+    *POLICY.read().unwrap()
+}
+
+/// Uses the cache-dir composer reported for the project instead of
+/// resolving it.
+pub fn set_cache_dir(project_dir: &Path, dir: PathBuf) {
+    let mut configured = CONFIGURED_DIRS.write().unwrap();
+    configured.retain(|(configured_project, _)| configured_project != project_dir);
+    configured.push((project_dir.to_path_buf(), dir));
+}
+
+/// The composer cache directory of the project, like composer resolves it:
+/// the configured cache-dir, else COMPOSER_CACHE_DIR, else the cache in
+/// COMPOSER_HOME, else the platform's cache directory.
+pub fn cache_dir(project_dir: Option<&Path>) -> Option<PathBuf> {
+    let configured = CONFIGURED_DIRS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(configured_project, _)| Some(configured_project.as_path()) == project_dir)
+        .map(|(_, dir)| dir.clone());
+    if configured.is_some() {
+        return configured;
+    }
+
+    resolve_cache_dir(
+        env::var_os("COMPOSER_CACHE_DIR").map(PathBuf::from),
+        env::var_os("COMPOSER_HOME").map(PathBuf::from),
+        env::var_os("XDG_CACHE_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+fn resolve_cache_dir(
+    cache_dir: Option<PathBuf>,
+    composer_home: Option<PathBuf>,
+    xdg_cache_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    if let Some(cache_dir) = cache_dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        return Some(cache_dir);
+    }
+    if let Some(composer_home) = composer_home.filter(|dir| !dir.as_os_str().is_empty()) {
+        return Some(composer_home.join("cache"));
+    }
+
+    if cfg!(windows) {
+        return env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("Composer"));
+    }
+
+    let home = home?;
+    if cfg!(target_os = "macos") {
+        return Some(home.join("Library/Caches/composer"));
+    }
+
+    let legacy = home.join(".composer/cache");
+    if legacy.is_dir() {
+        return Some(legacy);
+    }
+
+    let cache = xdg_cache_home.unwrap_or_else(|| home.join(".cache"));
+    Some(cache.join("composer"))
+}
+
+/// The file composer caches the metadata of a package in, e.g.
+/// "provider-psr~log.json", or "provider-psr~log~dev.json" for the branches.
+pub fn metadata_path(cache_dir: &Path, name: &str, dev: bool) -> PathBuf {
+    let mut file = format!("provider-{}", name.replace('/', "~"));
+    if dev {
+        file.push_str("~dev");
+    }
+    file.push_str(".json");
+
+    cache_dir.join("repo").join(PACKAGIST_REPO_DIR).join(file)
+}
+
+/// Reads the metadata of a package the composer CLI cached for the project,
+/// if any.
+pub fn read_metadata(name: &str, dev: bool, project_dir: Option<&Path>) -> Option<CachedMetadata> {
+    let path = metadata_path(&cache_dir(project_dir)?, name, dev);
+    let text = fs::read_to_string(&path).ok()?;
+
+    Some(CachedMetadata {
+        last_modified: last_modified(&text),
+        etag: None,
+        is_fresh: is_fresh(&path, policy().metadata_ttl),
+        text,
+    })
+}
+
+/// The directory the server caches its own responses in, like the list of
+/// all packages.
+pub fn server_cache_dir() -> Option<PathBuf> {
+    resolve_server_cache_dir(
+        env::var_os("XDG_CACHE_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+fn resolve_server_cache_dir(
+    xdg_cache_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("composer_lsp"));
+    }
+
+    if cfg!(target_os = "macos") {
+        return home.map(|home| home.join("Library/Caches/composer_lsp"));
+    }
+
+    let cache = match xdg_cache_home.filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(cache) => cache,
+        None => home?.join(".cache"),
+    };
+    Some(cache.join("composer_lsp"))
+}
+
+/// The key the server caches the metadata of a package under.
+pub fn metadata_key(name: &str, dev: bool) -> String {
+    let mut key = format!("p2/{}", name.replace('/', "~"));
+    if dev {
+        key.push_str("~dev");
+    }
+
+    key
+}
+
+/// The cache key of the package API response, with its download statistics.
+pub fn stats_key(name: &str) -> String {
+    format!("packages/{}", name.replace('/', "~"))
+}
+
+/// The cache key of the providers of a virtual package.
+pub fn providers_key(name: &str) -> String {
+    format!("providers/{}", name.replace('/', "~"))
+}
+
+/// Reads a response the server cached, if any.
+pub fn read_entry(key: &str, ttl: Duration) -> Option<CachedMetadata> {
+    read_entry_from(&server_cache_dir()?.join(format!("{}.json", key)), ttl)
+}
+
+/// Caches a response with its validators. Failing to write only means it's
+/// downloaded again.
+pub fn write_entry(key: &str, text: &str, etag: Option<&str>, last_modified: Option<&str>) {
+    let path = match server_cache_dir() {
+        Some(dir) => dir.join(format!("{}.json", key)),
+        None => return,
+    };
+
+    if let Err(error) = write_entry_to(&path, text, etag, last_modified) {
+        log::info!("Can't cache {}: {}", path.display(), error);
+    }
+}
+
+fn read_entry_from(path: &Path, ttl: Duration) -> Option<CachedMetadata> {
+    let contents: Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let field = |name: &str| {
+        contents
+            .get(name)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+
+    Some(CachedMetadata {
+        text: field("body")?,
+        etag: field("etag"),
+        last_modified: field("last-modified"),
+        is_fresh: is_fresh(path, ttl),
+    })
+}
+
+fn write_entry_to(
+    path: &Path,
+    text: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let contents = json!({
+        "etag": etag,
+        "last-modified": last_modified,
+        "body": text,
+    });
+
+    // Write to a temporary file first, so a concurrent read never sees half
+    // of the entry.
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents.to_string())?;
+    fs::rename(&temporary, path)
+}
+
+/// Whether the file was written less than the ttl ago.
+fn is_fresh(path: &Path, ttl: Duration) -> bool {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+    match modified.map(|modified| SystemTime::now().duration_since(modified)) {
+        Ok(Ok(age)) => age < ttl,
+        _ => false,
+    }
+}
+
+/// Composer stores the Last-Modified header in the cached metadata itself.
+fn last_modified(text: &str) -> Option<String> {
+    let contents: Value = serde_json::from_str(text).ok()?;
+
+    contents
+        .get("last-modified")
+        .and_then(|last_modified| last_modified.as_str())
+        .map(|last_modified| last_modified.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use std::time::Duration;
+
+    use crate::cache::{
+        cache_dir, last_modified, metadata_key, metadata_path, providers_key, read_entry_from,
+        resolve_cache_dir, resolve_server_cache_dir, set_cache_dir, write_entry_to,
+    };
+
+    #[test]
+    fn it_can_resolve_the_cache_dir() {
+        assert_eq!(
+            Some(PathBuf::from("/tmp/cache")),
+            resolve_cache_dir(
+                Some(PathBuf::from("/tmp/cache")),
+                Some(PathBuf::from("/opt/composer")),
+                None,
+                Some(PathBuf::from("/home/acme"))
+            )
+        );
+        assert_eq!(
+            Some(PathBuf::from("/opt/composer/cache")),
+            resolve_cache_dir(
+                None,
+                Some(PathBuf::from("/opt/composer")),
+                None,
+                Some(PathBuf::from("/home/acme"))
+            )
+        );
+        if cfg!(target_os = "linux") {
+            assert_eq!(
+                Some(PathBuf::from("/xdg/composer")),
+                resolve_cache_dir(
+                    None,
+                    None,
+                    Some(PathBuf::from("/xdg")),
+                    Some(PathBuf::from("/nonexistent/acme"))
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn it_keeps_the_configured_cache_dir_per_project() {
+        set_cache_dir(Path::new("/projects/a"), PathBuf::from("/cache/a"));
+        set_cache_dir(Path::new("/projects/b"), PathBuf::from("/cache/b"));
+        set_cache_dir(Path::new("/projects/a"), PathBuf::from("/cache/a2"));
+
+        assert_eq!(
+            Some(PathBuf::from("/cache/a2")),
+            cache_dir(Some(Path::new("/projects/a")))
+        );
+        assert_eq!(
+            Some(PathBuf::from("/cache/b")),
+            cache_dir(Some(Path::new("/projects/b")))
+        );
+        assert_ne!(
+            Some(PathBuf::from("/cache/b")),
+            cache_dir(Some(Path::new("/projects/c")))
+        );
+    }
+
+    #[test]
+    fn it_can_build_the_metadata_path() {
+        assert_eq!(
+            PathBuf::from("/cache/repo/https---repo.packagist.org/provider-psr~log~dev.json"),
+            metadata_path(Path::new("/cache"), "psr/log", true)
+        );
+    }
+
+    #[test]
+    fn it_can_resolve_the_server_cache_dir() {
+        if cfg!(target_os = "linux") {
+            assert_eq!(
+                Some(PathBuf::from("/xdg/composer_lsp")),
+                resolve_server_cache_dir(Some(PathBuf::from("/xdg")), None)
+            );
+            assert_eq!(
+                Some(PathBuf::from("/home/acme/.cache/composer_lsp")),
+                resolve_server_cache_dir(None, Some(PathBuf::from("/home/acme")))
+            );
+        }
+        assert_eq!("p2/psr~log~dev", metadata_key("psr/log", true));
+        assert_eq!(
+            "providers/psr~log-implementation",
+            providers_key("psr/log-implementation")
+        );
+    }
+
+    #[test]
+    fn it_can_cache_a_response() {
+        let path = std::env::temp_dir()
+            .join("composer_lsp_cache")
+            .join("p2/psr~log.json");
+        write_entry_to(&path, "{\"packages\": {}}", Some("\"abc\""), None).unwrap();
+
+        let cached = read_entry_from(&path, Duration::from_secs(60)).unwrap();
+        assert_eq!("{\"packages\": {}}", cached.text);
+        assert_eq!(Some("\"abc\"".to_string()), cached.etag);
+        assert_eq!(None, cached.last_modified);
+        assert!(cached.is_fresh);
+
+        assert!(!read_entry_from(&path, Duration::ZERO).unwrap().is_fresh);
+    }
+
+    #[test]
+    fn it_can_read_the_last_modified_header() {
+        assert_eq!(
+            Some("Tue, 01 Oct 2024 10:00:00 GMT".to_string()),
+            last_modified(r#"{"packages": {}, "last-modified": "Tue, 01 Oct 2024 10:00:00 GMT"}"#)
+        );
+        assert_eq!(None, last_modified("{}"));
+    }
+}
@@ -0,0 +1,214 @@
+use serde_json::{json, Value};
+
+/// The workspace commands the server can execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComposerCommand {
+    /// Runs `composer update` for a single package.
+    Update,
+    /// Runs `composer install`.
+    Install,
+    /// Replaces the constraint of a package in composer.json.
+    SetConstraint,
+    /// Runs `composer require` for a single package, with an optional
+    /// constraint.
+    Require,
+    /// Runs `composer remove` for a single package.
+    Remove,
+    /// Checks every composer.json in the workspace.
+    CheckWorkspace,
+    /// Opens the global composer.json.
+    OpenGlobal,
+    /// Opens the installed source of a package in the vendor directory.
+    OpenPackageSource,
+    /// Shows why a package is installed, from composer.lock.
+    Why,
+    /// Runs `composer run-script` for a script.
+    RunScript,
+    /// Runs `composer validate` and shows its problems as diagnostics.
+    Validate,
+}
+
+impl ComposerCommand {
+    pub const ALL: [ComposerCommand; 11] = [
+        ComposerCommand::Update,
+        ComposerCommand::Install,
+        ComposerCommand::SetConstraint,
+        ComposerCommand::Require,
+        ComposerCommand::Remove,
+        ComposerCommand::CheckWorkspace,
+        ComposerCommand::OpenGlobal,
+        ComposerCommand::OpenPackageSource,
+        ComposerCommand::Why,
+        ComposerCommand::RunScript,
+        ComposerCommand::Validate,
+    ];
+
+    /// The identifier the command is advertised and executed with.
+    pub fn id(self) -> &'static str {
+        match self {
+            ComposerCommand::Update => "composer.update",
+            ComposerCommand::Install => "composer.install",
+            ComposerCommand::SetConstraint => "composer.setConstraint",
+            ComposerCommand::Require => "composer.require",
+            ComposerCommand::Remove => "composer.remove",
+            ComposerCommand::CheckWorkspace => "composer.checkWorkspace",
+            ComposerCommand::OpenGlobal => "composer.openGlobal",
+            ComposerCommand::OpenPackageSource => "composer.openPackageSource",
+            ComposerCommand::Why => "composer.why",
+            ComposerCommand::RunScript => "composer.runScript",
+            ComposerCommand::Validate => "composer.validate",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<ComposerCommand> {
+        Self::ALL.into_iter().find(|command| command.id() == id)
+    }
+
+    /// All command identifiers, for the execute command capability.
+    pub fn ids() -> Vec<String> {
+        Self::ALL
+            .iter()
+            .map(|command| command.id().to_string())
+            .collect()
+    }
+}
+
+/// The last argument of the commands the server offers, naming the document
+/// they run for, e.g. `{ "uri": "file:///app/composer.json" }`.
+pub fn document_argument(uri: &str) -> Value {
+    json!({ "uri": uri })
+}
+
+/// Splits the document argument off the arguments of a command. Commands
+/// run by clients directly may not have one.
+pub fn split_document(mut arguments: Vec<Value>) -> (Option<String>, Vec<Value>) {
+    let uri = arguments
+        .last()
+        .and_then(|argument| argument.get("uri"))
+        .and_then(|uri| uri.as_str())
+        .map(|uri| uri.to_string());
+    if uri.is_some() {
+        arguments.pop();
+    }
+
+    (uri, arguments)
+}
+
+/// The package argument of `composer require`, e.g. "psr/log:^3.0", or just
+/// the name when there is no constraint.
+pub fn require_argument(package: &str, constraint: Option<&str>) -> String {
+    match constraint
+        .map(str::trim)
+        .filter(|constraint| !constraint.is_empty())
+    {
+        Some(constraint) => format!("{}:{}", package, constraint),
+        None => package.to_string(),
+    }
+}
+
+/// The arguments of `composer remove` for a package, with --dev for
+/// packages in require-dev, which composer won't remove otherwise.
+pub fn remove_args(package: &str, dev: bool) -> Vec<&str> {
+    match dev {
+        true => vec!["remove", "--dev", package],
+        false => vec!["remove", package],
+    }
+}
+
+/// Ready-to-run terminal commands for a package, one per line.
+pub fn shell_commands(package: &str, dev: bool) -> String {
+    let require = match dev {
+        true => format!("composer require --dev {}", package),
+        false => format!("composer require {}", package),
+    };
+
+    [
+        require,
+        format!("composer update {}", package),
+        format!("composer why {}", package),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::command::{
+        document_argument, remove_args, require_argument, shell_commands, split_document,
+        ComposerCommand,
+    };
+
+    #[test]
+    fn it_can_find_a_command_by_its_id() {
+        assert_eq!(
+            Some(ComposerCommand::SetConstraint),
+            ComposerCommand::from_id("composer.setConstraint")
+        );
+        assert_eq!(None, ComposerCommand::from_id("update"));
+    }
+
+    #[test]
+    fn it_can_list_all_command_ids() {
+        assert_eq!(
+            vec![
+                "composer.update",
+                "composer.install",
+                "composer.setConstraint",
+                "composer.require",
+                "composer.remove",
+                "composer.checkWorkspace",
+                "composer.openGlobal",
+                "composer.openPackageSource",
+                "composer.why",
+                "composer.runScript",
+                "composer.validate"
+            ],
+            ComposerCommand::ids()
+        );
+    }
+
+    #[test]
+    fn it_can_split_the_document_off_the_arguments() {
+        let arguments = vec![
+            Value::from("psr/log"),
+            document_argument("file:///app/composer.json"),
+        ];
+
+        assert_eq!(
+            (
+                Some("file:///app/composer.json".to_string()),
+                vec![Value::from("psr/log")]
+            ),
+            split_document(arguments)
+        );
+        assert_eq!(
+            (None, vec![Value::from("psr/log")]),
+            split_document(vec![Value::from("psr/log")])
+        );
+    }
+
+    #[test]
+    fn it_can_build_the_require_argument() {
+        assert_eq!("psr/log:^3.0", require_argument("psr/log", Some("^3.0")));
+        assert_eq!("psr/log", require_argument("psr/log", Some(" ")));
+        assert_eq!("psr/log", require_argument("psr/log", None));
+    }
+
+    #[test]
+    fn it_can_build_the_remove_arguments() {
+        assert_eq!(
+            vec!["remove", "--dev", "phpunit/phpunit"],
+            remove_args("phpunit/phpunit", true)
+        );
+        assert_eq!(vec!["remove", "psr/log"], remove_args("psr/log", false));
+    }
+
+    #[test]
+    fn it_can_build_shell_commands_for_a_package() {
+        assert_eq!(
+            "composer require --dev phpunit/phpunit\ncomposer update phpunit/phpunit\ncomposer why phpunit/phpunit",
+            shell_commands("phpunit/phpunit", true)
+        );
+    }
+}
@@ -0,0 +1,689 @@
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::constraint::{Stability, Version};
+use crate::index;
+use crate::packagist::{self, Package};
+
+/// How many package names are completed at most. The list is marked as
+/// incomplete when there are more, so it's asked for again while typing.
+pub const MAX_PACKAGE_COMPLETIONS: usize = 100;
+
+/// Popular packagist tags, offered inside the "keywords" array. Packagist
+/// has no API listing its tags, so they're picked by hand.
+pub const POPULAR_KEYWORDS: &[&str] = &[
+    "api",
+    "authentication",
+    "cache",
+    "cli",
+    "client",
+    "cms",
+    "command",
+    "console",
+    "database",
+    "debug",
+    "dev",
+    "doctrine",
+    "drupal",
+    "email",
+    "event",
+    "extension",
+    "filesystem",
+    "form",
+    "framework",
+    "guzzle",
+    "html",
+    "http",
+    "http-client",
+    "i18n",
+    "image",
+    "json",
+    "laravel",
+    "library",
+    "log",
+    "logging",
+    "markdown",
+    "middleware",
+    "orm",
+    "parser",
+    "pdf",
+    "php",
+    "phpunit",
+    "plugin",
+    "psr-3",
+    "psr-4",
+    "psr-7",
+    "psr-11",
+    "psr-15",
+    "psr-17",
+    "psr-18",
+    "queue",
+    "rest",
+    "routing",
+    "sdk",
+    "search",
+    "security",
+    "serializer",
+    "session",
+    "symfony",
+    "symfony-bundle",
+    "template",
+    "testing",
+    "translation",
+    "utility",
+    "validation",
+    "validator",
+    "wordpress",
+    "xml",
+    "yaml",
+];
+
+/// Whether the character can be part of a package name, e.g. "symfony/http-kernel".
+fn is_package_name_char(character: char) -> bool {
+    character.is_ascii_alphanumeric() || matches!(character, '/' | '-' | '_' | '.')
+}
+
+/// Converts a UTF-16 character offset on a line into a byte offset.
+pub fn character_to_byte(line_text: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (index, c) in line_text.char_indices() {
+        if units >= character {
+            return index;
+        }
+
+        units += c.len_utf16() as u32;
+    }
+
+    line_text.trim_end_matches(['\n', '\r']).len()
+}
+
+/// Returns the partially typed package name in front of the cursor, when the
+/// cursor is inside a quoted string.
+pub fn get_completion_prefix(line_text: &str, character: u32) -> Option<String> {
+    let cursor = character_to_byte(line_text, character);
+    let before_cursor = &line_text[..cursor];
+
+    let start = before_cursor
+        .rfind(|c: char| !is_package_name_char(c))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+
+    // Only complete inside a string, right after its opening quote.
+    if !before_cursor[..start].ends_with('"') {
+        return None;
+    }
+
+    Some(before_cursor[start..].to_string())
+}
+
+/// Converts a byte offset on a line into a UTF-16 character offset.
+pub fn byte_to_character(line_text: &str, byte: usize) -> u32 {
+    line_text[..byte.min(line_text.len())]
+        .chars()
+        .map(|c| c.len_utf16() as u32)
+        .sum()
+}
+
+/// Replaces the string the cursor is in with a completion, on the characters
+/// from `start` to `end` of the line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotedInsertion {
+    pub start: u32,
+    pub end: u32,
+    pub text: String,
+    /// Whether the text is a snippet, with a tab stop in the constraint.
+    pub snippet: bool,
+}
+
+/// Builds the insertion of a completion into the string at the cursor. The
+/// whole name is replaced, a missing closing quote is added, and keys get an
+/// empty constraint with the cursor in it, unless they already have a value.
+pub fn quoted_insertion(
+    line_text: &str,
+    character: u32,
+    value: &str,
+    is_key: bool,
+) -> Option<QuotedInsertion> {
+    let value_snippet = if is_key { Some("\"$1\"") } else { None };
+    insertion_at(line_text, character, value, value_snippet)
+}
+
+/// Builds the insertion of a key of composer.json, like quoted_insertion,
+/// with the snippet of its value, e.g. "{$1}" for an object.
+pub fn key_insertion(
+    line_text: &str,
+    character: u32,
+    key: &str,
+    value_snippet: &str,
+) -> Option<QuotedInsertion> {
+    insertion_at(line_text, character, key, Some(value_snippet))
+}
+
+fn insertion_at(
+    line_text: &str,
+    character: u32,
+    value: &str,
+    value_snippet: Option<&str>,
+) -> Option<QuotedInsertion> {
+    let cursor = character_to_byte(line_text, character);
+    let before_cursor = &line_text[..cursor];
+    let start = before_cursor
+        .rfind(|c: char| !is_package_name_char(c))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    if !before_cursor[..start].ends_with('"') {
+        return None;
+    }
+
+    let after_cursor = &line_text[cursor..];
+    let end = cursor
+        + after_cursor
+            .find(|c: char| !is_package_name_char(c))
+            .unwrap_or(after_cursor.len());
+    let after_name = &line_text[end..];
+    let has_closing_quote = after_name.starts_with('"');
+
+    let insertion = |end: usize, text: String, snippet: bool| QuotedInsertion {
+        start: byte_to_character(line_text, start),
+        end: byte_to_character(line_text, end),
+        text,
+        snippet,
+    };
+
+    if let Some(value_snippet) = value_snippet {
+        let has_value = has_closing_quote && after_name[1..].trim_start().starts_with(':');
+        if !has_value {
+            let end = end + usize::from(has_closing_quote);
+            let key = value.replace('$', "\\$");
+            return Some(insertion(
+                end,
+                format!("{}\": {}", key, value_snippet),
+                true,
+            ));
+        }
+
+        return Some(insertion(end, value.to_string(), false));
+    }
+
+    match has_closing_quote {
+        true => Some(insertion(end, value.to_string(), false)),
+        false => Some(insertion(end, format!("{}\"", value), false)),
+    }
+}
+
+/// Whether the string at the cursor is a key of an object, going by what
+/// comes before its opening quote on the line.
+pub fn is_key_at(line_text: &str, character: u32) -> bool {
+    let cursor = character_to_byte(line_text, character);
+    let before_cursor = &line_text[..cursor];
+    let quote = match before_cursor.rfind('"') {
+        Some(quote) => quote,
+        None => return false,
+    };
+
+    let before_quote = before_cursor[..quote].trim_end();
+    before_quote.is_empty() || before_quote.ends_with(['{', ','])
+}
+
+/// The text clients filter a package name on: the full name and the package
+/// part, so typing either one matches.
+pub fn package_filter_text(name: &str) -> String {
+    match name.split_once('/') {
+        Some((_, package)) => format!("{} {}", name, package),
+        None => name.to_string(),
+    }
+}
+
+/// Ranks the names of the typed vendor first, then the other names starting
+/// with the typed text, then the names whose package part matches, and the
+/// fuzzy matches last, the closest first.
+pub fn package_sort_text(name: &str, typed: &str) -> String {
+    let typed_vendor = typed.split('/').next().unwrap_or_default();
+    let (vendor, package) = name.split_once('/').unwrap_or((name, ""));
+
+    let (rank, score) = match name {
+        _ if !typed_vendor.is_empty() && vendor == typed_vendor => (0, 0),
+        name if name.starts_with(typed) => (1, 0),
+        _ if package.starts_with(typed) => (2, 0),
+        name => (3, index::fuzzy_score(name, typed).unwrap_or(usize::MAX)),
+    };
+    format!("{}-{:020}-{}", rank, score, name)
+}
+
+/// The documentation of a completed package name: the description of its
+/// latest version, the latest stable release and the packagist link.
+pub fn package_documentation(package: &Package) -> String {
+    let mut lines = vec![];
+    if let Some(description) = package
+        .versions
+        .first()
+        .and_then(|latest| latest.description.as_ref())
+        .filter(|description| !description.is_empty())
+    {
+        lines.push(description.to_string());
+    }
+    if let Some(latest) = package.latest_release() {
+        lines.push(latest);
+    }
+    if let Some(notice) = package.abandoned_notice() {
+        lines.push(notice);
+    }
+    lines.push(format!(
+        "[Packagist]({})",
+        packagist::package_url(&package.name)
+    ));
+
+    lines.join("\n\n")
+}
+
+/// Whether the byte offset is directly inside the array of the given key, e.g.
+/// "keywords". The text is scanned instead of parsed, because it's usually
+/// incomplete while typing.
+pub fn is_in_array(text: &str, offset: usize, key: &str) -> bool {
+    let before = &text[..offset.min(text.len())];
+
+    let mut depth = 0;
+    for (index, c) in before.char_indices().rev() {
+        match c {
+            ']' | '}' => depth += 1,
+            '{' if depth == 0 => return false,
+            '[' | '{' => depth -= 1,
+            _ => continue,
+        }
+
+        if c == '[' && depth < 0 {
+            let before_bracket = before[..index].trim_end();
+            return match before_bracket.strip_suffix(':') {
+                Some(before_colon) => before_colon.trim_end().ends_with(&format!("\"{}\"", key)),
+                None => false,
+            };
+        }
+    }
+
+    false
+}
+
+/// Reads the user name and email from the git configuration of the directory.
+pub async fn git_author(dir: &Path) -> (Option<String>, Option<String>) {
+    let read = |key: &'static str| async move {
+        let output = Command::new("git")
+            .arg("config")
+            .arg(key)
+            .current_dir(dir)
+            .output()
+            .await
+            .ok()?;
+
+        let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        match output.status.success() && !value.is_empty() {
+            true => Some(value),
+            false => None,
+        }
+    };
+
+    (read("user.name").await, read("user.email").await)
+}
+
+/// A snippet for an author object, pre-filled with the known name and email.
+pub fn author_snippet(name: Option<&str>, email: Option<&str>) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "\\$")
+            .replace('}', "\\}")
+    };
+
+    format!(
+        "{{\n\t\"name\": \"${{1:{}}}\",\n\t\"email\": \"${{2:{}}}\"\n}}$0",
+        escape(name.unwrap_or("Name")),
+        escape(email.unwrap_or("email@example.com"))
+    )
+}
+
+/// The constraint string the cursor is in, on a `"vendor/package": "^1.0"`
+/// line, with the characters of its value between the quotes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintAt {
+    pub package: String,
+    pub start: u32,
+    pub end: u32,
+    /// Whether the value still misses its closing quote.
+    pub unterminated: bool,
+}
+
+/// Finds the constraint of a requirement line the cursor is in.
+pub fn constraint_at(line_text: &str, character: u32) -> Option<ConstraintAt> {
+    let cursor = character_to_byte(line_text, character);
+
+    let key_start = line_text.find('"')? + 1;
+    let key_end = key_start + line_text[key_start..].find('"')?;
+    let package = &line_text[key_start..key_end];
+    if !package.contains('/') {
+        return None;
+    }
+
+    let after_key = line_text[key_end + 1..].trim_start().strip_prefix(':')?;
+    let after_colon = after_key.trim_start().strip_prefix('"')?;
+    let start = line_text.len() - after_colon.len();
+    let (end, unterminated) = match after_colon.find('"') {
+        Some(end) => (start + end, false),
+        None => (line_text.trim_end().len(), true),
+    };
+    if cursor < start || cursor > end {
+        return None;
+    }
+
+    Some(ConstraintAt {
+        package: package.to_string(),
+        start: byte_to_character(line_text, start),
+        end: byte_to_character(line_text, end),
+        unterminated,
+    })
+}
+
+/// Whether the byte offset is inside the object of one of the keys, e.g.
+/// "require". Like is_in_array, the text is scanned instead of parsed.
+pub fn is_in_object(text: &str, offset: usize, keys: &[&str]) -> bool {
+    let before = &text[..offset.min(text.len())];
+
+    let mut depth = 0;
+    for (index, c) in before.char_indices().rev() {
+        match c {
+            ']' | '}' => depth += 1,
+            '[' if depth == 0 => return false,
+            '[' | '{' => depth -= 1,
+            _ => continue,
+        }
+
+        if c == '{' && depth < 0 {
+            let before_brace = before[..index].trim_end();
+            return match before_brace.strip_suffix(':') {
+                Some(before_colon) => keys
+                    .iter()
+                    .any(|key| before_colon.trim_end().ends_with(&format!("\"{}\"", key))),
+                None => false,
+            };
+        }
+    }
+
+    false
+}
+
+/// Constraints for the versions, newest first: a caret and a tilde constraint
+/// for every minor release and every exact version. Unstable versions are
+/// left out, unless there are no stable ones.
+pub fn version_completions(versions: &[String]) -> Vec<String> {
+    let mut parsed: Vec<(Version, &String)> = versions
+        .iter()
+        .filter_map(|version| Some((Version::parse(version)?, version)))
+        .collect();
+    if parsed
+        .iter()
+        .any(|(version, _)| version.stability() >= Stability::Stable)
+    {
+        parsed.retain(|(version, _)| version.stability() >= Stability::Stable);
+    }
+    parsed.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    let mut completions: Vec<String> = vec![];
+    for (_, version) in parsed {
+        let version = version.trim_start_matches('v');
+        let numbers: Vec<&str> = version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .collect();
+
+        if let [major, minor, patch, ..] = numbers.as_slice() {
+            for constraint in [
+                format!("^{}.{}", major, minor),
+                format!("~{}.{}.{}", major, minor, patch),
+            ] {
+                if !completions.contains(&constraint) {
+                    completions.push(constraint);
+                }
+            }
+        }
+        if !completions.iter().any(|completion| completion == version) {
+            completions.push(version.to_string());
+        }
+    }
+
+    completions
+}
+
+/// Popular keywords starting with the prefix.
+pub fn keyword_completions(prefix: &str) -> Vec<&'static str> {
+    POPULAR_KEYWORDS
+        .iter()
+        .filter(|keyword| keyword.starts_with(prefix))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::{
+        author_snippet, constraint_at, get_completion_prefix, is_in_array, is_in_object, is_key_at,
+        key_insertion, keyword_completions, package_documentation, package_filter_text,
+        package_sort_text, quoted_insertion, version_completions, ConstraintAt, QuotedInsertion,
+    };
+    use crate::packagist::{Package, PackageVersion};
+
+    #[test]
+    fn it_can_rank_package_names() {
+        assert_eq!(
+            "symfony/http-kernel http-kernel",
+            package_filter_text("symfony/http-kernel")
+        );
+
+        let mut names = vec!["symfony-cmf/routing", "acme/symfony", "symfony/console"];
+        names.sort_by_key(|name| package_sort_text(name, "symfony"));
+        assert_eq!(
+            vec!["symfony/console", "symfony-cmf/routing", "acme/symfony"],
+            names
+        );
+
+        let mut names = vec!["acme/monolog-handler", "monolog/monolog", "monologger/log"];
+        names.sort_by_key(|name| package_sort_text(name, "monolog/"));
+        assert_eq!(
+            vec!["monolog/monolog", "monologger/log", "acme/monolog-handler"],
+            names
+        );
+
+        let mut names = vec!["acme/monolog-handler", "monolog/monolog"];
+        names.sort_by_key(|name| package_sort_text(name, "mnlg"));
+        assert_eq!(vec!["monolog/monolog", "acme/monolog-handler"], names);
+    }
+
+    #[test]
+    fn it_can_document_a_completed_package() {
+        let package = Package::new(
+            "monolog/monolog".to_string(),
+            vec![PackageVersion {
+                description: Some("Sends your logs to files".to_string()),
+                version: Some("3.5.0".to_string()),
+                time: Some("2023-10-27T15:32:31+00:00".to_string()),
+                ..Default::default()
+            }],
+        );
+
+        assert_eq!(
+            "Sends your logs to files\n\n**Latest:** `3.5.0`, released 2023-10-27\n\n[Packagist](https://packagist.org/packages/monolog/monolog)",
+            package_documentation(&package)
+        );
+    }
+
+    #[test]
+    fn it_can_get_the_prefix_at_the_cursor() {
+        let line = "        \"symfony/http-ke\": \"^6.0\",";
+
+        assert_eq!(
+            Some("symfony/http-ke".to_string()),
+            get_completion_prefix(line, 24)
+        );
+        assert_eq!(Some("symf".to_string()), get_completion_prefix(line, 13));
+    }
+
+    #[test]
+    fn it_can_get_the_prefix_before_a_closing_quote() {
+        let line = "        \"psr/log\"";
+
+        assert_eq!(Some("psr/l".to_string()), get_completion_prefix(line, 14));
+    }
+
+    #[test]
+    fn it_wont_get_a_prefix_outside_of_a_string() {
+        assert_eq!(None, get_completion_prefix("    monolog", 11));
+        assert_eq!(None, get_completion_prefix("    \"a/b\": ", 11));
+    }
+
+    #[test]
+    fn it_can_insert_a_key_between_quotes() {
+        // Only the opening quote is typed.
+        assert_eq!(
+            Some(QuotedInsertion {
+                start: 9,
+                end: 13,
+                text: "psr/log\": \"$1\"".to_string(),
+                snippet: true,
+            }),
+            quoted_insertion("        \"psr/", 13, "psr/log", true)
+        );
+
+        // Both quotes are typed, the closing one is replaced.
+        assert_eq!(
+            Some(QuotedInsertion {
+                start: 9,
+                end: 14,
+                text: "psr/log\": \"$1\"".to_string(),
+                snippet: true,
+            }),
+            quoted_insertion("        \"psr/\"", 13, "psr/log", true)
+        );
+
+        // The key already has a value, only the name is replaced.
+        assert_eq!(
+            Some(QuotedInsertion {
+                start: 9,
+                end: 17,
+                text: "psr/log".to_string(),
+                snippet: false,
+            }),
+            quoted_insertion("        \"psr/lo_g\": \"^3.0\",", 11, "psr/log", true)
+        );
+    }
+
+    #[test]
+    fn it_can_insert_a_key_with_its_value_snippet() {
+        assert_eq!(
+            Some("autoload\": {$1}".to_string()),
+            key_insertion("    \"auto", 9, "autoload", "{$1}").map(|insertion| insertion.text)
+        );
+        assert_eq!(
+            Some("\\$schema\": \"$1\"".to_string()),
+            key_insertion("    \"", 5, "$schema", "\"$1\"").map(|insertion| insertion.text)
+        );
+    }
+
+    #[test]
+    fn it_can_insert_a_value_between_quotes() {
+        assert_eq!(
+            Some("vcs\"".to_string()),
+            quoted_insertion("\"type\": \"v", 10, "vcs", false).map(|insertion| insertion.text)
+        );
+        assert_eq!(
+            Some("vcs".to_string()),
+            quoted_insertion("\"type\": \"v\"", 10, "vcs", false).map(|insertion| insertion.text)
+        );
+        assert_eq!(None, quoted_insertion("    psr", 7, "psr/log", false));
+    }
+
+    #[test]
+    fn it_can_detect_keys() {
+        assert!(is_key_at("        \"psr/", 13));
+        assert!(is_key_at("{ \"a/b\": \"^1.0\", \"psr", 21));
+        assert!(!is_key_at("\"type\": \"v", 10));
+    }
+
+    #[test]
+    fn it_can_detect_the_array_of_a_key() {
+        let text = "{\n    \"keywords\": [\"log\", \"ps\"],\n    \"require\": {}\n}";
+        let cursor = text.find("ps").unwrap() + 2;
+
+        assert!(is_in_array(text, cursor, "keywords"));
+        assert!(!is_in_array(
+            text,
+            text.find("require").unwrap(),
+            "keywords"
+        ));
+        assert!(!is_in_array(
+            "{ \"require\": { \"psr/log\": [\"",
+            28,
+            "keywords"
+        ));
+    }
+
+    #[test]
+    fn it_can_find_the_constraint_at_the_cursor() {
+        let line = r#"        "monolog/monolog": "^3""#;
+
+        assert_eq!(
+            Some(ConstraintAt {
+                package: "monolog/monolog".to_string(),
+                start: 28,
+                end: 30,
+                unterminated: false,
+            }),
+            constraint_at(line, 29)
+        );
+        assert_eq!(None, constraint_at(line, 12));
+        assert_eq!(
+            Some(true),
+            constraint_at(r#"    "monolog/monolog": "^"#, 24).map(|at| at.unterminated)
+        );
+        assert_eq!(None, constraint_at(r#"    "php": "^8"#, 13));
+    }
+
+    #[test]
+    fn it_can_detect_the_object_of_a_key() {
+        let text = "{\n    \"require\": {\n        \"monolog/monolog\": \"^\n";
+
+        assert!(is_in_object(text, text.len(), &["require", "require-dev"]));
+        assert!(!is_in_object("{\n    \"suggest\": {\n", 20, &["require"]));
+    }
+
+    #[test]
+    fn it_can_complete_versions_newest_first() {
+        let versions: Vec<String> = ["3.5.0", "3.4.1", "3.10.0", "4.0.0-beta1", "dev-main"]
+            .iter()
+            .map(|version| version.to_string())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "^3.10", "~3.10.0", "3.10.0", "^3.5", "~3.5.0", "3.5.0", "^3.4", "~3.4.1", "3.4.1"
+            ],
+            version_completions(&versions)
+        );
+    }
+
+    #[test]
+    fn it_can_complete_popular_keywords() {
+        assert_eq!(
+            vec!["psr-3", "psr-4", "psr-7", "psr-11", "psr-15", "psr-17", "psr-18"],
+            keyword_completions("psr")
+        );
+    }
+
+    #[test]
+    fn it_can_build_an_author_snippet() {
+        assert_eq!(
+            "{\n\t\"name\": \"${1:Jane Doe}\",\n\t\"email\": \"${2:email@example.com}\"\n}$0",
+            author_snippet(Some("Jane Doe"), None)
+        );
+        assert!(author_snippet(Some("A \"B\" $C"), None).contains("${1:A \\\"B\\\" \\$C}"));
+    }
+}
@@ -1,14 +1,229 @@
+use crate::constraint::{self, Alias, Branch, Stability};
+use crate::effective::EffectiveConfig;
+use crate::global;
+use crate::json::{self, LineIndex, Node, NodeKind, Span};
+use crate::packagist::{self, UpdatePolicy};
+use crate::repository::{self, Repositories};
 use crate::Url;
 use log::{info, warn};
 use serde::Deserialize;
 use serde_json::Value;
-use std::fs::{self, File};
-use std::io::{prelude::*, BufReader};
-use std::{collections::HashMap, hash::Hash};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tower_lsp::lsp_types::Range;
 
 #[derive(Debug, PartialEq, Deserialize, Clone)]
 pub struct ComposerLockFile {
     pub versions: HashMap<String, InstalledPackage>,
+    /// The locked packages providing each virtual package, from their
+    /// "provide" section.
+    #[serde(default)]
+    pub provides: HashMap<String, Vec<String>>,
+    /// The locked packages of type "composer-plugin".
+    #[serde(default)]
+    pub plugins: Vec<String>,
+    /// The names of all locked packages, including packages-dev.
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// The packages replaced by locked packages.
+    #[serde(default)]
+    pub replaced: HashSet<String>,
+    /// The locked packages requiring each package, by its lowercase name.
+    #[serde(default)]
+    pub required_by: HashMap<String, Vec<Dependent>>,
+    #[serde(default)]
+    pub format: LockFormat,
+    /// The hash of the relevant parts of composer.json the lock was written
+    /// for.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
+
+/// A locked package requiring another package, with its constraint.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Dependent {
+    pub name: String,
+    pub constraint: String,
+}
+
+/// The composer version that wrote the lock file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum LockFormat {
+    Composer1,
+    #[default]
+    Composer2,
+}
+
+impl ComposerLockFile {
+    /// Whether a locked package is, provides or replaces the package.
+    pub fn locks(&self, package: &str) -> bool {
+        let package = package.to_lowercase();
+        self.packages.contains(&package)
+            || self.provides.contains_key(&package)
+            || self.replaced.contains(&package)
+    }
+
+    /// Parses the contents of a composer.lock. Lock files of composer 1 lack
+    /// some fields and can have missing versions, so every field is optional.
+    pub fn parse(data: &str) -> Option<ComposerLockFile> {
+        let contents: Value = match serde_json::from_str(data) {
+            Ok(contents) => contents,
+            Err(error) => {
+                warn!("Error while parsing lock file: {}", error);
+                return None;
+            }
+        };
+        contents.as_object()?;
+
+        // Composer 2 records the plugin API it used.
+        let format = match contents
+            .get("plugin-api-version")
+            .and_then(|version| version.as_str())
+        {
+            Some(version) if !version.starts_with('1') => LockFormat::Composer2,
+            _ => LockFormat::Composer1,
+        };
+
+        let mut composer_lock = ComposerLockFile {
+            versions: HashMap::new(),
+            provides: HashMap::new(),
+            plugins: vec![],
+            packages: vec![],
+            replaced: HashSet::new(),
+            required_by: HashMap::new(),
+            format,
+            content_hash: contents
+                .get("content-hash")
+                .and_then(|hash| hash.as_str())
+                .map(|hash| hash.to_string()),
+        };
+
+        // Collect the installed versions, the plugins, and what every locked
+        // package provides, replaces and requires.
+        for block in ["packages", "packages-dev"] {
+            let packages = contents.get(block).and_then(|packages| packages.as_array());
+            for item in packages.into_iter().flatten() {
+                let name = match item.get("name").and_then(|name| name.as_str()) {
+                    Some(name) => name,
+                    None => continue,
+                };
+
+                if block == "packages" {
+                    let version = item
+                        .get("version")
+                        .or_else(|| item.get("version_normalized"))
+                        .and_then(|version| version.as_str())
+                        .map(normalize_locked_version);
+                    match version {
+                        Some(version) => {
+                            let reference = item
+                                .get("source")
+                                .or_else(|| item.get("dist"))
+                                .and_then(|source| source.get("reference"))
+                                .and_then(|reference| reference.as_str())
+                                .map(|reference| reference.to_string());
+                            let installed_package = InstalledPackage {
+                                name: name.to_string(),
+                                version,
+                                reference,
+                            };
+                            composer_lock
+                                .versions
+                                .insert(name.to_string(), installed_package);
+                        }
+                        None => info!("The lock file has no version for {}", name),
+                    }
+                }
+
+                if item
+                    .get("type")
+                    .and_then(|package_type| package_type.as_str())
+                    == Some("composer-plugin")
+                {
+                    composer_lock.plugins.push(name.to_string());
+                }
+                composer_lock.packages.push(name.to_string());
+
+                let members = |key: &str| {
+                    item.get(key)
+                        .and_then(|members| members.as_object())
+                        .map(|members| members.keys().map(|name| name.to_lowercase()).collect())
+                        .unwrap_or_else(Vec::new)
+                };
+                composer_lock.replaced.extend(members("replace"));
+
+                let requires = item.get("require").and_then(|require| require.as_object());
+                for (required, constraint) in requires.into_iter().flatten() {
+                    composer_lock
+                        .required_by
+                        .entry(required.to_lowercase())
+                        .or_default()
+                        .push(Dependent {
+                            name: name.to_string(),
+                            constraint: constraint.as_str().unwrap_or_default().to_string(),
+                        });
+                }
+
+                let provides = item.get("provide").and_then(|provide| provide.as_object());
+                for provided in provides.into_iter().flatten().map(|(key, _)| key) {
+                    composer_lock
+                        .provides
+                        .entry(provided.to_string())
+                        .or_default()
+                        .push(name.to_string());
+                }
+            }
+        }
+
+        Some(composer_lock)
+    }
+
+    /// The locked packages providing the (virtual) package.
+    pub fn providers_of(&self, name: &str) -> Vec<String> {
+        self.provides.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Returns the span of the package name in the packages or packages-dev
+    /// list of a composer.lock.
+    pub fn find_package_span(lock_text: &str, name: &str) -> Option<Span> {
+        let root = json::parse(lock_text).ok()?;
+
+        for block in ["packages", "packages-dev"] {
+            let packages = match root.get(block).and_then(|member| member.value.as_array()) {
+                Some(packages) => packages,
+                None => continue,
+            };
+
+            for package in packages {
+                if let Some(package_name) = package.get("name") {
+                    if package_name.value.as_str() == Some(name) {
+                        return Some(package_name.value.span);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Drops the "v" prefix, and the fourth part composer 1 normalized versions
+/// to, e.g. "1.2.3" for "v1.2.3" or "1.2.3.0".
+pub fn normalize_locked_version(version: &str) -> String {
+    let version = version.strip_prefix('v').unwrap_or(version);
+
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() == 4
+        && parts[3] == "0"
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    {
+        return parts[..3].join(".");
+    }
+
+    version.to_string()
 }
 
 #[derive(Debug, PartialEq, Deserialize, Clone)]
@@ -16,21 +231,42 @@ pub struct ComposerDependency {
     pub name: String,
     pub version: String,
     pub line: u32,
+    /// The range of the package name, without the quotes.
+    #[serde(default)]
+    pub name_range: Range,
+    /// The range of the version constraint, without the quotes.
+    #[serde(default)]
+    pub constraint_range: Range,
+}
+
+impl ComposerDependency {
+    /// The constraint without an inline alias, e.g. "dev-main" for
+    /// "dev-main as 1.2.0".
+    pub fn constraint(&self) -> String {
+        match self.alias() {
+            Some(alias) => alias.constraint,
+            None => self.version.clone(),
+        }
+    }
+
+    pub fn alias(&self) -> Option<Alias> {
+        constraint::parse_alias(&self.version)
+    }
+
+    /// The branch the dependency is required from, e.g. "dev-main" for
+    /// "dev-main#abc123" or "dev-feature/foo as 2.x-dev".
+    pub fn branch(&self) -> Option<Branch> {
+        constraint::parse_branch(&self.version)
+    }
 }
 
 #[derive(Debug, PartialEq, Deserialize, Clone)]
 pub struct InstalledPackage {
     pub name: String,
     pub version: String,
-}
-
-#[derive(Deserialize, Debug, Default)]
-struct ComposerJsonFile {
+    /// The commit the package was installed from, from "source.reference".
     #[serde(default)]
-    require: HashMap<String, String>,
-
-    #[serde(rename(deserialize = "require-dev"), default)]
-    require_dev: HashMap<String, String>,
+    pub reference: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Deserialize, Clone)]
@@ -40,6 +276,14 @@ pub struct ComposerFile {
     pub dev_dependencies: Vec<ComposerDependency>,
     pub lock: Option<ComposerLockFile>,
     pub dependencies_by_line: HashMap<u32, String>,
+    pub vendor_dir: Option<String>,
+    /// The platform overrides from config.platform, e.g. "php" => "8.1.0".
+    pub platform: HashMap<String, String>,
+    /// The minimum-stability, e.g. "beta", stable when it isn't set.
+    pub minimum_stability: Option<String>,
+    pub prefer_stable: bool,
+    #[serde(skip)]
+    pub repositories: Repositories,
 }
 
 impl ComposerFile {
@@ -56,12 +300,250 @@ impl ComposerFile {
             dev_dependencies,
             lock,
             dependencies_by_line,
+            vendor_dir: None,
+            platform: HashMap::new(),
+            minimum_stability: None,
+            prefer_stable: false,
+            repositories: Repositories::default(),
+        }
+    }
+
+    /// The directory of this composer.json. Composer commands run in it and
+    /// the lock file and vendor directory are resolved against it, so files
+    /// in subdirectories of a bigger repository work as well.
+    pub fn dir(&self) -> PathBuf {
+        Url::parse(&self.path)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+            .unwrap_or_default()
+    }
+
+    /// The vendor directory set in composer.json, or the default "vendor".
+    pub fn vendor_path(&self) -> PathBuf {
+        self.dir()
+            .join(self.vendor_dir.as_deref().unwrap_or("vendor"))
+    }
+
+    /// The vendor directory, taken from composer's effective configuration
+    /// when composer.json doesn't set it, as it can also come from the global
+    /// config or the environment.
+    pub fn resolve_vendor_path(&self, effective: &EffectiveConfig) -> PathBuf {
+        match (&self.vendor_dir, &effective.vendor_dir) {
+            (None, Some(vendor_dir)) => vendor_dir.clone(),
+            _ => self.vendor_path(),
+        }
+    }
+
+    /// The platform override of a package, from composer.json or else the
+    /// global config.
+    pub fn platform_override(&self, effective: &EffectiveConfig, package: &str) -> Option<String> {
+        self.platform
+            .get(package)
+            .or_else(|| effective.platform.get(package))
+            .cloned()
+    }
+
+    /// What updates may be, going by minimum-stability, prefer-stable and
+    /// the platform overrides of composer.json and the global config.
+    pub fn update_policy(&self, effective: &EffectiveConfig) -> UpdatePolicy {
+        let mut platform = effective.platform.clone();
+        platform.extend(self.platform.clone());
+
+        UpdatePolicy {
+            minimum_stability: self
+                .minimum_stability
+                .as_deref()
+                .and_then(Stability::parse)
+                .unwrap_or(Stability::Stable),
+            prefer_stable: self.prefer_stable,
+            platform,
+        }
+    }
+
+    /// The directory composer commands for this file should run in.
+    pub fn working_dir(&self) -> String {
+        self.dir().to_string_lossy().to_string()
+    }
+
+    /// Whether both files require the same packages with the same constraints.
+    pub fn dependencies_equal(&self, other: &ComposerFile) -> bool {
+        let constraints = |dependencies: &Vec<ComposerDependency>| {
+            dependencies
+                .iter()
+                .map(|item| (item.name.clone(), item.version.clone()))
+                .collect::<HashMap<String, String>>()
+        };
+
+        constraints(&self.dependencies) == constraints(&other.dependencies)
+            && constraints(&self.dev_dependencies) == constraints(&other.dev_dependencies)
+    }
+
+    /// Returns the required or dev required dependency with the given name on
+    /// the given line, so packages required in both blocks are told apart.
+    pub fn get_dependency_on_line(&self, line: u32, name: &str) -> Option<&ComposerDependency> {
+        self.dependencies
+            .iter()
+            .chain(self.dev_dependencies.iter())
+            .find(|dependency| dependency.line == line && dependency.name == name)
+    }
+
+    /// Returns the required or dev required dependency with the given name.
+    pub fn get_dependency(&self, name: &str) -> Option<&ComposerDependency> {
+        self.dependencies
+            .iter()
+            .chain(self.dev_dependencies.iter())
+            .find(|dependency| dependency.name == name)
+    }
+
+    /// Whether the package is in require-dev, where composer only changes it
+    /// with --dev.
+    pub fn is_dev_dependency(&self, name: &str) -> bool {
+        self.dev_dependencies
+            .iter()
+            .any(|dependency| dependency.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether this is the global composer.json in COMPOSER_HOME.
+    pub fn is_global(&self) -> bool {
+        global::is_global_manifest(&self.dir().join("composer.json"))
+    }
+
+    /// Whether the requirement is on a virtual package, which is provided by
+    /// other packages instead of being installed itself.
+    pub fn is_virtual(&self, name: &str) -> bool {
+        if packagist::is_virtual_package(name) {
+            return true;
+        }
+
+        match &self.lock {
+            Some(lock) => !lock.versions.contains_key(name) && lock.provides.contains_key(name),
+            None => false,
+        }
+    }
+
+    /// The url of the composer.lock next to this file.
+    pub fn lock_path(&self) -> String {
+        match Url::parse(&self.path).and_then(|url| url.join("composer.lock")) {
+            Ok(url) => url.to_string(),
+            Err(_) => self.path.replace("composer.json", "composer.lock"),
+        }
+    }
+
+    /// Refreshes the dependencies and their line numbers from unsaved text.
+    /// Comments and trailing commas are tolerated, while the previous state
+    /// is kept when the text can't be parsed at all, which is common while
+    /// the user is still typing.
+    pub fn update_from_text(&mut self, text: &str) -> bool {
+        let root = match json::parse_tolerant(text) {
+            Ok((root, _)) => root,
+            Err(_) => return false,
+        };
+
+        let line_index = LineIndex::new(text);
+        let mut dependencies_by_line = HashMap::new();
+        self.dependencies =
+            Self::collect_dependencies(&root, &line_index, "require", &mut dependencies_by_line);
+        self.dev_dependencies = Self::collect_dependencies(
+            &root,
+            &line_index,
+            "require-dev",
+            &mut dependencies_by_line,
+        );
+        self.dependencies_by_line = dependencies_by_line;
+        let config = root.get("config").map(|config| &config.value);
+        self.vendor_dir = config
+            .and_then(|config| config.get("vendor-dir"))
+            .and_then(|vendor_dir| vendor_dir.value.as_str())
+            .map(|vendor_dir| vendor_dir.to_string());
+        self.platform = config
+            .and_then(|config| config.get("platform"))
+            .and_then(|platform| platform.value.as_object())
+            .map(|members| {
+                members
+                    .iter()
+                    .filter_map(|member| {
+                        let version = member.value.as_str()?;
+                        Some((member.key.clone(), version.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.minimum_stability = root
+            .get("minimum-stability")
+            .and_then(|minimum| minimum.value.as_str())
+            .map(|minimum| minimum.to_string());
+        self.prefer_stable = matches!(
+            root.get("prefer-stable").map(|prefer| &prefer.value.kind),
+            Some(NodeKind::Bool(true))
+        );
+        self.repositories = repository::parse(&root, &self.dir());
+
+        true
+    }
+
+    fn collect_dependencies(
+        root: &json::Node,
+        line_index: &LineIndex,
+        block_name: &str,
+        dependencies_by_line: &mut HashMap<u32, String>,
+    ) -> Vec<ComposerDependency> {
+        let members = match root
+            .get(block_name)
+            .and_then(|block| block.value.as_object())
+        {
+            Some(members) => members,
+            None => return vec![],
+        };
+
+        let mut dependencies = vec![];
+        for member in members {
+            let name_range = line_index.range(member.key_span.unquoted());
+            let constraint_range = line_index.range(Self::constraint_span(&member.value));
+
+            dependencies.push(ComposerDependency {
+                name: member.key.clone(),
+                version: member.value.as_str().unwrap_or_default().to_string(),
+                line: name_range.start.line,
+                name_range,
+                constraint_range,
+            });
+            dependencies_by_line.insert(name_range.start.line, member.key.clone());
+        }
+
+        dependencies
+    }
+
+    /// The span of a constraint string without its quotes, or of the whole
+    /// value when it isn't a string.
+    fn constraint_span(value: &Node) -> Span {
+        match value.kind {
+            NodeKind::String(_) => value.span.unquoted(),
+            _ => value.span,
         }
     }
 
     pub fn parse_from_path(filepath: Url) -> Option<ComposerFile> {
-        let file = Url::parse(&filepath.to_string()).unwrap();
-        if file.path().ends_with("composer.json") == false {
+        if !filepath.path().ends_with("composer.json") {
+            return None;
+        }
+
+        let file_path = filepath.to_file_path().ok()?;
+        let text = match fs::read_to_string(&file_path) {
+            Ok(text) => text,
+            Err(error) => {
+                info!("Can't read {}: {}", file_path.display(), error);
+                return None;
+            }
+        };
+
+        Self::parse_from_str(&text, filepath)
+    }
+
+    /// Parses the contents of a composer.json, e.g. from an editor buffer
+    /// with unsaved changes. Only the lock file is read from disk.
+    pub fn parse_from_str(text: &str, filepath: Url) -> Option<ComposerFile> {
+        if !filepath.path().ends_with("composer.json") {
             return None;
         }
 
@@ -73,183 +555,50 @@ impl ComposerFile {
             HashMap::new(),
         );
 
-        let mut dependencies_by_line = HashMap::new();
-        let file_open = File::open(file.path().to_string()).unwrap();
-        let mut reader = BufReader::new(file_open);
-        let composer_json_parsed: ComposerJsonFile =
-            serde_json::from_reader(&mut reader).unwrap_or_default();
-
-        // Get dependencies.
-        for (name, version) in composer_json_parsed.require {
-            let line_num = Self::get_line_num(filepath.path(), "require", &name, version.clone());
-
-            match line_num {
-                Some(num) => {
-                    let composer_dependency = ComposerDependency {
-                        name: name.to_string(),
-                        version: version.to_string(),
-                        // @todo figure out why we need to do this.
-                        line: num - 1,
-                    };
-
-                    composer_file.dependencies.push(composer_dependency);
-                    dependencies_by_line.insert(num - 1, name);
-                }
-                None => {
-                    info!("Can't get a line number for dependency {}", name);
-                }
-            }
-        }
-
-        // Get dev dependencies.
-        for (name, version) in composer_json_parsed.require_dev {
-            let line_num =
-                Self::get_line_num(filepath.path(), "require-dev", &name, version.clone());
-
-            match line_num {
-                Some(num) => {
-                    let composer_dependency = ComposerDependency {
-                        name: name.to_string(),
-                        version: version.to_string(),
-                        line: num - 1,
-                    };
-
-                    composer_file.dev_dependencies.push(composer_dependency);
-                    dependencies_by_line.insert(num - 1, name);
-                }
-                None => {
-                    info!("Can't get a line number for dev-dependency {}", name);
-                }
-            }
+        // Comments or trailing commas make the file invalid JSON, but the
+        // requirements can still be extracted.
+        if !composer_file.update_from_text(text) {
+            info!("Can't parse {}", filepath);
         }
-
-        composer_file.dependencies_by_line = dependencies_by_line;
         composer_file.lock = Self::parse_lock_file(filepath);
 
         Some(composer_file)
     }
 
     fn parse_lock_file(composer_json_path: Url) -> Option<ComposerLockFile> {
-        let composer_lock_path = composer_json_path
-            .to_string()
-            .replace("composer.json", "composer.lock");
-
-        let file = Url::parse(&composer_lock_path);
+        let file = composer_json_path
+            .join("composer.lock")
+            .ok()
+            .and_then(|url| url.to_file_path().ok());
 
         match file {
-            Ok(file_url) => {
-                let mut composer_lock = ComposerLockFile {
-                    versions: HashMap::new(),
-                };
-
-                let contents = fs::read_to_string(file_url.path());
-
-                match contents {
-                    Ok(data) => {
-                        let parsed_contents: Value = match serde_json::from_str(&data) {
-                            Ok(v) => v,
-                            Err(error) => {
-                                warn!("Error while parsing lock file: {}", error);
-                                Value::Null
-                            }
-                        };
-
-                        if parsed_contents.is_null() {
-                            return None;
-                        }
-
-                        let parsed_contents_object = parsed_contents.as_object().unwrap();
-                        if parsed_contents_object.contains_key("packages") {
-                            let packages = parsed_contents_object.get("packages");
-                            for item in packages.unwrap().as_array().unwrap() {
-                                let package = item.as_object();
-                                match package {
-                                    Some(item) => {
-                                        // @todo handle unwrap.
-                                        let name = item
-                                            .get("name")
-                                            .unwrap()
-                                            .to_string()
-                                            .replace("\"", "")
-                                            .replace("\'", "");
-
-                                        let version = item
-                                            .get("version")
-                                            .unwrap()
-                                            .to_string()
-                                            .replace("\"", "")
-                                            .replace("v", "")
-                                            .replace("\'", "");
-
-                                        let installed_package = InstalledPackage {
-                                            name: name.clone(),
-                                            version,
-                                        };
-
-                                        composer_lock.versions.insert(name, installed_package);
-                                    }
-                                    None => {}
-                                }
-                            }
-                        }
+            Some(file_path) => match fs::read_to_string(file_path) {
+                Ok(data) => ComposerLockFile::parse(&data),
+                Err(error) => {
+                    info!("Can't read the lock file because its missing.");
+                    info!("{}", error);
 
-                        Some(composer_lock)
-                    }
-                    Err(error) => {
-                        info!("Can't read the lock file because its missing.");
-                        info!("{}", error);
-
-                        None
-                    }
+                    None
                 }
-            }
-            Err(_error) => {
+            },
+            None => {
                 info!("Can't parse the lock file URL.");
                 None
             }
         }
     }
-
-    fn get_line_num(
-        filepath: &str,
-        block_name: &str,
-        dependency_name: &str,
-        dependency_version: String,
-    ) -> Option<u32> {
-        let file = File::open(filepath);
-        let reader = BufReader::new(file.expect("Can't retrieve a file"));
-
-        let mut line_num = 1;
-        let mut require_block_start = 0;
-        let require_block_end = 0;
-        for line in reader.lines() {
-            if require_block_end > 0 {
-                break;
-            }
-
-            let line_text = line.as_ref().expect("Can't unwrap a line text.");
-            if line_text.contains(&format!("\"{}\":", block_name).to_string()) {
-                require_block_start = line_num;
-            }
-
-            if require_block_start > 0 && line_num > require_block_start {
-                if line_text.contains(dependency_name) && line_text.contains(&dependency_version) {
-                    return Some(line_num);
-                }
-            }
-
-            line_num += 1;
-        }
-
-        None
-    }
 }
 
 #[cfg(test)]
 mod tests {
     use reqwest::Url;
+    use std::collections::HashMap;
 
-    use crate::composer::ComposerFile;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use crate::composer::{ComposerFile, ComposerLockFile, Dependent, LockFormat};
+    use crate::constraint::Stability;
+    use crate::effective::EffectiveConfig;
 
     #[test]
     fn it_can_parse_a_valid_composer_json_file() {
@@ -287,60 +636,376 @@ mod tests {
         assert_eq!(83, composer_file.lock.unwrap().versions.len());
     }
 
+    #[test]
+    fn it_can_read_the_providers_from_the_lock_file() {
+        let root_path = env!("CARGO_MANIFEST_DIR");
+        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path));
+        let composer_file = ComposerFile::parse_from_path(test_file.unwrap()).unwrap();
+        let lock = composer_file.lock.as_ref().unwrap();
+
+        assert_eq!(
+            vec!["guzzlehttp/psr7", "laminas/laminas-diactoros"],
+            lock.providers_of("psr/http-message-implementation")
+        );
+        assert!(composer_file.is_virtual("psr/log-implementation"));
+        assert!(composer_file.is_virtual("symfony/service-implementation"));
+        assert!(!composer_file.is_virtual("symfony/console"));
+    }
+
+    #[test]
+    fn it_can_read_the_relations_from_the_lock_file() {
+        let root_path = env!("CARGO_MANIFEST_DIR");
+        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path));
+        let composer_file = ComposerFile::parse_from_path(test_file.unwrap()).unwrap();
+        let lock = composer_file.lock.as_ref().unwrap();
+
+        assert!(lock.packages.len() >= lock.versions.len());
+        assert!(lock.required_by["psr/http-message"].contains(&Dependent {
+            name: "guzzlehttp/psr7".to_string(),
+            constraint: "~1.0".to_string(),
+        }));
+    }
+
+    #[test]
+    fn it_can_read_an_inline_alias() {
+        let text = r#"{
+            "require": {
+                "acme/lib": "dev-feature-x as 1.2.0",
+                "psr/log": "^3.0"
+            }
+        }"#;
+        let url = Url::parse("file:///tmp/alias/composer.json").unwrap();
+        let composer_file = ComposerFile::parse_from_str(text, url).unwrap();
+        let dependency = composer_file.get_dependency("acme/lib").unwrap();
+
+        assert_eq!(2, dependency.line);
+        assert_eq!("dev-feature-x", dependency.constraint());
+        assert_eq!("1.2.0", dependency.alias().unwrap().alias);
+        assert_eq!(
+            "^3.0",
+            composer_file
+                .get_dependency("psr/log")
+                .unwrap()
+                .constraint()
+        );
+    }
+
+    #[test]
+    fn it_can_tell_dev_dependencies_apart() {
+        let text = r#"{
+            "require": { "psr/log": "^3.0" },
+            "require-dev": { "phpunit/phpunit": "^10.0" }
+        }"#;
+        let url = Url::parse("file:///tmp/dev/composer.json").unwrap();
+        let composer_file = ComposerFile::parse_from_str(text, url).unwrap();
+
+        assert!(composer_file.is_dev_dependency("PHPUnit/phpunit"));
+        assert!(!composer_file.is_dev_dependency("psr/log"));
+    }
+
+    #[test]
+    fn it_can_parse_a_composer_1_lock_file() {
+        let data = r#"{
+            "hash": "0123456789abcdef",
+            "packages": [
+                { "name": "psr/log", "version": "1.0.2", "version_normalized": "1.0.2.0" },
+                { "name": "acme/old", "version_normalized": "2.1.0.0" },
+                { "name": "acme/branch", "version": "dev-develop", "source": { "type": "git", "reference": "abc123" } },
+                { "version": "1.0.0" }
+            ],
+            "packages-dev": null
+        }"#;
+        let lock = ComposerLockFile::parse(data).unwrap();
+
+        assert_eq!(LockFormat::Composer1, lock.format);
+        assert_eq!(3, lock.versions.len());
+        assert_eq!("2.1.0", lock.versions["acme/old"].version);
+        assert_eq!("dev-develop", lock.versions["acme/branch"].version);
+        assert_eq!(
+            Some("abc123".to_string()),
+            lock.versions["acme/branch"].reference
+        );
+    }
+
+    #[test]
+    fn it_can_detect_a_composer_2_lock_file() {
+        let data = r#"{ "packages": [{ "name": "psr/log", "version": "v3.0.0" }], "packages-dev": [], "plugin-api-version": "2.6.0" }"#;
+        let lock = ComposerLockFile::parse(data).unwrap();
+
+        assert_eq!(LockFormat::Composer2, lock.format);
+        assert_eq!("3.0.0", lock.versions["psr/log"].version);
+    }
+
+    #[test]
+    fn it_can_find_a_package_in_the_lock_file() {
+        let root_path = env!("CARGO_MANIFEST_DIR");
+        let lock_text =
+            std::fs::read_to_string(format!("{}/tests/composer.lock", root_path)).unwrap();
+
+        let span = ComposerLockFile::find_package_span(&lock_text, "composer/installers").unwrap();
+        assert_eq!("\"composer/installers\"", &lock_text[span.start..span.end]);
+        assert_eq!(
+            None,
+            ComposerLockFile::find_package_span(&lock_text, "fake/dependency")
+        );
+    }
+
     #[test]
     fn it_can_get_the_correct_dependency_line_number() {
         let root_path = env!("CARGO_MANIFEST_DIR");
-        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path)).unwrap();
+        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path));
+        let composer_file = ComposerFile::parse_from_path(test_file.unwrap()).unwrap();
 
-        let line_number = ComposerFile::get_line_num(
-            test_file.path(),
-            "require",
-            "composer/installers",
-            "^2.0".to_string(),
-        )
-        .unwrap();
+        let line_number = composer_file
+            .get_dependency("composer/installers")
+            .unwrap()
+            .line;
 
-        assert_eq!(18, line_number);
+        assert_eq!(17, line_number);
     }
 
     #[test]
     fn it_can_get_the_correct_dev_dependency_line_number() {
+        let root_path = env!("CARGO_MANIFEST_DIR");
+        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path));
+        let composer_file = ComposerFile::parse_from_path(test_file.unwrap()).unwrap();
+
+        assert!(composer_file
+            .get_dependency_on_line(24, "fake/dependency")
+            .is_some());
+    }
+
+    #[test]
+    fn it_can_update_the_dependencies_from_unsaved_text() {
         let root_path = env!("CARGO_MANIFEST_DIR");
         let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path)).unwrap();
+        let mut composer_file = ComposerFile::parse_from_path(test_file.clone()).unwrap();
 
-        let line_number = ComposerFile::get_line_num(
-            test_file.path(),
-            "require-dev",
-            "fake/dependency",
-            "^8.0".to_string(),
-        )
-        .unwrap();
+        let text = std::fs::read_to_string(test_file.path()).unwrap().replace(
+            "\"require\": {",
+            "\"require\": {\n        \"new/package\": \"^1.0\",",
+        );
+
+        assert!(composer_file.update_from_text(&text));
+        assert_eq!(4, composer_file.dependencies.len());
+        assert_eq!(
+            Some(&"new/package".to_string()),
+            composer_file.dependencies_by_line.get(&17)
+        );
+        assert_eq!(
+            Some(&"composer/installers".to_string()),
+            composer_file.dependencies_by_line.get(&18)
+        );
 
-        assert_eq!(25, line_number);
+        assert!(!composer_file.update_from_text("{ \"require\": "));
+        assert_eq!(4, composer_file.dependencies.len());
     }
 
     #[test]
-    fn it_can_get_the_correct_dependency_line_number_with_same_name() {
+    fn it_can_parse_unsaved_contents() {
         let root_path = env!("CARGO_MANIFEST_DIR");
         let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path)).unwrap();
+        let text = std::fs::read_to_string(test_file.path()).unwrap().replace(
+            "\"require\": {",
+            "\"require\": {\n        \"psr/log\": \"^3.0\",",
+        );
 
-        let required_dev_line_number = ComposerFile::get_line_num(
-            test_file.path(),
-            "require-dev",
-            "fake/dependency",
-            "^8.0".to_string(),
-        )
-        .unwrap();
-
-        let required_line_number = ComposerFile::get_line_num(
-            test_file.path(),
-            "require",
-            "fake/dependency",
-            "^8.0".to_string(),
-        )
-        .unwrap();
-
-        assert_eq!(25, required_dev_line_number);
-        assert_eq!(20, required_line_number);
+        let composer_file = ComposerFile::parse_from_str(&text, test_file).unwrap();
+        let psr_log = composer_file
+            .dependencies
+            .iter()
+            .find(|dependency| dependency.name == "psr/log")
+            .unwrap();
+
+        assert_eq!(17, psr_log.line);
+        assert_eq!(
+            Some(&"psr/log".to_string()),
+            composer_file.dependencies_by_line.get(&17)
+        );
+    }
+
+    #[test]
+    fn it_can_parse_dependencies_from_a_file_with_comments() {
+        let text = "{\n    // Runtime dependencies.\n    \"require\": {\n        \"php\": \"^8.1\",\n    },\n}\n";
+        let mut composer_file = ComposerFile::new(
+            "file:///composer.json".to_string(),
+            vec![],
+            vec![],
+            None,
+            HashMap::new(),
+        );
+
+        assert!(composer_file.update_from_text(text));
+        assert_eq!(1, composer_file.dependencies.len());
+        assert_eq!(
+            Some(&"php".to_string()),
+            composer_file.dependencies_by_line.get(&3)
+        );
+    }
+
+    #[test]
+    fn it_can_resolve_paths_of_a_file_in_a_subdirectory() {
+        let composer_file = ComposerFile::new(
+            "file:///repo/composer.json-tools/php%20app/composer.json".to_string(),
+            vec![],
+            vec![],
+            None,
+            HashMap::new(),
+        );
+
+        assert_eq!(
+            "/repo/composer.json-tools/php app",
+            composer_file.working_dir()
+        );
+        assert_eq!(
+            "file:///repo/composer.json-tools/php%20app/composer.lock",
+            composer_file.lock_path()
+        );
+    }
+
+    #[test]
+    fn it_can_read_the_vendor_dir_from_the_config() {
+        let text = "{\n    \"config\": { \"vendor-dir\": \"lib/vendor\" }\n}\n";
+        let composer_file =
+            ComposerFile::parse_from_str(text, Url::parse("file:///app/composer.json").unwrap())
+                .unwrap();
+
+        assert_eq!(
+            std::path::PathBuf::from("/app/lib/vendor"),
+            composer_file.vendor_path()
+        );
+    }
+
+    #[test]
+    fn it_can_read_the_platform_overrides() {
+        let text =
+            "{\n    \"config\": { \"platform\": { \"php\": \"8.1.0\", \"ext-foo\": false } }\n}\n";
+        let composer_file =
+            ComposerFile::parse_from_str(text, Url::parse("file:///app/composer.json").unwrap())
+                .unwrap();
+
+        assert_eq!(
+            Some(&"8.1.0".to_string()),
+            composer_file.platform.get("php")
+        );
+        assert_eq!(1, composer_file.platform.len());
+    }
+
+    #[test]
+    fn it_can_build_the_update_policy() {
+        let text = r#"{
+            "minimum-stability": "beta",
+            "prefer-stable": true,
+            "config": { "platform": { "php": "8.1.0" } }
+        }"#;
+        let composer_file =
+            ComposerFile::parse_from_str(text, Url::parse("file:///app/composer.json").unwrap())
+                .unwrap();
+        let effective = EffectiveConfig {
+            platform: HashMap::from([
+                ("php".to_string(), "7.4.0".to_string()),
+                ("ext-redis".to_string(), "5.3.0".to_string()),
+            ]),
+            ..EffectiveConfig::default()
+        };
+        let policy = composer_file.update_policy(&effective);
+
+        assert_eq!(Stability::Beta, policy.minimum_stability);
+        assert!(policy.prefer_stable);
+        assert_eq!(Some(&"8.1.0".to_string()), policy.platform.get("php"));
+        assert_eq!(Some(&"5.3.0".to_string()), policy.platform.get("ext-redis"));
+    }
+
+    #[test]
+    fn it_can_fall_back_to_the_effective_config() {
+        let text = "{\n    \"config\": { \"platform\": { \"php\": \"8.1.0\" } }\n}\n";
+        let composer_file =
+            ComposerFile::parse_from_str(text, Url::parse("file:///app/composer.json").unwrap())
+                .unwrap();
+        let effective = EffectiveConfig {
+            vendor_dir: Some(std::path::PathBuf::from("/shared/vendor")),
+            platform: HashMap::from([
+                ("php".to_string(), "7.4.0".to_string()),
+                ("ext-redis".to_string(), "5.3.0".to_string()),
+            ]),
+            ..EffectiveConfig::default()
+        };
+
+        assert_eq!(
+            std::path::PathBuf::from("/shared/vendor"),
+            composer_file.resolve_vendor_path(&effective)
+        );
+        assert_eq!(
+            Some("8.1.0".to_string()),
+            composer_file.platform_override(&effective, "php")
+        );
+        assert_eq!(
+            Some("5.3.0".to_string()),
+            composer_file.platform_override(&effective, "ext-redis")
+        );
+    }
+
+    #[test]
+    fn it_can_compare_the_dependencies_of_two_files() {
+        let root_path = env!("CARGO_MANIFEST_DIR");
+        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path)).unwrap();
+        let composer_file = ComposerFile::parse_from_path(test_file.clone()).unwrap();
+        let mut changed_file = ComposerFile::parse_from_path(test_file).unwrap();
+
+        assert!(composer_file.dependencies_equal(&changed_file));
+
+        changed_file.dependencies[0].version = "^99.0".to_string();
+        assert!(!composer_file.dependencies_equal(&changed_file));
+    }
+
+    #[test]
+    fn it_can_get_the_ranges_of_a_dependency() {
+        let text = "{\n    \"require\": {\n        \"composer/installers\": \"^2.0\",\n        \"psr/log\":\n            \"^3.0\"\n    }\n}\n";
+        let composer_file =
+            ComposerFile::parse_from_str(text, Url::parse("file:///app/composer.json").unwrap())
+                .unwrap();
+
+        let installers = composer_file.get_dependency("composer/installers").unwrap();
+        assert_eq!(
+            Range::new(Position::new(2, 9), Position::new(2, 28)),
+            installers.name_range
+        );
+        assert_eq!(
+            Range::new(Position::new(2, 32), Position::new(2, 36)),
+            installers.constraint_range
+        );
+
+        // The constraint can be on another line than the package name.
+        let log = composer_file.get_dependency("psr/log").unwrap();
+        assert_eq!(3, log.line);
+        assert_eq!(
+            Range::new(Position::new(4, 13), Position::new(4, 17)),
+            log.constraint_range
+        );
+    }
+
+    #[test]
+    fn it_can_get_the_correct_dependency_line_number_with_same_name() {
+        let root_path = env!("CARGO_MANIFEST_DIR");
+        let test_file = Url::from_file_path(format!("{}/tests/composer.json", root_path));
+        let composer_file = ComposerFile::parse_from_path(test_file.unwrap()).unwrap();
+
+        let required = composer_file
+            .dependencies
+            .iter()
+            .find(|dependency| dependency.name == "fake/dependency")
+            .unwrap();
+        let required_dev = composer_file
+            .dev_dependencies
+            .iter()
+            .find(|dependency| dependency.name == "fake/dependency")
+            .unwrap();
+
+        assert_eq!(19, required.line);
+        assert_eq!(24, required_dev.line);
+        assert_eq!(
+            Some(required_dev),
+            composer_file.get_dependency_on_line(24, "fake/dependency")
+        );
     }
 }
@@ -0,0 +1,429 @@
+use crate::cache::CachePolicy;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// Server settings, read from the initialization options and updated on
+/// workspace/didChangeConfiguration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub on_save: OnSaveSettings,
+    pub definition: DefinitionSettings,
+    pub hover: HoverSettings,
+    pub lint: LintSettings,
+    pub commands: CommandSettings,
+    pub features: FeatureSettings,
+    pub updates: UpdateSettings,
+    pub packagist: PackagistSettings,
+    pub workspace: WorkspaceSettings,
+}
+
+/// Optional actions that run after a composer.json is saved.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OnSaveSettings {
+    /// Run `composer validate`.
+    pub validate: bool,
+    /// Run `composer audit` against the lock file.
+    pub audit: bool,
+    /// Run `composer update --lock` when only the metadata changed.
+    pub update_lock: bool,
+}
+
+/// Optional editor features, registered with the client only when enabled.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FeatureSettings {
+    /// Update commands above the require blocks.
+    pub code_lens: bool,
+    /// The installed version after every constraint.
+    pub inlay_hints: bool,
+    /// Highlighting of vendor and package names.
+    pub semantic_tokens: bool,
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        FeatureSettings {
+            code_lens: true,
+            inlay_hints: true,
+            semantic_tokens: true,
+        }
+    }
+}
+
+/// The composer.json files checked besides the open ones.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspaceSettings {
+    /// Check every composer.json of the workspace folders on startup, and
+    /// again when they or their composer.lock change on disk.
+    pub scan: bool,
+}
+
+impl Default for WorkspaceSettings {
+    fn default() -> Self {
+        WorkspaceSettings { scan: true }
+    }
+}
+
+/// The diagnostics for packages with an update available.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UpdateSettings {
+    /// Severity of the diagnostics, "off" disables them.
+    pub severity: LintLevel,
+    /// Packages that are never reported as outdated, e.g. "symfony/*".
+    pub ignore: Vec<String>,
+}
+
+impl UpdateSettings {
+    /// Whether the package matches one of the ignored names, which may end
+    /// with a "*" wildcard.
+    pub fn is_ignored(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+
+        self.ignore.iter().any(|ignored| {
+            let ignored = ignored.to_lowercase();
+            match ignored.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == ignored,
+            }
+        })
+    }
+}
+
+/// Where packagist.org metadata is fetched from, and how long it's cached.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackagistSettings {
+    /// A mirror of repo.packagist.org, e.g. "https://packagist.example.com".
+    /// The one in the global composer config is used when it isn't set.
+    pub mirror: Option<String>,
+    /// A proxy of packagist.org, for the package list, statistics,
+    /// providers and security advisories.
+    pub url: Option<String>,
+    /// Seconds package metadata is cached before it's revalidated.
+    pub metadata_ttl: u64,
+    /// Seconds the list of all package names is cached.
+    pub package_list_ttl: u64,
+    /// Only use cached metadata, never fetching it.
+    pub offline: bool,
+}
+
+impl Default for PackagistSettings {
+    fn default() -> Self {
+        PackagistSettings {
+            mirror: None,
+            url: None,
+            metadata_ttl: 10 * 60,
+            package_list_ttl: 24 * 60 * 60,
+            offline: false,
+        }
+    }
+}
+
+impl PackagistSettings {
+    pub fn cache_policy(&self) -> CachePolicy {
+        CachePolicy {
+            metadata_ttl: Duration::from_secs(self.metadata_ttl),
+            package_list_ttl: Duration::from_secs(self.package_list_ttl),
+            offline: self.offline,
+        }
+    }
+}
+
+/// Limits and environment of the composer commands the server runs.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommandSettings {
+    /// The composer binary, by default the one in PATH.
+    pub composer_path: String,
+    /// Seconds after which a composer command is killed.
+    pub timeout: u64,
+    /// Passed to composer as COMPOSER_MEMORY_LIMIT, e.g. "2G" or "-1".
+    pub memory_limit: Option<String>,
+    /// Environment variables passed to composer, besides PATH, HOME and
+    /// COMPOSER_HOME.
+    pub env_passthrough: Vec<String>,
+}
+
+impl Default for CommandSettings {
+    fn default() -> Self {
+        CommandSettings {
+            composer_path: "composer".to_string(),
+            timeout: 300,
+            memory_limit: None,
+            env_passthrough: DEFAULT_ENV_PASSTHROUGH
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Variables composer commonly needs, for authentication, proxies, caching
+/// and the platform.
+const DEFAULT_ENV_PASSTHROUGH: &[&str] = &[
+    "COMPOSER_AUTH",
+    "COMPOSER_CACHE_DIR",
+    "COMPOSER_ALLOW_SUPERUSER",
+    "GITHUB_TOKEN",
+    "SSH_AUTH_SOCK",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+    "LANG",
+    "LC_ALL",
+    "TMPDIR",
+    "USER",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "SystemRoot",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+];
+
+impl CommandSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DefinitionSettings {
+    pub target: DefinitionTarget,
+    /// Only used by the packagist and repository targets.
+    pub network: NetworkMode,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HoverSettings {
+    pub network: NetworkMode,
+}
+
+/// Whether a feature may fetch package data from packagist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkMode {
+    /// Fetch the latest data.
+    #[default]
+    Online,
+    /// Only use the data fetched by the package checks on save.
+    CacheOnly,
+    /// Don't show any package data.
+    Disabled,
+}
+
+/// Where goto-definition on a dependency leads to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefinitionTarget {
+    /// The installed package's composer.json in the vendor directory.
+    #[default]
+    Vendor,
+    /// The package page on packagist.org, opened in the browser.
+    Packagist,
+    /// The package's source repository, opened in the browser.
+    Repository,
+    /// Goto-definition does nothing.
+    Disabled,
+}
+
+/// Severities of the optional lint rules.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LintSettings {
+    /// Exact version pins in require, which block security patch updates.
+    pub exact_pins: LintLevel,
+    /// A require block without a php constraint.
+    pub missing_php: LintLevel,
+    /// Dev tools in require and production packages only in require-dev.
+    pub misplaced_dependencies: LintLevel,
+    /// Constraints asking for a lower stability than minimum-stability.
+    pub stability: LintLevel,
+    /// php and extension requirements the local interpreter, or
+    /// config.platform, doesn't satisfy.
+    pub platform: LintLevel,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        LintSettings {
+            exact_pins: LintLevel::Warning,
+            missing_php: LintLevel::Warning,
+            // It's a heuristic, so it doesn't warn by default.
+            misplaced_dependencies: LintLevel::Information,
+            stability: LintLevel::Warning,
+            platform: LintLevel::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Off,
+    Hint,
+    Information,
+    #[default]
+    Warning,
+    Error,
+}
+
+impl LintLevel {
+    /// The diagnostic severity for this level, or None when the rule is off.
+    pub fn severity(self) -> Option<DiagnosticSeverity> {
+        match self {
+            LintLevel::Off => None,
+            LintLevel::Hint => Some(DiagnosticSeverity::HINT),
+            LintLevel::Information => Some(DiagnosticSeverity::INFORMATION),
+            LintLevel::Warning => Some(DiagnosticSeverity::WARNING),
+            LintLevel::Error => Some(DiagnosticSeverity::ERROR),
+        }
+    }
+}
+
+impl Settings {
+    /// Builds the settings from a client payload, which may either be the
+    /// settings object itself or be nested under a "composer_lsp" key.
+    pub fn from_value(value: Option<Value>) -> Settings {
+        let value = match value {
+            Some(Value::Object(mut object)) => match object.remove("composer_lsp") {
+                Some(nested) => nested,
+                None => Value::Object(object),
+            },
+            _ => return Settings::default(),
+        };
+
+        match serde_json::from_value(value) {
+            Ok(settings) => settings,
+            Err(error) => {
+                log::warn!("Can't parse the settings: {}", error);
+                Settings::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::time::Duration;
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    use crate::config::{DefinitionTarget, LintLevel, NetworkMode, Settings};
+
+    #[test]
+    fn it_uses_the_defaults_without_settings() {
+        assert_eq!(Settings::default(), Settings::from_value(None));
+    }
+
+    #[test]
+    fn it_can_parse_nested_settings() {
+        let settings = Settings::from_value(Some(json!({
+            "composer_lsp": {
+                "onSave": { "validate": true, "updateLock": true }
+            }
+        })));
+
+        assert!(settings.on_save.validate);
+        assert!(!settings.on_save.audit);
+        assert!(settings.on_save.update_lock);
+    }
+
+    #[test]
+    fn it_can_parse_the_definition_target() {
+        let settings = Settings::from_value(Some(json!({
+            "definition": { "target": "disabled" }
+        })));
+
+        assert_eq!(DefinitionTarget::Disabled, settings.definition.target);
+    }
+
+    #[test]
+    fn it_can_parse_the_network_modes() {
+        let settings = Settings::from_value(Some(json!({
+            "hover": { "network": "cacheOnly" },
+            "definition": { "network": "disabled" }
+        })));
+
+        assert_eq!(NetworkMode::CacheOnly, settings.hover.network);
+        assert_eq!(NetworkMode::Disabled, settings.definition.network);
+        assert_eq!(NetworkMode::Online, Settings::default().hover.network);
+    }
+
+    #[test]
+    fn it_can_turn_off_a_lint_rule() {
+        let settings = Settings::from_value(Some(json!({
+            "lint": { "exactPins": "off" }
+        })));
+
+        assert_eq!(None, settings.lint.exact_pins.severity());
+        assert_eq!(
+            Some(DiagnosticSeverity::WARNING),
+            Settings::default().lint.exact_pins.severity()
+        );
+    }
+
+    #[test]
+    fn it_can_turn_off_the_workspace_scan() {
+        let settings = Settings::from_value(Some(json!({
+            "workspace": { "scan": false }
+        })));
+
+        assert!(!settings.workspace.scan);
+        assert!(Settings::default().workspace.scan);
+    }
+
+    #[test]
+    fn it_can_set_the_command_timeout() {
+        let settings = Settings::from_value(Some(json!({
+            "commands": { "timeout": 60 }
+        })));
+
+        assert_eq!(60, settings.commands.timeout().as_secs());
+        assert_eq!(300, Settings::default().commands.timeout().as_secs());
+    }
+
+    #[test]
+    fn it_can_configure_the_update_checks() {
+        let settings = Settings::from_value(Some(json!({
+            "commands": { "composerPath": "/opt/composer.phar" },
+            "updates": { "severity": "information", "ignore": ["symfony/*", "psr/log"] },
+            "packagist": {
+                "mirror": "https://packagist.example.com",
+                "url": "https://packagist-proxy.example.com",
+                "offline": true
+            }
+        })));
+
+        assert_eq!("/opt/composer.phar", settings.commands.composer_path);
+        assert_eq!(LintLevel::Information, settings.updates.severity);
+        assert!(settings.updates.is_ignored("symfony/console"));
+        assert!(settings.updates.is_ignored("PSR/Log"));
+        assert!(!settings.updates.is_ignored("psr/log-implementation"));
+        assert_eq!(
+            Some("https://packagist.example.com".to_string()),
+            settings.packagist.mirror
+        );
+        assert_eq!(
+            Some("https://packagist-proxy.example.com".to_string()),
+            settings.packagist.url
+        );
+        assert!(settings.packagist.cache_policy().offline);
+        assert_eq!(
+            Duration::from_secs(600),
+            settings.packagist.cache_policy().metadata_ttl
+        );
+        assert_eq!(LintLevel::Warning, Settings::default().updates.severity);
+    }
+}
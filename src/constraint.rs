@@ -0,0 +1,799 @@
+/// An inline alias, like "dev-feature-x as 1.2.0", which installs the branch
+/// while other packages see it as the aliased version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alias {
+    pub constraint: String,
+    pub alias: String,
+}
+
+/// Splits an inline alias into the actual constraint and the alias.
+pub fn parse_alias(constraint: &str) -> Option<Alias> {
+    let (actual, alias) = constraint.split_once(" as ")?;
+    let (actual, alias) = (actual.trim(), alias.trim());
+    if actual.is_empty() || alias.is_empty() {
+        return None;
+    }
+
+    Some(Alias {
+        constraint: actual.to_string(),
+        alias: alias.to_string(),
+    })
+}
+
+/// A development branch constraint, like "dev-main" or "2.x-dev", optionally
+/// pinned to a commit, like "dev-main#abc123".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    /// The branch version, e.g. "dev-main".
+    pub name: String,
+    pub commit: Option<String>,
+}
+
+/// Parses a constraint requiring a single branch, ignoring an inline alias,
+/// e.g. "dev-feature/foo" for "dev-feature/foo as 2.x-dev".
+pub fn parse_branch(constraint: &str) -> Option<Branch> {
+    let constraint = match parse_alias(constraint) {
+        Some(alias) => alias.constraint,
+        None => constraint.trim().to_string(),
+    };
+    if constraint.contains(|c: char| c.is_whitespace() || c == '|' || c == ',') {
+        return None;
+    }
+
+    let (name, commit) = match constraint.split_once('#') {
+        Some((name, commit)) => (name, Some(commit.to_string()).filter(|c| !c.is_empty())),
+        None => (constraint.as_str(), None),
+    };
+    // Stability flags are implied by branches.
+    let name = name.split('@').next().unwrap_or_default();
+    if !is_branch(name) {
+        return None;
+    }
+
+    Some(Branch {
+        name: name.to_string(),
+        commit,
+    })
+}
+
+/// Whether the constraint pins a single exact version, e.g. "1.4.2".
+pub fn is_exact(constraint: &str) -> bool {
+    let version = constraint.trim().trim_start_matches('v');
+
+    !version.is_empty()
+        && version
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether two versions are the same, e.g. "v1.10.0" and "1.10.0.0", but not
+/// "1.10.0" and "11.0.0". Branches are compared by name.
+pub fn same_version(a: &str, b: &str) -> bool {
+    match (Version::parse(a), Version::parse(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.trim().eq_ignore_ascii_case(b.trim()),
+    }
+}
+
+/// Rewrites an exact version to a caret constraint, e.g. "1.4.2" to "^1.4".
+pub fn exact_to_caret(constraint: &str) -> Option<String> {
+    if !is_exact(constraint) {
+        return None;
+    }
+
+    let version = constraint.trim().trim_start_matches('v');
+    let parts: Vec<&str> = version.split('.').collect();
+
+    match parts.as_slice() {
+        [major, minor, ..] => Some(format!("^{}.{}", major, minor)),
+        _ => Some(format!("^{}", version)),
+    }
+}
+
+/// Rewrites a caret constraint to a tilde constraint, e.g. "^1.4" to "~1.4".
+pub fn caret_to_tilde(constraint: &str) -> Option<String> {
+    let version = constraint.trim().strip_prefix('^')?;
+    if !is_exact(version) {
+        return None;
+    }
+
+    Some(format!("~{}", version))
+}
+
+/// Widens a caret, tilde or exact constraint to also allow the next major
+/// version, e.g. "^1.4" to "^1.4 || ^2.0". Exact versions become a caret
+/// constraint first, e.g. "1.4.2" to "^1.4.2 || ^2.0", as the pin alone
+/// wouldn't allow the rest of the current major version.
+pub fn widen_to_next_major(constraint: &str) -> Option<String> {
+    let constraint = constraint.trim();
+    let version = constraint.trim_start_matches(['^', '~']);
+    if !is_exact(version) {
+        return None;
+    }
+
+    let major = version
+        .trim_start_matches('v')
+        .split('.')
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+
+    let current = match constraint == version {
+        true => format!("^{}", version),
+        false => constraint.to_string(),
+    };
+
+    Some(format!("{} || ^{}.0", current, major + 1))
+}
+
+/// The stabilities of versions, from the least to the most stable. Patch
+/// releases, like "1.0.0-p1", come after the release they patch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stability {
+    Dev,
+    Alpha,
+    Beta,
+    RC,
+    Stable,
+    Patch,
+}
+
+impl Stability {
+    pub fn parse(stability: &str) -> Option<Stability> {
+        match stability.to_lowercase().as_str() {
+            "dev" => Some(Stability::Dev),
+            "alpha" | "a" => Some(Stability::Alpha),
+            "beta" | "b" => Some(Stability::Beta),
+            "rc" => Some(Stability::RC),
+            "stable" => Some(Stability::Stable),
+            "patch" | "pl" | "p" => Some(Stability::Patch),
+            _ => None,
+        }
+    }
+
+    /// The stability of a version or branch, like composer names it, e.g.
+    /// "beta" for "2.1.0-beta2" and "dev" for "dev-main". None for anything
+    /// that isn't a version.
+    pub fn of(version: &str) -> Option<Stability> {
+        if is_branch(version) {
+            return Some(Stability::Dev);
+        }
+
+        Version::parse(version).map(|version| version.stability())
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stability::Dev => "dev",
+            Stability::Alpha => "alpha",
+            Stability::Beta => "beta",
+            Stability::RC => "RC",
+            Stability::Stable => "stable",
+            Stability::Patch => "patch",
+        }
+    }
+}
+
+/// A version like composer normalizes it, e.g. "1.2.0-beta2" as 1.2.0.0
+/// beta 2. Versions compare by their numbers, then their stability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    numbers: [u64; 4],
+    stability: Stability,
+    build: u64,
+}
+
+impl Version {
+    /// Parses a version, ignoring a "v" prefix and unknown suffixes like the
+    /// "-1ubuntu2" of distribution packages. Branches, like "dev-main" or
+    /// "2.x-dev", aren't versions.
+    pub fn parse(version: &str) -> Option<Version> {
+        if is_branch(version) {
+            return None;
+        }
+
+        let (parts, stability) = parse_parts(version)?;
+        Some(Version::new(
+            &parts,
+            stability.unwrap_or((Stability::Stable, 0)),
+        ))
+    }
+
+    fn new(parts: &[u64], (stability, build): (Stability, u64)) -> Version {
+        let mut numbers = [0; 4];
+        for (number, part) in numbers.iter_mut().zip(parts) {
+            *number = *part;
+        }
+
+        Version {
+            numbers,
+            stability,
+            build,
+        }
+    }
+
+    /// The lowest version starting with the parts, e.g. 2.0.0.0-dev for [2].
+    fn lowest(parts: &[u64]) -> Version {
+        Version::new(parts, (Stability::Dev, 0))
+    }
+
+    pub fn stability(&self) -> Stability {
+        self.stability
+    }
+}
+
+/// The numbers of a version and its stability with its number, if any.
+type VersionParts = (Vec<u64>, Option<(Stability, u64)>);
+
+fn is_branch(version: &str) -> bool {
+    let version = version.trim().to_lowercase();
+
+    version.starts_with("dev-") || version.ends_with("-dev")
+}
+
+/// Splits a version into its numbers and, if it has one, its stability, e.g.
+/// [1, 2] and beta 2 for "v1.2-beta2".
+fn parse_parts(version: &str) -> Option<VersionParts> {
+    let version = version.trim();
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+
+    let end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    let (numbers, suffix) = version.split_at(end);
+    let parts = numbers
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    if parts.is_empty() || parts.len() > 4 {
+        return None;
+    }
+
+    let suffix = suffix.trim_start_matches(['-', '_', '.', '+']);
+    let name: String = suffix
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    let build = suffix[name.len()..]
+        .trim_start_matches(['-', '_', '.'])
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse::<u64>()
+        .unwrap_or(0);
+
+    Some((
+        parts,
+        Stability::parse(&name).map(|stability| (stability, build)),
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Operator {
+    fn matches(self, version: &Version, bound: &Version) -> bool {
+        match self {
+            Operator::Equal => version == bound,
+            Operator::NotEqual => version != bound,
+            Operator::Less => version < bound,
+            Operator::LessOrEqual => version <= bound,
+            Operator::Greater => version > bound,
+            Operator::GreaterOrEqual => version >= bound,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Alternative {
+    /// A development branch, like "dev-main", which only matches itself.
+    Branch(String),
+    /// Bounds that all have to match, none for "*".
+    Range(Vec<(Operator, Version)>),
+}
+
+/// A composer version constraint, with alternatives ("||"), conjunctions ("," or
+/// a space), hyphen ranges, caret, tilde and wildcard constraints, stability
+/// flags and branches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    alternatives: Vec<Alternative>,
+    /// The least stable versions the constraint asks for, through a stability
+    /// flag like "@beta" or a version like "2.0.0-RC1".
+    pub stability: Stability,
+}
+
+impl Constraint {
+    /// Parses the constraint, returning None when it can't be understood.
+    pub fn parse(constraint: &str) -> Option<Constraint> {
+        let constraint = match parse_alias(constraint) {
+            Some(alias) => alias.constraint,
+            None => constraint.to_string(),
+        };
+
+        let mut parsed = Constraint {
+            alternatives: vec![],
+            stability: Stability::Stable,
+        };
+        for alternative in constraint.split('|').filter(|part| !part.trim().is_empty()) {
+            let alternative = parsed.parse_alternative(alternative)?;
+            parsed.alternatives.push(alternative);
+        }
+        if parsed.alternatives.is_empty() {
+            return None;
+        }
+
+        Some(parsed)
+    }
+
+    fn parse_alternative(&mut self, alternative: &str) -> Option<Alternative> {
+        // Join operators with their versions, e.g. ">= 1.0".
+        let mut tokens: Vec<String> = vec![];
+        for token in alternative
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|token| !token.is_empty())
+        {
+            match tokens.last_mut() {
+                Some(last) if last.chars().all(|c| "<>=!^~".contains(c)) => last.push_str(token),
+                _ => tokens.push(token.to_string()),
+            }
+        }
+
+        let mut bounds = vec![];
+        let mut position = 0;
+        while position < tokens.len() {
+            let token = self.strip_flag(&tokens[position]);
+
+            // A hyphen range, e.g. "1.0 - 2.0".
+            if tokens.get(position + 1).map(|token| token.as_str()) == Some("-") {
+                let upper = self.strip_flag(tokens.get(position + 2)?);
+                bounds.extend(self.hyphen_range(&token, &upper)?);
+                position += 3;
+                continue;
+            }
+
+            if tokens.len() == 1 && is_branch(&token) {
+                // A commit reference pins the branch, e.g. "dev-main#abc123".
+                let branch = token.split('#').next().unwrap_or_default();
+                return Some(Alternative::Branch(branch.to_lowercase()));
+            }
+
+            bounds.extend(self.parse_atom(&token)?);
+            position += 1;
+        }
+
+        Some(Alternative::Range(bounds))
+    }
+
+    /// Removes a stability flag, like the "@beta" of "^2.0@beta".
+    fn strip_flag(&mut self, token: &str) -> String {
+        match token.split_once('@') {
+            Some((token, flag)) => {
+                if let Some(stability) = Stability::parse(flag) {
+                    self.stability = self.stability.min(stability);
+                }
+                token.to_string()
+            }
+            None => token.to_string(),
+        }
+    }
+
+    /// Parses a version of the constraint, lowering the stability when it
+    /// explicitly asks for an unstable one.
+    fn parse_version(&mut self, version: &str) -> Option<VersionParts> {
+        let (parts, stability) = parse_parts(version)?;
+        if let Some((explicit, _)) = stability {
+            self.stability = self.stability.min(explicit);
+        }
+
+        Some((parts, stability))
+    }
+
+    fn hyphen_range(&mut self, lower: &str, upper: &str) -> Option<Vec<(Operator, Version)>> {
+        let (lower_parts, lower_stability) = self.parse_version(lower)?;
+        let (upper_parts, upper_stability) = self.parse_version(upper)?;
+
+        // A partial upper version includes all versions starting with it.
+        let upper = match upper_parts.len() {
+            1 | 2 => (
+                Operator::Less,
+                Version::lowest(&bump(&upper_parts, upper_parts.len() - 1)),
+            ),
+            _ => (
+                Operator::LessOrEqual,
+                Version::new(
+                    &upper_parts,
+                    upper_stability.unwrap_or((Stability::Stable, 0)),
+                ),
+            ),
+        };
+
+        Some(vec![
+            (
+                Operator::GreaterOrEqual,
+                lower_bound(&lower_parts, lower_stability),
+            ),
+            upper,
+        ])
+    }
+
+    fn parse_atom(&mut self, atom: &str) -> Option<Vec<(Operator, Version)>> {
+        if matches!(atom, "*" | "x" | "X") {
+            return Some(vec![]);
+        }
+
+        // Wildcards, e.g. "1.2.*" or "1.x".
+        if let Some(prefix) = atom
+            .strip_suffix(".*")
+            .or_else(|| atom.strip_suffix(".x"))
+            .or_else(|| atom.strip_suffix(".X"))
+        {
+            let (parts, _) = self.parse_version(prefix)?;
+            return Some(vec![
+                (Operator::GreaterOrEqual, Version::lowest(&parts)),
+                (
+                    Operator::Less,
+                    Version::lowest(&bump(&parts, parts.len() - 1)),
+                ),
+            ]);
+        }
+
+        if let Some(version) = atom.strip_prefix('^') {
+            let (parts, stability) = self.parse_version(version)?;
+            let position = match parts.as_slice() {
+                [major, ..] if *major > 0 => 0,
+                [_] => 0,
+                [_, minor, ..] if *minor > 0 => 1,
+                [_, _] => 1,
+                _ => 2,
+            };
+            return Some(vec![
+                (Operator::GreaterOrEqual, lower_bound(&parts, stability)),
+                (Operator::Less, Version::lowest(&bump(&parts, position))),
+            ]);
+        }
+
+        if let Some(version) = atom.strip_prefix('~') {
+            let (parts, stability) = self.parse_version(version)?;
+            let position = parts.len().saturating_sub(2);
+            return Some(vec![
+                (Operator::GreaterOrEqual, lower_bound(&parts, stability)),
+                (Operator::Less, Version::lowest(&bump(&parts, position))),
+            ]);
+        }
+
+        let (operator, version) = [
+            (">=", Operator::GreaterOrEqual),
+            ("<=", Operator::LessOrEqual),
+            ("!=", Operator::NotEqual),
+            ("<>", Operator::NotEqual),
+            ("==", Operator::Equal),
+            (">", Operator::Greater),
+            ("<", Operator::Less),
+            ("=", Operator::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, operator)| Some((operator, atom.strip_prefix(prefix)?)))
+        .unwrap_or((Operator::Equal, atom));
+
+        let (parts, stability) = self.parse_version(version)?;
+        let bound = match operator {
+            // Without a stability, "<2.0" excludes the prereleases of 2.0 and
+            // ">=2.0" includes them.
+            Operator::Less | Operator::GreaterOrEqual => lower_bound(&parts, stability),
+            _ => Version::new(&parts, stability.unwrap_or((Stability::Stable, 0))),
+        };
+
+        Some(vec![(operator, bound)])
+    }
+
+    /// Whether some version, or branch, satisfies both constraints.
+    pub fn intersects(&self, other: &Constraint) -> bool {
+        self.alternatives.iter().any(|alternative| {
+            other
+                .alternatives
+                .iter()
+                .any(|other| match (alternative, other) {
+                    (Alternative::Branch(branch), Alternative::Branch(other)) => branch == other,
+                    // Only "*" allows branches.
+                    (Alternative::Branch(_), Alternative::Range(bounds))
+                    | (Alternative::Range(bounds), Alternative::Branch(_)) => bounds.is_empty(),
+                    (Alternative::Range(bounds), Alternative::Range(other)) => {
+                        let mut all = bounds.clone();
+                        all.extend(other.iter().copied());
+                        is_satisfiable(&all)
+                    }
+                })
+        })
+    }
+
+    /// Whether the version, or branch, satisfies the constraint.
+    pub fn matches(&self, version: &str) -> bool {
+        if is_branch(version) {
+            let branch = version.trim().to_lowercase();
+            return self
+                .alternatives
+                .iter()
+                .any(|alternative| *alternative == Alternative::Branch(branch.clone()));
+        }
+
+        let version = match Version::parse(version) {
+            Some(version) => version,
+            None => return false,
+        };
+
+        self.alternatives
+            .iter()
+            .any(|alternative| match alternative {
+                Alternative::Branch(_) => false,
+                Alternative::Range(bounds) => bounds
+                    .iter()
+                    .all(|(operator, bound)| operator.matches(&version, bound)),
+            })
+    }
+}
+
+/// Whether some version satisfies all the bounds. "!=" only rules out a
+/// single version, so it only matters when the others pin that version.
+fn is_satisfiable(bounds: &[(Operator, Version)]) -> bool {
+    // The bounds with whether they include their version.
+    let mut lower: Option<(Version, bool)> = None;
+    let mut upper: Option<(Version, bool)> = None;
+    for (operator, version) in bounds {
+        let (raises, lowers) = match operator {
+            Operator::Equal => (Some(true), Some(true)),
+            Operator::Greater => (Some(false), None),
+            Operator::GreaterOrEqual => (Some(true), None),
+            Operator::Less => (None, Some(false)),
+            Operator::LessOrEqual => (None, Some(true)),
+            Operator::NotEqual => (None, None),
+        };
+        if let Some(inclusive) = raises {
+            let stricter = match lower {
+                Some((current, current_inclusive)) => {
+                    *version > current || (*version == current && current_inclusive && !inclusive)
+                }
+                None => true,
+            };
+            if stricter {
+                lower = Some((*version, inclusive));
+            }
+        }
+        if let Some(inclusive) = lowers {
+            let stricter = match upper {
+                Some((current, current_inclusive)) => {
+                    *version < current || (*version == current && current_inclusive && !inclusive)
+                }
+                None => true,
+            };
+            if stricter {
+                upper = Some((*version, inclusive));
+            }
+        }
+    }
+
+    match (lower, upper) {
+        (Some((lower, lower_inclusive)), Some((upper, upper_inclusive))) => {
+            if lower == upper {
+                lower_inclusive && upper_inclusive && !bounds.contains(&(Operator::NotEqual, lower))
+            } else {
+                lower < upper
+            }
+        }
+        _ => true,
+    }
+}
+
+/// The lower bound of a version without a stability includes its
+/// prereleases, e.g. ">=1.2" allows "1.2.0-beta1".
+fn lower_bound(parts: &[u64], stability: Option<(Stability, u64)>) -> Version {
+    match stability {
+        Some(stability) => Version::new(parts, stability),
+        None => Version::lowest(parts),
+    }
+}
+
+/// Increments the part at the position and drops the ones after it, e.g.
+/// [1, 3] for [1, 2, 5] at position 1.
+fn bump(parts: &[u64], position: usize) -> Vec<u64> {
+    let mut bumped: Vec<u64> = parts.iter().take(position + 1).copied().collect();
+    bumped.resize(position + 1, 0);
+    bumped[position] += 1;
+
+    bumped
+}
+
+/// Whether the version satisfies the composer constraint, e.g. "8.1.2" and
+/// "^7.4 || ^8.0". Returns None when the constraint can't be understood.
+pub fn matches(constraint: &str, version: &str) -> Option<bool> {
+    Some(Constraint::parse(constraint)?.matches(version))
+}
+
+/// Whether some version satisfies both composer constraints, e.g. "^1.0" and
+/// "<1.5". Returns None when either can't be understood.
+pub fn intersects(constraint: &str, other: &str) -> Option<bool> {
+    Some(Constraint::parse(constraint)?.intersects(&Constraint::parse(other)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::{
+        caret_to_tilde, exact_to_caret, intersects, is_exact, matches, parse_alias, parse_branch,
+        same_version, widen_to_next_major, Alias, Branch, Constraint, Stability, Version,
+    };
+
+    #[test]
+    fn it_can_tell_the_stability_of_a_version() {
+        let label = |version: &str| Stability::of(version).map(|stability| stability.as_str());
+
+        assert_eq!(Some("stable"), label("v2.1.0"));
+        assert_eq!(Some("beta"), label("2.1.0-beta2"));
+        assert_eq!(Some("alpha"), label("3.0.0-alpha.1"));
+        assert_eq!(Some("RC"), label("3.0.0-RC1"));
+        assert_eq!(Some("patch"), label("1.0.0-p1"));
+        assert_eq!(Some("dev"), label("dev-main"));
+        assert_eq!(Some("dev"), label("2.x-dev"));
+        assert_eq!(None, label("self.version"));
+    }
+
+    #[test]
+    fn it_can_parse_inline_aliases() {
+        assert_eq!(
+            Some(Alias {
+                constraint: "dev-feature-x".to_string(),
+                alias: "1.2.0".to_string(),
+            }),
+            parse_alias("dev-feature-x as 1.2.0")
+        );
+        assert_eq!(None, parse_alias("^1.2"));
+        assert_eq!(None, parse_alias("dev-main as "));
+    }
+
+    #[test]
+    fn it_can_detect_exact_versions() {
+        assert!(is_exact("1.4.2"));
+        assert!(is_exact("v1.4"));
+        assert!(!is_exact("^1.4"));
+        assert!(!is_exact("1.4.*"));
+        assert!(!is_exact("dev-main"));
+    }
+
+    #[test]
+    fn it_can_tell_whether_versions_are_the_same() {
+        assert!(same_version("v1.10.0", "1.10.0.0"));
+        assert!(same_version("dev-main", "dev-main"));
+        assert!(!same_version("1.10.0", "11.0.0"));
+        assert!(!same_version("1.1.0", "11.0"));
+        assert!(!same_version("2.0.0-beta1", "2.0.0"));
+    }
+
+    #[test]
+    fn it_can_rewrite_exact_versions_to_caret() {
+        assert_eq!(Some("^1.4".to_string()), exact_to_caret("1.4.2"));
+        assert_eq!(Some("^3.5".to_string()), exact_to_caret("v3.5.1"));
+        assert_eq!(Some("^2".to_string()), exact_to_caret("2"));
+        assert_eq!(None, exact_to_caret("^1.4"));
+    }
+
+    #[test]
+    fn it_can_rewrite_caret_to_tilde() {
+        assert_eq!(Some("~1.4".to_string()), caret_to_tilde("^1.4"));
+        assert_eq!(None, caret_to_tilde("~1.4"));
+        assert_eq!(None, caret_to_tilde("^1.4 || ^2.0"));
+    }
+
+    #[test]
+    fn it_can_widen_to_the_next_major() {
+        assert_eq!(
+            Some("^1.4 || ^2.0".to_string()),
+            widen_to_next_major("^1.4")
+        );
+        assert_eq!(
+            Some("^1.4.2 || ^2.0".to_string()),
+            widen_to_next_major("1.4.2")
+        );
+        assert_eq!(
+            Some("~1.4.2 || ^2.0".to_string()),
+            widen_to_next_major("~1.4.2")
+        );
+        assert_eq!(None, widen_to_next_major(">=1.0"));
+    }
+
+    #[test]
+    fn it_can_match_versions_against_constraints() {
+        assert_eq!(Some(true), matches("^8.1", "8.2.4"));
+        assert_eq!(Some(false), matches("^8.1", "8.0.30"));
+        assert_eq!(Some(true), matches("^7.4 || ^8.0", "7.4.33"));
+        assert_eq!(Some(true), matches(">=7.4 <8.2", "8.1.2-1ubuntu2"));
+        assert_eq!(Some(true), matches("~8.1", "8.3.0"));
+        assert_eq!(Some(false), matches("~8.1.2", "8.2.0"));
+        assert_eq!(Some(true), matches("8.1.*", "8.1.9"));
+        assert_eq!(Some(false), matches("dev-main", "8.1.0"));
+        assert_eq!(None, matches("^foo", "8.1.0"));
+    }
+
+    #[test]
+    fn it_can_compare_versions() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert!(version("2.10.0") > version("2.9.1"));
+        assert!(version("1.0.0-beta2") > version("1.0.0-alpha3"));
+        assert!(version("1.0.0-RC1") < version("1.0.0"));
+        assert!(version("v1.0.0") == version("1.0"));
+        assert_eq!(None, Version::parse("dev-main"));
+    }
+
+    #[test]
+    fn it_can_match_composer_constraints() {
+        let constraint = |constraint: &str| Constraint::parse(constraint).unwrap();
+
+        assert!(constraint("1.0 - 2.0").matches("2.0.9"));
+        assert!(!constraint("1.0 - 2.0").matches("2.1.0"));
+        assert!(!constraint("1.0.0 - 2.0.0").matches("2.0.1"));
+        assert!(constraint("^0.3").matches("0.3.9"));
+        assert!(!constraint("^0.3").matches("0.4.0"));
+        assert!(!constraint("^1.0").matches("2.0.0-beta1"));
+        assert!(constraint("^1.0@beta").matches("1.1.0-beta1"));
+        assert!(constraint(">= 1.0, < 1.5").matches("1.4.9"));
+        assert!(!constraint("<=2.1").matches("2.1.1"));
+        assert!(!constraint("!=1.2.3").matches("1.2.3"));
+        assert!(constraint("dev-main").matches("dev-main"));
+        assert!(constraint("dev-main as 1.0.0").matches("dev-main"));
+        assert!(constraint("1.x").matches("1.9.0"));
+    }
+
+    #[test]
+    fn it_can_detect_the_stability_a_constraint_asks_for() {
+        let stability = |constraint: &str| Constraint::parse(constraint).unwrap().stability;
+
+        assert_eq!(Stability::Stable, stability("^1.0"));
+        assert_eq!(Stability::Beta, stability("^1.0@beta"));
+        assert_eq!(Stability::RC, stability("^1.0 || 2.0.0-RC1"));
+    }
+
+    #[test]
+    fn it_can_parse_a_branch_constraint() {
+        assert_eq!(
+            Some(Branch {
+                name: "dev-main".to_string(),
+                commit: Some("abc123".to_string()),
+            }),
+            parse_branch("dev-main#abc123")
+        );
+        assert_eq!(
+            Some(Branch {
+                name: "dev-feature/foo".to_string(),
+                commit: None,
+            }),
+            parse_branch("dev-feature/foo as 2.x-dev")
+        );
+        assert_eq!("2.x-dev", parse_branch("2.x-dev@dev").unwrap().name);
+        assert_eq!(None, parse_branch("^1.0 || dev-main"));
+        assert_eq!(None, parse_branch("^2.0"));
+
+        assert_eq!(Some(true), matches("dev-main#abc123", "dev-main"));
+    }
+
+    #[test]
+    fn it_can_intersect_constraints() {
+        assert_eq!(Some(true), intersects("^1.0", "<1.5"));
+        assert_eq!(Some(true), intersects("^1.0", "1.*"));
+        assert_eq!(Some(true), intersects("^1.0 || ^2.0", ">=2.3,<2.4"));
+        assert_eq!(Some(true), intersects("^1.2", "1.4.0"));
+        assert_eq!(Some(true), intersects("dev-main", "*"));
+        assert_eq!(Some(false), intersects("^1.0", "^2.0"));
+        assert_eq!(Some(false), intersects("^1.0", "<1.0"));
+        assert_eq!(Some(false), intersects(">1.0", "<=1.0"));
+        assert_eq!(Some(false), intersects("1.4.0", "!=1.4.0"));
+        assert_eq!(Some(false), intersects("dev-main", "^1.0"));
+        assert_eq!(None, intersects("^1.0", "not a constraint"));
+    }
+}
@@ -0,0 +1,349 @@
+use md5::{Digest, Md5};
+
+use crate::composer::ComposerLockFile;
+use crate::constraint;
+use crate::json::{Node, NodeKind, Span};
+use crate::packagist;
+
+/// The keys of composer.json that composer hashes into the content-hash of
+/// composer.lock, besides config.platform.
+const HASHED_KEYS: [&str; 11] = [
+    "name",
+    "version",
+    "require",
+    "require-dev",
+    "conflict",
+    "replace",
+    "provide",
+    "minimum-stability",
+    "prefer-stable",
+    "repositories",
+    "extra",
+];
+
+/// A requirement of composer.json that isn't in the lock file yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotLocked {
+    /// The span of the package name.
+    pub span: Span,
+    pub package: String,
+}
+
+/// The non-platform requirements of require and require-dev, lowercased.
+fn requirements(root: &Node) -> Vec<(Span, String)> {
+    let mut requirements = vec![];
+    for block in ["require", "require-dev"] {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            if !packagist::is_platform_package(&member.key) {
+                requirements.push((member.key_span, member.key.to_lowercase()));
+            }
+        }
+    }
+
+    requirements
+}
+
+/// Finds the requirements that no locked package is, provides or replaces.
+pub fn find_not_locked(root: &Node, lock: &ComposerLockFile) -> Vec<NotLocked> {
+    requirements(root)
+        .into_iter()
+        .filter(|(_, package)| !lock.locks(package))
+        .map(|(span, package)| NotLocked { span, package })
+        .collect()
+}
+
+/// A requirement whose constraint the locked version doesn't satisfy anymore.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unsatisfied {
+    /// The span of the constraint.
+    pub span: Span,
+    pub package: String,
+    pub constraint: String,
+    pub locked: String,
+}
+
+/// Finds the requirements whose constraint changed since composer.lock was
+/// written, so the locked version no longer satisfies it.
+pub fn find_unsatisfied(root: &Node, lock: &ComposerLockFile) -> Vec<Unsatisfied> {
+    let mut unsatisfied = vec![];
+    for block in ["require", "require-dev"] {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            let (constraint, locked) = match (
+                member.value.as_str(),
+                lock.versions.get(&member.key.to_lowercase()),
+            ) {
+                (Some(constraint), Some(locked)) => (constraint, &locked.version),
+                _ => continue,
+            };
+            // Branches and aliases are left to composer.
+            if constraint::parse_alias(constraint).is_some()
+                || constraint::Version::parse(locked).is_none()
+            {
+                continue;
+            }
+
+            if constraint::matches(constraint, locked) == Some(false) {
+                unsatisfied.push(Unsatisfied {
+                    span: member.value.span,
+                    package: member.key.clone(),
+                    constraint: constraint.to_string(),
+                    locked: locked.clone(),
+                });
+            }
+        }
+    }
+
+    unsatisfied
+}
+
+/// The content-hash composer writes to composer.lock for the composer.json:
+/// the md5 of the relevant keys, sorted and encoded like PHP's json_encode.
+pub fn content_hash(root: &Node) -> String {
+    let mut relevant: Vec<(String, String)> = vec![];
+    for member in root.as_object().into_iter().flatten() {
+        if !HASHED_KEYS.contains(&member.key.as_str()) {
+            continue;
+        }
+
+        // A duplicate key overwrites the value, like in a PHP array.
+        let mut encoded = String::new();
+        encode_php_json(&member.value, &mut encoded);
+        match relevant.iter_mut().find(|(key, _)| *key == member.key) {
+            Some((_, value)) => *value = encoded,
+            None => relevant.push((member.key.clone(), encoded)),
+        }
+    }
+
+    let platform = root
+        .get("config")
+        .and_then(|config| config.value.get("platform"));
+    if let Some(platform) = platform {
+        let mut encoded = String::from("{\"platform\":");
+        encode_php_json(&platform.value, &mut encoded);
+        encoded.push('}');
+        relevant.push(("config".to_string(), encoded));
+    }
+
+    relevant.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut encoded = String::from("{");
+    for (index, (key, value)) in relevant.iter().enumerate() {
+        if index > 0 {
+            encoded.push(',');
+        }
+        encode_php_string(key, &mut encoded);
+        encoded.push(':');
+        encoded.push_str(value);
+    }
+    encoded.push('}');
+
+    format!("{:x}", Md5::digest(encoded.as_bytes()))
+}
+
+/// Encodes the node like PHP's json_encode after json_decode to an array:
+/// empty objects become lists, and slashes and non-ASCII characters are
+/// escaped.
+fn encode_php_json(node: &Node, encoded: &mut String) {
+    match &node.kind {
+        NodeKind::Object(members) => {
+            // Objects with the keys 0, 1, ... become lists, like empty ones.
+            let is_list = members
+                .iter()
+                .enumerate()
+                .all(|(index, member)| member.key == index.to_string());
+            let (open, close) = if is_list { ('[', ']') } else { ('{', '}') };
+
+            encoded.push(open);
+            for (index, member) in members.iter().enumerate() {
+                if index > 0 {
+                    encoded.push(',');
+                }
+                if !is_list {
+                    encode_php_string(&member.key, encoded);
+                    encoded.push(':');
+                }
+                encode_php_json(&member.value, encoded);
+            }
+            encoded.push(close);
+        }
+        NodeKind::Array(items) => {
+            encoded.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    encoded.push(',');
+                }
+                encode_php_json(item, encoded);
+            }
+            encoded.push(']');
+        }
+        NodeKind::String(value) => encode_php_string(value, encoded),
+        NodeKind::Number(number) => encoded.push_str(number),
+        NodeKind::Bool(value) => encoded.push_str(if *value { "true" } else { "false" }),
+        NodeKind::Null => encoded.push_str("null"),
+    }
+}
+
+fn encode_php_string(value: &str, encoded: &mut String) {
+    encoded.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '/' => encoded.push_str("\\/"),
+            '\u{8}' => encoded.push_str("\\b"),
+            '\u{c}' => encoded.push_str("\\f"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            '\t' => encoded.push_str("\\t"),
+            ' '..='\u{7f}' => encoded.push(character),
+            _ => {
+                let mut units = [0; 2];
+                for unit in character.encode_utf16(&mut units) {
+                    encoded.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+        }
+    }
+    encoded.push('"');
+}
+
+/// Finds the locked packages that are neither required by composer.json nor
+/// by another locked package, so composer update would remove them.
+pub fn find_not_required(root: &Node, lock: &ComposerLockFile) -> Vec<String> {
+    let requirements: Vec<String> = requirements(root)
+        .into_iter()
+        .map(|(_, package)| package)
+        .collect();
+
+    lock.packages
+        .iter()
+        .filter(|package| {
+            !requirements.contains(package) && !lock.required_by.contains_key(package.as_str())
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::composer::{ComposerLockFile, Dependent, InstalledPackage, LockFormat};
+    use crate::drift::{content_hash, find_not_locked, find_not_required, find_unsatisfied};
+    use crate::json;
+
+    fn lock() -> ComposerLockFile {
+        ComposerLockFile {
+            versions: HashMap::from([(
+                "monolog/monolog".to_string(),
+                InstalledPackage {
+                    name: "monolog/monolog".to_string(),
+                    version: "2.9.1".to_string(),
+                    reference: None,
+                },
+            )]),
+            provides: HashMap::from([(
+                "psr/log-implementation".to_string(),
+                vec!["monolog/monolog".to_string()],
+            )]),
+            plugins: vec![],
+            packages: vec![
+                "monolog/monolog".to_string(),
+                "psr/log".to_string(),
+                "acme/removed".to_string(),
+                "phpunit/phpunit".to_string(),
+            ],
+            replaced: HashSet::from(["symfony/polyfill-php80".to_string()]),
+            required_by: HashMap::from([(
+                "psr/log".to_string(),
+                vec![Dependent {
+                    name: "monolog/monolog".to_string(),
+                    constraint: "^2.0 || ^3.0".to_string(),
+                }],
+            )]),
+            format: LockFormat::Composer2,
+            content_hash: None,
+        }
+    }
+
+    #[test]
+    fn it_can_find_requirements_missing_from_the_lock() {
+        let text = r#"{
+            "require": {
+                "php": "^8.1",
+                "Monolog/Monolog": "^3.0",
+                "psr/log-implementation": "*",
+                "symfony/polyfill-php80": "*",
+                "acme/new": "^1.0"
+            },
+            "require-dev": { "phpunit/phpunit": "^10.0" }
+        }"#;
+        let not_locked = find_not_locked(&json::parse(text).unwrap(), &lock());
+
+        assert_eq!(1, not_locked.len());
+        assert_eq!("acme/new", not_locked[0].package);
+        assert_eq!(
+            "\"acme/new\"",
+            &text[not_locked[0].span.start..not_locked[0].span.end]
+        );
+    }
+
+    #[test]
+    fn it_can_find_locked_packages_that_are_no_longer_required() {
+        let text = r#"{
+            "require": { "monolog/monolog": "^3.0" },
+            "require-dev": { "phpunit/phpunit": "^10.0" }
+        }"#;
+
+        assert_eq!(
+            vec!["acme/removed".to_string()],
+            find_not_required(&json::parse(text).unwrap(), &lock())
+        );
+    }
+
+    #[test]
+    fn it_can_find_constraints_the_locked_version_does_not_satisfy() {
+        let text = r#"{
+            "require": { "monolog/monolog": "^3.0", "psr/log": "^1.0" },
+            "require-dev": { "phpunit/phpunit": "^10.0" }
+        }"#;
+        let unsatisfied = find_unsatisfied(&json::parse(text).unwrap(), &lock());
+
+        assert_eq!(1, unsatisfied.len());
+        assert_eq!("monolog/monolog", unsatisfied[0].package);
+        assert_eq!("2.9.1", unsatisfied[0].locked);
+        assert_eq!(
+            "\"^3.0\"",
+            &text[unsatisfied[0].span.start..unsatisfied[0].span.end]
+        );
+
+        let text = r#"{ "require": { "monolog/monolog": "^2.0 || ^3.0" } }"#;
+        assert!(find_unsatisfied(&json::parse(text).unwrap(), &lock()).is_empty());
+    }
+
+    #[test]
+    fn it_can_compute_the_content_hash_like_composer() {
+        let text = r#"{
+            "require": { "php": "^8.1", "monolog/monolog": "^3.0" },
+            "name": "acme/app",
+            "description": "Ignored",
+            "extra": { "note": "Café" },
+            "config": { "sort-packages": true, "platform": { "php": "8.1.0" } },
+            "require-dev": {}
+        }"#;
+
+        assert_eq!(
+            "97b665b072392a151f10e519eb4dde32",
+            content_hash(&json::parse(text).unwrap())
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::composer::ComposerLockFile;
+use crate::constraint;
+use crate::json::{Member, Node, Span};
+use crate::packagist;
+
+/// How a requirement clashes with another entry of composer.json.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// Listed twice in the same block, composer only keeps the last one.
+    Repeated,
+    /// Listed in both require and require-dev.
+    RequiredAndDev,
+    /// Replaced by the project itself.
+    Replaced,
+    /// In conflict with the version that is required.
+    Conflicting,
+}
+
+/// A requirement with another entry it clashes with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub kind: DuplicateKind,
+    pub package: String,
+    /// The span of the package name the problem is reported on.
+    pub span: Span,
+    /// The span of the package name of the other entry.
+    pub other_span: Span,
+}
+
+impl Duplicate {
+    pub fn message(&self) -> String {
+        match self.kind {
+            DuplicateKind::Repeated => format!(
+                "{} is listed more than once, only the last constraint is used.",
+                self.package
+            ),
+            DuplicateKind::RequiredAndDev => format!(
+                "{} is in both \"require\" and \"require-dev\", which can lead to unexpected behavior.",
+                self.package
+            ),
+            DuplicateKind::Replaced => format!(
+                "{} is required, but the project replaces it, so it's never installed.",
+                self.package
+            ),
+            DuplicateKind::Conflicting => format!(
+                "{} is required in a version the project conflicts with.",
+                self.package
+            ),
+        }
+    }
+
+    /// The message of the related information on the other entry.
+    pub fn other_message(&self) -> &'static str {
+        match self.kind {
+            DuplicateKind::Repeated => "Also listed here",
+            DuplicateKind::RequiredAndDev => "Also required here",
+            DuplicateKind::Replaced => "Replaced here",
+            DuplicateKind::Conflicting => "Conflict declared here",
+        }
+    }
+}
+
+fn members<'a>(root: &'a Node, block: &str) -> &'a [Member] {
+    root.get(block)
+        .and_then(|block| block.value.as_object())
+        .map(|members| members.as_slice())
+        .unwrap_or_default()
+}
+
+/// Finds requirements listed twice, in both require and require-dev, or
+/// also in replace or conflict with a version the requirement can resolve
+/// to. That's the locked version once there is one, else any version both
+/// constraints allow. Names are compared case-insensitively, like composer
+/// does.
+pub fn find_duplicates(root: &Node, lock: Option<&ComposerLockFile>) -> Vec<Duplicate> {
+    let mut duplicates = vec![];
+
+    // Point at every repetition, with the first occurrence as the other entry.
+    for block in ["require", "require-dev"] {
+        let members = members(root, block);
+        for (index, member) in members.iter().enumerate() {
+            if let Some(first) = members[..index]
+                .iter()
+                .find(|first| first.key.eq_ignore_ascii_case(&member.key))
+            {
+                duplicates.push(Duplicate {
+                    kind: DuplicateKind::Repeated,
+                    package: member.key.clone(),
+                    span: member.key_span,
+                    other_span: first.key_span,
+                });
+            }
+        }
+    }
+
+    let require = members(root, "require");
+    for member in members(root, "require-dev") {
+        if let Some(required) = require
+            .iter()
+            .find(|required| required.key.eq_ignore_ascii_case(&member.key))
+        {
+            duplicates.push(Duplicate {
+                kind: DuplicateKind::RequiredAndDev,
+                package: member.key.clone(),
+                span: member.key_span,
+                other_span: required.key_span,
+            });
+        }
+    }
+
+    for member in require.iter().chain(members(root, "require-dev")) {
+        // Platform packages can't be replaced or installed in another version.
+        if packagist::is_platform_package(&member.key) {
+            continue;
+        }
+        let locked = lock
+            .and_then(|lock| lock.versions.get(&member.key.to_lowercase()))
+            .map(|installed| installed.version.as_str());
+        let required = member.value.as_str();
+        // Whether the other entry's constraint covers what the requirement
+        // resolves to.
+        let overlaps = |other: &str| match (locked, required) {
+            (Some(locked), _) => constraint::matches(other, locked).unwrap_or(false),
+            (None, Some(required)) => constraint::intersects(other, required).unwrap_or(false),
+            (None, None) => false,
+        };
+
+        let find = |block: &str| {
+            members(root, block)
+                .iter()
+                .find(|other| other.key.eq_ignore_ascii_case(&member.key))
+        };
+
+        if let Some(replaced) = find("replace") {
+            // Replacing every version, or the project's own, covers whatever
+            // the requirement resolves to.
+            let replaces = match replaced.value.as_str() {
+                Some("*" | "self.version") => true,
+                Some(replaced) => overlaps(replaced),
+                None => false,
+            };
+            if replaces {
+                duplicates.push(Duplicate {
+                    kind: DuplicateKind::Replaced,
+                    package: member.key.clone(),
+                    span: member.key_span,
+                    other_span: replaced.key_span,
+                });
+            }
+        }
+
+        if let Some(conflict) = find("conflict") {
+            if conflict.value.as_str().is_some_and(overlaps) {
+                duplicates.push(Duplicate {
+                    kind: DuplicateKind::Conflicting,
+                    package: member.key.clone(),
+                    span: member.key_span,
+                    other_span: conflict.key_span,
+                });
+            }
+        }
+    }
+
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::composer::ComposerLockFile;
+    use crate::duplicates::{find_duplicates, DuplicateKind};
+    use crate::json;
+
+    #[test]
+    fn it_can_find_repeated_requirements() {
+        let text = r#"{
+            "require": { "psr/log": "^1.0", "php": "^8.1", "Psr/Log": "^3.0" },
+            "require-dev": { "psr/log": "^3.0", "phpunit/phpunit": "^10.0" }
+        }"#;
+        let duplicates = find_duplicates(&json::parse(text).unwrap(), None);
+
+        assert_eq!(2, duplicates.len());
+        assert_eq!(DuplicateKind::Repeated, duplicates[0].kind);
+        assert_eq!(
+            "\"Psr/Log\"",
+            &text[duplicates[0].span.start..duplicates[0].span.end]
+        );
+        assert_eq!(
+            "\"psr/log\"",
+            &text[duplicates[0].other_span.start..duplicates[0].other_span.end]
+        );
+        assert_eq!(DuplicateKind::RequiredAndDev, duplicates[1].kind);
+        assert!(duplicates[1].span.start > duplicates[1].other_span.start);
+    }
+
+    #[test]
+    fn it_can_find_replaced_and_conflicting_requirements() {
+        let text = r#"{
+            "require": { "acme/a": "^1.0", "acme/b": "^2.0", "acme/c": "^3.0" },
+            "replace": { "acme/a": "self.version" },
+            "conflict": { "acme/b": "<2.1", "acme/c": "<3.1" }
+        }"#;
+        let lock = ComposerLockFile::parse(
+            r#"{ "packages": [
+                { "name": "acme/b", "version": "2.0.4" },
+                { "name": "acme/c", "version": "3.2.0" }
+            ] }"#,
+        )
+        .unwrap();
+        let duplicates = find_duplicates(&json::parse(text).unwrap(), Some(&lock));
+
+        assert_eq!(2, duplicates.len());
+        assert_eq!(DuplicateKind::Replaced, duplicates[0].kind);
+        assert_eq!("acme/a", duplicates[0].package);
+        assert_eq!(DuplicateKind::Conflicting, duplicates[1].kind);
+        assert_eq!("acme/b", duplicates[1].package);
+    }
+
+    #[test]
+    fn it_can_intersect_the_constraints_without_a_lock_file() {
+        let text = r#"{
+            "require": { "acme/a": "^1.0", "acme/b": "^2.0", "acme/c": "^3.0" },
+            "replace": { "acme/a": "1.*", "acme/c": "^4.0" },
+            "conflict": { "acme/b": "<2.1", "acme/c": "<3.0" }
+        }"#;
+        let duplicates = find_duplicates(&json::parse(text).unwrap(), None);
+
+        assert_eq!(2, duplicates.len());
+        assert_eq!(DuplicateKind::Replaced, duplicates[0].kind);
+        assert_eq!("acme/a", duplicates[0].package);
+        assert_eq!(DuplicateKind::Conflicting, duplicates[1].kind);
+        assert_eq!("acme/b", duplicates[1].package);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::json::{self, Member, NodeKind, Span};
+use crate::packagist;
+
+/// A replacement of the text in the span.
+pub type Edit = (Span, String);
+
+/// The formatting conventions of a composer.json, so generated edits look
+/// like the rest of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    /// One level of indentation, e.g. four spaces or a tab.
+    pub indent: String,
+    pub newline: &'static str,
+    /// Whether the objects end with a trailing comma.
+    pub trailing_commas: bool,
+    /// Whether "config.sort-packages" keeps the requirements sorted.
+    pub sort_packages: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            indent: "    ".to_string(),
+            newline: "\n",
+            trailing_commas: false,
+            sort_packages: false,
+        }
+    }
+}
+
+impl Style {
+    /// Detects the style of the text, falling back to composer's own style.
+    pub fn detect(text: &str) -> Style {
+        let (root, errors) = match json::parse_tolerant(text) {
+            Ok(parsed) => parsed,
+            Err(_) => return Style::default(),
+        };
+
+        // The top level members are indented by one level.
+        let indent = root
+            .as_object()
+            .and_then(|members| members.first())
+            .map(|first| indentation(text, first.key_span.start))
+            .filter(|indent| !indent.is_empty())
+            .unwrap_or("    ");
+
+        let sort_packages = root
+            .get("config")
+            .and_then(|config| config.value.get("sort-packages"))
+            .map(|sort_packages| sort_packages.value.kind == NodeKind::Bool(true))
+            .unwrap_or(false);
+
+        Style {
+            indent: indent.to_string(),
+            newline: match text.contains("\r\n") {
+                true => "\r\n",
+                false => "\n",
+            },
+            trailing_commas: errors
+                .iter()
+                .any(|error| error.message == json::TRAILING_COMMA),
+            sort_packages,
+        }
+    }
+}
+
+/// The order composer sorts requirements in: the platform first, starting
+/// with php, then the packages by name.
+fn package_order(name: &str) -> String {
+    let name = name.to_lowercase();
+    if !packagist::is_platform_package(&name) {
+        return format!("5-{}", name);
+    }
+
+    let prefix = match &name {
+        name if name.starts_with("php") => "0",
+        name if name.starts_with("hhvm") => "1",
+        name if name.starts_with("ext") => "2",
+        name if name.starts_with("lib") => "3",
+        _ => "4",
+    };
+    format!("{}-{}", prefix, name)
+}
+
+/// The edit adding a member to the object block, following the layout of
+/// its members. Sorted blocks keep their order, others get the member at
+/// the top.
+pub fn insert_member(
+    text: &str,
+    style: &Style,
+    block: &Member,
+    key: &str,
+    value: &str,
+) -> Option<Edit> {
+    let members = block.value.as_object()?;
+    let open = block.value.span.start + 1;
+
+    let first = match members.first() {
+        Some(first) => first,
+        None => {
+            // Rewrite the empty block, so it doesn't keep stray whitespace.
+            let indent = indentation(text, block.key_span.start);
+            return Some((
+                block.value.span,
+                format!(
+                    "{{{nl}{}{}\"{}\": {}{}{nl}{}}}",
+                    indent,
+                    style.indent,
+                    key,
+                    value,
+                    trailing_comma(style),
+                    indent,
+                    nl = style.newline
+                ),
+            ));
+        }
+    };
+    let multiline = text[open..first.key_span.start].contains('\n');
+
+    let is_sorted = members.len() > 1
+        && members
+            .windows(2)
+            .all(|pair| package_order(&pair[0].key) <= package_order(&pair[1].key));
+    let previous = match style.sort_packages || is_sorted {
+        true => members
+            .iter()
+            .take_while(|member| package_order(&member.key) <= package_order(key))
+            .last(),
+        false => None,
+    };
+
+    if let Some(previous) = previous {
+        let insertion = match multiline {
+            true => format!(
+                ",{}{}\"{}\": {}",
+                style.newline,
+                indentation(text, previous.key_span.start),
+                key,
+                value
+            ),
+            false => format!(", \"{}\": {}", key, value),
+        };
+
+        return Some((Span::new(previous.span.end, previous.span.end), insertion));
+    }
+
+    let insertion = match multiline {
+        true => format!(
+            "{}{}\"{}\": {},",
+            style.newline,
+            indentation(text, first.key_span.start),
+            key,
+            value
+        ),
+        false => format!(" \"{}\": {},", key, value),
+    };
+
+    Some((Span::new(open, open), insertion))
+}
+
+fn trailing_comma(style: &Style) -> &'static str {
+    match style.trailing_commas {
+        true => ",",
+        false => "",
+    }
+}
+
+/// The edit removing a member from the object block, along with its comma.
+pub fn remove_member(block: &Member, key: &str) -> Option<Edit> {
+    let members = block.value.as_object()?;
+    let position = members.iter().position(|member| member.key == key)?;
+
+    let span = match (position.checked_sub(1), members.get(position + 1)) {
+        // Remove from the end of the previous member, to keep its line intact.
+        (Some(previous), _) => Span::new(members[previous].span.end, members[position].span.end),
+        (None, Some(next)) => Span::new(members[position].span.start, next.span.start),
+        (None, None) => {
+            return Some((block.value.span, "{}".to_string()));
+        }
+    };
+
+    Some((span, "".to_string()))
+}
+
+/// The edit adding a new object block with a single member right after the
+/// given member.
+pub fn insert_block_after(
+    text: &str,
+    style: &Style,
+    after: &Member,
+    block: &str,
+    key: &str,
+    value: &str,
+) -> Edit {
+    let indent = indentation(text, after.key_span.start);
+
+    (
+        Span::new(after.span.end, after.span.end),
+        format!(
+            ",{nl}{}\"{}\": {{{nl}{}{}\"{}\": {}{}{nl}{}}}",
+            indent,
+            block,
+            indent,
+            style.indent,
+            key,
+            value,
+            trailing_comma(style),
+            indent,
+            nl = style.newline
+        ),
+    )
+}
+
+/// The leading whitespace of the line the byte offset is on.
+pub fn indentation(text: &str, offset: usize) -> &str {
+    let line_start = text[..offset]
+        .rfind('\n')
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let line = &text[line_start..];
+
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Applies non-overlapping edits to the text.
+#[cfg(test)]
+pub fn apply(text: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|(span, _)| span.start);
+
+    let mut result = text.to_string();
+    for (span, replacement) in edits.iter().rev() {
+        result.replace_range(span.start..span.end, replacement);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edit::{apply, insert_block_after, insert_member, remove_member, Style};
+    use crate::json;
+
+    #[test]
+    fn it_can_detect_the_style() {
+        let text = "{\r\n\t\"require\": {\r\n\t\t\"a/a\": \"^1.0\",\r\n\t},\r\n\t\"config\": { \"sort-packages\": true }\r\n}";
+
+        assert_eq!(
+            Style {
+                indent: "\t".to_string(),
+                newline: "\r\n",
+                trailing_commas: true,
+                sort_packages: true,
+            },
+            Style::detect(text)
+        );
+        assert_eq!(Style::default(), Style::detect("{ \"require\": {} }"));
+    }
+
+    #[test]
+    fn it_can_insert_a_member_in_a_sorted_block() {
+        let text = "{\n  \"require\": {\n    \"php\": \"^8.1\",\n    \"ext-json\": \"*\",\n    \"psr/log\": \"^3.0\"\n  }\n}";
+        let root = json::parse(text).unwrap();
+        let style = Style::detect(text);
+        let edit = insert_member(
+            text,
+            &style,
+            root.get("require").unwrap(),
+            "monolog/monolog",
+            "\"^3.0\"",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "{\n  \"require\": {\n    \"php\": \"^8.1\",\n    \"ext-json\": \"*\",\n    \"monolog/monolog\": \"^3.0\",\n    \"psr/log\": \"^3.0\"\n  }\n}",
+            apply(text, vec![edit])
+        );
+    }
+
+    #[test]
+    fn it_can_insert_a_member_into_an_empty_block() {
+        let text = "{\n\t\"require\": {},\n}";
+        let root = json::parse_tolerant(text).unwrap().0;
+        let style = Style::detect(text);
+        let edit = insert_member(
+            text,
+            &style,
+            root.get("require").unwrap(),
+            "a/a",
+            "\"^1.0\"",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "{\n\t\"require\": {\n\t\t\"a/a\": \"^1.0\",\n\t},\n}",
+            apply(text, vec![edit])
+        );
+    }
+
+    #[test]
+    fn it_can_prepend_a_member_inline() {
+        let text = "{ \"require\": { \"psr/log\": \"^3.0\" } }";
+        let root = json::parse(text).unwrap();
+        let edit = insert_member(
+            text,
+            &Style::default(),
+            root.get("require").unwrap(),
+            "php",
+            "\"^8.1\"",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "{ \"require\": { \"php\": \"^8.1\", \"psr/log\": \"^3.0\" } }",
+            apply(text, vec![edit])
+        );
+    }
+
+    #[test]
+    fn it_can_remove_members() {
+        let text = "{\n    \"require\": {\n        \"a/a\": \"^1.0\",\n        \"b/b\": \"^1.0\"\n    }\n}";
+        let root = json::parse(text).unwrap();
+        let require = root.get("require").unwrap();
+
+        assert_eq!(
+            "{\n    \"require\": {\n        \"a/a\": \"^1.0\"\n    }\n}",
+            apply(text, vec![remove_member(require, "b/b").unwrap()])
+        );
+        assert_eq!(
+            "{\n    \"require\": {\n        \"b/b\": \"^1.0\"\n    }\n}",
+            apply(text, vec![remove_member(require, "a/a").unwrap()])
+        );
+    }
+
+    #[test]
+    fn it_can_insert_a_block() {
+        let text = "{\n    \"require\": {}\n}";
+        let root = json::parse(text).unwrap();
+        let edit = insert_block_after(
+            text,
+            &Style::default(),
+            root.get("require").unwrap(),
+            "require-dev",
+            "a/a",
+            "\"^1.0\"",
+        );
+
+        assert_eq!(
+            "{\n    \"require\": {},\n    \"require-dev\": {\n        \"a/a\": \"^1.0\"\n    }\n}",
+            apply(text, vec![edit])
+        );
+    }
+}
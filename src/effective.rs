@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The configuration composer uses for a project, merged from composer.json,
+/// the global config and the environment, as `composer config --list` shows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EffectiveConfig {
+    pub vendor_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    /// The platform overrides, e.g. "php" => "8.1.0".
+    pub platform: HashMap<String, String>,
+    /// The URLs of the repositories, including the global ones.
+    pub repositories: Vec<String>,
+}
+
+impl EffectiveConfig {
+    /// The repositories besides packagist.org.
+    pub fn custom_repositories(&self) -> Vec<&str> {
+        self.repositories
+            .iter()
+            .map(|url| url.as_str())
+            .filter(|url| !url.contains("packagist.org"))
+            .collect()
+    }
+}
+
+/// Parses the output of `composer config --list`, lines like
+/// "[vendor-dir] vendor (/project/vendor)" or "[platform.php] 8.1.0".
+/// Relative directories are resolved against the project directory.
+pub fn parse(output: &str, dir: &Path) -> EffectiveConfig {
+    let mut config = EffectiveConfig::default();
+
+    for line in output.lines() {
+        let (key, value) = match line
+            .trim()
+            .strip_prefix('[')
+            .and_then(|line| line.split_once("] "))
+        {
+            Some((key, value)) => (key, value.trim()),
+            None => continue,
+        };
+
+        match key {
+            "vendor-dir" => config.vendor_dir = Some(resolved_path(value, dir)),
+            "cache-dir" => config.cache_dir = Some(resolved_path(value, dir)),
+            _ => {
+                if let Some(package) = key.strip_prefix("platform.") {
+                    // "false" hides a platform package instead of overriding it.
+                    if value != "false" {
+                        config
+                            .platform
+                            .insert(package.to_string(), value.to_string());
+                    }
+                } else if key.starts_with("repositories.") && key.ends_with(".url") {
+                    config.repositories.push(value.to_string());
+                }
+            }
+        }
+    }
+
+    config
+}
+
+/// Composer lists a directory as written, followed by the resolved path in
+/// parentheses when they differ.
+fn resolved_path(value: &str, dir: &Path) -> PathBuf {
+    let path = match value.split_once(" (") {
+        Some((_, resolved)) if resolved.ends_with(')') => &resolved[..resolved.len() - 1],
+        _ => value,
+    };
+
+    dir.join(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use crate::effective::parse;
+
+    #[test]
+    fn it_can_parse_the_config_list() {
+        let output = "[repositories.0.type] composer
+[repositories.0.url] https://repo.acme.com
+[repositories.packagist.org.type] composer
+[repositories.packagist.org.url] https://repo.packagist.org
+[process-timeout] 300
+[vendor-dir] lib/vendor (/project/lib/vendor)
+[cache-dir] /home/acme/.cache/composer
+[platform.php] 8.1.0
+[platform.ext-redis] false
+";
+        let config = parse(output, Path::new("/project"));
+
+        assert_eq!(
+            Some(PathBuf::from("/project/lib/vendor")),
+            config.vendor_dir
+        );
+        assert_eq!(
+            Some(PathBuf::from("/home/acme/.cache/composer")),
+            config.cache_dir
+        );
+        assert_eq!(
+            HashMap::from([("php".to_string(), "8.1.0".to_string())]),
+            config.platform
+        );
+        assert_eq!(vec!["https://repo.acme.com"], config.custom_repositories());
+    }
+
+    #[test]
+    fn it_can_resolve_relative_directories() {
+        let config = parse("[vendor-dir] vendor\n", Path::new("/project"));
+
+        assert_eq!(Some(PathBuf::from("/project/vendor")), config.vendor_dir);
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+
+use crate::audit::Advisory;
+use crate::composer::{ComposerDependency, ComposerLockFile};
+use crate::packagist::Package;
+
+/// The analysis of every composer.json in the workspace, returned by the
+/// "composer/analysisExport" request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisExport {
+    pub files: Vec<FileAnalysis>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAnalysis {
+    pub uri: String,
+    pub packages: Vec<PackageAnalysis>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageAnalysis {
+    pub name: String,
+    pub dev: bool,
+    pub constraint: String,
+    pub installed: Option<String>,
+    pub latest: Option<String>,
+    pub abandoned: bool,
+    pub replacement: Option<String>,
+    pub license: Vec<String>,
+    pub advisories: Vec<AdvisoryExport>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdvisoryExport {
+    pub title: String,
+    pub cve: Option<String>,
+    pub link: Option<String>,
+}
+
+/// Combines what is known about a requirement: the locked version, the
+/// packagist data and the advisories of `composer audit`.
+pub fn package_analysis(
+    dependency: &ComposerDependency,
+    dev: bool,
+    lock: Option<&ComposerLockFile>,
+    package: Option<&Package>,
+    advisories: &[Advisory],
+) -> PackageAnalysis {
+    let name = dependency.name.to_lowercase();
+    let installed = lock
+        .and_then(|lock| lock.versions.get(&name))
+        .map(|installed| installed.version.clone());
+
+    // The license of the installed version, else of the latest one.
+    let versions = package
+        .map(|package| package.versions.as_slice())
+        .unwrap_or_default();
+    let installed_version = versions.iter().find(|version| {
+        version
+            .version
+            .as_deref()
+            .map(|version| version.trim_start_matches('v'))
+            == installed.as_deref()
+    });
+    let license = installed_version
+        .or_else(|| versions.first())
+        .and_then(|version| version.license.clone())
+        .unwrap_or_default();
+
+    PackageAnalysis {
+        name: dependency.name.clone(),
+        dev,
+        constraint: dependency.version.clone(),
+        installed,
+        latest: package.and_then(|package| package.latest_stable_version()),
+        abandoned: package
+            .map(|package| package.is_abandoned())
+            .unwrap_or(false),
+        replacement: package.and_then(|package| package.replacement()),
+        license,
+        advisories: advisories
+            .iter()
+            .filter(|advisory| advisory.package.eq_ignore_ascii_case(&dependency.name))
+            .map(|advisory| AdvisoryExport {
+                title: advisory.title.clone(),
+                cve: advisory.cve.clone(),
+                link: advisory.link.clone(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Url;
+
+    use crate::audit::Advisory;
+    use crate::composer::ComposerFile;
+    use crate::export::package_analysis;
+    use crate::packagist::{Abandoned, Package, PackageVersion};
+
+    #[test]
+    fn it_can_combine_the_analysis_of_a_package() {
+        let text = "{\n    \"require\": {\n        \"acme/lib\": \"^1.0\"\n    }\n}\n";
+        let composer_file =
+            ComposerFile::parse_from_str(text, Url::parse("file:///app/composer.json").unwrap())
+                .unwrap();
+        let version = |version: &str, license: &str| PackageVersion {
+            version: Some(version.to_string()),
+            license: Some(vec![license.to_string()]),
+            ..PackageVersion::default()
+        };
+        let mut latest = version("2.0.0", "Apache-2.0");
+        latest.abandoned = Some(Abandoned::Replacement("acme/new".to_string()));
+        let package = Package::new(
+            "acme/lib".to_string(),
+            vec![latest, version("1.0.0", "MIT")],
+        );
+        let advisories = vec![Advisory {
+            package: "acme/lib".to_string(),
+            title: "Remote code execution".to_string(),
+            cve: Some("CVE-2024-0001".to_string()),
+            link: None,
+        }];
+
+        let analysis = package_analysis(
+            &composer_file.dependencies[0],
+            false,
+            None,
+            Some(&package),
+            &advisories,
+        );
+
+        assert_eq!("^1.0", analysis.constraint);
+        assert_eq!(None, analysis.installed);
+        assert_eq!(Some("2.0.0".to_string()), analysis.latest);
+        assert!(analysis.abandoned);
+        assert_eq!(Some("acme/new".to_string()), analysis.replacement);
+        assert_eq!(vec!["Apache-2.0".to_string()], analysis.license);
+        assert_eq!(1, analysis.advisories.len());
+    }
+}
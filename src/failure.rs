@@ -0,0 +1,107 @@
+/// A problem reported by a failed composer command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// The requirement the problem is about, when it can be identified, e.g.
+    /// "monolog/monolog" or "php".
+    pub package: Option<String>,
+    pub message: String,
+}
+
+/// Extracts the problems from the error output of composer update/install.
+pub fn parse_failures(output: &str) -> Vec<Failure> {
+    let mut failures = vec![];
+
+    if output.contains("Allowed memory size of") {
+        failures.push(Failure {
+            package: None,
+            message: "Composer ran out of memory. Raise the memory_limit or run it with COMPOSER_MEMORY_LIMIT=-1.".to_string(),
+        });
+    }
+
+    // Every "Problem N" block starts with the requirement that couldn't be
+    // resolved, followed by the explanation of the conflict.
+    let mut lines = output.lines().peekable();
+    while let Some(line) = lines.next() {
+        if !line.trim_start().starts_with("Problem ") {
+            continue;
+        }
+
+        let mut explanation = vec![];
+        while let Some(next) = lines.peek() {
+            let next = next.trim();
+            if !next.starts_with("- ") {
+                break;
+            }
+
+            explanation.push(next.trim_start_matches("- ").to_string());
+            lines.next();
+        }
+
+        let first = match explanation.first() {
+            Some(first) => first,
+            None => continue,
+        };
+
+        failures.push(Failure {
+            package: problem_package(first),
+            message: explanation.join("\n"),
+        });
+    }
+
+    failures
+}
+
+/// The requirement a problem line is about, e.g. "monolog/monolog" for
+/// "Root composer.json requires monolog/monolog ^9.0, found ...".
+fn problem_package(line: &str) -> Option<String> {
+    if let Some(rest) = line.strip_prefix("Root composer.json requires ") {
+        return rest
+            .split_whitespace()
+            .next()
+            .map(|name| name.trim_end_matches(',').to_string());
+    }
+
+    // "acme/lib 1.0.0 requires php >=8.3 -> your php version ..."
+    let name = line.split_whitespace().next()?;
+    match name.contains('/') {
+        true => Some(name.to_string()),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::failure::{parse_failures, Failure};
+
+    #[test]
+    fn it_can_parse_conflicts() {
+        let output = "Loading composer repositories with package information
+Your requirements could not be resolved to an installable set of packages.
+
+  Problem 1
+    - Root composer.json requires monolog/monolog ^9.0, found monolog/monolog[1.0.0, ..., 3.5.0] but it does not match the constraint.
+  Problem 2
+    - acme/lib 1.0.0 requires php >=8.3 -> your php version (8.1.2) does not satisfy that requirement.
+    - Root composer.json requires acme/lib ^1.0 -> satisfiable by acme/lib[1.0.0].
+";
+        let failures = parse_failures(output);
+
+        assert_eq!(2, failures.len());
+        assert_eq!(Some("monolog/monolog".to_string()), failures[0].package);
+        assert_eq!(Some("acme/lib".to_string()), failures[1].package);
+        assert_eq!(2, failures[1].message.lines().count());
+    }
+
+    #[test]
+    fn it_can_detect_memory_errors() {
+        let output = "PHP Fatal error:  Allowed memory size of 1610612736 bytes exhausted";
+
+        assert_eq!(
+            vec![Failure {
+                package: None,
+                message: "Composer ran out of memory. Raise the memory_limit or run it with COMPOSER_MEMORY_LIMIT=-1.".to_string(),
+            }],
+            parse_failures(output)
+        );
+    }
+}
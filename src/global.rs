@@ -0,0 +1,157 @@
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The global composer directory, like composer resolves it: COMPOSER_HOME,
+/// else ~/.composer when it exists, else the XDG config directory.
+pub fn home_dir() -> Option<PathBuf> {
+    resolve_home(
+        env::var_os("COMPOSER_HOME").map(PathBuf::from),
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+fn resolve_home(
+    composer_home: Option<PathBuf>,
+    xdg_config_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    if let Some(composer_home) = composer_home.filter(|dir| !dir.as_os_str().is_empty()) {
+        return Some(composer_home);
+    }
+
+    let home = home?;
+    let legacy = home.join(".composer");
+    if legacy.is_dir() {
+        return Some(legacy);
+    }
+
+    let config = xdg_config_home.unwrap_or_else(|| home.join(".config"));
+    Some(config.join("composer"))
+}
+
+/// The global composer.json.
+pub fn manifest_path() -> Option<PathBuf> {
+    home_dir().map(|dir| dir.join("composer.json"))
+}
+
+/// The global config.json, with what was set up with `composer config
+/// --global`.
+pub fn config() -> Option<Value> {
+    let text = fs::read_to_string(home_dir()?.join("config.json")).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// The packagist.org mirror of the global config, set up with e.g.
+/// `composer config --global repos.packagist composer https://mirror`.
+pub fn packagist_mirror() -> Option<String> {
+    find_packagist_mirror(&config()?)
+}
+
+fn find_packagist_mirror(config: &Value) -> Option<String> {
+    let repositories = config.get("repositories")?;
+    let named = |name: &str| {
+        repositories.get(name).or_else(|| {
+            repositories
+                .as_array()?
+                .iter()
+                .find_map(|repository| repository.get(name))
+        })
+    };
+
+    ["packagist.org", "packagist"]
+        .into_iter()
+        .filter_map(named)
+        .find_map(|repository| repository.get("url")?.as_str())
+        .map(|url| url.to_string())
+}
+
+/// Whether the composer.json is the global manifest.
+pub fn is_global_manifest(path: &Path) -> bool {
+    match manifest_path() {
+        Some(manifest) => manifest == path,
+        None => false,
+    }
+}
+
+/// The arguments of a composer command, run with `composer global` for the
+/// global manifest.
+pub fn command_args<'a>(global: bool, args: &[&'a str]) -> Vec<&'a str> {
+    let mut command_args = vec![];
+    if global {
+        command_args.push("global");
+    }
+    command_args.extend_from_slice(args);
+
+    command_args
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use crate::global::{command_args, find_packagist_mirror, resolve_home};
+
+    #[test]
+    fn it_can_resolve_the_composer_home() {
+        assert_eq!(
+            Some(PathBuf::from("/opt/composer")),
+            resolve_home(
+                Some(PathBuf::from("/opt/composer")),
+                None,
+                Some(PathBuf::from("/home/acme"))
+            )
+        );
+        assert_eq!(
+            Some(PathBuf::from("/home/acme/.config/composer")),
+            resolve_home(None, None, Some(PathBuf::from("/home/acme")))
+        );
+        assert_eq!(
+            Some(PathBuf::from("/xdg/composer")),
+            resolve_home(
+                None,
+                Some(PathBuf::from("/xdg")),
+                Some(PathBuf::from("/home/acme"))
+            )
+        );
+    }
+
+    #[test]
+    fn it_can_prefix_global_commands() {
+        assert_eq!(
+            vec!["global", "update", "psr/log"],
+            command_args(true, &["update", "psr/log"])
+        );
+        assert_eq!(vec!["install"], command_args(false, &["install"]));
+    }
+
+    #[test]
+    fn it_can_find_the_packagist_mirror() {
+        let mirror = Some("https://mirror.acme.com".to_string());
+
+        assert_eq!(
+            mirror,
+            find_packagist_mirror(&json!({
+                "repositories": {
+                    "packagist.org": { "type": "composer", "url": "https://mirror.acme.com" }
+                }
+            }))
+        );
+        assert_eq!(
+            mirror,
+            find_packagist_mirror(&json!({
+                "repositories": [
+                    { "packagist": { "type": "composer", "url": "https://mirror.acme.com" } }
+                ]
+            }))
+        );
+        assert_eq!(
+            None,
+            find_packagist_mirror(&json!({ "repositories": { "packagist.org": false } }))
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::header::{HeaderValue, RETRY_AFTER};
+use reqwest::{Certificate, Client, Proxy, RequestBuilder, Response, StatusCode, Url};
+
+use crate::global;
+
+/// How often a request is sent before a 429 or 5xx response is returned.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry, doubled for every further one.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The longest delay, even when the server asks to wait longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// How requests get out of a corporate network: its proxies, and the
+/// certificate authority of a proxy inspecting TLS.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkConfig {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    /// Hosts and domains reached without the proxy, or "*" for all.
+    pub no_proxy: Vec<String>,
+    /// Extra certificate authorities in PEM, composer's "cafile" config.
+    pub cafile: Option<PathBuf>,
+}
+
+impl NetworkConfig {
+    /// Reads the proxies from HTTP_PROXY, HTTPS_PROXY and NO_PROXY, or their
+    /// lowercase forms, and the cafile from the global composer config.
+    pub fn from_env() -> NetworkConfig {
+        let var = |name: &str| {
+            env::var(name.to_lowercase())
+                .or_else(|_| env::var(name))
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+
+        NetworkConfig {
+            http_proxy: var("HTTP_PROXY"),
+            https_proxy: var("HTTPS_PROXY"),
+            no_proxy: var("NO_PROXY")
+                .map(|hosts| {
+                    hosts
+                        .split(',')
+                        .map(|host| host.trim().trim_start_matches('.').to_lowercase())
+                        .filter(|host| !host.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            cafile: global::config().and_then(|config| {
+                config
+                    .get("config")?
+                    .get("cafile")?
+                    .as_str()
+                    .map(PathBuf::from)
+            }),
+        }
+    }
+
+    /// The proxy the URL is fetched through, if any.
+    fn proxy_for(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?.to_lowercase();
+        let bypassed = self.no_proxy.iter().any(|no_proxy| {
+            no_proxy == "*" || host == *no_proxy || host.ends_with(&format!(".{}", no_proxy))
+        });
+        if bypassed {
+            return None;
+        }
+
+        let proxy = match url.scheme() {
+            "https" => self.https_proxy.as_ref().or(self.http_proxy.as_ref()),
+            _ => self.http_proxy.as_ref(),
+        }?;
+        // Proxies are often given as host and port only.
+        match proxy.contains("://") {
+            true => Url::parse(proxy).ok(),
+            false => Url::parse(&format!("http://{}", proxy)).ok(),
+        }
+    }
+}
+
+/// The client shared by all requests, so connections are reused.
+pub fn client() -> Client {
+    CLIENT
+        .get_or_init(|| build_client(NetworkConfig::from_env()))
+        .clone()
+}
+
+fn build_client(config: NetworkConfig) -> Client {
+    let mut builder = Client::builder();
+    if let Some(cafile) = &config.cafile {
+        match fs::read(cafile)
+            .ok()
+            .and_then(|pem| Certificate::from_pem(&pem).ok())
+        {
+            Some(certificate) => builder = builder.add_root_certificate(certificate),
+            None => log::warn!("Can't read the certificates of {}", cafile.display()),
+        }
+    }
+    // A custom proxy replaces the one reqwest reads from the environment,
+    // which doesn't know NO_PROXY.
+    builder = builder.proxy(Proxy::custom(move |url| config.proxy_for(url)));
+
+    builder.build().unwrap_or_else(|error| {
+        log::warn!("Can't configure the HTTP client: {}", error);
+        Client::new()
+    })
+}
+
+/// Whether the server is rate limiting or failing, so a retry can succeed.
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The delay before the retry after the attempt, from the Retry-After
+/// seconds of the response or else doubling with every attempt.
+fn backoff(attempt: u32, retry_after: Option<&HeaderValue>) -> Duration {
+    retry_after
+        .and_then(|retry_after| retry_after.to_str().ok())
+        .and_then(|seconds| seconds.trim().parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(INITIAL_BACKOFF * 2u32.pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// Sends the request, retrying with a backoff on 429 and 5xx responses.
+/// The response of the last attempt is returned, whatever its status.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let mut attempt = 0;
+    loop {
+        let retry = match request.try_clone() {
+            Some(retry) if attempt + 1 < MAX_ATTEMPTS => retry,
+            _ => return request.send().await,
+        };
+
+        let response = retry.send().await?;
+        if !is_retryable(response.status()) {
+            return Ok(response);
+        }
+
+        let delay = backoff(attempt, response.headers().get(RETRY_AFTER));
+        log::info!(
+            "{} responded with {}, retrying in {:?}",
+            response.url(),
+            response.status(),
+            delay
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::header::HeaderValue;
+    use reqwest::{StatusCode, Url};
+
+    use crate::http::{backoff, is_retryable, NetworkConfig};
+
+    #[test]
+    fn it_can_retry_rate_limited_and_failed_requests() {
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+        assert!(!is_retryable(StatusCode::NOT_MODIFIED));
+    }
+
+    #[test]
+    fn it_can_compute_the_backoff() {
+        assert_eq!(Duration::from_millis(500), backoff(0, None));
+        assert_eq!(Duration::from_secs(2), backoff(2, None));
+        assert_eq!(
+            Duration::from_secs(3),
+            backoff(0, Some(&HeaderValue::from_static("3")))
+        );
+        assert_eq!(
+            Duration::from_secs(10),
+            backoff(0, Some(&HeaderValue::from_static("120")))
+        );
+        // Dates aren't supported, the usual backoff applies.
+        assert_eq!(
+            Duration::from_secs(1),
+            backoff(
+                1,
+                Some(&HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"))
+            )
+        );
+    }
+
+    #[test]
+    fn it_can_choose_the_proxy() {
+        let config = NetworkConfig {
+            http_proxy: Some("proxy.acme.com:3128".to_string()),
+            https_proxy: Some("http://secure-proxy.acme.com:3128".to_string()),
+            no_proxy: vec!["acme.com".to_string(), "localhost".to_string()],
+            cafile: None,
+        };
+        let proxy_for = |url: &str| {
+            config
+                .proxy_for(&Url::parse(url).unwrap())
+                .map(|proxy| proxy.to_string())
+        };
+
+        assert_eq!(
+            Some("http://secure-proxy.acme.com:3128/".to_string()),
+            proxy_for("https://repo.packagist.org/p2/psr/log.json")
+        );
+        assert_eq!(
+            Some("http://proxy.acme.com:3128/".to_string()),
+            proxy_for("http://packagist.example.com/packages.json")
+        );
+        assert_eq!(None, proxy_for("https://satis.acme.com/packages.json"));
+        assert_eq!(None, proxy_for("http://localhost:8080/packages.json"));
+        assert_eq!(
+            None,
+            NetworkConfig::default().proxy_for(&Url::parse("https://packagist.org").unwrap())
+        );
+    }
+}
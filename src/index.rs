@@ -0,0 +1,234 @@
+/// The shortest query fuzzy matching is done for.
+const MIN_FUZZY_QUERY: usize = 3;
+
+/// The names of all packagist packages, used for completion. The names are
+/// sorted and stored back to back in a single buffer, which takes a fraction
+/// of the memory of a string per name and allows prefix lookups by binary
+/// search. The positions of the names sorted by their package part allow the
+/// same lookups without the vendor.
+#[derive(Debug, Default)]
+pub struct PackageIndex {
+    names: String,
+    ends: Vec<u32>,
+    by_package_part: Vec<u32>,
+}
+
+impl PackageIndex {
+    pub fn new(mut names: Vec<String>) -> PackageIndex {
+        names.sort_unstable();
+        names.dedup();
+
+        let mut index = PackageIndex {
+            names: String::with_capacity(names.iter().map(|name| name.len()).sum()),
+            ends: Vec::with_capacity(names.len()),
+            by_package_part: vec![],
+        };
+        for name in names {
+            index.names.push_str(&name);
+            index.ends.push(index.names.len() as u32);
+        }
+
+        let mut by_package_part: Vec<u32> = (0..index.len())
+            .filter(|position| index.get(*position).contains('/'))
+            .map(|position| position as u32)
+            .collect();
+        by_package_part.sort_by_cached_key(|position| package_part(index.get(*position as usize)));
+        index.by_package_part = by_package_part;
+
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// The heap memory used by the index, in bytes.
+    pub fn memory_bytes(&self) -> usize {
+        self.names.capacity()
+            + (self.ends.capacity() + self.by_package_part.capacity()) * std::mem::size_of::<u32>()
+    }
+
+    fn get(&self, position: usize) -> &str {
+        let start = match position {
+            0 => 0,
+            _ => self.ends[position - 1] as usize,
+        };
+
+        &self.names[start..self.ends[position] as usize]
+    }
+
+    /// The position of the first name that isn't smaller than the prefix.
+    fn lower_bound(&self, prefix: &str) -> usize {
+        let mut low = 0;
+        let mut high = self.len();
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.get(middle) < prefix {
+                true => low = middle + 1,
+                false => high = middle,
+            }
+        }
+
+        low
+    }
+
+    /// All names starting with the prefix, in alphabetical order.
+    pub fn starting_with<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        (self.lower_bound(prefix)..self.len())
+            .map(move |position| self.get(position))
+            .take_while(move |name| name.starts_with(prefix))
+    }
+
+    /// Names whose package part, after the vendor, starts with the prefix,
+    /// e.g. "symfony/http-kernel" for "http-k", in the order of their package
+    /// part.
+    pub fn package_part_starting_with(&self, prefix: &str, limit: usize) -> Vec<&str> {
+        let part = |index: usize| package_part(self.get(self.by_package_part[index] as usize));
+
+        // Binary search for the first package part that isn't smaller than
+        // the prefix.
+        let mut low = 0;
+        let mut high = self.by_package_part.len();
+        while low < high {
+            let middle = (low + high) / 2;
+            match part(middle) < prefix {
+                true => low = middle + 1,
+                false => high = middle,
+            }
+        }
+
+        (low..self.by_package_part.len())
+            .take_while(|index| part(*index).starts_with(prefix))
+            .take(limit)
+            .map(|index| self.get(self.by_package_part[index] as usize))
+            .collect()
+    }
+
+    /// The names containing the characters of the query in order, the best
+    /// matches first, e.g. "monolog/monolog" for "mnlg". Only the names
+    /// starting with the first character of the query are scored, so a
+    /// keystroke doesn't scan all names, and shorter queries match nothing.
+    pub fn fuzzy_matching(&self, query: &str, limit: usize) -> Vec<&str> {
+        let first = match query.chars().next() {
+            Some(first) if query.chars().count() >= MIN_FUZZY_QUERY => first,
+            _ => return vec![],
+        };
+
+        let start = self.lower_bound(&query[..first.len_utf8()]);
+        let mut matches: Vec<(usize, &str)> = (start..self.len())
+            .map(|position| self.get(position))
+            .take_while(|name| name.starts_with(first))
+            .filter_map(|name| Some((fuzzy_score(name, query)?, name)))
+            .collect();
+        matches.sort_unstable();
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, name)| name)
+            .collect()
+    }
+}
+
+/// The part of a name after the vendor, e.g. "log" for "psr/log".
+fn package_part(name: &str) -> &str {
+    name.split_once('/').map_or(name, |(_, package)| package)
+}
+
+/// How far apart the characters of the query are in the name, lower is
+/// better, or None when the name doesn't contain them in order.
+pub fn fuzzy_score(name: &str, query: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut previous = None;
+    let mut chars = name.char_indices();
+    for query_char in query.chars() {
+        let (index, _) = chars.by_ref().find(|(_, c)| *c == query_char)?;
+        score += match previous {
+            Some(previous) => index - previous - 1,
+            None => index,
+        };
+        previous = Some(index);
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::{fuzzy_score, PackageIndex};
+
+    #[test]
+    fn it_can_find_names_by_prefix() {
+        let index = PackageIndex::new(vec![
+            "symfony/console".to_string(),
+            "monolog/monolog".to_string(),
+            "symfony/http-kernel".to_string(),
+            "symfony/cache".to_string(),
+            "psr/log".to_string(),
+        ]);
+
+        assert_eq!(
+            vec!["symfony/cache", "symfony/console", "symfony/http-kernel"],
+            index.starting_with("symfony/").collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            vec!["psr/log"],
+            index.starting_with("ps").collect::<Vec<&str>>()
+        );
+        assert_eq!(0, index.starting_with("zz").count());
+    }
+
+    #[test]
+    fn it_can_find_names_by_the_package_part() {
+        let index = PackageIndex::new(vec![
+            "symfony/http-kernel".to_string(),
+            "http-interop/http-factory".to_string(),
+            "laminas/laminas-http".to_string(),
+            "acme/http".to_string(),
+        ]);
+
+        assert_eq!(
+            vec![
+                "acme/http",
+                "http-interop/http-factory",
+                "symfony/http-kernel"
+            ],
+            index.package_part_starting_with("http", 10)
+        );
+        assert_eq!(1, index.package_part_starting_with("http", 1).len());
+        assert_eq!(
+            vec!["laminas/laminas-http"],
+            index.package_part_starting_with("laminas-", 10)
+        );
+        assert!(index.package_part_starting_with("kernel", 10).is_empty());
+    }
+
+    #[test]
+    fn it_can_find_names_fuzzily() {
+        let index = PackageIndex::new(vec![
+            "monolog/monolog".to_string(),
+            "mongodb/mongodb".to_string(),
+            "acme/monolog-handler".to_string(),
+            "psr/log".to_string(),
+        ]);
+
+        assert_eq!(Some(0), fuzzy_score("monolog/monolog", "mono"));
+        assert_eq!(None, fuzzy_score("psr/log", "mnlg"));
+        assert_eq!(
+            vec!["mongodb/mongodb", "monolog/monolog"],
+            index.fuzzy_matching("mog", 10)
+        );
+        assert_eq!(vec!["monolog/monolog"], index.fuzzy_matching("mnlg", 1));
+        // Only names starting with the first character are matched.
+        assert!(index.fuzzy_matching("cmnl", 10).is_empty());
+        assert!(index.fuzzy_matching("mn", 10).is_empty());
+    }
+
+    #[test]
+    fn it_stores_the_names_in_a_single_buffer() {
+        let index = PackageIndex::new(vec!["b/b".to_string(), "a/a".to_string()]);
+
+        assert_eq!(2, index.len());
+        assert_eq!("a/ab/b", index.names);
+    }
+}
@@ -0,0 +1,577 @@
+use tower_lsp::lsp_types::{Position, Range};
+
+/// The error of a trailing comma, which the tolerant parser recovers from.
+pub const TRAILING_COMMA: &str = "Trailing commas are not allowed in JSON";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+
+    /// The span of a string without its quotes.
+    pub fn unquoted(&self) -> Span {
+        if self.end - self.start < 2 {
+            return *self;
+        }
+
+        Span::new(self.start + 1, self.end - 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Object(Vec<Member>),
+    Array(Vec<Node>),
+    String(String),
+    Number(String),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub key: String,
+    pub key_span: Span,
+    pub value: Node,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Node {
+    pub fn as_object(&self) -> Option<&Vec<Member>> {
+        match &self.kind {
+            NodeKind::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Node>> {
+        match &self.kind {
+            NodeKind::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Member> {
+        self.as_object()?.iter().find(|member| member.key == key)
+    }
+
+    /// Returns the spans enclosing the offset, from the outermost node to the
+    /// innermost one.
+    pub fn spans_at(&self, offset: usize) -> Vec<Span> {
+        let mut spans = vec![];
+        let mut node = self;
+
+        loop {
+            if !node.span.contains(offset) {
+                break;
+            }
+
+            spans.push(node.span);
+
+            match &node.kind {
+                NodeKind::Object(members) => {
+                    match members.iter().find(|member| member.span.contains(offset)) {
+                        Some(member) => {
+                            spans.push(member.span);
+
+                            if member.key_span.contains(offset) {
+                                spans.push(member.key_span);
+                                break;
+                            }
+
+                            node = &member.value;
+                        }
+                        None => break,
+                    }
+                }
+                NodeKind::Array(items) => {
+                    match items.iter().find(|item| item.span.contains(offset)) {
+                        Some(item) => node = item,
+                        None => break,
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        spans.dedup();
+        spans
+    }
+}
+
+/// Parses strict JSON, failing on the first invalid construct.
+pub fn parse(text: &str) -> Result<Node, ParseError> {
+    let (node, mut errors) = parse_tolerant(text)?;
+
+    if !errors.is_empty() {
+        return Err(errors.remove(0));
+    }
+
+    Ok(node)
+}
+
+/// Parses JSON while tolerating comments, trailing commas and missing commas,
+/// which are returned as errors next to the parsed document. Other invalid
+/// constructs still fail the whole parse.
+pub fn parse_tolerant(text: &str) -> Result<(Node, Vec<ParseError>), ParseError> {
+    let mut parser = Parser {
+        text,
+        bytes: text.as_bytes(),
+        pos: 0,
+        errors: vec![],
+    };
+
+    parser.skip_whitespace();
+    let node = parser.parse_value()?;
+    parser.skip_whitespace();
+
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("Unexpected content after the end of the document"));
+    }
+
+    Ok((node, parser.errors))
+}
+
+/// Converts a byte offset into a LSP position, counting characters in UTF-16.
+pub fn offset_to_position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line = before.matches('\n').count() as u32;
+    let line_start = before.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+    let character = before[line_start..].encode_utf16().count() as u32;
+
+    Position::new(line, character)
+}
+
+/// Converts a LSP position into a byte offset, clamped to the line length.
+pub fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(pos) => line_start += pos + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line_end = text[line_start..]
+        .find('\n')
+        .map(|pos| line_start + pos)
+        .unwrap_or(text.len());
+
+    let mut units = 0;
+    for (index, character) in text[line_start..line_end].char_indices() {
+        if units >= position.character {
+            return line_start + index;
+        }
+
+        units += character.len_utf16() as u32;
+    }
+
+    line_end
+}
+
+pub fn span_to_range(text: &str, span: Span) -> Range {
+    Range::new(
+        offset_to_position(text, span.start),
+        offset_to_position(text, span.end),
+    )
+}
+
+/// The start offsets of the lines of a text, to convert many spans into
+/// ranges without scanning the text for every one.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> LineIndex<'a> {
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            text.bytes()
+                .enumerate()
+                .filter(|(_, byte)| *byte == b'\n')
+                .map(|(index, _)| index + 1),
+        );
+
+        LineIndex { text, line_starts }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let character = self.text[line_start..offset].encode_utf16().count() as u32;
+
+        Position::new(line as u32, character)
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            span: Span::new(self.pos, (self.pos + 1).min(self.bytes.len())),
+        }
+    }
+
+    fn recover(&mut self, message: &str, start: usize, end: usize) {
+        self.errors.push(ParseError {
+            message: message.to_string(),
+            span: Span::new(start, end),
+        });
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if byte.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if self.text[self.pos..].starts_with("//") {
+                let start = self.pos;
+                self.pos = self.text[start..]
+                    .find('\n')
+                    .map(|end| start + end)
+                    .unwrap_or(self.bytes.len());
+                self.recover("Comments are not allowed in JSON", start, self.pos);
+            } else if self.text[self.pos..].starts_with("/*") {
+                let start = self.pos;
+                self.pos = self.text[start + 2..]
+                    .find("*/")
+                    .map(|end| start + 2 + end + 2)
+                    .unwrap_or(self.bytes.len());
+                self.recover("Comments are not allowed in JSON", start, self.pos);
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Consumes the separator after an object member or array item and
+    /// returns true once the closing character was reached.
+    fn parse_separator(&mut self, closing: u8) -> Result<bool, ParseError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b',') => {
+                let comma = self.pos;
+                self.pos += 1;
+                self.skip_whitespace();
+
+                if self.peek() == Some(closing) {
+                    self.recover(TRAILING_COMMA, comma, comma + 1);
+                    self.pos += 1;
+                    return Ok(true);
+                }
+
+                Ok(false)
+            }
+            Some(byte) if byte == closing => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(b'"') | Some(b'{') | Some(b'[') => {
+                self.recover("Expected a comma", self.pos, self.pos + 1);
+                Ok(false)
+            }
+            _ => Err(self.error(&format!("Expected ',' or '{}'", closing as char))),
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), ParseError> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("Expected '{}'", expected as char)));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Node, ParseError> {
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => {
+                let start = self.pos;
+                let value = self.parse_string()?;
+
+                Ok(Node {
+                    kind: NodeKind::String(value),
+                    span: Span::new(start, self.pos),
+                })
+            }
+            Some(b't') => self.parse_literal("true", NodeKind::Bool(true)),
+            Some(b'f') => self.parse_literal("false", NodeKind::Bool(false)),
+            Some(b'n') => self.parse_literal("null", NodeKind::Null),
+            Some(byte) if byte == b'-' || byte.is_ascii_digit() => self.parse_number(),
+            Some(_) => Err(self.error("Expected a value")),
+            None => Err(self.error("Unexpected end of the document")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        let mut members = vec![];
+        self.expect(b'{')?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Node {
+                kind: NodeKind::Object(members),
+                span: Span::new(start, self.pos),
+            });
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("Expected a property name"));
+            }
+
+            let key_start = self.pos;
+            let key = self.parse_string()?;
+            let key_span = Span::new(key_start, self.pos);
+
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+
+            let value = self.parse_value()?;
+            let span = Span::new(key_start, value.span.end);
+            members.push(Member {
+                key,
+                key_span,
+                value,
+                span,
+            });
+
+            if self.parse_separator(b'}')? {
+                break;
+            }
+        }
+
+        Ok(Node {
+            kind: NodeKind::Object(members),
+            span: Span::new(start, self.pos),
+        })
+    }
+
+    fn parse_array(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        let mut items = vec![];
+        self.expect(b'[')?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Node {
+                kind: NodeKind::Array(items),
+                span: Span::new(start, self.pos),
+            });
+        }
+
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value()?);
+
+            if self.parse_separator(b']')? {
+                break;
+            }
+        }
+
+        Ok(Node {
+            kind: NodeKind::Array(items),
+            span: Span::new(start, self.pos),
+        })
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.expect(b'"')?;
+        let start = self.pos;
+
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'"' => {
+                    let raw = &self.text[start..self.pos];
+                    self.pos += 1;
+
+                    return Ok(serde_json::from_str(&format!("\"{}\"", raw))
+                        .unwrap_or_else(|_| raw.to_string()));
+                }
+                b'\\' => self.pos += 2,
+                b'\n' => break,
+                _ => self.pos += 1,
+            }
+        }
+
+        Err(self.error("Unterminated string"))
+    }
+
+    fn parse_number(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            if !(byte.is_ascii_digit() || matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E')) {
+                break;
+            }
+
+            self.pos += 1;
+        }
+
+        Ok(Node {
+            kind: NodeKind::Number(self.text[start..self.pos].to_string()),
+            span: Span::new(start, self.pos),
+        })
+    }
+
+    fn parse_literal(&mut self, literal: &str, kind: NodeKind) -> Result<Node, ParseError> {
+        let start = self.pos;
+        if !self.text[start..].starts_with(literal) {
+            return Err(self.error("Expected a value"));
+        }
+
+        self.pos += literal.len();
+        Ok(Node {
+            kind,
+            span: Span::new(start, self.pos),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Position;
+
+    use crate::json::{
+        offset_to_position, parse, parse_tolerant, position_to_offset, LineIndex, NodeKind, Span,
+    };
+
+    const DOCUMENT: &str = "{\n    \"require\": {\n        \"php\": \"^8.1\"\n    }\n}\n";
+
+    #[test]
+    fn it_can_parse_a_document_with_spans() {
+        let root = parse(DOCUMENT).unwrap();
+        let require = match root.kind {
+            NodeKind::Object(members) => members[0].clone(),
+            _ => panic!("Expected an object"),
+        };
+        let php = match require.value.kind {
+            NodeKind::Object(members) => members[0].clone(),
+            _ => panic!("Expected an object"),
+        };
+
+        assert_eq!(
+            "\"require\"",
+            &DOCUMENT[require.key_span.start..require.key_span.end]
+        );
+        assert_eq!(
+            "\"^8.1\"",
+            &DOCUMENT[php.value.span.start..php.value.span.end]
+        );
+        assert_eq!(NodeKind::String("^8.1".to_string()), php.value.kind);
+    }
+
+    #[test]
+    fn it_reports_the_offset_of_invalid_json() {
+        let error = parse("{\n  \"name\": \"a/b\"\n  \"type\": \"library\"\n}").unwrap_err();
+
+        assert_eq!(20, error.span.start);
+    }
+
+    #[test]
+    fn it_tolerates_comments_and_trailing_commas() {
+        let text =
+            "{\n  // The name.\n  \"name\": \"a/b\",\n  \"require\": { \"php\": \"^8.1\", },\n}";
+        let (root, errors) = parse_tolerant(text).unwrap();
+
+        assert_eq!(3, errors.len());
+        assert_eq!(
+            "// The name.",
+            &text[errors[0].span.start..errors[0].span.end]
+        );
+        assert_eq!(",", &text[errors[1].span.start..errors[1].span.end]);
+        assert_eq!(2, root.as_object().unwrap().len());
+    }
+
+    #[test]
+    fn it_can_get_the_enclosing_spans() {
+        let root = parse(DOCUMENT).unwrap();
+        let offset = DOCUMENT.find("^8.1").unwrap();
+        let spans = root.spans_at(offset);
+
+        assert_eq!(5, spans.len());
+        assert_eq!(Span::new(0, DOCUMENT.len() - 1), spans[0]);
+        assert_eq!("\"^8.1\"", &DOCUMENT[spans[4].start..spans[4].end]);
+    }
+
+    #[test]
+    fn it_can_index_the_lines_of_a_text() {
+        let text = "{\n  \"é\": 1\n}";
+        let line_index = LineIndex::new(text);
+
+        assert_eq!(Position::new(0, 0), line_index.position(0));
+        assert_eq!(Position::new(1, 0), line_index.position(2));
+        assert_eq!(Position::new(1, 4), line_index.position(7));
+        assert_eq!(Position::new(2, 0), line_index.position(text.len() - 1));
+        for offset in [0, 1, 2, 5, 7, 11, text.len()] {
+            assert_eq!(
+                offset_to_position(text, offset),
+                line_index.position(offset)
+            );
+        }
+    }
+
+    #[test]
+    fn it_can_convert_between_offsets_and_positions() {
+        let offset = DOCUMENT.find("\"php\"").unwrap();
+
+        assert_eq!(Position::new(2, 8), offset_to_position(DOCUMENT, offset));
+        assert_eq!(offset, position_to_offset(DOCUMENT, Position::new(2, 8)));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::json::{Node, Span};
+use crate::packagist;
+
+/// A clickable part of composer.json.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// The span of the linked text, without its quotes.
+    pub span: Span,
+    pub url: String,
+    pub tooltip: String,
+}
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Links the required packages to their most useful support page, from the
+/// labelled support links of the package, or else to their packagist page.
+/// The homepage and support URLs of composer.json link to themselves.
+pub fn find_links(
+    root: &Node,
+    support_link: impl Fn(&str) -> Option<(&'static str, String)>,
+) -> Vec<Link> {
+    let mut links = vec![];
+    for block in ["require", "require-dev"] {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            if packagist::is_platform_package(&member.key)
+                || packagist::is_virtual_package(&member.key)
+            {
+                continue;
+            }
+
+            let (url, tooltip) = match support_link(&member.key) {
+                Some((label, url)) => (url, format!("Open {} of {}", label, member.key)),
+                None => (
+                    packagist::package_url(&member.key.to_lowercase()),
+                    format!("Open {} on Packagist", member.key),
+                ),
+            };
+            links.push(Link {
+                span: member.key_span.unquoted(),
+                url,
+                tooltip,
+            });
+        }
+    }
+
+    if let Some(homepage) = root.get("homepage") {
+        if let Some(url) = homepage.value.as_str().filter(|url| is_web_url(url)) {
+            links.push(Link {
+                span: homepage.value.span.unquoted(),
+                url: url.to_string(),
+                tooltip: "Open the homepage".to_string(),
+            });
+        }
+    }
+
+    let support = root
+        .get("support")
+        .and_then(|support| support.value.as_object());
+    for member in support.into_iter().flatten() {
+        if let Some(url) = member.value.as_str().filter(|url| is_web_url(url)) {
+            links.push(Link {
+                span: member.value.span.unquoted(),
+                url: url.to_string(),
+                tooltip: format!("Open the {} link", member.key),
+            });
+        }
+    }
+
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::links::find_links;
+
+    #[test]
+    fn it_can_find_the_links() {
+        let text = r#"{
+            "homepage": "https://example.com",
+            "support": {
+                "source": "https://github.com/acme/app",
+                "email": "help@example.com"
+            },
+            "require": {
+                "php": "^8.1",
+                "psr/log-implementation": "*",
+                "Monolog/Monolog": "^3.0",
+                "acme/lib": "^1.0"
+            }
+        }"#;
+        let links = find_links(&json::parse(text).unwrap(), |name| match name {
+            "acme/lib" => Some(("Docs", "https://docs.acme.test".to_string())),
+            _ => None,
+        });
+        let linked: Vec<(&str, &str)> = links
+            .iter()
+            .map(|link| (&text[link.span.start..link.span.end], link.url.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    "Monolog/Monolog",
+                    "https://packagist.org/packages/monolog/monolog"
+                ),
+                ("acme/lib", "https://docs.acme.test"),
+                ("https://example.com", "https://example.com"),
+                ("https://github.com/acme/app", "https://github.com/acme/app"),
+            ],
+            linked
+        );
+        assert_eq!("Open Monolog/Monolog on Packagist", links[0].tooltip);
+        assert_eq!("Open Docs of acme/lib", links[1].tooltip);
+        assert_eq!("Open the source link", links[3].tooltip);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::composer;
+use crate::json::{Node, Span};
+use crate::packagist;
+use crate::Url;
+
+/// A package entry of composer.lock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockedEntry {
+    pub name: String,
+    /// The locked version, normalized like the installed versions, e.g.
+    /// "1.2.3" for "v1.2.3".
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// Whether the entry is in packages-dev.
+    pub dev: bool,
+    /// The span of the name value, with its quotes.
+    pub name_span: Span,
+    /// The span of the whole entry.
+    pub span: Span,
+}
+
+/// Whether the document is a composer.lock, rather than a composer.json.
+pub fn is_lock_file(uri: &Url) -> bool {
+    uri.path().ends_with("/composer.lock")
+}
+
+/// The composer.json next to the composer.lock.
+pub fn composer_json_uri(lock_uri: &Url) -> Option<Url> {
+    lock_uri.join("composer.json").ok()
+}
+
+/// The entries of packages and packages-dev.
+pub fn find_entries(root: &Node) -> Vec<LockedEntry> {
+    let mut entries = vec![];
+    for block in ["packages", "packages-dev"] {
+        let packages = match root.get(block).and_then(|block| block.value.as_array()) {
+            Some(packages) => packages,
+            None => continue,
+        };
+
+        for package in packages {
+            let name = match package.get("name") {
+                Some(name) => name,
+                None => continue,
+            };
+            let field = |key: &str| {
+                package
+                    .get(key)
+                    .and_then(|member| member.value.as_str())
+                    .map(|value| value.to_string())
+            };
+
+            entries.push(LockedEntry {
+                name: name.value.as_str().unwrap_or_default().to_string(),
+                version: field("version")
+                    .map(|version| composer::normalize_locked_version(&version)),
+                description: field("description"),
+                dev: block == "packages-dev",
+                name_span: name.value.span,
+                span: package.span,
+            });
+        }
+    }
+
+    entries
+}
+
+/// The entry the offset is in.
+pub fn entry_at(entries: &[LockedEntry], offset: usize) -> Option<&LockedEntry> {
+    entries.iter().find(|entry| entry.span.contains(offset))
+}
+
+/// How the locked version compares to what composer.json allows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateStatus {
+    /// composer.json requires the package with the constraint, which allows
+    /// the newer version if any.
+    Required {
+        constraint: String,
+        update: Option<String>,
+    },
+    /// The package is only required by other packages.
+    Transitive {
+        latest: Option<String>,
+        required_by: Option<String>,
+    },
+    /// The package data couldn't be fetched.
+    Unknown,
+}
+
+/// The hover of a composer.lock entry, in markdown.
+pub fn entry_hover(entry: &LockedEntry, status: &UpdateStatus) -> String {
+    let mut lines = vec![];
+    let version = entry
+        .version
+        .as_ref()
+        .map(|version| format!(" `{}`", version))
+        .unwrap_or_default();
+    let dev = if entry.dev { " (dev)" } else { "" };
+    lines.push(format!("**{}**{}{}", entry.name, version, dev));
+
+    if let Some(description) = &entry.description {
+        lines.push(description.to_string());
+    }
+
+    match status {
+        UpdateStatus::Required {
+            constraint,
+            update: Some(update),
+        } => lines.push(format!(
+            "Update available: `{}`, allowed by `{}` in composer.json.",
+            update, constraint
+        )),
+        UpdateStatus::Required {
+            constraint,
+            update: None,
+        } => lines.push(format!(
+            "Up to date with `{}` in composer.json.",
+            constraint
+        )),
+        UpdateStatus::Transitive {
+            latest,
+            required_by,
+        } => {
+            let mut line = "Not required by composer.json directly".to_string();
+            if let Some(required_by) = required_by {
+                line.push_str(&format!(", but by {}", required_by));
+            }
+            line.push('.');
+            if let Some(latest) = latest {
+                line.push_str(&format!(" The latest stable version is `{}`.", latest));
+            }
+            lines.push(line);
+        }
+        UpdateStatus::Unknown => {}
+    }
+
+    lines.push(format!(
+        "[Packagist]({})",
+        packagist::package_url(&entry.name.to_lowercase())
+    ));
+
+    lines.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::lockfile::{
+        composer_json_uri, entry_at, entry_hover, find_entries, is_lock_file, UpdateStatus,
+    };
+    use crate::Url;
+
+    const LOCK: &str = r#"{
+    "packages": [
+        {
+            "name": "monolog/monolog",
+            "version": "v2.9.1",
+            "description": "Sends your logs to files"
+        }
+    ],
+    "packages-dev": [
+        {
+            "name": "phpunit/phpunit",
+            "version": "10.5.0"
+        }
+    ]
+}"#;
+
+    #[test]
+    fn it_can_detect_lock_files() {
+        let uri = Url::parse("file:///app/composer.lock").unwrap();
+
+        assert!(is_lock_file(&uri));
+        assert!(!is_lock_file(
+            &Url::parse("file:///app/composer.json").unwrap()
+        ));
+        assert_eq!(
+            "file:///app/composer.json",
+            composer_json_uri(&uri).unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn it_can_find_the_entries_of_the_lock() {
+        let entries = find_entries(&json::parse(LOCK).unwrap());
+
+        assert_eq!(2, entries.len());
+        assert_eq!(Some("2.9.1".to_string()), entries[0].version);
+        assert!(!entries[0].dev);
+        assert!(entries[1].dev);
+        assert_eq!(
+            "\"monolog/monolog\"",
+            &LOCK[entries[0].name_span.start..entries[0].name_span.end]
+        );
+
+        let offset = LOCK.find("Sends").unwrap();
+        assert_eq!(
+            Some("monolog/monolog"),
+            entry_at(&entries, offset).map(|entry| entry.name.as_str())
+        );
+        assert_eq!(None, entry_at(&entries, 0));
+    }
+
+    #[test]
+    fn it_can_describe_an_entry() {
+        let entries = find_entries(&json::parse(LOCK).unwrap());
+
+        assert_eq!(
+            "**monolog/monolog** `2.9.1`\n\nSends your logs to files\n\nUpdate available: `2.10.0`, allowed by `^2.0` in composer.json.\n\n[Packagist](https://packagist.org/packages/monolog/monolog)",
+            entry_hover(
+                &entries[0],
+                &UpdateStatus::Required {
+                    constraint: "^2.0".to_string(),
+                    update: Some("2.10.0".to_string()),
+                }
+            )
+        );
+        assert_eq!(
+            "**phpunit/phpunit** `10.5.0` (dev)\n\nNot required by composer.json directly, but by `acme/tools`. The latest stable version is `11.0.0`.\n\n[Packagist](https://packagist.org/packages/phpunit/phpunit)",
+            entry_hover(
+                &entries[1],
+                &UpdateStatus::Transitive {
+                    latest: Some("11.0.0".to_string()),
+                    required_by: Some("`acme/tools`".to_string()),
+                }
+            )
+        );
+    }
+}
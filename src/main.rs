@@ -1,44 +1,269 @@
 use dashmap::DashMap;
 use log::info;
-use log4rs;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
-use std::{process::Command as ProcessCommand, str::from_utf8};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::task::JoinHandle;
 use tower_lsp::jsonrpc::{Error, ErrorCode::ServerError, Result};
+use tower_lsp::lsp_types::request::{
+    GotoDeclarationParams, GotoDeclarationResponse, InlayHintRefreshRequest,
+};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use crate::{composer::ComposerFile, packagist::PackageVersion};
+use crate::command::ComposerCommand;
+use crate::composer::{ComposerFile, ComposerLockFile, LockFormat};
+use crate::config::{
+    CommandSettings, DefinitionTarget, NetworkMode, OnSaveSettings, PackagistSettings, Settings,
+};
+use crate::effective::EffectiveConfig;
+use crate::index::PackageIndex;
+use crate::packagist::{Package, UpdatePolicy};
+use crate::registration::Feature;
+use crate::repository::Repositories;
 
+mod audit;
+mod auth;
+mod cache;
+mod command;
+mod completion;
 mod composer;
+mod config;
+mod constraint;
+mod drift;
+mod duplicates;
+mod edit;
+mod effective;
+mod export;
+mod failure;
+mod global;
+mod http;
+mod index;
+mod json;
+mod links;
+mod lockfile;
+mod naming;
+mod outcome;
 mod packagist;
+mod placement;
+mod platform;
+mod plugins;
+mod provide;
+mod pull;
+mod registration;
+mod repositories;
+mod repository;
+mod runner;
+mod scaffold;
+mod schema;
+mod scripts;
+mod stability;
+mod summary;
+mod symbols;
+mod tokens;
+mod validate;
+mod why;
+mod workspace;
+
+const EXACT_PIN_CODE: &str = "composer:exact-pin";
+const INVALID_JSON_CODE: &str = "composer:invalid-json";
+const REPOSITORY_CODE: &str = "composer:repository";
+const COMMAND_FAILURE_CODE: &str = "composer:command-failure";
+const UNCHECKED_CODE: &str = "composer:unchecked-packages";
+/// How long typing has to pause before the diagnostics are refreshed.
+const CHANGE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+const UPDATE_AVAILABLE_CODE: &str = "composer:update-available";
+const NEW_COMMITS_CODE: &str = "composer:new-commits";
+const MISSING_PHP_CODE: &str = "composer:missing-php";
+const PLATFORM_CODE: &str = "composer:platform";
+const MISPLACED_CODE: &str = "composer:misplaced-dependency";
+const PLUGIN_NOT_ALLOWED_CODE: &str = "composer:plugin-not-allowed";
+const MISSING_SCRIPT_CODE: &str = "composer:missing-script";
+const UNDEFINED_SCRIPT_CODE: &str = "composer:undefined-script";
+const SECURITY_ADVISORY_CODE: &str = "composer:security-advisory";
+const MIXED_CASE_CODE: &str = "composer:mixed-case-name";
+const DUPLICATE_CODE: &str = "composer:duplicate";
+const CONFLICT_CODE: &str = "composer:conflict";
+const SUMMARY_CODE: &str = "composer:summary";
+const NOT_LOCKED_CODE: &str = "composer:not-locked";
+const NOT_REQUIRED_CODE: &str = "composer:not-required";
+const STABILITY_CODE: &str = "composer:stability";
+const LEGACY_LOCK_CODE: &str = "composer:legacy-lock";
+const ABANDONED_CODE: &str = "composer:abandoned";
+const NOT_SATISFIED_CODE: &str = "composer:not-satisfied";
+const LOCK_OUTDATED_CODE: &str = "composer:lock-outdated";
+const SCHEMA_CODE: &str = "composer:schema";
+const VALIDATE_CODE: &str = "composer:validate";
+const PACKAGE_INDEX_TASK: &str = "package-index";
+
+// Documentation linked from the diagnostics.
+const SCHEMA_URL: &str = "https://getcomposer.org/doc/04-schema.md";
+const REPOSITORIES_URL: &str = "https://getcomposer.org/doc/05-repositories.md";
+const PLATFORM_PACKAGES_URL: &str =
+    "https://getcomposer.org/doc/01-basic-usage.md#platform-packages";
+const VERSIONS_URL: &str = "https://getcomposer.org/doc/articles/versions.md";
+const ALLOW_PLUGINS_URL: &str = "https://getcomposer.org/doc/06-config.md#allow-plugins";
+const SCRIPTS_URL: &str = "https://getcomposer.org/doc/articles/scripts.md";
+const REQUIRE_DEV_URL: &str = "https://getcomposer.org/doc/04-schema.md#require-dev";
+const NAME_URL: &str = "https://getcomposer.org/doc/04-schema.md#name";
+const MINIMUM_STABILITY_URL: &str = "https://getcomposer.org/doc/04-schema.md#minimum-stability";
+const TROUBLESHOOTING_URL: &str = "https://getcomposer.org/doc/articles/troubleshooting.md";
 
 #[derive(Debug)]
 struct Backend {
     client: Client,
+    /// The open composer.json documents, by URI.
     composer_file: DashMap<String, ComposerFile>,
-    packagist_packages: DashMap<String, Vec<String>>,
-    buffer: DashMap<u32, String>,
+    package_index: Arc<RwLock<Option<Arc<PackageIndex>>>>,
+    /// The lines of the open documents, with their unsaved changes, by URI.
+    buffer: DashMap<String, Vec<String>>,
+    /// The document that changed last, for commands run without one.
+    active_document: RwLock<Option<String>>,
+    saved_text: DashMap<String, String>,
+    settings: RwLock<Settings>,
+    background_tasks: DashMap<String, JoinHandle<()>>,
+    /// The folders of the workspace, scanned for composer.json files.
+    workspace_folders: RwLock<Vec<Url>>,
+    workspace_files: DashMap<String, ComposerFile>,
+    document_versions: DashMap<String, i32>,
+    analyses: DashMap<String, u64>,
+    saved_checks: DashMap<String, SavedChecks>,
+    diagnostics: DashMap<String, Vec<Diagnostic>>,
+    command_diagnostics: DashMap<String, Vec<Diagnostic>>,
+    packages_fetched_at: Arc<RwLock<Option<SystemTime>>>,
+    /// The latest package data fetched from packagist, for features that
+    /// aren't allowed to go online.
+    package_cache: DashMap<String, Package>,
+    /// The features the client lets the server register dynamically.
+    dynamic_features: RwLock<Vec<Feature>>,
+    registered_features: RwLock<Vec<Feature>>,
+    /// The features the client can be asked to request again.
+    refreshable_features: RwLock<Vec<Feature>>,
+    /// Whether the client can be asked to watch composer.json and
+    /// composer.lock on disk.
+    file_watching: RwLock<bool>,
+    /// The effective composer configuration, by working directory.
+    effective_configs: DashMap<String, EffectiveConfig>,
+    /// Whether the client pulls the diagnostics of open documents.
+    pull_support: Arc<RwLock<pull::PullSupport>>,
+    /// The last semantic tokens of every document, for deltas.
+    semantic_tokens: DashMap<String, SemanticTokens>,
 }
 
 struct TextDocumentItem {
     uri: Url,
     version: i32,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageVersionsParams {
+    package: String,
+    /// The composer.json whose repositories to search, else the active one.
+    #[serde(default)]
+    uri: Option<Url>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageVersionsResult {
+    package: String,
+    versions: Vec<PackageVersionItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageVersionItem {
+    version: String,
+    version_normalized: Option<String>,
+    stability: String,
+    time: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerStatusResult {
+    packagist_reachable: bool,
+    package_index: PackageIndexStatus,
+    tracked_documents: usize,
+    workspace_files: usize,
+    background_tasks: BackgroundTasksStatus,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageIndexStatus {
+    entries: usize,
+    memory_bytes: usize,
+    age_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackgroundTasksStatus {
+    running: usize,
+    finished: usize,
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _params: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        *self.settings.write().unwrap() = Settings::from_value(params.initialization_options);
+        configure_packagist(&self.settings.read().unwrap().packagist);
+
+        *self.workspace_folders.write().unwrap() = params
+            .workspace_folders
+            .unwrap_or_default()
+            .into_iter()
+            .map(|folder| folder.uri)
+            .collect();
+        *self.file_watching.write().unwrap() =
+            registration::supports_file_watching(&params.capabilities);
+
+        // Features the client can't register dynamically are advertised up
+        // front, if they are enabled.
+        let dynamic_features: Vec<Feature> = Feature::ALL
+            .into_iter()
+            .filter(|feature| feature.is_dynamic(&params.capabilities))
+            .collect();
+        let features = self.settings.read().unwrap().features.clone();
+        let is_static = |feature: Feature| {
+            feature.is_enabled(&features) && !dynamic_features.contains(&feature)
+        };
+        let code_lens_provider = is_static(Feature::CodeLens).then(registration::code_lens_options);
+        let inlay_hint_provider = is_static(Feature::InlayHints).then(|| {
+            OneOf::Right(InlayHintServerCapabilities::Options(
+                registration::inlay_hint_options(),
+            ))
+        });
+        let semantic_tokens_provider = is_static(Feature::SemanticTokens).then(|| {
+            SemanticTokensServerCapabilities::SemanticTokensOptions(
+                registration::semantic_tokens_options(),
+            )
+        });
+        *self.dynamic_features.write().unwrap() = dynamic_features;
+        *self.refreshable_features.write().unwrap() = Feature::ALL
+            .into_iter()
+            .filter(|feature| feature.supports_refresh(&params.capabilities))
+            .collect();
+
         Ok(InitializeResult {
             server_info: None,
+            offset_encoding: None,
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
                     TextDocumentSyncKind::FULL,
                 )),
                 completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
+                    resolve_provider: Some(true),
                     trigger_characters: {
-                        let chars = ('a'..='z').into_iter().collect::<Vec<char>>();
+                        let mut chars = ('a'..='z').collect::<Vec<char>>();
+                        // Constraints are completed after their quote or operator.
+                        chars.extend(['"', '^', '~']);
                         let triggers: Vec<String> =
                             chars.clone().iter().map(|x| x.to_string()).collect();
 
@@ -46,10 +271,25 @@ impl LanguageServer for Backend {
                     },
                     work_done_progress_options: Default::default(),
                     all_commit_characters: None,
+                    completion_item: None,
                 }),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 definition_provider: Some(OneOf::Left(true)),
+                declaration_provider: Some(DeclarationCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: Default::default(),
+                }),
+                code_lens_provider,
+                inlay_hint_provider,
+                semantic_tokens_provider,
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: ComposerCommand::ids(),
+                    work_done_progress_options: Default::default(),
+                }),
                 workspace: Some(WorkspaceServerCapabilities {
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                         supported: Some(true),
@@ -63,19 +303,58 @@ impl LanguageServer for Backend {
     }
 
     async fn initialized(&self, _: InitializedParams) {
-        let all_packages = packagist::get_all_packages().await;
+        // Load the completion index in the background, so everything else
+        // works right away, even on slow connections.
+        let client = self.client.clone();
+        let package_index = self.package_index.clone();
+        let packages_fetched_at = self.packages_fetched_at.clone();
+        let task = tokio::spawn(async move {
+            let names = match packagist::get_all_packages().await {
+                Ok(names) => names,
+                Err(error) => {
+                    client
+                        .log_message(
+                            MessageType::WARNING,
+                            format!("Package completion is unavailable: {}", error),
+                        )
+                        .await;
+                    return;
+                }
+            };
+            let index = PackageIndex::new(names);
 
-        // Clear any old data.
-        if self.packagist_packages.contains_key("data") {
-            self.packagist_packages.remove("data").unwrap();
-        }
+            // Swap in the new index, the old one is freed once no completion
+            // request is using it anymore.
+            *package_index.write().unwrap() = Some(Arc::new(index));
+            *packages_fetched_at.write().unwrap() = Some(SystemTime::now());
+
+            client
+                .log_message(MessageType::INFO, "Package completion is ready.")
+                .await;
+        });
+        self.background_tasks
+            .insert(PACKAGE_INDEX_TASK.to_string(), task);
 
-        self.packagist_packages
-            .insert("data".to_string(), all_packages);
+        self.sync_registrations().await;
+        if *self.file_watching.read().unwrap() {
+            let registrations = vec![registration::file_watcher_registration()];
+            if let Err(error) = self.client.register_capability(registrations).await {
+                info!("Can't watch the composer files: {}", error);
+            }
+        }
 
         self.client
             .log_message(MessageType::INFO, "composer_lsp initialized!")
             .await;
+
+        let folders = self.workspace_folders.read().unwrap().clone();
+        let tracked = folders
+            .iter()
+            .flat_map(|folder| self.track_workspace_folder(folder))
+            .collect();
+        if self.settings.read().unwrap().workspace.scan {
+            self.check_workspace_files(tracked).await;
+        }
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
@@ -93,543 +372,4269 @@ impl LanguageServer for Backend {
         Ok(self.goto_definition(params).await)
     }
 
+    async fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> Result<Option<GotoDeclarationResponse>> {
+        Ok(self.goto_declaration(params).await)
+    }
+
     async fn shutdown(&self) -> Result<()> {
+        // Stop any composer commands that are still running in the background.
+        for task in self.background_tasks.iter() {
+            task.value().abort();
+        }
+        self.background_tasks.clear();
+
         Ok(())
     }
 
-    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
-        self.on_change(params).await
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        self.on_change_workspace_folders(params.event).await
     }
 
-    async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        self.on_save(TextDocumentItem {
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        self.on_change_watched_files(params.changes).await
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        *self.settings.write().unwrap() = Settings::from_value(Some(params.settings));
+        configure_packagist(&self.settings.read().unwrap().packagist);
+        self.sync_registrations().await;
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let uri = params.text_document.uri.clone();
+        // The opened text is what's on disk, the first save compares with it.
+        if !lockfile::is_lock_file(&uri) {
+            self.saved_text
+                .insert(uri.to_string(), params.text_document.text.clone());
+        }
+        self.on_change(TextDocumentItem {
             uri: params.text_document.uri,
-            version: 1,
+            version: params.text_document.version,
+            text: params.text_document.text,
         })
         .await;
-    }
 
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        if !self.packagist_packages.contains_key("data") {
-            return Ok(None);
+        if lockfile::is_lock_file(&uri) {
+            self.check_lock_file(uri).await;
         }
-
-        let position = params.text_document_position.position;
-        let line_text = self.buffer.get(&position.line).unwrap().to_owned();
-
-        let start_completion_pos = line_text.rfind("\"");
-        match start_completion_pos {
-            Some(start_pos) => {
-                let partial_completion = line_text[start_pos..]
-                    .to_string()
-                    .replace(" ", "")
-                    .replace("\"", "")
-                    .replace("\n", "");
-
-                if partial_completion.len() >= 2 {
-                    let completions = || -> Option<Vec<CompletionItem>> {
-                        let mut ret = vec![];
-                        let all_packages = self.packagist_packages.get("data").unwrap();
-                        for name in all_packages.iter() {
-                            if name.starts_with(&partial_completion) {
-                                ret.push(CompletionItem {
-                                    label: name.to_string(),
-                                    insert_text: Some(name.to_string()),
-                                    kind: Some(CompletionItemKind::VARIABLE),
-                                    detail: Some(name.to_string()),
-                                    ..Default::default()
-                                });
-                            }
-                        }
-
-                        Some(ret)
-                    }();
-
-                    return Ok(completions.map(CompletionResponse::Array));
-                }
-            }
-            None => {}
-        };
-
-        Ok(None)
     }
 
-    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
-        self.on_execute_command(params).await
+    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+        self.on_change(TextDocumentItem {
+            uri: params.text_document.uri,
+            version: params.text_document.version,
+            text: std::mem::take(&mut params.content_changes[0].text),
+        })
+        .await
     }
-}
 
-impl Backend {
-    async fn on_change(&self, params: DidChangeTextDocumentParams) {
-        let changes = &params.content_changes[0];
-        let ropey = ropey::Rope::from_str(&changes.text);
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.on_close(params.text_document.uri).await
+    }
 
-        // clear buffer.
-        self.buffer.clear();
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        self.on_save(params.text_document.uri).await;
+    }
 
-        // write to buffer.
-        let mut line_num = 0;
-        for line in ropey.lines() {
-            self.buffer.insert(line_num, line.to_string());
-            line_num += 1;
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position.position;
+        let uri = &params.text_document_position.text_document.uri;
+        if lockfile::is_lock_file(uri) {
+            return Ok(None);
         }
-    }
+        let line_text = match self.buffer_line(uri, position.line) {
+            Some(line_text) => line_text,
+            None => return Ok(None),
+        };
 
-    async fn on_save(&self, params: TextDocumentItem) {
-        let composer_file =
-            ComposerFile::parse_from_path(params.uri.clone()).expect("Can't parse composer file");
+        let text = self.get_document_text(uri).unwrap_or_default();
+        let offset = json::position_to_offset(&text, position);
 
-        // Clear any old data.
-        if self.composer_file.contains_key("data") {
-            self.composer_file.remove("data").unwrap();
-        }
+        // Complete an author object from the git configuration.
+        if completion::is_in_array(&text, offset, "authors") {
+            let dir = uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+                .unwrap_or_default();
+            let (name, email) = completion::git_author(&dir).await;
 
-        self.composer_file
-            .insert("data".to_string(), composer_file.clone());
+            return Ok(Some(CompletionResponse::Array(vec![CompletionItem {
+                label: "author".to_string(),
+                kind: Some(CompletionItemKind::SNIPPET),
+                detail: name.clone(),
+                insert_text: Some(completion::author_snippet(
+                    name.as_deref(),
+                    email.as_deref(),
+                )),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            }])));
+        }
 
-        let update_data = packagist::get_packages_info(composer_file.dependencies.clone()).await;
+        // Complete references to other scripts in the commands of a script.
+        if let Some(script) = scripts::script_at(&text, offset) {
+            let (start, _) = match scripts::typed_reference(&line_text, position.character) {
+                Some(typed) => typed,
+                None => return Ok(None),
+            };
+            let root = match json::parse_tolerant(&text) {
+                Ok((root, _)) => root,
+                Err(_) => return Ok(None),
+            };
 
-        let mut diagnostics: Vec<Diagnostic> = vec![];
+            let completions = scripts::reference_completions(&root, &script)
+                .into_iter()
+                .map(|reference| CompletionItem {
+                    kind: Some(match reference.as_str() {
+                        "@php" | "@composer" | "@putenv" => CompletionItemKind::KEYWORD,
+                        _ => CompletionItemKind::FUNCTION,
+                    }),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                        range: Range::new(
+                            Position::new(position.line, start),
+                            Position::new(position.line, position.character),
+                        ),
+                        new_text: reference.clone(),
+                    })),
+                    label: reference,
+                    ..Default::default()
+                })
+                .collect();
 
-        // Loop through "require".
-        for item in composer_file.dependencies {
-            if item.name == "" {
-                continue;
-            }
+            return Ok(Some(CompletionResponse::Array(completions)));
+        }
 
-            // Packagist data.
-            let packagist_data = update_data.get(&item.name);
-            match packagist_data {
-                Some(package) => {
-                    let mut composer_lock_version = "".to_string();
-
-                    let composer_json_version = item.version.replace("\"", "");
-                    if composer_file.lock.is_some() {
-                        let lock_file = composer_file.lock.clone().unwrap();
-
-                        if lock_file.versions.len() > 0 {
-                            let installed_package = lock_file.versions.get(&item.name);
-                            match installed_package {
-                                Some(installed) => {
-                                    composer_lock_version = installed.version.clone()
-                                }
-                                None => {}
-                            }
+        // Complete the constraint of a requirement with the published versions.
+        if let Some(at) = completion::constraint_at(&line_text, position.character)
+            .filter(|_| completion::is_in_object(&text, offset, &["require", "require-dev"]))
+        {
+            let repositories = self
+                .document(uri)
+                .map(|composer_file| composer_file.repositories)
+                .unwrap_or_default();
+            let cached = self
+                .package_cache
+                .get(&at.package)
+                .map(|package| package.clone());
+            let package = match cached {
+                Some(package) => package,
+                None => {
+                    match repository::get_package_info(&repositories, at.package.clone(), false)
+                        .await
+                    {
+                        Some(package) => {
+                            self.package_cache
+                                .insert(at.package.clone(), package.clone());
+                            package
                         }
-                    }
-
-                    if let Some(version) = packagist::check_for_package_update(
-                        package,
-                        composer_json_version,
-                        composer_lock_version,
-                    ) {
-                        let diagnostic = || -> Option<Diagnostic> {
-                            Some(Diagnostic::new(
-                                Range::new(
-                                    Position {
-                                        line: item.line,
-                                        character: 1,
-                                    },
-                                    Position {
-                                        line: 0,
-                                        character: 1,
-                                    },
-                                ),
-                                Some(DiagnosticSeverity::WARNING),
-                                None,
-                                None,
-                                format!("Update available: {:?}", version),
-                                None,
-                                None,
-                            ))
-                        }();
-
-                        diagnostics.push(diagnostic.unwrap());
+                        None => return Ok(None),
                     }
                 }
-                None => {}
-            }
-        }
+            };
 
-        self.client
-            .publish_diagnostics(params.uri.clone(), diagnostics, Some(params.version))
-            .await;
-    }
+            let versions: Vec<String> = package
+                .versions
+                .iter()
+                .filter_map(|version| version.version.clone())
+                .collect();
+            let completions = completion::version_completions(&versions)
+                .into_iter()
+                .enumerate()
+                .map(|(index, constraint)| CompletionItem {
+                    label: constraint.clone(),
+                    kind: Some(CompletionItemKind::CONSTANT),
+                    // Keep the newest versions first.
+                    sort_text: Some(format!("{:05}", index)),
+                    filter_text: Some(constraint.clone()),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                        range: Range::new(
+                            Position::new(position.line, at.start),
+                            Position::new(position.line, at.end),
+                        ),
+                        new_text: match at.unterminated {
+                            true => format!("{}\"", constraint),
+                            false => constraint,
+                        },
+                    })),
+                    ..Default::default()
+                })
+                .collect();
 
-    async fn on_hover(&self, params: TextDocumentPositionParams) -> Option<Hover> {
-        if !self.composer_file.contains_key("data") {
-            return None;
+            return Ok(Some(CompletionResponse::Array(completions)));
         }
 
-        let composer_file = self.composer_file.get("data").unwrap();
+        let partial_completion =
+            match completion::get_completion_prefix(&line_text, position.character) {
+                Some(partial_completion) => partial_completion,
+                None => return Ok(None),
+            };
 
-        let line = params.position.line;
-        let dependency = composer_file.dependencies_by_line.get(&line);
+        // Replace the whole string, so typed quotes aren't doubled.
+        let quoted = |mut item: CompletionItem, is_key: bool| {
+            if let Some(insertion) =
+                completion::quoted_insertion(&line_text, position.character, &item.label, is_key)
+            {
+                item.text_edit = Some(CompletionTextEdit::Edit(TextEdit {
+                    range: Range::new(
+                        Position::new(position.line, insertion.start),
+                        Position::new(position.line, insertion.end),
+                    ),
+                    new_text: insertion.text,
+                }));
+                item.insert_text = None;
+                if insertion.snippet {
+                    item.insert_text_format = Some(InsertTextFormat::SNIPPET);
+                }
+            }
 
-        match dependency {
-            Some(name) => {
-                let package_info = packagist::get_package_info(name.to_string()).await;
-                match package_info {
-                    Some(data) => {
-                        let mut package_version = PackageVersion {
-                            name: None,
-                            description: None,
-                            keywords: None,
-                            homepage: None,
-                            version: None,
-                            version_normalized: None,
-                            license: None,
-                            authors: None,
-                            packagist_url: None,
-                        };
+            item
+        };
+        let is_key = completion::is_key_at(&line_text, position.character);
 
-                        match &composer_file.lock {
-                            Some(lock) => {
-                                if lock.versions.contains_key(name) {
-                                    let installed_package = lock.versions.get(name).unwrap();
+        // Complete the type of a repository.
+        if repositories::is_in_type_value(&text, offset) {
+            let completions = repositories::REPOSITORY_TYPES
+                .iter()
+                .filter(|repository_type| repository_type.starts_with(&partial_completion))
+                .map(|repository_type| {
+                    quoted(
+                        CompletionItem {
+                            label: repository_type.to_string(),
+                            kind: Some(CompletionItemKind::ENUM_MEMBER),
+                            ..Default::default()
+                        },
+                        false,
+                    )
+                })
+                .collect();
 
-                                    for item in data.versions.iter() {
-                                        let item_version =
-                                            item.version.as_ref().unwrap().to_owned();
+            return Ok(Some(CompletionResponse::Array(completions)));
+        }
 
-                                        if item_version.replace(".", "")
-                                            == installed_package.version.replace(".", "")
-                                        {
-                                            package_version = item.to_owned();
-                                        }
-                                    }
-                                } else {
-                                    package_version = data.versions.get(0).unwrap().to_owned();
-                                }
-                            }
-                            None => {
-                                package_version = data.versions.get(0).unwrap().to_owned();
-                            }
-                        }
+        // Complete the required plugins in allow-plugins.
+        if plugins::is_in_allow_plugins_key(&text, offset) {
+            let composer_file = match self.document(uri) {
+                Some(composer_file) => composer_file,
+                None => return Ok(None),
+            };
+            let locked_plugins = composer_file
+                .lock
+                .as_ref()
+                .map(|lock| lock.plugins.clone())
+                .unwrap_or_default();
 
-                        let mut contents = vec![];
+            let completions = locked_plugins
+                .into_iter()
+                .filter(|plugin| {
+                    plugin.starts_with(&partial_completion)
+                        && composer_file.get_dependency(plugin).is_some()
+                })
+                .map(|plugin| {
+                    quoted(
+                        CompletionItem {
+                            label: plugin,
+                            kind: Some(CompletionItemKind::MODULE),
+                            detail: Some("composer-plugin".to_string()),
+                            ..Default::default()
+                        },
+                        false,
+                    )
+                })
+                .collect();
 
-                        let description = package_version.description.as_ref();
-                        match description {
-                            Some(desc) => {
-                                let description_contents =
-                                    MarkedString::from_markdown(desc.to_string());
-                                contents.push(description_contents);
+            return Ok(Some(CompletionResponse::Array(completions)));
+        }
 
-                                let new_line = MarkedString::from_markdown("".to_string());
-                                contents.push(new_line);
-                            }
-                            None => {
-                                // Just pull latest.
-                                let latest_package_version =
-                                    data.versions.get(0).unwrap().to_owned();
-
-                                let description_contents = MarkedString::from_markdown(
-                                    latest_package_version.description.unwrap().to_string(),
-                                );
-                                contents.push(description_contents);
-                            }
-                        }
+        // Complete popular tags inside the keywords array.
+        if completion::is_in_array(&text, offset, "keywords") {
+            let completions = completion::keyword_completions(&partial_completion)
+                .into_iter()
+                .map(|keyword| {
+                    quoted(
+                        CompletionItem {
+                            label: keyword.to_string(),
+                            kind: Some(CompletionItemKind::KEYWORD),
+                            ..Default::default()
+                        },
+                        false,
+                    )
+                })
+                .collect();
 
-                        let homepage = package_version.homepage.as_ref();
-                        match homepage {
-                            Some(page) => {
-                                let homepage_contents =
-                                    MarkedString::from_markdown(format!("Homepage: {}", page));
-                                contents.push(homepage_contents);
+            return Ok(Some(CompletionResponse::Array(completions)));
+        }
 
-                                let new_line = MarkedString::from_markdown("".to_string());
-                                contents.push(new_line);
-                            }
-                            None => {
-                                // Just pull latest.
-                                let latest_package_version =
-                                    data.versions.get(0).unwrap().to_owned();
-
-                                let homepage_contents = MarkedString::from_markdown(
-                                    latest_package_version.homepage.unwrap().to_string(),
-                                );
-                                contents.push(homepage_contents);
+        // Complete the keys and values the composer schema knows.
+        if let Some(path) = schema::object_path_at(&text, offset) {
+            let properties = schema::properties(&path).filter(|_| is_key);
+            if let Some(properties) = properties {
+                let completions = properties
+                    .iter()
+                    .filter(|property| property.name.starts_with(&partial_completion))
+                    .map(|property| {
+                        let mut item = CompletionItem {
+                            label: property.name.to_string(),
+                            kind: Some(CompletionItemKind::PROPERTY),
+                            detail: Some(property.description.to_string()),
+                            ..Default::default()
+                        };
+                        if let Some(insertion) = completion::key_insertion(
+                            &line_text,
+                            position.character,
+                            property.name,
+                            property.value_snippet(),
+                        ) {
+                            item.text_edit = Some(CompletionTextEdit::Edit(TextEdit {
+                                range: Range::new(
+                                    Position::new(position.line, insertion.start),
+                                    Position::new(position.line, insertion.end),
+                                ),
+                                new_text: insertion.text,
+                            }));
+                            if insertion.snippet {
+                                item.insert_text_format = Some(InsertTextFormat::SNIPPET);
                             }
                         }
 
-                        let range = Range::new(
-                            Position { line, character: 1 },
-                            Position {
-                                line: 0,
-                                character: 1,
-                            },
-                        );
+                        item
+                    })
+                    .collect();
 
-                        return Some(Hover {
-                            contents: HoverContents::Array(contents),
-                            range: Some(range),
-                        });
-                    }
-                    None => {
-                        let error = format!("No hover data found for: {}", name);
-                        log::error!("{}", error);
-                        self.client.log_message(MessageType::ERROR, error).await;
-                    }
-                }
+                return Ok(Some(CompletionResponse::Array(completions)));
             }
 
-            None => {
-                let error = format!(
-                    "Hover failed, because we can't find this line number: {}",
-                    line
-                );
+            let property = schema::value_key_at(&line_text, position.character)
+                .filter(|_| !is_key)
+                .and_then(|key| schema::property(&path, &key))
+                .filter(|property| !property.values.is_empty());
+            if let Some(property) = property {
+                let completions = property
+                    .values
+                    .iter()
+                    .filter(|value| {
+                        value
+                            .to_lowercase()
+                            .starts_with(&partial_completion.to_lowercase())
+                    })
+                    .map(|value| {
+                        quoted(
+                            CompletionItem {
+                                label: value.to_string(),
+                                kind: Some(CompletionItemKind::ENUM_MEMBER),
+                                ..Default::default()
+                            },
+                            false,
+                        )
+                    })
+                    .collect();
 
-                log::error!("{}", error);
-                self.client.log_message(MessageType::ERROR, error).await;
+                return Ok(Some(CompletionResponse::Array(completions)));
             }
         }
 
-        None
-    }
-
-    async fn goto_definition(
-        &self,
-        params: GotoDefinitionParams,
-    ) -> Option<GotoDefinitionResponse> {
-        if !self.composer_file.contains_key("data") {
-            return None;
-        }
+        // Suggest concrete providers for a virtual package.
+        if packagist::is_virtual_package(&partial_completion) {
+            let dir = uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| path.parent().map(|dir| dir.to_path_buf()));
+            let providers =
+                match packagist::get_providers(&partial_completion, dir.as_deref()).await {
+                    Ok(providers) => providers,
+                    Err(error) => {
+                        log::info!(
+                            "Can't get the providers of {}: {}",
+                            partial_completion,
+                            error
+                        );
+                        return Ok(None);
+                    }
+                };
 
-        let composer_file = self.composer_file.get("data").unwrap();
+            let completions = providers
+                .into_iter()
+                .enumerate()
+                .map(|(position, provider)| {
+                    quoted(
+                        CompletionItem {
+                            label: provider.name,
+                            // Keep the providers visible, even though they
+                            // don't match the typed name.
+                            filter_text: Some(partial_completion.clone()),
+                            sort_text: Some(format!("{:05}", position)),
+                            kind: Some(CompletionItemKind::VARIABLE),
+                            detail: provider.description,
+                            ..Default::default()
+                        },
+                        is_key,
+                    )
+                })
+                .collect();
 
-        let line = params.text_document_position_params.position.line;
-        let dependency = composer_file.dependencies_by_line.get(&line);
+            return Ok(Some(CompletionResponse::Array(completions)));
+        }
 
-        match dependency {
-            Some(name) => {
-                let package_info = packagist::get_package_info(name.to_string()).await;
-                match package_info {
-                    Some(data) => {
-                        let mut package_version = PackageVersion {
-                            name: None,
-                            description: None,
-                            keywords: None,
-                            homepage: None,
-                            version: None,
-                            version_normalized: None,
-                            license: None,
-                            authors: None,
-                            packagist_url: None,
-                        };
+        let package_index = match self.package_index.read().unwrap().clone() {
+            Some(package_index) => package_index,
+            None => return Ok(None),
+        };
 
-                        match &composer_file.lock {
-                            Some(lock) => {
-                                if lock.versions.contains_key(name) {
-                                    let installed_package = lock.versions.get(name).unwrap();
+        if partial_completion.len() >= 2 {
+            let typed = partial_completion.to_lowercase();
+            let limit = completion::MAX_PACKAGE_COMPLETIONS;
 
-                                    for item in data.versions.iter() {
-                                        let item_version =
-                                            item.version.as_ref().unwrap().to_owned();
+            let mut names: Vec<&str> = package_index.starting_with(&typed).collect();
+            // Also match on the package part, when only that is typed.
+            if !typed.contains('/') {
+                names.extend(package_index.package_part_starting_with(&typed, limit));
+            }
+            // Fall back to fuzzy matches, for typos and abbreviations.
+            if names.len() < limit {
+                names.extend(package_index.fuzzy_matching(&typed, limit));
+            }
 
-                                        if item_version == installed_package.version {
-                                            package_version = item.to_owned();
-                                        }
-                                    }
-                                } else {
-                                    package_version = data.versions.get(0).unwrap().to_owned();
-                                }
-                            }
-                            None => {
-                                package_version = data.versions.get(0).unwrap().to_owned();
-                            }
-                        }
+            // The names of the typed vendor, e.g. "monolog/*" for "monolog",
+            // come first.
+            names.sort_by_cached_key(|name| completion::package_sort_text(name, &typed));
+            names.dedup();
+            let is_incomplete = names.len() > limit;
+            names.truncate(limit);
 
-                        let packagist_url = package_version.packagist_url.as_ref();
-                        match packagist_url {
-                            Some(page) => {
-                                if webbrowser::open(page).is_ok() {
-                                    return None;
-                                }
-                            }
-                            None => {
-                                // Try to get the latest one.
-                                let latest_package_version =
-                                    data.versions.get(0).unwrap().to_owned();
-
-                                if webbrowser::open(&latest_package_version.packagist_url.unwrap())
-                                    .is_ok()
-                                {
-                                    return None;
-                                } else {
-                                    let error =
-                                        format!("Can't open the definition_url for: {}", name);
-                                    log::error!("{}", error);
-                                    self.client.log_message(MessageType::ERROR, error).await;
-                                }
-                            }
-                        }
-                    }
-                    None => {
-                        let error = format!("No definiton data found for: {}", name);
-                        log::error!("{}", error);
-                        self.client.log_message(MessageType::ERROR, error).await;
-                    }
-                }
+            let mut completions = vec![];
+            for name in names {
+                completions.push(quoted(
+                    CompletionItem {
+                        label: name.to_string(),
+                        filter_text: Some(completion::package_filter_text(name)),
+                        sort_text: Some(completion::package_sort_text(name, &typed)),
+                        kind: Some(CompletionItemKind::VARIABLE),
+                        // The description and latest version are added on
+                        // resolve, so the list stays cheap.
+                        data: Some(serde_json::json!({
+                            "package": name,
+                            "uri": uri.to_string(),
+                        })),
+                        ..Default::default()
+                    },
+                    is_key,
+                ));
             }
-            None => {
-                let error = format!(
-                    "Go to definition failed, because we can't find this line number: {}",
-                    line
-                );
 
-                log::error!("{}", error);
-                self.client.log_message(MessageType::ERROR, error).await;
-            }
+            return Ok(Some(CompletionResponse::List(CompletionList {
+                is_incomplete,
+                items: completions,
+            })));
         }
 
-        None
+        Ok(None)
     }
 
-    async fn on_code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
-        if !self.composer_file.contains_key("data") {
-            return Err(Error::method_not_found());
-        }
+    async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
+        Ok(self.on_completion_resolve(params).await)
+    }
 
-        let composer_file = self.composer_file.get("data").unwrap();
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
+        self.on_execute_command(params).await
+    }
 
-        let range_start_line = params.range.start.line;
-        let range_end_line = params.range.end.line;
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        Ok(self.on_selection_range(params))
+    }
 
-        if range_start_line != range_end_line {
-            return Err(Error::method_not_found());
-        }
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        Ok(self.on_document_symbol(params))
+    }
 
-        let line = range_start_line;
-        let dependency_found = composer_file.dependencies_by_line.get(&line);
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        Ok(self.on_document_link(params))
+    }
 
-        match dependency_found {
-            Some(dependency) => {
-                let mut commands = vec![];
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        Ok(self.on_code_lens(params))
+    }
 
-                if composer_file.lock.is_none() {
-                    let install_command = Command {
-                        title: "Install all packages".to_string(),
-                        command: "install".to_string(),
-                        arguments: Some(vec![]),
-                    };
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        Ok(self.on_semantic_tokens(params))
+    }
 
-                    commands.push(CodeActionOrCommand::Command(install_command));
-                } else {
-                    let update_command = Command {
-                        title: "Update package".to_string(),
-                        command: "update".to_string(),
-                        arguments: Some(vec![Value::from(dependency.to_owned())]),
-                    };
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensFullDeltaResult>> {
+        Ok(self.on_semantic_tokens_delta(params))
+    }
+}
 
-                    commands.push(CodeActionOrCommand::Command(update_command));
-                }
+impl Backend {
+    async fn on_change(&self, params: TextDocumentItem) {
+        self.document_versions
+            .insert(params.uri.to_string(), params.version);
 
-                return Ok(Some(commands));
-            }
-            None => {
-                return Err(Error::method_not_found());
+        let ropey = ropey::Rope::from_str(&params.text);
+        let lines = ropey.lines().map(|line| line.to_string()).collect();
+        self.buffer.insert(params.uri.to_string(), lines);
+
+        // composer.lock only gets hovers, and diagnostics once it's opened
+        // or saved.
+        if lockfile::is_lock_file(&params.uri) {
+            return;
+        }
+        *self.active_document.write().unwrap() = Some(params.uri.to_string());
+
+        // Keep the dependency lines in sync with the unsaved changes, so
+        // hover and definition don't point at stale lines until the next save.
+        let updated = match self.composer_file.get_mut(params.uri.as_str()) {
+            Some(mut composer_file) => composer_file.update_from_text(&params.text),
+            None => false,
+        };
+        if !updated {
+            match ComposerFile::parse_from_str(&params.text, params.uri.clone()) {
+                Some(composer_file) => {
+                    self.composer_file
+                        .insert(params.uri.to_string(), composer_file);
+                }
+                None => return,
             }
         }
+
+        self.refresh_diagnostics(params.uri, params.text).await;
     }
 
-    async fn on_execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
-        if !self.composer_file.contains_key("data") {
-            return Ok(None);
+    /// Refreshes the diagnostics while the document is edited, once the
+    /// typing paused. Clients pulling diagnostics request them on every change
+    /// themselves.
+    async fn refresh_diagnostics(&self, uri: Url, text: String) {
+        if self.pull_support.read().unwrap().enabled {
+            return;
         }
 
-        let composer_file = self.composer_file.get("data").unwrap();
-        let command = &params.command[..];
+        let analysis = self.start_analysis(&uri);
+        tokio::time::sleep(CHANGE_DEBOUNCE).await;
+        if !self.is_latest_analysis(&uri, analysis) {
+            return;
+        }
 
-        match command {
-            "update" => {
-                let command_path = composer_file
-                    .path
-                    .replace("/composer.json", "")
-                    .replace("file://", "");
-                if params.arguments.len() <= 0 {
-                    return Ok(None);
-                }
+        let version = self
+            .document_versions
+            .get(uri.as_str())
+            .map(|version| *version);
+        let diagnostics = match self.current_diagnostics(&uri, &text).await {
+            Some(diagnostics) => diagnostics,
+            None => return,
+        };
 
-                let dependency = params.arguments.get(0).unwrap().as_str().unwrap();
-                let output = ProcessCommand::new("composer")
-                    .arg(format!("--working-dir={}", command_path).as_str())
-                    .arg("update")
-                    .arg(dependency)
-                    .output()
-                    .expect("failed to execute process");
+        if !self.is_latest_analysis(&uri, analysis) {
+            return;
+        }
+        self.diagnostics.insert(uri.to_string(), diagnostics);
+        self.publish_document_diagnostics(uri, version).await;
+    }
 
-                if !output.status.success() {
-                    self.client
-                        .show_message(MessageType::INFO, "Composer command failed.")
-                        .await;
-                    return Err(Error::new(ServerError(400)));
-                }
+    /// The diagnostics of the document's current text. Packages are only
+    /// fetched on save, so this reuses what the last save found out about
+    /// them.
+    async fn current_diagnostics(&self, uri: &Url, text: &str) -> Option<Vec<Diagnostic>> {
+        let composer_file = self.document(uri)?;
+        let checks = self
+            .saved_checks
+            .get(uri.as_str())
+            .map(|checks| checks.clone())
+            .unwrap_or_default();
 
-                match from_utf8(&output.stderr) {
-                    Ok(message) => {
-                        if message.contains("Your requirements could not be resolved to an installable set of packages") {
-                            self.client.show_message(MessageType::INFO, "Composer dependencies could not be resolved.").await;
-                            return Ok(None);
-                        }
+        let update_data = composer_file
+            .dependencies
+            .iter()
+            .chain(composer_file.dev_dependencies.iter())
+            .filter_map(|dependency| {
+                let package = self.package_cache.get(&dependency.name)?;
+                Some((dependency.name.clone(), package.clone()))
+            })
+            .collect();
+        // Removed packages aren't unchecked anymore.
+        let failed_packages = checks
+            .failed_packages
+            .into_iter()
+            .filter(|name| composer_file.get_dependency(name).is_some())
+            .collect();
 
-                        self.client
-                            .show_message(
-                                MessageType::INFO,
-                                format!("Composer package {} was updated.", dependency),
+        let mut diagnostics = self
+            .diagnose(text, composer_file.clone(), &update_data, failed_packages)
+            .await;
+        if !checks.advisories.is_empty() {
+            add_advisories(
+                &mut diagnostics,
+                advisory_diagnostics(text, &checks.advisories),
+                &composer_file,
+            );
+        }
+        diagnostics.extend(validation_diagnostics(
+            text,
+            &composer_file,
+            &checks.validation,
+        ));
+
+        Some(diagnostics)
+    }
+
+    /// Answers textDocument/diagnostic with the diagnostics of the current
+    /// buffer, or that they didn't change since the previous report.
+    async fn document_diagnostic(
+        &self,
+        params: pull::DocumentDiagnosticParams,
+    ) -> Result<pull::DocumentDiagnosticReport> {
+        let uri = params.text_document.uri;
+
+        // composer.lock is only checked when it's opened or saved.
+        let mut diagnostics = match self.get_document_text(&uri) {
+            Some(text) if !lockfile::is_lock_file(&uri) => self
+                .current_diagnostics(&uri, &text)
+                .await
+                .unwrap_or_default(),
+            _ => self
+                .diagnostics
+                .get(uri.as_str())
+                .map(|diagnostics| diagnostics.clone())
+                .unwrap_or_default(),
+        };
+        if let Some(command_diagnostics) = self.command_diagnostics.get(uri.as_str()) {
+            diagnostics.extend(command_diagnostics.iter().cloned());
+        }
+
+        Ok(pull::report(
+            diagnostics,
+            params.previous_result_id.as_deref(),
+        ))
+    }
+
+    /// Counts a new analysis of the document, so a slower previous one can't
+    /// overwrite its diagnostics.
+    fn start_analysis(&self, uri: &Url) -> u64 {
+        let mut counter = self.analyses.entry(uri.to_string()).or_insert(0);
+        *counter += 1;
+        *counter
+    }
+
+    fn is_latest_analysis(&self, uri: &Url, analysis: u64) -> bool {
+        match self.analyses.get(uri.as_str()) {
+            Some(latest) => *latest == analysis,
+            None => false,
+        }
+    }
+
+    /// Adds the latest version and the description to a completed package
+    /// name.
+    async fn on_completion_resolve(&self, mut item: CompletionItem) -> CompletionItem {
+        let data = match &item.data {
+            Some(data) => data.clone(),
+            None => return item,
+        };
+        let name = match data.get("package").and_then(|name| name.as_str()) {
+            Some(name) => name,
+            None => return item,
+        };
+        let repositories = data
+            .get("uri")
+            .and_then(|uri| uri.as_str())
+            .and_then(|uri| Url::parse(uri).ok())
+            .and_then(|uri| self.document(&uri))
+            .map(|composer_file| composer_file.repositories)
+            .unwrap_or_default();
+        let network = self.settings.read().unwrap().hover.network;
+
+        if let Some(package) = self.package_info(name, &repositories, false, network).await {
+            item.detail = package.latest_stable_version();
+            item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: completion::package_documentation(&package),
+            }));
+        }
+
+        item
+    }
+
+    /// Adds the packagist advisories of the locked versions to the
+    /// diagnostics, and keeps them for the diagnostics while editing.
+    async fn add_packagist_advisories(
+        &self,
+        uri: &Url,
+        text: &str,
+        composer_file: &ComposerFile,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let lock = match &composer_file.lock {
+            Some(lock) => lock,
+            None => return,
+        };
+
+        let installed = lock
+            .versions
+            .iter()
+            .map(|(name, package)| (name.to_string(), package.version.to_string()))
+            .collect();
+        match packagist::get_security_advisories(&installed, Some(&composer_file.dir())).await {
+            Ok(advisories) => {
+                add_advisories(
+                    diagnostics,
+                    advisory_diagnostics(text, &advisories),
+                    composer_file,
+                );
+                self.saved_checks
+                    .entry(uri.to_string())
+                    .or_default()
+                    .advisories = advisories;
+            }
+            Err(error) => log::warn!("Can't get the security advisories: {}", error),
+        }
+    }
+
+    /// Puts diagnostics on the locked packages with security advisories.
+    async fn check_lock_file(&self, uri: Url) {
+        let analysis = self.start_analysis(&uri);
+        let version = self
+            .document_versions
+            .get(uri.as_str())
+            .map(|version| *version);
+        let text = match self.get_document_text(&uri) {
+            Some(text) => text,
+            None => return,
+        };
+        let entries = match json::parse_tolerant(&text) {
+            Ok((root, _)) => lockfile::find_entries(&root),
+            Err(_) => return,
+        };
+
+        let installed = entries
+            .iter()
+            .filter_map(|entry| Some((entry.name.to_lowercase(), entry.version.clone()?)))
+            .collect();
+        let dir = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()));
+        let advisories = match packagist::get_security_advisories(&installed, dir.as_deref()).await
+        {
+            Ok(advisories) => advisories,
+            Err(error) => {
+                log::warn!("Can't get the security advisories: {}", error);
+                return;
+            }
+        };
+
+        let index = json::LineIndex::new(&text);
+        let diagnostics = advisories
+            .iter()
+            .filter_map(|advisory| {
+                let entry = entries
+                    .iter()
+                    .find(|entry| entry.name.eq_ignore_ascii_case(&advisory.package))?;
+                Some(advisory_diagnostic(index.range(entry.name_span), advisory))
+            })
+            .collect();
+
+        if !self.is_latest_analysis(&uri, analysis) {
+            return;
+        }
+        self.diagnostics.insert(uri.to_string(), diagnostics);
+        self.publish_document_diagnostics(uri, version).await;
+    }
+
+    /// Describes the composer.lock entry under the cursor, and whether the
+    /// composer.json next to it allows an update.
+    async fn lock_file_hover(&self, params: TextDocumentPositionParams) -> Option<Hover> {
+        let text = self.get_document_text(&params.text_document.uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let entries = lockfile::find_entries(&root);
+        let offset = json::position_to_offset(&text, params.position);
+        let entry = lockfile::entry_at(&entries, offset)?;
+        let name = entry.name.to_lowercase();
+
+        let json_uri = lockfile::composer_json_uri(&params.text_document.uri)?;
+        let composer_file = self
+            .document(&json_uri)
+            .or_else(|| ComposerFile::parse_from_path(json_uri));
+        let network = self.settings.read().unwrap().hover.network;
+
+        let status = match composer_file {
+            Some(composer_file) => {
+                let constraint = composer_file
+                    .get_dependency(&name)
+                    .map(|dependency| dependency.version.clone());
+                let include_dev = constraint
+                    .as_deref()
+                    .map(packagist::requires_dev_branch)
+                    .unwrap_or(false);
+                let package = self
+                    .package_info(&name, &composer_file.repositories, include_dev, network)
+                    .await;
+
+                match (constraint, package) {
+                    (Some(constraint), Some(package)) => {
+                        let update = if self.settings.read().unwrap().updates.is_ignored(&name) {
+                            None
+                        } else {
+                            packagist::check_for_package_update(
+                                &package,
+                                constraint.clone(),
+                                entry.version.clone().unwrap_or_default(),
+                                &self.update_policy(&composer_file),
+                            )
+                        };
+                        lockfile::UpdateStatus::Required { constraint, update }
+                    }
+                    (None, package) => lockfile::UpdateStatus::Transitive {
+                        latest: package.and_then(|package| package.latest_stable_version()),
+                        required_by: composer_file
+                            .lock
+                            .as_ref()
+                            .and_then(|lock| why::required_by_summary(lock, &name)),
+                    },
+                    (Some(_), None) => lockfile::UpdateStatus::Unknown,
+                }
+            }
+            None => lockfile::UpdateStatus::Unknown,
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: lockfile::entry_hover(entry, &status),
+            }),
+            range: Some(json::span_to_range(&text, entry.name_span)),
+        })
+    }
+
+    /// The parsed open document.
+    fn document(&self, uri: &Url) -> Option<ComposerFile> {
+        self.composer_file
+            .get(uri.as_str())
+            .map(|composer_file| composer_file.clone())
+    }
+
+    /// A line of the open document, with its unsaved changes.
+    fn buffer_line(&self, uri: &Url, line: u32) -> Option<String> {
+        self.buffer.get(uri.as_str())?.get(line as usize).cloned()
+    }
+
+    async fn on_close(&self, uri: Url) {
+        // Drop everything we know about the closed document.
+        self.buffer.remove(&uri.to_string());
+        self.composer_file.remove(&uri.to_string());
+        self.saved_text.remove(&uri.to_string());
+        self.document_versions.remove(&uri.to_string());
+        // Bump the generation instead of forgetting it, so the pending
+        // analyses are dropped, even when the document is opened again.
+        self.start_analysis(&uri);
+        self.saved_checks.remove(&uri.to_string());
+        self.diagnostics.remove(&uri.to_string());
+        self.command_diagnostics.remove(&uri.to_string());
+        self.semantic_tokens.remove(&uri.to_string());
+        if let Some((_, task)) = self.background_tasks.remove(&uri.to_string()) {
+            task.abort();
+        }
+
+        {
+            let mut active_document = self.active_document.write().unwrap();
+            if active_document.as_deref() == Some(uri.as_str()) {
+                *active_document = None;
+            }
+        }
+
+        // The diagnostics of a workspace file stay, as they are on disk.
+        if self.workspace_files.contains_key(uri.as_str()) {
+            self.check_workspace_file(uri).await;
+            return;
+        }
+
+        // Clear the diagnostics of the closed document.
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn on_change_workspace_folders(&self, event: WorkspaceFoldersChangeEvent) {
+        for folder in event.removed {
+            self.workspace_folders
+                .write()
+                .unwrap()
+                .retain(|uri| *uri != folder.uri);
+            let folder_dir = match folder.uri.to_file_path() {
+                Ok(folder_dir) => folder_dir,
+                Err(_) => continue,
+            };
+            let is_in_folder = |uri: &str| {
+                Url::parse(uri)
+                    .ok()
+                    .and_then(|uri| uri.to_file_path().ok())
+                    .is_some_and(|path| path.starts_with(&folder_dir))
+            };
+
+            // Forget the composer files of the removed folder.
+            let removed: Vec<String> = self
+                .workspace_files
+                .iter()
+                .map(|file| file.key().to_string())
+                .filter(|uri| is_in_folder(uri))
+                .collect();
+            for uri in removed {
+                self.workspace_files.remove(&uri);
+                if let Ok(uri) = Url::parse(&uri) {
+                    self.client.publish_diagnostics(uri, vec![], None).await;
+                }
+            }
+
+            let open: Vec<String> = self
+                .composer_file
+                .iter()
+                .map(|composer_file| composer_file.key().to_string())
+                .filter(|uri| is_in_folder(uri))
+                .collect();
+            for uri in open {
+                if let Ok(uri) = Url::parse(&uri) {
+                    self.on_close(uri).await;
+                }
+            }
+        }
+
+        let mut tracked = vec![];
+        for folder in event.added {
+            self.workspace_folders
+                .write()
+                .unwrap()
+                .push(folder.uri.clone());
+            tracked.extend(self.track_workspace_folder(&folder.uri));
+        }
+        if self.settings.read().unwrap().workspace.scan {
+            self.check_workspace_files(tracked).await;
+        }
+    }
+
+    /// Parses the composer.json files of the workspace folder, all of them
+    /// when the workspace is scanned, else only the root one.
+    fn track_workspace_folder(&self, folder_uri: &Url) -> Vec<Url> {
+        let folder_path = match folder_uri.to_file_path() {
+            Ok(folder_path) => folder_path,
+            Err(_) => return vec![],
+        };
+        let paths = match self.settings.read().unwrap().workspace.scan {
+            true => workspace::find_composer_files(&folder_path),
+            false => vec![folder_path.join("composer.json")],
+        };
+
+        let mut tracked = vec![];
+        for path in paths.into_iter().filter(|path| path.exists()) {
+            let uri = match Url::from_file_path(&path) {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+
+            info!("Tracking {}", uri);
+            if let Some(composer_file) = ComposerFile::parse_from_path(uri.clone()) {
+                self.workspace_files.insert(uri.to_string(), composer_file);
+                tracked.push(uri);
+            }
+        }
+
+        tracked
+    }
+
+    /// Checks the composer.json files and publishes their diagnostics, with
+    /// progress.
+    async fn check_workspace_files(&self, uris: Vec<Url>) {
+        if uris.is_empty() {
+            return;
+        }
+
+        let progress = runner::Progress::begin(&self.client, "Checking the workspace").await;
+        for (checked, uri) in uris.iter().enumerate() {
+            progress
+                .report(
+                    format!("Checking {}", uri),
+                    Some((checked * 100 / uris.len()) as u32),
+                )
+                .await;
+            self.check_workspace_file(uri.clone()).await;
+        }
+        progress.end(None).await;
+
+        self.refresh_features().await;
+    }
+
+    /// Checks a composer.json as it's on disk, or in the buffer when it's
+    /// open, and publishes its diagnostics.
+    async fn check_workspace_file(&self, uri: Url) {
+        let analysis = self.start_analysis(&uri);
+        let (text, composer_file) = match self.read_workspace_file(&uri) {
+            Some(file) => file,
+            None => return,
+        };
+        match self.composer_file.contains_key(uri.as_str()) {
+            true => self
+                .composer_file
+                .insert(uri.to_string(), composer_file.clone()),
+            false => self
+                .workspace_files
+                .insert(uri.to_string(), composer_file.clone()),
+        };
+
+        let mut diagnostics = self.analyze(&text, composer_file.clone(), None).await;
+        self.add_packagist_advisories(&uri, &text, &composer_file, &mut diagnostics)
+            .await;
+
+        if !self.is_latest_analysis(&uri, analysis) {
+            return;
+        }
+        let version = self
+            .document_versions
+            .get(uri.as_str())
+            .map(|version| *version);
+        self.diagnostics.insert(uri.to_string(), diagnostics);
+        self.publish_document_diagnostics(uri, version).await;
+    }
+
+    /// Checks the composer.json files again that changed on disk, or whose
+    /// composer.lock did, e.g. after a composer update in a terminal.
+    async fn on_change_watched_files(&self, changes: Vec<FileEvent>) {
+        let scan = self.settings.read().unwrap().workspace.scan;
+        let folders = self.workspace_folders.read().unwrap().clone();
+        let is_in_workspace = |uri: &Url| {
+            folders.iter().any(|folder| {
+                uri.path()
+                    .starts_with(&format!("{}/", folder.path().trim_end_matches('/')))
+            })
+        };
+
+        let mut changed: Vec<Url> = vec![];
+        for change in changes {
+            if !workspace::is_workspace_path(change.uri.path()) {
+                continue;
+            }
+
+            let uri = match lockfile::is_lock_file(&change.uri) {
+                true => {
+                    if self.buffer.contains_key(change.uri.as_str()) {
+                        self.check_lock_file(change.uri.clone()).await;
+                    }
+                    match lockfile::composer_json_uri(&change.uri) {
+                        Some(uri) => uri,
+                        None => continue,
+                    }
+                }
+                false if change.uri.path().ends_with("/composer.json") => change.uri.clone(),
+                false => continue,
+            };
+            let is_open = self.composer_file.contains_key(uri.as_str());
+
+            if change.typ == FileChangeType::DELETED && change.uri == uri {
+                if self.workspace_files.remove(uri.as_str()).is_some() && !is_open {
+                    self.diagnostics.remove(uri.as_str());
+                    self.client.publish_diagnostics(uri, vec![], None).await;
+                }
+                continue;
+            }
+            // Saving an open composer.json checks it already.
+            if is_open && change.uri == uri {
+                continue;
+            }
+
+            let is_tracked = self.workspace_files.contains_key(uri.as_str());
+            if (is_open || (scan && (is_tracked || is_in_workspace(&uri))))
+                && !changed.contains(&uri)
+            {
+                changed.push(uri);
+            }
+        }
+
+        changed.retain(|uri| uri.to_file_path().is_ok_and(|path| path.exists()));
+        self.check_workspace_files(changed).await;
+    }
+
+    fn get_document_text(&self, uri: &Url) -> Option<String> {
+        match self.buffer.get(uri.as_str()) {
+            Some(lines) => Some(lines.concat()),
+            None => std::fs::read_to_string(uri.to_file_path().ok()?).ok(),
+        }
+    }
+
+    fn on_selection_range(&self, params: SelectionRangeParams) -> Option<Vec<SelectionRange>> {
+        let text = self.get_document_text(&params.text_document.uri)?;
+        let root = match json::parse(&text) {
+            Ok(root) => root,
+            Err(error) => {
+                log::info!("Can't build selection ranges: {}", error.message);
+                return None;
+            }
+        };
+
+        let document_range = json::span_to_range(&text, json::Span::new(0, text.len()));
+
+        let mut ranges = vec![];
+        for position in params.positions {
+            let offset = json::position_to_offset(&text, position);
+
+            // Grow from the innermost node outwards, ending with the whole document.
+            let mut selection_range = SelectionRange {
+                range: document_range,
+                parent: None,
+            };
+            for span in root.spans_at(offset) {
+                let range = json::span_to_range(&text, span);
+                if range == selection_range.range {
+                    continue;
+                }
+
+                selection_range = SelectionRange {
+                    range,
+                    parent: Some(Box::new(selection_range)),
+                };
+            }
+
+            ranges.push(selection_range);
+        }
+
+        Some(ranges)
+    }
+
+    async fn on_save(&self, uri: Url) {
+        if lockfile::is_lock_file(&uri) {
+            self.check_lock_file(uri).await;
+            return;
+        }
+
+        // auth.json may have changed along with composer.json.
+        auth::clear_cache();
+
+        let analysis = self.start_analysis(&uri);
+        let version = self
+            .document_versions
+            .get(&uri.to_string())
+            .map(|version| *version);
+
+        let text = match self.get_document_text(&uri) {
+            Some(text) => text,
+            None => return,
+        };
+        let composer_file = match ComposerFile::parse_from_str(&text, uri.clone()) {
+            Some(composer_file) => composer_file,
+            None => return,
+        };
+
+        // The saved file can change the effective configuration.
+        self.effective_configs.remove(&composer_file.working_dir());
+
+        // Clear any old data.
+        self.composer_file.remove(&uri.to_string());
+
+        // The document follows the unsaved changes, so the requirements are
+        // compared with the previously saved text.
+        let previous_text = self.saved_text.insert(uri.to_string(), text.clone());
+        let previous_file = previous_text
+            .as_ref()
+            .and_then(|previous_text| ComposerFile::parse_from_str(previous_text, uri.clone()));
+        let metadata_only = match (previous_file, previous_text) {
+            (Some(previous_file), Some(previous_text)) => {
+                previous_text != text
+                    && previous_file.dependencies_equal(&composer_file)
+                    && composer_file.lock.is_some()
+            }
+            _ => false,
+        };
+
+        let (on_save, commands) = {
+            let settings = self.settings.read().unwrap();
+            (settings.on_save.clone(), settings.commands.clone())
+        };
+        let task = tokio::spawn(run_on_save_hooks(
+            self.client.clone(),
+            on_save.clone(),
+            commands.clone(),
+            composer_file.working_dir(),
+            composer_file.is_global(),
+            metadata_only,
+        ));
+
+        // A newer save supersedes the hooks of the previous one.
+        if let Some(previous_task) = self.background_tasks.insert(uri.to_string(), task) {
+            previous_task.abort();
+        }
+
+        self.composer_file
+            .insert(uri.to_string(), composer_file.clone());
+
+        let progress = runner::Progress::begin(&self.client, "Checking packages").await;
+        let mut diagnostics = self
+            .analyze(&text, composer_file.clone(), Some(&progress))
+            .await;
+        progress.end(None).await;
+
+        self.add_packagist_advisories(&uri, &text, &composer_file, &mut diagnostics)
+            .await;
+
+        if on_save.validate {
+            let problems = self.validate(&composer_file, &commands).await;
+            diagnostics.extend(validation_diagnostics(&text, &composer_file, &problems));
+        }
+
+        if !self.is_latest_analysis(&uri, analysis) {
+            info!(
+                "Dropping the diagnostics of an outdated analysis of {}",
+                uri
+            );
+            return;
+        }
+
+        self.diagnostics.insert(uri.to_string(), diagnostics);
+        self.publish_document_diagnostics(uri, version).await;
+        self.refresh_features().await;
+    }
+
+    /// Asks the client to request the features showing package data again.
+    async fn refresh_features(&self) {
+        let features = self.refreshable_features.read().unwrap().clone();
+        for feature in features {
+            if !self.is_feature_enabled(feature) {
+                continue;
+            }
+
+            let refreshed = match feature {
+                Feature::CodeLens => self.client.code_lens_refresh().await,
+                Feature::InlayHints => {
+                    self.client
+                        .send_request::<InlayHintRefreshRequest>(())
+                        .await
+                }
+                _ => Ok(()),
+            };
+            if let Err(error) = refreshed {
+                info!("Can't refresh {}: {}", feature.method(), error);
+            }
+        }
+    }
+
+    /// Links every package name to its support page, from the packages
+    /// checked on save, or else to its packagist page, and the homepage and
+    /// support URLs of composer.json.
+    fn on_document_link(&self, params: DocumentLinkParams) -> Option<Vec<DocumentLink>> {
+        let text = self.get_document_text(&params.text_document.uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+
+        let support_link = |name: &str| {
+            let package = self.package_cache.get(name)?;
+            let (label, url) = package
+                .versions
+                .first()?
+                .support_links()
+                .into_iter()
+                .next()?;
+            // Without support links, the packagist page is linked anyway.
+            Some((label, url)).filter(|(label, _)| *label != "Packagist")
+        };
+        let line_index = json::LineIndex::new(&text);
+        let links = links::find_links(&root, support_link)
+            .into_iter()
+            .map(|link| DocumentLink {
+                range: line_index.range(link.span),
+                target: Url::parse(&link.url).ok(),
+                tooltip: Some(link.tooltip),
+                data: None,
+            })
+            .collect();
+
+        Some(links)
+    }
+
+    fn on_document_symbol(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let text = self.get_document_text(&params.text_document.uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+
+        let line_index = json::LineIndex::new(&text);
+        let symbols = symbols::find_symbols(&root)
+            .into_iter()
+            .map(|symbol| document_symbol(&line_index, symbol))
+            .collect();
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    /// The effective composer configuration of the file, read once per
+    /// working directory with `composer config --list`.
+    async fn effective_config(&self, composer_file: &ComposerFile) -> EffectiveConfig {
+        let working_dir = composer_file.working_dir();
+        if let Some(config) = self.effective_configs.get(&working_dir) {
+            return config.clone();
+        }
+
+        let commands = self.settings.read().unwrap().commands.clone();
+        let output = runner::run_composer(
+            &self.client,
+            "Reading the composer configuration",
+            &working_dir,
+            &["config", "--list"],
+            &commands,
+        )
+        .await;
+        let config = match output {
+            Ok(output) if output.status.success() => effective::parse(
+                &String::from_utf8_lossy(&output.stdout),
+                &composer_file.dir(),
+            ),
+            _ => {
+                info!("Can't read the composer configuration of {}", working_dir);
+                EffectiveConfig::default()
+            }
+        };
+
+        if let Some(cache_dir) = &config.cache_dir {
+            cache::set_cache_dir(&composer_file.dir(), cache_dir.clone());
+        }
+        self.effective_configs.insert(working_dir, config.clone());
+
+        config
+    }
+
+    /// Registers the features enabled in the settings and unregisters the
+    /// disabled ones, as far as the client supports dynamic registration.
+    async fn sync_registrations(&self) {
+        let dynamic_features = self.dynamic_features.read().unwrap().clone();
+        let registered = self.registered_features.read().unwrap().clone();
+        let features = self.settings.read().unwrap().features.clone();
+        let (register, unregister) = registration::changes(&registered, &features);
+
+        let register: Vec<Feature> = register
+            .into_iter()
+            .filter(|feature| dynamic_features.contains(feature))
+            .collect();
+        if !register.is_empty() {
+            let registrations = register.iter().map(|feature| feature.registration());
+            match self
+                .client
+                .register_capability(registrations.collect())
+                .await
+            {
+                Ok(()) => self.registered_features.write().unwrap().extend(register),
+                Err(error) => info!("Can't register the features: {}", error),
+            }
+        }
+
+        if !unregister.is_empty() {
+            let unregistrations = unregister.iter().map(|feature| feature.unregistration());
+            match self
+                .client
+                .unregister_capability(unregistrations.collect())
+                .await
+            {
+                Ok(()) => self
+                    .registered_features
+                    .write()
+                    .unwrap()
+                    .retain(|feature| !unregister.contains(feature)),
+                Err(error) => info!("Can't unregister the features: {}", error),
+            }
+        }
+    }
+
+    fn is_feature_enabled(&self, feature: Feature) -> bool {
+        feature.is_enabled(&self.settings.read().unwrap().features)
+    }
+
+    /// Update and install commands above the require blocks, and an update
+    /// command above every outdated dependency.
+    fn on_code_lens(&self, params: CodeLensParams) -> Option<Vec<CodeLens>> {
+        if !self.is_feature_enabled(Feature::CodeLens) {
+            return None;
+        }
+
+        let uri = params.text_document.uri;
+        let text = self.get_document_text(&uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let composer_file = self.document(&uri)?;
+
+        let mut lenses = vec![];
+        for block in [placement::REQUIRE, placement::REQUIRE_DEV] {
+            let member = match root.get(block) {
+                Some(member) => member,
+                None => continue,
+            };
+
+            let range = json::span_to_range(&text, member.key_span);
+            if let Some(command) = self.update_block_command(&uri, range.start.line) {
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(command),
+                    data: None,
+                });
+            }
+            if block == placement::REQUIRE {
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(Command {
+                        title: "Install packages".to_string(),
+                        command: ComposerCommand::Install.id().to_string(),
+                        arguments: Some(vec![command::document_argument(uri.as_str())]),
+                    }),
+                    data: None,
+                });
+            }
+        }
+
+        let policy = self.update_policy(&composer_file);
+        for dependency in composer_file
+            .dependencies
+            .iter()
+            .chain(composer_file.dev_dependencies.iter())
+        {
+            let version = match self.available_update(
+                composer_file.lock.as_ref(),
+                &policy,
+                &dependency.name,
+                &dependency.version,
+            ) {
+                Some(version) => version,
+                None => continue,
+            };
+
+            lenses.push(CodeLens {
+                range: dependency.name_range,
+                command: Some(Command {
+                    title: format!("⬆ Update to {}", version),
+                    command: ComposerCommand::Update.id().to_string(),
+                    arguments: Some(vec![
+                        Value::from(dependency.name.clone()),
+                        command::document_argument(uri.as_str()),
+                    ]),
+                }),
+                data: None,
+            });
+        }
+
+        Some(lenses)
+    }
+
+    /// The update policy of the file, with the platform overrides of the
+    /// global config once the effective configuration was read.
+    fn update_policy(&self, composer_file: &ComposerFile) -> UpdatePolicy {
+        let effective = self
+            .effective_configs
+            .get(&composer_file.working_dir())
+            .map(|effective| effective.clone())
+            .unwrap_or_default();
+
+        composer_file.update_policy(&effective)
+    }
+
+    /// The newest version the constraint allows, if it's newer than the
+    /// installed one. Only the package data already fetched is used.
+    fn available_update(
+        &self,
+        lock: Option<&ComposerLockFile>,
+        policy: &UpdatePolicy,
+        name: &str,
+        constraint: &str,
+    ) -> Option<String> {
+        if self.settings.read().unwrap().updates.is_ignored(name) {
+            return None;
+        }
+
+        let package = self.package_cache.get(name)?;
+        let installed = lock
+            .and_then(|lock| lock.versions.get(&name.to_lowercase()))
+            .map(|installed| installed.version.clone())
+            .unwrap_or_default();
+
+        packagist::check_for_package_update(&package, constraint.to_string(), installed, policy)
+    }
+
+    /// The installed version after the constraint of every dependency, and
+    /// the latest version the constraint allows once the checks found one.
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        if !self.is_feature_enabled(Feature::InlayHints) {
+            return Ok(None);
+        }
+
+        let (lock, policy) = match self.document(&params.text_document.uri) {
+            Some(composer_file) => (
+                composer_file.lock.clone(),
+                self.update_policy(&composer_file),
+            ),
+            None => return Ok(None),
+        };
+        let text = match self.get_document_text(&params.text_document.uri) {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let root = match json::parse_tolerant(&text) {
+            Ok((root, _)) => root,
+            Err(_) => return Ok(None),
+        };
+
+        let line_index = json::LineIndex::new(&text);
+        let mut hints = vec![];
+        for block in [placement::REQUIRE, placement::REQUIRE_DEV] {
+            let members = match root.get(block).and_then(|block| block.value.as_object()) {
+                Some(members) => members,
+                None => continue,
+            };
+
+            for member in members {
+                let constraint = match member.value.as_str() {
+                    Some(constraint) => constraint,
+                    None => continue,
+                };
+                let position = line_index.position(member.value.span.end);
+                if position < params.range.start || params.range.end < position {
+                    continue;
+                }
+
+                let installed = lock
+                    .as_ref()
+                    .and_then(|lock| lock.versions.get(&member.key.to_lowercase()))
+                    .map(|installed| installed.version.clone());
+                let latest = self.available_update(lock.as_ref(), &policy, &member.key, constraint);
+
+                let label = match (&installed, &latest) {
+                    (Some(installed), Some(latest)) => {
+                        format!("installed: {} → latest: {}", installed, latest)
+                    }
+                    (Some(installed), None) => format!("installed: {}", installed),
+                    (None, Some(latest)) => format!("latest: {}", latest),
+                    (None, None) => continue,
+                };
+
+                hints.push(InlayHint {
+                    position,
+                    label: InlayHintLabel::String(label),
+                    kind: None,
+                    text_edits: None,
+                    tooltip: Some(InlayHintTooltip::String(
+                        "The installed version, and the latest version the constraint allows"
+                            .to_string(),
+                    )),
+                    padding_left: Some(true),
+                    padding_right: None,
+                    data: None,
+                });
+            }
+        }
+
+        Ok(Some(hints))
+    }
+
+    fn on_semantic_tokens(&self, params: SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let tokens = self.semantic_tokens(&params.text_document.uri)?;
+
+        Some(SemanticTokensResult::Tokens(tokens))
+    }
+
+    /// Sends only what changed since the previous tokens the client has, or
+    /// all tokens when they're no longer known.
+    fn on_semantic_tokens_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Option<SemanticTokensFullDeltaResult> {
+        let uri = params.text_document.uri;
+        let previous = self
+            .semantic_tokens
+            .get(uri.as_str())
+            .map(|previous| previous.clone());
+        let tokens = self.semantic_tokens(&uri)?;
+
+        match previous {
+            Some(previous) if previous.result_id.as_ref() == Some(&params.previous_result_id) => {
+                Some(SemanticTokensFullDeltaResult::TokensDelta(
+                    SemanticTokensDelta {
+                        result_id: tokens.result_id,
+                        edits: tokens::edits(&previous.data, &tokens.data),
+                    },
+                ))
+            }
+            _ => Some(SemanticTokensFullDeltaResult::Tokens(tokens)),
+        }
+    }
+
+    /// The semantic tokens of the document, kept with a new result ID as the
+    /// base of the next delta.
+    fn semantic_tokens(&self, uri: &Url) -> Option<SemanticTokens> {
+        if !self.is_feature_enabled(Feature::SemanticTokens) {
+            return None;
+        }
+
+        let text = self.get_document_text(uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let composer_file = self.document(uri);
+        let lock = composer_file
+            .as_ref()
+            .and_then(|composer_file| composer_file.lock.as_ref());
+        let policy = composer_file
+            .as_ref()
+            .map(|composer_file| self.update_policy(composer_file))
+            .unwrap_or_default();
+
+        let result_id = self
+            .semantic_tokens
+            .get(uri.as_str())
+            .and_then(|previous| previous.result_id.as_ref()?.parse::<u64>().ok())
+            .map(|previous| previous + 1)
+            .unwrap_or_default();
+        let tokens = SemanticTokens {
+            result_id: Some(result_id.to_string()),
+            data: tokens::semantic_tokens(&text, &root, |name, constraint| {
+                self.available_update(lock, &policy, name, constraint)
+                    .is_some()
+            }),
+        };
+        self.semantic_tokens.insert(uri.to_string(), tokens.clone());
+
+        Some(tokens)
+    }
+
+    /// The packagist data of the package, as far as the network mode allows.
+    async fn package_info(
+        &self,
+        name: &str,
+        repositories: &Repositories,
+        include_dev: bool,
+        network: NetworkMode,
+    ) -> Option<Package> {
+        let cached = || self.package_cache.get(name).map(|package| package.clone());
+
+        match network {
+            NetworkMode::Online => {
+                match repository::get_package_info(repositories, name.to_string(), include_dev)
+                    .await
+                {
+                    Some(package) => {
+                        self.package_cache.insert(name.to_string(), package.clone());
+                        Some(package)
+                    }
+                    None => cached(),
+                }
+            }
+            NetworkMode::CacheOnly => cached(),
+            NetworkMode::Disabled => None,
+        }
+    }
+
+    /// Checks the composer.json for updates and mistakes.
+    async fn analyze(
+        &self,
+        text: &str,
+        composer_file: ComposerFile,
+        progress: Option<&runner::Progress>,
+    ) -> Vec<Diagnostic> {
+        // Virtual packages have no versions of their own to check. The dev
+        // dependencies are fetched too, to warn when they're abandoned.
+        let checked_dependencies = composer_file
+            .dependencies
+            .iter()
+            .chain(composer_file.dev_dependencies.iter())
+            .filter(|dependency| !composer_file.is_virtual(&dependency.name))
+            .cloned()
+            .collect();
+        let (update_data, mut failed_packages) = packagist::get_packages_info(
+            checked_dependencies,
+            &composer_file.repositories,
+            progress,
+        )
+        .await;
+        for (name, package) in update_data.iter() {
+            self.package_cache.insert(name.to_string(), package.clone());
+        }
+        // The replacements of abandoned packages, for their quickfix.
+        for replacement in update_data
+            .values()
+            .filter_map(|package| package.replacement())
+        {
+            self.package_info(
+                &replacement,
+                &composer_file.repositories,
+                false,
+                NetworkMode::Online,
+            )
+            .await;
+        }
+        // Only "require" is checked for updates.
+        failed_packages.retain(|name| {
+            composer_file
+                .dependencies
+                .iter()
+                .any(|dependency| dependency.name.eq_ignore_ascii_case(name))
+        });
+        self.saved_checks
+            .entry(composer_file.path.clone())
+            .or_default()
+            .failed_packages = failed_packages.clone();
+
+        self.diagnose(text, composer_file, &update_data, failed_packages)
+            .await
+    }
+
+    /// The diagnostics of the composer.json, given the package data.
+    async fn diagnose(
+        &self,
+        text: &str,
+        composer_file: ComposerFile,
+        update_data: &HashMap<String, Package>,
+        failed_packages: Vec<String>,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+
+        // Point at invalid JSON constructs, like comments and trailing commas.
+        let (root, syntax_errors) = match json::parse_tolerant(text) {
+            Ok((root, errors)) => (Some(root), errors),
+            Err(error) => (None, vec![error]),
+        };
+
+        for error in syntax_errors {
+            diagnostics.push(new_diagnostic(
+                json::span_to_range(text, error.span),
+                Some(DiagnosticSeverity::ERROR),
+                INVALID_JSON_CODE,
+                Some(SCHEMA_URL),
+                format!("Invalid composer.json: {}.", error.message),
+            ));
+        }
+
+        // Check the keys and value types against the composer schema.
+        if let Some(root) = &root {
+            for problem in schema::validate(root) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, problem.span),
+                    Some(problem.severity),
+                    SCHEMA_CODE,
+                    Some(SCHEMA_URL),
+                    problem.message,
+                ));
+            }
+        }
+
+        // Check the structure of the repositories.
+        if let Some(root) = &root {
+            for problem in repositories::validate(root) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, problem.span),
+                    Some(problem.severity),
+                    REPOSITORY_CODE,
+                    Some(REPOSITORIES_URL),
+                    problem.message,
+                ));
+            }
+        }
+
+        // List the packages that couldn't be checked on the "require" key.
+        if !failed_packages.is_empty() {
+            let span = root
+                .as_ref()
+                .and_then(|root| root.get("require"))
+                .map(|require| require.key_span)
+                .unwrap_or_else(|| json::Span::new(0, 0));
+
+            diagnostics.push(new_diagnostic(
+                json::span_to_range(text, span),
+                Some(DiagnosticSeverity::INFORMATION),
+                UNCHECKED_CODE,
+                None,
+                format!(
+                    "Can't check these packages for updates: {}{}",
+                    failed_packages.join(", "),
+                    match self
+                        .effective_config(&composer_file)
+                        .await
+                        .custom_repositories()
+                    {
+                        repositories if repositories.is_empty() => String::new(),
+                        repositories => format!(
+                            ". They may come from one of the repositories: {}",
+                            repositories.join(", ")
+                        ),
+                    }
+                ),
+            ));
+        }
+
+        // Lint exact version pins in "require".
+        let exact_pins = self.settings.read().unwrap().lint.exact_pins;
+        if let Some(severity) = exact_pins.severity() {
+            for item in composer_file.dependencies.iter() {
+                if !constraint::is_exact(&item.constraint()) {
+                    continue;
+                }
+
+                diagnostics.push(new_diagnostic(
+                    item.constraint_range,
+                    Some(severity),
+                    EXACT_PIN_CODE,
+                    Some(VERSIONS_URL),
+                    format!(
+                        "{} is pinned to an exact version, which blocks security patch updates.",
+                        item.name
+                    ),
+                ));
+            }
+        }
+
+        // Lint a require block without a php constraint.
+        let missing_php = self.settings.read().unwrap().lint.missing_php;
+        if let (Some(severity), Some(root)) = (missing_php.severity(), &root) {
+            if let Some(require) = root
+                .get("require")
+                .filter(|_| platform::is_missing_php_requirement(root))
+            {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, require.key_span),
+                    Some(severity),
+                    MISSING_PHP_CODE,
+                    Some(PLATFORM_PACKAGES_URL),
+                    "The php version isn't constrained, so the project may be installed on an unsupported version.".to_string(),
+                ));
+            }
+        }
+
+        // Check the php and extension requirements against the platform.
+        let platform_level = self.settings.read().unwrap().lint.platform;
+        if let Some(severity) = platform_level.severity() {
+            let local = platform::local_platform().await;
+            let overrides = self.update_policy(&composer_file).platform;
+            let requirements: Vec<(&str, &str)> = composer_file
+                .dependencies
+                .iter()
+                .chain(composer_file.dev_dependencies.iter())
+                .filter(|dependency| packagist::is_platform_package(&dependency.name))
+                .map(|dependency| (dependency.name.as_str(), dependency.version.as_str()))
+                .collect();
+
+            for problem in platform::platform_problems(&requirements, &local, &overrides) {
+                if let Some(dependency) = composer_file.get_dependency(&problem.package) {
+                    diagnostics.push(new_diagnostic(
+                        dependency.name_range,
+                        Some(severity),
+                        PLATFORM_CODE,
+                        Some(PLATFORM_PACKAGES_URL),
+                        problem.message,
+                    ));
+                }
+            }
+        }
+
+        // Lint dependencies that are likely in the wrong block.
+        let misplaced_dependencies = self.settings.read().unwrap().lint.misplaced_dependencies;
+        if let (Some(severity), Some(root)) = (misplaced_dependencies.severity(), &root) {
+            for misplacement in placement::find_misplaced(root) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, misplacement.span),
+                    Some(severity),
+                    MISPLACED_CODE,
+                    Some(REQUIRE_DEV_URL),
+                    misplacement.message(),
+                ));
+            }
+        }
+
+        // Lint constraints that the minimum stability doesn't allow.
+        let stability = self.settings.read().unwrap().lint.stability;
+        if let (Some(severity), Some(root)) = (stability.severity(), &root) {
+            for problem in stability::find_problems(root) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, problem.span),
+                    Some(severity),
+                    STABILITY_CODE,
+                    Some(MINIMUM_STABILITY_URL),
+                    problem.message(),
+                ));
+            }
+        }
+
+        // Composer lowercases package names, and warns about the others.
+        if let Some(root) = &root {
+            for name in naming::find_mixed_case(root) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, name.span),
+                    Some(DiagnosticSeverity::WARNING),
+                    MIXED_CASE_CODE,
+                    Some(NAME_URL),
+                    format!(
+                        "{} isn't lowercase, composer normalizes it to {}.",
+                        name.package,
+                        name.package.to_lowercase()
+                    ),
+                ));
+            }
+        }
+
+        // Point at requirements clashing with another entry, and at that entry.
+        if let Some(root) = &root {
+            let uri = Url::parse(&composer_file.path).ok();
+            for duplicate in duplicates::find_duplicates(root, composer_file.lock.as_ref()) {
+                let (severity, code) = match duplicate.kind {
+                    duplicates::DuplicateKind::Conflicting => {
+                        (DiagnosticSeverity::ERROR, CONFLICT_CODE)
+                    }
+                    _ => (DiagnosticSeverity::WARNING, DUPLICATE_CODE),
+                };
+                let mut diagnostic = new_diagnostic(
+                    json::span_to_range(text, duplicate.span),
+                    Some(severity),
+                    code,
+                    Some(SCHEMA_URL),
+                    duplicate.message(),
+                );
+                diagnostic.related_information = uri.clone().map(|uri| {
+                    vec![DiagnosticRelatedInformation {
+                        location: Location::new(
+                            uri,
+                            json::span_to_range(text, duplicate.other_span),
+                        ),
+                        message: duplicate.other_message().to_string(),
+                    }]
+                });
+                diagnostics.push(diagnostic);
+            }
+        }
+
+        // Required plugins have to be allowed, or composer asks about them.
+        if let (Some(lock), Some(root)) = (&composer_file.lock, &root) {
+            for plugin in plugins::find_unallowed(root, &lock.plugins) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, plugin.span),
+                    Some(DiagnosticSeverity::WARNING),
+                    PLUGIN_NOT_ALLOWED_CODE,
+                    Some(ALLOW_PLUGINS_URL),
+                    format!(
+                        "{} is a plugin missing from config.allow-plugins, so composer will prompt for it or fail in CI.",
+                        plugin.package
+                    ),
+                ));
+            }
+        }
+
+        // Installed versions read from a composer 1 lock file may be incomplete.
+        if let (Some(lock), Some(require)) = (
+            &composer_file.lock,
+            root.as_ref().and_then(|root| root.get("require")),
+        ) {
+            if lock.format == LockFormat::Composer1 {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, require.key_span),
+                    Some(DiagnosticSeverity::INFORMATION),
+                    LEGACY_LOCK_CODE,
+                    None,
+                    "composer.lock was written by composer 1, run composer update with composer 2 to refresh it.".to_string(),
+                ));
+            }
+        }
+
+        // Point out where composer.json and the lock file drifted apart.
+        if let (Some(lock), Some(root)) = (&composer_file.lock, &root) {
+            for not_locked in drift::find_not_locked(root, lock) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, not_locked.span),
+                    Some(DiagnosticSeverity::WARNING),
+                    NOT_LOCKED_CODE,
+                    None,
+                    format!(
+                        "{} is required but not locked, run composer update.",
+                        not_locked.package
+                    ),
+                ));
+            }
+
+            for unsatisfied in drift::find_unsatisfied(root, lock) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, unsatisfied.span),
+                    Some(DiagnosticSeverity::WARNING),
+                    NOT_SATISFIED_CODE,
+                    None,
+                    format!(
+                        "The locked version {} doesn't satisfy {}, run composer update {}.",
+                        unsatisfied.locked, unsatisfied.constraint, unsatisfied.package
+                    ),
+                ));
+            }
+
+            // composer install warns about a lock file written for other
+            // requirements.
+            let is_outdated = lock
+                .content_hash
+                .as_ref()
+                .map(|hash| *hash != drift::content_hash(root))
+                .unwrap_or(false);
+            if let Some(require) = root.get("require").filter(|_| is_outdated) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, require.key_span),
+                    Some(DiagnosticSeverity::WARNING),
+                    LOCK_OUTDATED_CODE,
+                    None,
+                    "composer.lock is out of sync with composer.json, run composer update."
+                        .to_string(),
+                ));
+            }
+
+            let not_required = drift::find_not_required(root, lock);
+            if let Some(require) = root.get("require").filter(|_| !not_required.is_empty()) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, require.key_span),
+                    Some(DiagnosticSeverity::INFORMATION),
+                    NOT_REQUIRED_CODE,
+                    None,
+                    format!(
+                        "Locked but no longer required, run composer update to prune: {}",
+                        not_required.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        // Point at @php scripts that don't exist.
+        if let Some(root) = &root {
+            let missing_scripts = scripts::find_missing_php_scripts(
+                root,
+                &composer_file.dir(),
+                &composer_file.vendor_path(),
+            );
+            for (command, path) in missing_scripts {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, command.span),
+                    Some(DiagnosticSeverity::WARNING),
+                    MISSING_SCRIPT_CODE,
+                    Some(SCRIPTS_URL),
+                    format!(
+                        "The \"{}\" script runs {}, which doesn't exist.",
+                        command.script,
+                        path.display()
+                    ),
+                ));
+            }
+
+            for command in scripts::find_undefined_references(root) {
+                diagnostics.push(new_diagnostic(
+                    json::span_to_range(text, command.span),
+                    Some(DiagnosticSeverity::WARNING),
+                    UNDEFINED_SCRIPT_CODE,
+                    Some(SCRIPTS_URL),
+                    format!(
+                        "The \"{}\" script refers to {}, which isn't a script.",
+                        command.script,
+                        command
+                            .command
+                            .split_whitespace()
+                            .next()
+                            .unwrap_or_default()
+                    ),
+                ));
+            }
+        }
+
+        // Warn about abandoned packages, naming their replacement.
+        for item in composer_file
+            .dependencies
+            .iter()
+            .chain(composer_file.dev_dependencies.iter())
+        {
+            let notice = match update_data
+                .get(&item.name)
+                .and_then(|package| package.abandoned_notice())
+            {
+                Some(notice) => notice,
+                None => continue,
+            };
+
+            let mut diagnostic = new_diagnostic(
+                item.name_range,
+                Some(DiagnosticSeverity::WARNING),
+                ABANDONED_CODE,
+                Some(&packagist::package_url(&item.name)),
+                notice,
+            );
+            // The replacement and its constraint, for the quickfix.
+            let replacement = update_data
+                .get(&item.name)
+                .and_then(|package| package.replacement());
+            if let Some(replacement) = replacement {
+                let constraint = self
+                    .package_cache
+                    .get(&replacement)
+                    .and_then(|package| package.latest_stable_version())
+                    .and_then(|version| constraint::exact_to_caret(&version));
+                if let Some(constraint) = constraint {
+                    diagnostic.data = Some(serde_json::json!({
+                        "replacement": replacement,
+                        "constraint": constraint,
+                    }));
+                }
+            }
+            diagnostics.push(diagnostic);
+        }
+
+        // Loop through "require".
+        let updates = self.settings.read().unwrap().updates.clone();
+        let policy = composer_file.update_policy(&self.effective_config(&composer_file).await);
+        for item in composer_file.dependencies.iter() {
+            if item.name.is_empty() || updates.is_ignored(&item.name) {
+                continue;
+            }
+            let severity = match updates.severity.severity() {
+                Some(severity) => severity,
+                None => break,
+            };
+
+            // Packagist data.
+            let packagist_data = update_data.get(&item.name);
+            if let Some(package) = packagist_data {
+                let mut composer_lock_version = "".to_string();
+
+                let mut composer_lock_reference = None;
+
+                let composer_json_version = item.constraint().replace('"', "");
+                if let Some(lock_file) = &composer_file.lock {
+                    if let Some(installed) = lock_file.versions.get(&item.name.to_lowercase()) {
+                        composer_lock_version = installed.version.clone();
+                        composer_lock_reference = installed.reference.as_deref();
+                    }
+                }
+
+                // Branches have no newer versions, but newer commits.
+                if let Some(new_commits) = item.branch().and_then(|branch| {
+                    packagist::check_for_new_commits(package, &branch, composer_lock_reference)
+                }) {
+                    diagnostics.push(new_diagnostic(
+                        item.name_range,
+                        Some(severity),
+                        NEW_COMMITS_CODE,
+                        Some(&packagist::version_url(&item.name, &new_commits.branch)),
+                        new_commits.message(),
+                    ));
+                    continue;
+                }
+
+                if let Some(version) = packagist::check_for_package_update(
+                    package,
+                    composer_json_version,
+                    composer_lock_version,
+                    &policy,
+                ) {
+                    let mut diagnostic = new_diagnostic(
+                        item.name_range,
+                        Some(severity),
+                        UPDATE_AVAILABLE_CODE,
+                        Some(&packagist::version_url(&item.name, &version)),
+                        format!("Update available: {:?}", version),
+                    );
+                    // The version the constraint quickfix raises to.
+                    diagnostic.data = Some(serde_json::json!({ "version": version }));
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+
+        if let Some(require) = root.as_ref().and_then(|root| root.get("require")) {
+            let range = json::span_to_range(text, require.key_span);
+            add_summary(&mut diagnostics, range, &composer_file);
+        }
+
+        diagnostics
+    }
+
+    /// Opens the global composer.json in the editor.
+    async fn open_global_manifest(&self) {
+        let path = match global::manifest_path() {
+            Some(path) if path.exists() => path,
+            Some(path) => {
+                let message = format!("There is no global composer.json at {}.", path.display());
+                self.client
+                    .show_message(MessageType::WARNING, message)
+                    .await;
+                return;
+            }
+            None => {
+                self.client
+                    .show_message(
+                        MessageType::ERROR,
+                        "Can't find the composer home directory.",
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        self.show_path(&path).await;
+    }
+
+    /// Opens the installed source of the package, in the vendor directory.
+    async fn open_package_source(&self, composer_file: &ComposerFile, name: &str) {
+        let effective = self.effective_config(composer_file).await;
+        let path = composer_file.resolve_vendor_path(&effective).join(name);
+        if !path.is_dir() {
+            let message = format!("{} isn't installed, run composer install.", name);
+            self.client
+                .show_message(MessageType::WARNING, message)
+                .await;
+            return;
+        }
+
+        self.show_path(&path).await;
+    }
+
+    /// Asks the client to open the file or directory.
+    async fn show_path(&self, path: &std::path::Path) {
+        let uri = match path.is_dir() {
+            true => Url::from_directory_path(path),
+            false => Url::from_file_path(path),
+        };
+        let uri = match uri {
+            Ok(uri) => uri,
+            Err(_) => return,
+        };
+
+        let shown = self
+            .client
+            .send_request::<request::ShowDocument>(ShowDocumentParams {
+                uri,
+                external: Some(false),
+                take_focus: Some(true),
+                selection: None,
+            })
+            .await;
+        if !matches!(shown, Ok(ShowDocumentResult { success: true })) {
+            let message = format!("Can't open {}.", path.display());
+            self.client.show_message(MessageType::ERROR, message).await;
+        }
+    }
+
+    /// The composer.json files of the workspace and the open document.
+    fn workspace_uris(&self) -> Vec<String> {
+        let mut uris: Vec<String> = self
+            .workspace_files
+            .iter()
+            .map(|file| file.key().to_string())
+            .collect();
+        for open in self.composer_file.iter() {
+            if !uris.contains(open.key()) {
+                uris.push(open.key().to_string());
+            }
+        }
+        uris.sort();
+
+        uris
+    }
+
+    /// Reads a workspace file, from the buffer when it's open, as it can have
+    /// unsaved changes.
+    fn read_workspace_file(&self, uri: &Url) -> Option<(String, ComposerFile)> {
+        let text = self.get_document_text(uri)?;
+        let composer_file = ComposerFile::parse_from_str(&text, uri.clone())?;
+
+        Some((text, composer_file))
+    }
+
+    /// Analyzes every composer.json in the workspace, publishes their
+    /// diagnostics and sums them up in a notification.
+    async fn check_workspace(&self, commands: &CommandSettings) {
+        let uris = self.workspace_uris();
+
+        let progress = runner::Progress::begin(&self.client, "Checking the workspace").await;
+        let mut updates = 0;
+        let mut advisories = 0;
+        let mut unreadable = 0;
+        for (checked, uri) in uris.iter().enumerate() {
+            progress
+                .report(
+                    format!("Checking {}", uri),
+                    Some((checked * 100 / uris.len()) as u32),
+                )
+                .await;
+
+            let uri = match Url::parse(uri) {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+            let (text, composer_file) = match self.read_workspace_file(&uri) {
+                Some(file) => file,
+                None => {
+                    unreadable += 1;
+                    continue;
+                }
+            };
+
+            let analysis = self.start_analysis(&uri);
+            let mut diagnostics = self.analyze(&text, composer_file.clone(), None).await;
+            updates += diagnostics
+                .iter()
+                .filter(|diagnostic| {
+                    diagnostic.code
+                        == Some(NumberOrString::String(UPDATE_AVAILABLE_CODE.to_string()))
+                })
+                .count();
+
+            if composer_file.lock.is_some() {
+                let found = self.audit(&text, &composer_file, commands).await;
+                advisories += found.len();
+                add_advisories(&mut diagnostics, found, &composer_file);
+            }
+
+            // The document changed while it was checked.
+            if !self.is_latest_analysis(&uri, analysis) {
+                continue;
+            }
+            let version = self
+                .document_versions
+                .get(&uri.to_string())
+                .map(|version| *version);
+            self.diagnostics.insert(uri.to_string(), diagnostics);
+            self.publish_document_diagnostics(uri, version).await;
+        }
+        progress.end(None).await;
+
+        let mut summary = format!(
+            "Checked {} composer.json files: {} updates available, {} security advisories.",
+            uris.len() - unreadable,
+            updates,
+            advisories
+        );
+        if unreadable > 0 {
+            summary.push_str(&format!(" {} files couldn't be read.", unreadable));
+        }
+
+        let message_type = match advisories {
+            0 => MessageType::INFO,
+            _ => MessageType::WARNING,
+        };
+        self.client.show_message(message_type, summary).await;
+    }
+
+    /// Tells the client what a successful composer command changed, as the
+    /// "composer/commandFinished" notification and a message.
+    async fn report_outcome(&self, command: &str, started: Instant, output: &std::process::Output) {
+        // Composer reports the operations on stderr.
+        let text = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stderr),
+            String::from_utf8_lossy(&output.stdout)
+        );
+        let outcome = outcome::CommandOutcome {
+            command: command.to_string(),
+            changes: outcome::parse_changes(&text),
+            duration_ms: started.elapsed().as_millis(),
+        };
+
+        self.client
+            .show_message(MessageType::INFO, outcome.message())
+            .await;
+        self.client
+            .send_notification::<outcome::CommandFinished>(outcome)
+            .await;
+    }
+
+    /// The advisories `composer audit` reports for the locked packages.
+    async fn audit_advisories(
+        &self,
+        composer_file: &ComposerFile,
+        commands: &CommandSettings,
+    ) -> Vec<audit::Advisory> {
+        let output = runner::run_composer(
+            &self.client,
+            "composer audit",
+            &composer_file.working_dir(),
+            &global::command_args(
+                composer_file.is_global(),
+                &["audit", "--locked", "--format=json"],
+            ),
+            commands,
+        )
+        .await;
+
+        let output = match output {
+            Ok(output) => output,
+            Err(error) => {
+                log::warn!("Can't audit {}: {}", composer_file.path, error);
+                return vec![];
+            }
+        };
+
+        audit::parse_advisories(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Runs `composer audit` and turns the advisories into diagnostics on
+    /// the affected requirements.
+    async fn audit(
+        &self,
+        text: &str,
+        composer_file: &ComposerFile,
+        commands: &CommandSettings,
+    ) -> Vec<Diagnostic> {
+        let advisories = self.audit_advisories(composer_file, commands).await;
+
+        advisory_diagnostics(text, &advisories)
+    }
+
+    /// The analysis of every composer.json in the workspace, for tools that
+    /// don't speak the diagnostics.
+    async fn analysis_export(&self) -> Result<export::AnalysisExport> {
+        let commands = self.settings.read().unwrap().commands.clone();
+
+        let mut files = vec![];
+        for uri in self.workspace_uris() {
+            let uri = match Url::parse(&uri) {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+            let composer_file = match self.read_workspace_file(&uri) {
+                Some((_, composer_file)) => composer_file,
+                None => continue,
+            };
+
+            let dependencies = composer_file
+                .dependencies
+                .iter()
+                .map(|dependency| (dependency, false))
+                .chain(
+                    composer_file
+                        .dev_dependencies
+                        .iter()
+                        .map(|dependency| (dependency, true)),
+                )
+                .filter(|(dependency, _)| !packagist::is_platform_package(&dependency.name))
+                .filter(|(dependency, _)| !packagist::is_virtual_package(&dependency.name));
+            let (package_data, _) = packagist::get_packages_info(
+                dependencies
+                    .clone()
+                    .map(|(dependency, _)| dependency.clone())
+                    .collect(),
+                &composer_file.repositories,
+                None,
+            )
+            .await;
+            let advisories = match composer_file.lock {
+                Some(_) => self.audit_advisories(&composer_file, &commands).await,
+                None => vec![],
+            };
+
+            let packages = dependencies
+                .map(|(dependency, dev)| {
+                    export::package_analysis(
+                        dependency,
+                        dev,
+                        composer_file.lock.as_ref(),
+                        package_data.get(&dependency.name),
+                        &advisories,
+                    )
+                })
+                .collect();
+            files.push(export::FileAnalysis {
+                uri: uri.to_string(),
+                packages,
+            });
+        }
+
+        Ok(export::AnalysisExport { files })
+    }
+
+    async fn server_status(&self) -> Result<ServerStatusResult> {
+        let (entries, memory_bytes) = match self.package_index.read().unwrap().as_ref() {
+            Some(package_index) => (package_index.len(), package_index.memory_bytes()),
+            None => (0, 0),
+        };
+        let age_seconds = self
+            .packages_fetched_at
+            .read()
+            .unwrap()
+            .and_then(|fetched_at| fetched_at.elapsed().ok())
+            .map(|age| age.as_secs());
+
+        let running = self
+            .background_tasks
+            .iter()
+            .filter(|task| !task.value().is_finished())
+            .count();
+
+        Ok(ServerStatusResult {
+            packagist_reachable: packagist::is_reachable().await,
+            package_index: PackageIndexStatus {
+                entries,
+                memory_bytes,
+                age_seconds,
+            },
+            tracked_documents: self.document_versions.len(),
+            workspace_files: self.workspace_files.len(),
+            background_tasks: BackgroundTasksStatus {
+                running,
+                finished: self.background_tasks.len() - running,
+            },
+        })
+    }
+
+    async fn package_versions(
+        &self,
+        params: PackageVersionsParams,
+    ) -> Result<Option<PackageVersionsResult>> {
+        let uri = params.uri.or_else(|| {
+            let active_document = self.active_document.read().unwrap().clone()?;
+            Url::parse(&active_document).ok()
+        });
+        let repositories = uri
+            .and_then(|uri| self.document(&uri))
+            .map(|composer_file| composer_file.repositories)
+            .unwrap_or_default();
+        let package_info =
+            repository::get_package_info(&repositories, params.package.clone(), true).await;
+
+        match package_info {
+            Some(data) => {
+                let mut versions = vec![];
+                for item in data.versions.into_iter() {
+                    let version = match item.version {
+                        Some(version) => version,
+                        None => continue,
+                    };
+
+                    versions.push(PackageVersionItem {
+                        stability: constraint::Stability::of(&version)
+                            .unwrap_or(constraint::Stability::Stable)
+                            .as_str()
+                            .to_string(),
+                        version,
+                        version_normalized: item.version_normalized,
+                        time: item.time,
+                    });
+                }
+
+                Ok(Some(PackageVersionsResult {
+                    package: params.package,
+                    versions,
+                }))
+            }
+            None => {
+                let error = format!("No version data found for: {}", params.package);
+                log::error!("{}", error);
+                self.client.log_message(MessageType::ERROR, error).await;
+
+                Ok(None)
+            }
+        }
+    }
+
+    async fn on_hover(&self, params: TextDocumentPositionParams) -> Option<Hover> {
+        if lockfile::is_lock_file(&params.text_document.uri) {
+            return self.lock_file_hover(params).await;
+        }
+
+        let composer_file = self.document(&params.text_document.uri)?;
+        let network = self.settings.read().unwrap().hover.network;
+
+        // Show the binaries a script command runs.
+        if let Some(text) = self.get_document_text(&params.text_document.uri) {
+            let offset = json::position_to_offset(&text, params.position);
+            let command = json::parse_tolerant(&text).ok().and_then(|(root, _)| {
+                scripts::find_commands(&root)
+                    .into_iter()
+                    .find(|command| command.span.contains(offset))
+            });
+
+            if let Some(command) = command {
+                let php = scripts::find_binary("php");
+                let composer = scripts::find_binary("composer");
+
+                return Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: scripts::command_hover(
+                            &command.command,
+                            php.as_deref(),
+                            composer.as_deref(),
+                        ),
+                    }),
+                    range: Some(json::span_to_range(&text, command.span)),
+                });
+            }
+        }
+
+        let line = params.position.line;
+        let dependency = composer_file.dependencies_by_line.get(&line);
+
+        // Compare the php requirement with the local interpreter.
+        if let Some(php) = dependency
+            .filter(|name| name.as_str() == "php")
+            .and_then(|name| composer_file.get_dependency(name))
+        {
+            let local = platform::local_platform().await;
+            let effective = self.effective_config(&composer_file).await;
+            let contents = platform::php_hover(
+                &php.version,
+                local.php.as_deref(),
+                composer_file
+                    .platform_override(&effective, "php")
+                    .as_deref(),
+            );
+
+            return Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: contents,
+                }),
+                range: None,
+            });
+        }
+
+        // Extensions and the other platform packages aren't on packagist.
+        if let Some(requirement) = dependency
+            .filter(|name| packagist::is_platform_package(name))
+            .and_then(|name| composer_file.get_dependency(name))
+        {
+            let local = platform::local_platform().await;
+            let effective = self.effective_config(&composer_file).await;
+            let contents = platform::platform_hover(
+                &requirement.name,
+                &requirement.version,
+                &local,
+                composer_file
+                    .platform_override(&effective, &requirement.name.to_lowercase())
+                    .as_deref(),
+            );
+
+            return Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: contents,
+                }),
+                range: None,
+            });
+        }
+
+        // Virtual packages have no page of their own, show what provides them.
+        if let Some(name) = dependency.filter(|name| composer_file.is_virtual(name)) {
+            let installed: Vec<(String, String)> = match &composer_file.lock {
+                Some(lock) => lock
+                    .providers_of(name)
+                    .into_iter()
+                    .map(|provider| {
+                        let version = lock
+                            .versions
+                            .get(&provider)
+                            .map(|installed| installed.version.clone())
+                            .unwrap_or_default();
+                        (provider, version)
+                    })
+                    .collect(),
+                None => vec![],
+            };
+
+            let dir = composer_file.dir();
+            let providers = match network {
+                NetworkMode::Online => match packagist::get_providers(name, Some(&dir)).await {
+                    Ok(providers) => providers,
+                    Err(error) => {
+                        log::info!("Can't get the providers of {}: {}", name, error);
+                        vec![]
+                    }
+                },
+                _ => vec![],
+            };
+
+            return Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: provide::virtual_hover(name, &installed, &providers),
+                }),
+                range: None,
+            });
+        }
+
+        match dependency {
+            Some(name) => {
+                let include_dev = composer_file
+                    .get_dependency(name)
+                    .map(|dependency| packagist::requires_dev_branch(&dependency.version))
+                    .unwrap_or(false);
+                let package_info = self
+                    .package_info(name, &composer_file.repositories, include_dev, network)
+                    .await;
+                match package_info {
+                    Some(data) => {
+                        // Describe the installed version, or the latest one.
+                        let installed = composer_file
+                            .lock
+                            .as_ref()
+                            .and_then(|lock| lock.versions.get(&name.to_lowercase()));
+                        let package_version = installed
+                            .and_then(|installed| {
+                                data.versions.iter().find(|item| match &item.version {
+                                    Some(version) => {
+                                        constraint::same_version(version, &installed.version)
+                                    }
+                                    None => false,
+                                })
+                            })
+                            .or_else(|| data.versions.first())
+                            .cloned()
+                            .unwrap_or_default();
+
+                        let mut contents = vec![];
+
+                        if let Some(notice) = data.abandoned_notice() {
+                            contents.push(MarkedString::from_markdown(format!(
+                                "**Abandoned:** {}",
+                                notice
+                            )));
+                            contents.push(MarkedString::from_markdown("".to_string()));
+                        }
+
+                        if let Some(licenses) = package_version.license_badges() {
+                            contents.push(MarkedString::from_markdown(licenses));
+                        }
+
+                        if let Some(authors) = package_version.author_names() {
+                            contents.push(MarkedString::from_markdown(authors));
+                        }
+
+                        if let Some(desc) = &package_version.description {
+                            contents.push(MarkedString::from_markdown(desc.to_string()));
+                            contents.push(MarkedString::from_markdown("".to_string()));
+                        }
+
+                        if let Some(tags) = package_version.keyword_tags() {
+                            contents.push(MarkedString::from_markdown(tags));
+                        }
+
+                        if let Some(release) = data.latest_release() {
+                            contents.push(MarkedString::from_markdown(release));
+                        }
+
+                        // Only packagist.org knows the downloads and stars.
+                        if network == NetworkMode::Online {
+                            match packagist::get_package_stats(name).await {
+                                Ok(stats) => {
+                                    contents.push(MarkedString::from_markdown(stats.summary()))
+                                }
+                                Err(error) => {
+                                    log::info!("Can't get the stats of {}: {}", name, error)
+                                }
+                            }
+                        }
+
+                        if let Some(page) = &package_version.homepage {
+                            contents
+                                .push(MarkedString::from_markdown(format!("Homepage: {}", page)));
+                            contents.push(MarkedString::from_markdown("".to_string()));
+                        }
+
+                        let mut links: Vec<String> = package_version
+                            .support_links()
+                            .into_iter()
+                            .map(|(label, url)| format!("[{}]({})", label, url))
+                            .collect();
+                        let packagist_link =
+                            format!("[Packagist]({})", packagist::package_url(name));
+                        if !links.contains(&packagist_link) {
+                            links.push(packagist_link);
+                        }
+                        if !links.is_empty() {
+                            contents.push(MarkedString::from_markdown(links.join(" · ")));
+                        }
+
+                        if let Some(required_by) = composer_file
+                            .lock
+                            .as_ref()
+                            .and_then(|lock| why::required_by_summary(lock, name))
+                        {
+                            contents.push(MarkedString::from_markdown(format!(
+                                "**Required by:** {}",
+                                required_by
+                            )));
+                        }
+
+                        if let Some(alias) = composer_file
+                            .get_dependency(name)
+                            .and_then(|dependency| dependency.alias())
+                        {
+                            contents.push(MarkedString::from_markdown(format!(
+                                "Installs `{}`, aliased as `{}`.",
+                                alias.constraint, alias.alias
+                            )));
+                        }
+
+                        // Copyable commands, for users who prefer the terminal.
+                        let dev = composer_file
+                            .dev_dependencies
+                            .iter()
+                            .any(|dependency| &dependency.name == name);
+                        contents.push(MarkedString::LanguageString(LanguageString {
+                            language: "shell".to_string(),
+                            value: command::shell_commands(name, dev),
+                        }));
+
+                        let range = composer_file
+                            .get_dependency_on_line(line, name)
+                            .map(|dependency| dependency.name_range);
+
+                        return Some(Hover {
+                            contents: HoverContents::Array(contents),
+                            range,
+                        });
+                    }
+                    None if network == NetworkMode::Disabled => {}
+                    None => {
+                        let error = format!("No hover data found for: {}", name);
+                        log::error!("{}", error);
+                        self.client.log_message(MessageType::ERROR, error).await;
+                    }
+                }
+            }
+
+            None => {
+                let error = format!(
+                    "Hover failed, because we can't find this line number: {}",
+                    line
+                );
+
+                log::error!("{}", error);
+                self.client.log_message(MessageType::ERROR, error).await;
+            }
+        }
+
+        None
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        let target = self.settings.read().unwrap().definition.target;
+        if target == DefinitionTarget::Disabled {
+            return None;
+        }
+
+        let composer_file =
+            self.document(&params.text_document_position_params.text_document.uri)?;
+
+        let line = params.text_document_position_params.position.line;
+        let name = match composer_file.dependencies_by_line.get(&line) {
+            Some(name) => name,
+            None => {
+                let error = format!(
+                    "Go to definition failed, because we can't find this line number: {}",
+                    line
+                );
+
+                log::error!("{}", error);
+                self.client.log_message(MessageType::ERROR, error).await;
+                return None;
+            }
+        };
+
+        if target == DefinitionTarget::Vendor {
+            let effective = self.effective_config(&composer_file).await;
+            let path = composer_file
+                .resolve_vendor_path(&effective)
+                .join(name)
+                .join("composer.json");
+
+            // Editors show nothing for an empty definition, so say why.
+            if !path.exists() {
+                let error = format!(
+                    "Package {} is not installed in vendor, run composer install to go to its definition.",
+                    name
+                );
+                log::info!("{}", error);
+                self.client.show_message(MessageType::WARNING, error).await;
+                return None;
+            }
+
+            let uri = Url::from_file_path(path).ok()?;
+            return Some(GotoDefinitionResponse::Scalar(Location::new(
+                uri,
+                Range::default(),
+            )));
+        }
+
+        let include_dev = composer_file
+            .get_dependency(name)
+            .map(|dependency| packagist::requires_dev_branch(&dependency.version))
+            .unwrap_or(false);
+        let network = self.settings.read().unwrap().definition.network;
+        let package_info = self
+            .package_info(name, &composer_file.repositories, include_dev, network)
+            .await;
+        match package_info {
+            Some(data) => {
+                // Packages with only dev branches have no versions unless
+                // those are included.
+                let latest_package_version = data.versions.first().cloned().unwrap_or_default();
+                let installed = composer_file
+                    .lock
+                    .as_ref()
+                    .and_then(|lock| lock.versions.get(&name.to_lowercase()));
+                let package_version = match installed {
+                    Some(installed) => data
+                        .versions
+                        .iter()
+                        .find(|item| {
+                            item.version.as_deref().is_some_and(|version| {
+                                constraint::same_version(version, &installed.version)
+                            })
+                        })
+                        .cloned()
+                        .unwrap_or_default(),
+                    None => latest_package_version.clone(),
+                };
+
+                // Try to get the latest one, if the installed version has no url.
+                let definition_url = match target {
+                    DefinitionTarget::Repository => package_version
+                        .source
+                        .or(latest_package_version.source)
+                        .and_then(|source| source.url)
+                        .map(|url| packagist::repository_web_url(&url)),
+                    _ => Some(
+                        package_version
+                            .packagist_url
+                            .or(latest_package_version.packagist_url)
+                            .unwrap_or_else(|| packagist::package_url(&name.to_lowercase())),
+                    ),
+                };
+
+                match definition_url {
+                    Some(page) if webbrowser::open(&page).is_ok() => {}
+                    _ => {
+                        let error = format!("Can't open the definition_url for: {}", name);
+                        log::error!("{}", error);
+                        self.client.log_message(MessageType::ERROR, error).await;
+                    }
+                }
+            }
+            None if network == NetworkMode::Disabled => {}
+            None => {
+                let error = format!("No definiton data found for: {}", name);
+                log::error!("{}", error);
+                self.client.log_message(MessageType::ERROR, error).await;
+            }
+        }
+
+        None
+    }
+
+    async fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> Option<GotoDeclarationResponse> {
+        let composer_file =
+            self.document(&params.text_document_position_params.text_document.uri)?;
+
+        let line = params.text_document_position_params.position.line;
+        let name = composer_file.dependencies_by_line.get(&line)?;
+
+        let lock_uri = Url::parse(&composer_file.lock_path()).ok()?;
+        let lock_text = match std::fs::read_to_string(composer_file.dir().join("composer.lock")) {
+            Ok(text) => text,
+            Err(_) => {
+                let error = format!("Can't find a composer.lock for: {}", name);
+                log::error!("{}", error);
+                self.client.log_message(MessageType::ERROR, error).await;
+                return None;
+            }
+        };
+
+        match ComposerLockFile::find_package_span(&lock_text, name) {
+            Some(span) => Some(GotoDeclarationResponse::Scalar(Location::new(
+                lock_uri,
+                json::span_to_range(&lock_text, span),
+            ))),
+            None => {
+                let error = format!("Package {} is not locked in composer.lock.", name);
+                log::error!("{}", error);
+                self.client.log_message(MessageType::ERROR, error).await;
+                None
+            }
+        }
+    }
+
+    async fn on_code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let composer_file = match self.document(&params.text_document.uri) {
+            Some(composer_file) => composer_file,
+            None => return Err(Error::method_not_found()),
+        };
+
+        // Offer to scaffold an empty composer.json.
+        if let Some(text) = self.get_document_text(&params.text_document.uri) {
+            if scaffold::is_skeletal(&text) {
+                let action = self.initialize_project_action(&params.text_document.uri, &text);
+                return Ok(Some(vec![CodeActionOrCommand::CodeAction(action)]));
+            }
+        }
+
+        let range_start_line = params.range.start.line;
+        let range_end_line = params.range.end.line;
+
+        // Update all packages of a selection spanning several lines.
+        if range_start_line != range_end_line {
+            return match self.update_selection_command(&composer_file, params.range) {
+                Some(command) => Ok(Some(vec![CodeActionOrCommand::Command(command)])),
+                None => Err(Error::method_not_found()),
+            };
+        }
+
+        let line = range_start_line;
+        let dependency_found = composer_file.dependencies_by_line.get(&line);
+
+        let missing_php = params.context.diagnostics.iter().find(|diagnostic| {
+            diagnostic.code == Some(NumberOrString::String(MISSING_PHP_CODE.to_string()))
+        });
+        let mut commands = vec![];
+        if let Some(diagnostic) = missing_php {
+            if let Some(action) = self
+                .add_php_requirement_action(&composer_file, &params.text_document.uri, diagnostic)
+                .await
+            {
+                commands.push(CodeActionOrCommand::CodeAction(action));
+            }
+        }
+
+        for diagnostic in params.context.diagnostics.iter().filter(|diagnostic| {
+            diagnostic.code == Some(NumberOrString::String(MIXED_CASE_CODE.to_string()))
+        }) {
+            if let Some(action) = self.lowercase_name_action(&params.text_document.uri, diagnostic)
+            {
+                commands.push(CodeActionOrCommand::CodeAction(action));
+            }
+        }
+
+        for diagnostic in params.context.diagnostics.iter().filter(|diagnostic| {
+            diagnostic.code == Some(NumberOrString::String(MISPLACED_CODE.to_string()))
+        }) {
+            if let Some(action) = self.move_dependency_action(&params.text_document.uri, diagnostic)
+            {
+                commands.push(CodeActionOrCommand::CodeAction(action));
+            }
+        }
+
+        if let Some(command) = self.update_block_command(&params.text_document.uri, line) {
+            commands.push(CodeActionOrCommand::Command(command));
+        }
+
+        if let Some(command) = self.run_script_command(&composer_file, params.range.start) {
+            commands.push(CodeActionOrCommand::Command(command));
+        }
+
+        match dependency_found {
+            Some(dependency) => {
+                if composer_file.lock.is_none() {
+                    let install_command = Command {
+                        title: "Install all packages".to_string(),
+                        command: ComposerCommand::Install.id().to_string(),
+                        arguments: Some(vec![command::document_argument(&composer_file.path)]),
+                    };
+
+                    commands.push(CodeActionOrCommand::Command(install_command));
+                } else if composer_file
+                    .lock
+                    .as_ref()
+                    .is_some_and(|lock| !lock.locks(dependency))
+                    && !packagist::is_platform_package(dependency)
+                {
+                    // Composer.json has it, but composer.lock doesn't yet.
+                    let constraint = composer_file
+                        .get_dependency(dependency)
+                        .map(|dependency| dependency.version.clone())
+                        .unwrap_or_default();
+                    commands.push(CodeActionOrCommand::Command(Command {
+                        title: "Add & install package".to_string(),
+                        command: ComposerCommand::Require.id().to_string(),
+                        arguments: Some(vec![
+                            Value::from(dependency.to_owned()),
+                            Value::from(constraint),
+                            command::document_argument(&composer_file.path),
+                        ]),
+                    }));
+                } else {
+                    let update_command = Command {
+                        title: "Update package".to_string(),
+                        command: ComposerCommand::Update.id().to_string(),
+                        arguments: Some(vec![
+                            Value::from(dependency.to_owned()),
+                            command::document_argument(&composer_file.path),
+                        ]),
+                    };
+
+                    commands.push(CodeActionOrCommand::Command(update_command));
+
+                    commands.push(CodeActionOrCommand::Command(Command {
+                        title: "Open package source (vendor)".to_string(),
+                        command: ComposerCommand::OpenPackageSource.id().to_string(),
+                        arguments: Some(vec![
+                            Value::from(dependency.to_owned()),
+                            command::document_argument(&composer_file.path),
+                        ]),
+                    }));
+
+                    commands.push(CodeActionOrCommand::Command(Command {
+                        title: "Why is this package installed?".to_string(),
+                        command: ComposerCommand::Why.id().to_string(),
+                        arguments: Some(vec![
+                            Value::from(dependency.to_owned()),
+                            command::document_argument(&composer_file.path),
+                        ]),
+                    }));
+                }
+
+                for diagnostic in params.context.diagnostics.iter().filter(|diagnostic| {
+                    diagnostic.range.start.line == line
+                        && diagnostic.code
+                            == Some(NumberOrString::String(UPDATE_AVAILABLE_CODE.to_string()))
+                }) {
+                    if let Some(action) =
+                        self.raise_constraint_action(&composer_file, line, dependency, diagnostic)
+                    {
+                        commands.push(CodeActionOrCommand::CodeAction(action));
+                    }
+                }
+
+                for action in self.constraint_style_actions(
+                    &composer_file,
+                    line,
+                    dependency,
+                    &params.context.diagnostics,
+                ) {
+                    commands.push(CodeActionOrCommand::CodeAction(action));
+                }
+
+                for diagnostic in params.context.diagnostics.iter().filter(|diagnostic| {
+                    diagnostic.range.start.line == line
+                        && diagnostic.code
+                            == Some(NumberOrString::String(ABANDONED_CODE.to_string()))
+                }) {
+                    if let Some(action) =
+                        self.replace_abandoned_action(&composer_file, line, dependency, diagnostic)
+                    {
+                        commands.push(CodeActionOrCommand::CodeAction(action));
+                    }
+                }
+
+                commands.push(CodeActionOrCommand::Command(Command {
+                    title: "Remove this package".to_string(),
+                    command: ComposerCommand::Remove.id().to_string(),
+                    arguments: Some(vec![
+                        Value::from(dependency.to_owned()),
+                        command::document_argument(&composer_file.path),
+                    ]),
+                }));
+
+                // Offer to require one of the most used providers.
+                if composer_file.is_virtual(dependency) {
+                    let providers =
+                        packagist::get_providers(dependency, Some(&composer_file.dir()))
+                            .await
+                            .unwrap_or_default();
+                    for provider in providers
+                        .iter()
+                        .filter(|provider| composer_file.get_dependency(&provider.name).is_none())
+                        .take(5)
+                    {
+                        commands.push(CodeActionOrCommand::Command(Command {
+                            title: format!("Require {}", provider.name),
+                            command: ComposerCommand::Require.id().to_string(),
+                            arguments: Some(vec![
+                                Value::from(provider.name.to_owned()),
+                                command::document_argument(&composer_file.path),
+                            ]),
+                        }));
+                    }
+                }
+
+                Ok(Some(commands))
+            }
+            None if !commands.is_empty() => Ok(Some(commands)),
+            None => Err(Error::method_not_found()),
+        }
+    }
+
+    /// Builds a command running the script at the position.
+    fn run_script_command(
+        &self,
+        composer_file: &ComposerFile,
+        position: Position,
+    ) -> Option<Command> {
+        let uri = Url::parse(&composer_file.path).ok()?;
+        let text = self.get_document_text(&uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let script = scripts::script_member_at(&root, json::position_to_offset(&text, position))?;
+
+        Some(Command {
+            title: match scripts::is_event(&script) {
+                true => format!("Run the {} scripts", script),
+                false => format!("Run script {}", script),
+            },
+            command: ComposerCommand::RunScript.id().to_string(),
+            arguments: Some(vec![
+                Value::from(script),
+                command::document_argument(&composer_file.path),
+            ]),
+        })
+    }
+
+    /// Builds a command updating the packages on the selected lines.
+    fn update_selection_command(
+        &self,
+        composer_file: &ComposerFile,
+        range: Range,
+    ) -> Option<Command> {
+        composer_file.lock.as_ref()?;
+
+        // A selection ending at the start of a line doesn't include it.
+        let end_line = match range.end.character {
+            0 => range.end.line.saturating_sub(1),
+            _ => range.end.line,
+        };
+        let mut packages: Vec<Value> = (range.start.line..=end_line)
+            .filter_map(|line| composer_file.dependencies_by_line.get(&line))
+            .filter(|name| !packagist::is_platform_package(name) && !composer_file.is_virtual(name))
+            .map(|name| Value::from(name.clone()))
+            .collect();
+        if packages.is_empty() {
+            return None;
+        }
+        packages.push(command::document_argument(&composer_file.path));
+
+        Some(Command {
+            title: "Update selected packages".to_string(),
+            command: ComposerCommand::Update.id().to_string(),
+            arguments: Some(packages),
+        })
+    }
+
+    /// Builds a command updating all packages of the block, when the line is
+    /// the "require" or "require-dev" key.
+    fn update_block_command(&self, uri: &Url, line: u32) -> Option<Command> {
+        let text = self.get_document_text(uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+
+        for block in [placement::REQUIRE, placement::REQUIRE_DEV] {
+            let member = match root.get(block) {
+                Some(member) => member,
+                None => continue,
+            };
+            if json::span_to_range(&text, member.key_span).start.line != line {
+                continue;
+            }
+
+            let mut packages: Vec<Value> = member
+                .value
+                .as_object()?
+                .iter()
+                .filter(|member| {
+                    !packagist::is_platform_package(&member.key)
+                        && !packagist::is_virtual_package(&member.key)
+                })
+                .map(|member| Value::from(member.key.clone()))
+                .collect();
+            if packages.is_empty() {
+                return None;
+            }
+            packages.push(command::document_argument(uri.as_str()));
+
+            return Some(Command {
+                title: format!("Update all packages in {}", block),
+                command: ComposerCommand::Update.id().to_string(),
+                arguments: Some(packages),
+            });
+        }
+
+        None
+    }
+
+    /// Builds a quickfix moving a misplaced dependency to the other block.
+    fn move_dependency_action(&self, uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+        let text = self.get_document_text(uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let misplacement = placement::find_misplaced(&root)
+            .into_iter()
+            .find(|misplacement| {
+                json::span_to_range(&text, misplacement.span) == diagnostic.range
+            })?;
+
+        let edits = placement::move_edits(&text, &root, &misplacement)?
+            .into_iter()
+            .map(|(span, new_text)| TextEdit::new(json::span_to_range(&text, span), new_text))
+            .collect();
+
+        let mut changes = HashMap::new();
+        changes.insert(uri.clone(), edits);
+
+        Some(CodeAction {
+            title: format!("Move {} to {}", misplacement.package, misplacement.to),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(changes)),
+            is_preferred: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Builds a quickfix lowercasing the package name of the diagnostic.
+    fn lowercase_name_action(&self, uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+        let text = self.get_document_text(uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let name = naming::find_mixed_case(&root)
+            .into_iter()
+            .find(|name| json::span_to_range(&text, name.span) == diagnostic.range)?;
+
+        let (span, new_text) = name.lowercase_edit();
+        let mut changes = HashMap::new();
+        changes.insert(
+            uri.clone(),
+            vec![TextEdit::new(json::span_to_range(&text, span), new_text)],
+        );
+
+        Some(CodeAction {
+            title: format!("Rename to {}", name.package.to_lowercase()),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(changes)),
+            is_preferred: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Builds a quickfix adding a php requirement for the platform override,
+    /// or else the local interpreter, at the top of "require".
+    async fn add_php_requirement_action(
+        &self,
+        composer_file: &ComposerFile,
+        uri: &Url,
+        diagnostic: &Diagnostic,
+    ) -> Option<CodeAction> {
+        let effective = self.effective_config(composer_file).await;
+        let version = match composer_file.platform_override(&effective, "php") {
+            Some(version) => version,
+            None => platform::local_php_version().await?,
+        };
+        let requirement = platform::caret_requirement(&version)?;
+
+        let text = self.get_document_text(uri)?;
+        let (root, _) = json::parse_tolerant(&text).ok()?;
+        let (span, insertion) = platform::php_requirement_edit(&text, &root, &requirement)?;
+
+        let mut changes = HashMap::new();
+        changes.insert(
+            uri.clone(),
+            vec![TextEdit::new(json::span_to_range(&text, span), insertion)],
+        );
+
+        Some(CodeAction {
+            title: format!("Require php {}", requirement),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(changes)),
+            is_preferred: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Builds a quickfix changing the constraint to the version of an update
+    /// available diagnostic, e.g. "^2.2.1".
+    fn raise_constraint_action(
+        &self,
+        composer_file: &ComposerFile,
+        line: u32,
+        dependency: &str,
+        diagnostic: &Diagnostic,
+    ) -> Option<CodeAction> {
+        let version = diagnostic.data.as_ref()?.get("version")?.as_str()?;
+        // Branches, like "dev-main", can't be a caret constraint.
+        constraint::Version::parse(version)?;
+        let rewrite = format!("^{}", version.trim_start_matches('v'));
+
+        let range = composer_file
+            .get_dependency_on_line(line, dependency)?
+            .constraint_range;
+        let uri = Url::parse(&composer_file.path).ok()?;
+
+        let mut changes = HashMap::new();
+        changes.insert(uri, vec![TextEdit::new(range, rewrite.clone())]);
+
+        Some(CodeAction {
+            title: format!("Change constraint to {}", rewrite),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(changes)),
+            is_preferred: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Builds rewrites of the dependency's constraint into other styles.
+    fn constraint_style_actions(
+        &self,
+        composer_file: &ComposerFile,
+        line: u32,
+        dependency: &str,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<CodeAction> {
+        let (range, current) = match composer_file.get_dependency_on_line(line, dependency) {
+            Some(dependency) => (dependency.constraint_range, dependency.version.clone()),
+            None => return vec![],
+        };
+
+        let uri = match Url::parse(&composer_file.path) {
+            Ok(uri) => uri,
+            Err(_) => return vec![],
+        };
+
+        // The caret rewrite doubles as the fix of an exact pin lint.
+        let rewrites = [
+            (
+                "Change to caret constraint",
+                constraint::exact_to_caret(&current),
+                true,
+            ),
+            (
+                "Change to tilde constraint",
+                constraint::caret_to_tilde(&current),
+                false,
+            ),
+            (
+                "Allow the next major version",
+                constraint::widen_to_next_major(&current),
+                false,
+            ),
+        ];
+
+        let exact_pins: Vec<Diagnostic> = diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                diagnostic.range.start.line == line
+                    && diagnostic.code == Some(NumberOrString::String(EXACT_PIN_CODE.to_string()))
+            })
+            .cloned()
+            .collect();
+
+        let mut actions = vec![];
+        for (title, rewrite, fixes_exact_pin) in rewrites {
+            let rewrite = match rewrite {
+                Some(rewrite) => rewrite,
+                None => continue,
+            };
+
+            let edit = TextEdit::new(range, rewrite.clone());
+
+            let mut changes = HashMap::new();
+            changes.insert(uri.clone(), vec![edit]);
+
+            let mut action = CodeAction {
+                title: format!("{}: {}", title, rewrite),
+                kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                edit: Some(WorkspaceEdit::new(changes)),
+                ..Default::default()
+            };
+
+            if fixes_exact_pin && !exact_pins.is_empty() {
+                action.kind = Some(CodeActionKind::QUICKFIX);
+                action.diagnostics = Some(exact_pins.clone());
+                action.is_preferred = Some(true);
+            }
+
+            actions.push(action);
+        }
+
+        actions
+    }
+
+    /// Publishes the analysis diagnostics of a document, together with the
+    /// problems of the last failed composer command.
+    async fn publish_document_diagnostics(&self, uri: Url, version: Option<i32>) {
+        // Clients pulling diagnostics request them for the open documents,
+        // they only need to know that they changed. Diagnostics pushed while
+        // the document wasn't open are cleared, so they don't show twice.
+        let pull_support = *self.pull_support.read().unwrap();
+        if pull_support.enabled && self.buffer.contains_key(uri.as_str()) {
+            self.client.publish_diagnostics(uri, vec![], version).await;
+            if pull_support.refresh {
+                let refreshed = self
+                    .client
+                    .send_request::<pull::WorkspaceDiagnosticRefresh>(())
+                    .await;
+                if let Err(error) = refreshed {
+                    info!("Can't refresh the diagnostics: {}", error);
+                }
+            }
+            return;
+        }
+
+        let mut diagnostics = match self.diagnostics.get(&uri.to_string()) {
+            Some(diagnostics) => diagnostics.clone(),
+            None => vec![],
+        };
+        if let Some(command_diagnostics) = self.command_diagnostics.get(&uri.to_string()) {
+            diagnostics.extend(command_diagnostics.iter().cloned());
+        }
+
+        self.client
+            .publish_diagnostics(uri, diagnostics, version)
+            .await;
+    }
+
+    /// Runs `composer validate` for the document and keeps its problems for
+    /// the diagnostics. When composer can't run, the checks that don't need
+    /// it are done on the buffer instead.
+    async fn validate(
+        &self,
+        composer_file: &ComposerFile,
+        commands: &CommandSettings,
+    ) -> Vec<validate::Problem> {
+        let output = runner::run_composer(
+            &self.client,
+            "composer validate",
+            &composer_file.working_dir(),
+            &global::command_args(
+                composer_file.is_global(),
+                &["validate", "--no-check-publish", "--with-dependencies"],
+            ),
+            commands,
+        )
+        .await;
+
+        // Exit codes above 2 mean the file couldn't be validated at all.
+        let problems = match output {
+            Ok(output) if matches!(output.status.code(), Some(0..=2)) => {
+                let output = format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                validate::parse_output(&output)
+            }
+            output => {
+                if let Err(error) = output {
+                    info!("Can't run composer validate: {}", error);
+                }
+                let text = Url::parse(&composer_file.path)
+                    .ok()
+                    .and_then(|uri| self.get_document_text(&uri));
+                match text.as_deref().map(json::parse_tolerant) {
+                    Some(Ok((root, _))) => validate::check(&root),
+                    _ => vec![],
+                }
+            }
+        };
+
+        self.saved_checks
+            .entry(composer_file.path.clone())
+            .or_default()
+            .validation = problems.clone();
+
+        problems
+    }
+
+    /// Shows why a composer command failed, with a diagnostic on the
+    /// offending requirement where it can be identified.
+    async fn report_failure(&self, composer_file: &ComposerFile, stderr: &[u8]) {
+        let output = String::from_utf8_lossy(stderr);
+        let failures = failure::parse_failures(&output);
+
+        let mut diagnostics = vec![];
+        let mut messages = vec![];
+        for failure in failures.iter() {
+            let dependency = failure
+                .package
+                .as_ref()
+                .and_then(|package| composer_file.get_dependency(package));
+
+            match dependency {
+                Some(dependency) => {
+                    diagnostics.push(new_diagnostic(
+                        dependency.name_range,
+                        Some(DiagnosticSeverity::ERROR),
+                        COMMAND_FAILURE_CODE,
+                        Some(TROUBLESHOOTING_URL),
+                        failure.message.to_string(),
+                    ));
+                }
+                None => messages.push(failure.message.to_string()),
+            }
+        }
+
+        if failures.is_empty() {
+            let last_line = output.lines().rev().find(|line| !line.trim().is_empty());
+            messages.push(match last_line {
+                Some(line) => format!("Composer command failed: {}", line.trim()),
+                None => "Composer command failed.".to_string(),
+            });
+        }
+
+        if !messages.is_empty() {
+            self.client
+                .show_message(MessageType::ERROR, messages.join("\n"))
+                .await;
+        }
+
+        let uri = match Url::parse(&composer_file.path) {
+            Ok(uri) => uri,
+            Err(_) => return,
+        };
+        self.command_diagnostics
+            .insert(uri.to_string(), diagnostics);
+        let version = self.document_versions.get(&uri.to_string()).map(|v| *v);
+        self.publish_document_diagnostics(uri, version).await;
+    }
+
+    /// Removes the diagnostics of a previously failed composer command.
+    async fn clear_failure(&self, composer_file: &ComposerFile) {
+        let uri = match Url::parse(&composer_file.path) {
+            Ok(uri) => uri,
+            Err(_) => return,
+        };
+
+        if self.command_diagnostics.remove(&uri.to_string()).is_some() {
+            let version = self.document_versions.get(&uri.to_string()).map(|v| *v);
+            self.publish_document_diagnostics(uri, version).await;
+        }
+    }
+
+    /// Builds an action replacing the whole document with a new project scaffold.
+    fn initialize_project_action(&self, uri: &Url, text: &str) -> CodeAction {
+        let dir = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+            .unwrap_or_default();
+        let vendor = env::var("USER").unwrap_or_else(|_| "vendor".to_string());
+
+        let edit = TextEdit::new(
+            json::span_to_range(text, json::Span::new(0, text.len())),
+            scaffold::composer_json(&vendor, &dir),
+        );
+
+        let mut changes = HashMap::new();
+        changes.insert(uri.clone(), vec![edit]);
+
+        CodeAction {
+            title: "Initialize project".to_string(),
+            kind: Some(CodeActionKind::SOURCE),
+            edit: Some(WorkspaceEdit::new(changes)),
+            ..Default::default()
+        }
+    }
+
+    /// Builds a quickfix replacing an abandoned package with the successor
+    /// suggested by packagist, constrained to the successor's latest version.
+    /// Both come with the diagnostic, so nothing is fetched here.
+    fn replace_abandoned_action(
+        &self,
+        composer_file: &ComposerFile,
+        line: u32,
+        dependency: &str,
+        diagnostic: &Diagnostic,
+    ) -> Option<CodeAction> {
+        let data = diagnostic.data.as_ref()?;
+        let replacement = data.get("replacement")?.as_str()?.to_string();
+        let constraint = data.get("constraint")?.as_str()?.to_string();
+
+        let required = composer_file.get_dependency_on_line(line, dependency)?;
+        let edits = vec![
+            TextEdit::new(required.name_range, replacement.clone()),
+            TextEdit::new(required.constraint_range, constraint.clone()),
+        ];
+
+        let mut changes = HashMap::new();
+        changes.insert(Url::parse(&composer_file.path).ok()?, edits);
+
+        Some(CodeAction {
+            title: format!(
+                "Replace abandoned {} with {} {}",
+                dependency, replacement, constraint
+            ),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(changes)),
+            is_preferred: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Runs a composer command that changes the installed packages, showing
+    /// why it failed or reporting its outcome.
+    async fn run_package_command(
+        &self,
+        composer_file: &ComposerFile,
+        title: &str,
+        args: &[&str],
+        commands: &CommandSettings,
+    ) -> Result<Option<Value>> {
+        let started = Instant::now();
+        let output = runner::run_composer(
+            &self.client,
+            title,
+            &composer_file.working_dir(),
+            &global::command_args(composer_file.is_global(), args),
+            commands,
+        )
+        .await;
+
+        let output = match output {
+            Ok(output) => output,
+            Err(error) => {
+                self.client
+                    .show_message(MessageType::ERROR, error.to_string())
+                    .await;
+                return Err(Error::new(ServerError(400)));
+            }
+        };
+
+        if !output.status.success() {
+            self.report_failure(composer_file, &output.stderr).await;
+            return Err(Error::new(ServerError(400)));
+        }
+        self.clear_failure(composer_file).await;
+
+        self.report_outcome(title, started, &output).await;
+        Ok(None)
+    }
+
+    async fn on_execute_command(&self, mut params: ExecuteCommandParams) -> Result<Option<Value>> {
+        let command = match ComposerCommand::from_id(&params.command) {
+            Some(command) => command,
+            None => return Err(Error::method_not_found()),
+        };
+
+        // Commands that don't need a project file to be open.
+        match command {
+            ComposerCommand::OpenGlobal => {
+                self.open_global_manifest().await;
+                return Ok(None);
+            }
+            ComposerCommand::CheckWorkspace => {
+                let commands = self.settings.read().unwrap().commands.clone();
+                self.check_workspace(&commands).await;
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        // Commands run for the document they name, else the active one.
+        let (uri, arguments) = command::split_document(std::mem::take(&mut params.arguments));
+        params.arguments = arguments;
+        let uri = uri.or_else(|| self.active_document.read().unwrap().clone());
+        let composer_file = match uri.and_then(|uri| self.composer_file.get(&uri)) {
+            Some(composer_file) => composer_file.clone(),
+            None => return Ok(None),
+        };
+        let commands = self.settings.read().unwrap().commands.clone();
+
+        match command {
+            ComposerCommand::Update => {
+                if params.arguments.is_empty() {
+                    return Ok(None);
+                }
+
+                // Several packages are updated in one run.
+                let dependencies: Vec<&str> = params
+                    .arguments
+                    .iter()
+                    .filter_map(|argument| argument.as_str())
+                    .collect();
+                let mut args = vec!["update"];
+                args.extend_from_slice(&dependencies);
+                let title = format!("composer update {}", dependencies.join(" "));
+                self.run_package_command(&composer_file, &title, &args, &commands)
+                    .await
+            }
+            ComposerCommand::Require => {
+                let dependency = match params.arguments.first().and_then(|name| name.as_str()) {
+                    Some(dependency) => dependency,
+                    None => return Ok(None),
+                };
+                let constraint = params
+                    .arguments
+                    .get(1)
+                    .and_then(|constraint| constraint.as_str());
+
+                // Packages already in require-dev stay there.
+                let mut args = vec!["require"];
+                if composer_file.is_dev_dependency(dependency) {
+                    args.push("--dev");
+                }
+                let package = command::require_argument(dependency, constraint);
+                args.push(&package);
+
+                let title = format!("composer {}", args.join(" "));
+                self.run_package_command(&composer_file, &title, &args, &commands)
+                    .await
+            }
+            ComposerCommand::Remove => {
+                let dependency = match params.arguments.first().and_then(|name| name.as_str()) {
+                    Some(dependency) => dependency,
+                    None => return Ok(None),
+                };
+
+                // Packages in require-dev are only removed with --dev.
+                let args =
+                    command::remove_args(dependency, composer_file.is_dev_dependency(dependency));
+                let title = format!("composer {}", args.join(" "));
+                self.run_package_command(&composer_file, &title, &args, &commands)
+                    .await
+            }
+            // Run without a document, above.
+            ComposerCommand::CheckWorkspace | ComposerCommand::OpenGlobal => Ok(None),
+            ComposerCommand::OpenPackageSource => {
+                if let Some(name) = params.arguments.first().and_then(|name| name.as_str()) {
+                    self.open_package_source(&composer_file, name).await;
+                }
+                Ok(None)
+            }
+            ComposerCommand::Why => {
+                let name = match params.arguments.first().and_then(|name| name.as_str()) {
+                    Some(name) => name,
+                    None => return Ok(None),
+                };
+                let lock = match &composer_file.lock {
+                    Some(lock) => lock,
+                    None => {
+                        self.client
+                            .show_message(
+                                MessageType::WARNING,
+                                "There is no composer.lock yet, install the packages first.",
                             )
                             .await;
                         return Ok(None);
                     }
-                    Err(_) => {
+                };
+
+                let requirements: Vec<String> = composer_file
+                    .dependencies
+                    .iter()
+                    .chain(composer_file.dev_dependencies.iter())
+                    .map(|dependency| dependency.name.clone())
+                    .collect();
+                self.client
+                    .show_message(MessageType::INFO, why::explain(lock, &requirements, name))
+                    .await;
+
+                let dependents: Vec<Value> = why::dependents(lock, name)
+                    .into_iter()
+                    .map(|dependent| {
+                        serde_json::json!({
+                            "name": dependent.name,
+                            "constraint": dependent.constraint,
+                        })
+                    })
+                    .collect();
+                Ok(Some(serde_json::json!({
+                    "package": name,
+                    "dependents": dependents,
+                    "chain": why::chain(lock, &requirements, name),
+                })))
+            }
+            ComposerCommand::RunScript => {
+                let script = match params.arguments.first().and_then(|name| name.as_str()) {
+                    Some(script) => script,
+                    None => return Ok(None),
+                };
+
+                let title = format!("composer run-script {}", script);
+                let output = runner::run_script(
+                    &self.client,
+                    &title,
+                    &composer_file.working_dir(),
+                    &global::command_args(composer_file.is_global(), &["run-script", script]),
+                    &commands,
+                )
+                .await;
+                let output = match output {
+                    Ok(output) => output,
+                    Err(error) => {
+                        self.client
+                            .show_message(MessageType::ERROR, error.to_string())
+                            .await;
                         return Err(Error::new(ServerError(400)));
                     }
                 };
+
+                match output.status.success() {
+                    true => {
+                        self.client
+                            .show_message(MessageType::INFO, format!("{} finished.", title))
+                            .await
+                    }
+                    false => {
+                        let stderr = String::from_utf8_lossy(&output.stderr);
+                        let reason = stderr
+                            .lines()
+                            .rev()
+                            .find(|line| !line.trim().is_empty())
+                            .unwrap_or("see the output for details");
+                        self.client
+                            .show_message(
+                                MessageType::ERROR,
+                                format!("{} failed: {}", title, reason.trim()),
+                            )
+                            .await
+                    }
+                }
+
+                Ok(Some(serde_json::json!({
+                    "script": script,
+                    "exitCode": output.status.code(),
+                })))
             }
-            "install" => {
-                let command_path = composer_file
-                    .path
-                    .replace("/composer.json", "")
-                    .replace("file://", "");
+            ComposerCommand::Validate => {
+                let problems = self.validate(&composer_file, &commands).await;
+                let errors = problems
+                    .iter()
+                    .filter(|problem| problem.severity == validate::Severity::Error)
+                    .count();
+                let warnings = problems.len() - errors;
 
-                let output = ProcessCommand::new("composer")
-                    .arg(format!("--working-dir={}", command_path).as_str())
-                    .arg("install")
-                    .output()
-                    .expect("failed to execute process");
+                let uri = match Url::parse(&composer_file.path).ok() {
+                    Some(uri) => uri,
+                    None => return Ok(None),
+                };
+                if let Some(text) = self.get_document_text(&uri) {
+                    if let Some(diagnostics) = self.current_diagnostics(&uri, &text).await {
+                        self.diagnostics.insert(uri.to_string(), diagnostics);
+                    }
+                }
+                let version = self.document_versions.get(uri.as_str()).map(|v| *v);
+                self.publish_document_diagnostics(uri, version).await;
 
-                if !output.status.success() {
-                    self.client
-                        .show_message(MessageType::INFO, "Composer command failed.")
-                        .await;
-                    return Err(Error::new(ServerError(400)));
+                let message = match problems.is_empty() {
+                    true => "composer.json is valid.".to_string(),
+                    false => format!(
+                        "composer validate found {} errors and {} warnings.",
+                        errors, warnings
+                    ),
+                };
+                self.client.show_message(MessageType::INFO, message).await;
+
+                Ok(Some(serde_json::json!({
+                    "errors": errors,
+                    "warnings": warnings,
+                })))
+            }
+            ComposerCommand::Install => {
+                self.run_package_command(
+                    &composer_file,
+                    "composer install",
+                    &["install"],
+                    &commands,
+                )
+                .await
+            }
+            ComposerCommand::SetConstraint => {
+                if params.arguments.len() < 2 {
+                    return Ok(None);
                 }
 
-                match from_utf8(&output.stderr) {
-                    Ok(message) => {
-                        if message.contains("Your requirements could not be resolved to an installable set of packages") {
-                            self.client.show_message(MessageType::INFO, "Composer dependencies could not be resolved.").await;
-                            return Ok(None);
-                        }
+                let dependency = params.arguments[0].as_str().unwrap_or_default();
+                let constraint = params.arguments[1].as_str().unwrap_or_default();
 
-                        self.client
-                            .show_message(
-                                MessageType::INFO,
-                                format!("Composer packages were installed.",),
-                            )
-                            .await;
+                let range = match composer_file.get_dependency(dependency) {
+                    Some(dependency) => dependency.constraint_range,
+                    None => {
+                        let error = format!("Can't find the dependency: {}", dependency);
+                        log::error!("{}", error);
+                        self.client.log_message(MessageType::ERROR, error).await;
                         return Ok(None);
                     }
-                    Err(_) => {
-                        return Err(Error::new(ServerError(400)));
-                    }
                 };
+
+                let uri = match Url::parse(&composer_file.path) {
+                    Ok(uri) => uri,
+                    Err(_) => return Ok(None),
+                };
+                let edit = TextEdit::new(range, constraint.to_string());
+
+                let mut changes = HashMap::new();
+                changes.insert(uri, vec![edit]);
+
+                match self.client.apply_edit(WorkspaceEdit::new(changes)).await {
+                    Ok(response) if response.applied => Ok(None),
+                    _ => Err(Error::new(ServerError(400))),
+                }
+            }
+        }
+    }
+}
+
+/// Runs the optional on-save actions enabled in the settings.
+async fn run_on_save_hooks(
+    client: Client,
+    settings: OnSaveSettings,
+    commands: CommandSettings,
+    working_dir: String,
+    global: bool,
+    metadata_only: bool,
+) {
+    if settings.audit {
+        let output = runner::run_composer(
+            &client,
+            "composer audit",
+            &working_dir,
+            &global::command_args(global, &["audit", "--locked", "--format=summary"]),
+            &commands,
+        )
+        .await;
+
+        if let Ok(output) = output {
+            if !output.status.success() {
+                let message = format!(
+                    "composer audit found security advisories:\n{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                client.show_message(MessageType::WARNING, message).await;
+            }
+        }
+    }
+
+    if settings.update_lock && metadata_only {
+        let output = runner::run_composer(
+            &client,
+            "composer update --lock",
+            &working_dir,
+            &global::command_args(global, &["update", "--lock"]),
+            &commands,
+        )
+        .await;
+
+        match output {
+            Ok(output) if output.status.success() => {
+                client
+                    .show_message(MessageType::INFO, "composer.lock was refreshed.")
+                    .await;
+            }
+            Ok(_) => {
+                client
+                    .show_message(MessageType::ERROR, "Composer command failed.")
+                    .await;
+            }
+            Err(error) => {
+                client
+                    .show_message(MessageType::ERROR, error.to_string())
+                    .await;
             }
-            _ => return Err(Error::method_not_found()),
         }
     }
 }
 
+/// Points packagist requests at the configured mirror, or the one of the
+/// global composer config, and applies the cache policy.
+fn configure_packagist(settings: &PackagistSettings) {
+    packagist::set_mirror(settings.mirror.clone().or_else(global::packagist_mirror));
+    packagist::set_packagist_url(settings.url.clone());
+    cache::set_policy(settings.cache_policy());
+    auth::clear_cache();
+}
+
+/// A diagnostic with a stable code, so clients can filter on it, and an
+/// optional link to learn more about it.
+fn new_diagnostic(
+    range: Range,
+    severity: Option<DiagnosticSeverity>,
+    code: &str,
+    href: Option<&str>,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        code_description: href
+            .and_then(|href| Url::parse(href).ok())
+            .map(|href| CodeDescription { href }),
+        ..Diagnostic::new(
+            range,
+            severity,
+            Some(NumberOrString::String(code.to_string())),
+            None,
+            message,
+            None,
+            None,
+        )
+    }
+}
+
+/// What the last check on save found out about the packages of a document,
+/// reused to refresh its diagnostics while it's edited.
+#[derive(Debug, Clone, Default)]
+struct SavedChecks {
+    failed_packages: Vec<String>,
+    advisories: Vec<audit::Advisory>,
+    /// The problems the last `composer validate` found.
+    validation: Vec<validate::Problem>,
+}
+
+/// Diagnostics on the keys with problems found by `composer validate`.
+/// Problems of dependencies are shown on their requirement.
+fn validation_diagnostics(
+    text: &str,
+    composer_file: &ComposerFile,
+    problems: &[validate::Problem],
+) -> Vec<Diagnostic> {
+    let root = match json::parse_tolerant(text) {
+        Ok((root, _)) => root,
+        Err(_) => return vec![],
+    };
+
+    let mut diagnostics = vec![];
+    for problem in problems {
+        let (range, message) = match &problem.package {
+            Some(package) => {
+                let range = match composer_file.get_dependency(package) {
+                    Some(dependency) => dependency.name_range,
+                    // Indirect dependencies are shown on "require".
+                    None => match root.get("require") {
+                        Some(require) => json::span_to_range(text, require.key_span),
+                        None => continue,
+                    },
+                };
+                (range, format!("{}: {}", package, problem.message))
+            }
+            None => match problem.span(&root) {
+                Some(span) => (json::span_to_range(text, span), problem.message.clone()),
+                None => continue,
+            },
+        };
+
+        let severity = match problem.severity {
+            validate::Severity::Error => DiagnosticSeverity::ERROR,
+            validate::Severity::Warning => DiagnosticSeverity::WARNING,
+        };
+        diagnostics.push(new_diagnostic(
+            range,
+            Some(severity),
+            VALIDATE_CODE,
+            Some(SCHEMA_URL),
+            message,
+        ));
+    }
+
+    diagnostics
+}
+
+/// Diagnostics on the requirements of the packages with advisories.
+fn advisory_diagnostics(text: &str, advisories: &[audit::Advisory]) -> Vec<Diagnostic> {
+    let root = match json::parse_tolerant(text) {
+        Ok((root, _)) => root,
+        Err(_) => return vec![],
+    };
+
+    let mut diagnostics = vec![];
+    for advisory in advisories {
+        // Advisories of indirect dependencies are shown on "require".
+        let span = ["require", "require-dev"]
+            .iter()
+            .filter_map(|block| root.get(block))
+            .find_map(|block| block.value.get(&advisory.package))
+            .map(|member| member.key_span)
+            .or_else(|| root.get("require").map(|require| require.key_span))
+            .unwrap_or_else(|| json::Span::new(0, 0));
+
+        diagnostics.push(advisory_diagnostic(
+            json::span_to_range(text, span),
+            advisory,
+        ));
+    }
+
+    diagnostics
+}
+
+/// The outline entry as an LSP symbol, with line ranges.
+#[allow(deprecated)]
+fn document_symbol(line_index: &json::LineIndex, symbol: symbols::Symbol) -> DocumentSymbol {
+    DocumentSymbol {
+        name: symbol.name,
+        detail: symbol.detail,
+        kind: symbol.kind,
+        tags: None,
+        deprecated: None,
+        range: line_index.range(symbol.span),
+        selection_range: line_index.range(symbol.selection_span),
+        children: Some(
+            symbol
+                .children
+                .into_iter()
+                .map(|child| document_symbol(line_index, child))
+                .collect(),
+        ),
+    }
+}
+
+/// The diagnostic of a security advisory.
+fn advisory_diagnostic(range: Range, advisory: &audit::Advisory) -> Diagnostic {
+    let mut message = match &advisory.cve {
+        Some(cve) => format!("{}: {} ({})", advisory.package, advisory.title, cve),
+        None => format!("{}: {}", advisory.package, advisory.title),
+    };
+    if let Some(link) = &advisory.link {
+        message.push_str(&format!("\n{}", link));
+    }
+
+    new_diagnostic(
+        range,
+        Some(DiagnosticSeverity::ERROR),
+        SECURITY_ADVISORY_CODE,
+        advisory.link.as_deref(),
+        message,
+    )
+}
+
+/// Adds the advisory diagnostics and counts them in the summary too.
+fn add_advisories(
+    diagnostics: &mut Vec<Diagnostic>,
+    advisories: Vec<Diagnostic>,
+    composer_file: &ComposerFile,
+) {
+    diagnostics.extend(advisories);
+
+    let summary = diagnostics.iter().find(|diagnostic| {
+        diagnostic.code == Some(NumberOrString::String(SUMMARY_CODE.to_string()))
+    });
+    if let Some(range) = summary.map(|summary| summary.range) {
+        add_summary(diagnostics, range, composer_file);
+    }
+}
+
+/// Replaces the summary of the outdated dependencies and advisories among
+/// the diagnostics, placed on the range of the "require" key.
+fn add_summary(diagnostics: &mut Vec<Diagnostic>, range: Range, composer_file: &ComposerFile) {
+    // Only packages can be outdated, not the platform or virtual packages.
+    let total = composer_file
+        .dependencies
+        .iter()
+        .filter(|dependency| {
+            !packagist::is_platform_package(&dependency.name)
+                && !composer_file.is_virtual(&dependency.name)
+        })
+        .count();
+    let count = |code: &str| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == Some(NumberOrString::String(code.to_string())))
+            .count()
+    };
+    let message = summary::message(
+        count(UPDATE_AVAILABLE_CODE),
+        total,
+        count(SECURITY_ADVISORY_CODE),
+    );
+
+    diagnostics.retain(|diagnostic| {
+        diagnostic.code != Some(NumberOrString::String(SUMMARY_CODE.to_string()))
+    });
+    if total > 0 {
+        diagnostics.push(new_diagnostic(
+            range,
+            Some(DiagnosticSeverity::HINT),
+            SUMMARY_CODE,
+            None,
+            message,
+        ));
+    }
+}
+
 #[tokio::main]
 async fn main() {
     match env::var("COMPOSER_LSP_LOG") {
@@ -643,12 +4648,43 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
+    let pull_support = Arc::new(RwLock::new(pull::PullSupport::default()));
     let (service, socket) = LspService::build(|client| Backend {
         client,
         composer_file: DashMap::new(),
-        packagist_packages: DashMap::new(),
+        package_index: Arc::new(RwLock::new(None)),
         buffer: DashMap::new(),
+        active_document: RwLock::new(None),
+        saved_text: DashMap::new(),
+        settings: RwLock::new(Settings::default()),
+        background_tasks: DashMap::new(),
+        workspace_folders: RwLock::new(vec![]),
+        workspace_files: DashMap::new(),
+        document_versions: DashMap::new(),
+        analyses: DashMap::new(),
+        saved_checks: DashMap::new(),
+        diagnostics: DashMap::new(),
+        command_diagnostics: DashMap::new(),
+        packages_fetched_at: Arc::new(RwLock::new(None)),
+        package_cache: DashMap::new(),
+        dynamic_features: RwLock::new(vec![]),
+        refreshable_features: RwLock::new(vec![]),
+        registered_features: RwLock::new(vec![]),
+        file_watching: RwLock::new(false),
+        effective_configs: DashMap::new(),
+        pull_support: pull_support.clone(),
+        semantic_tokens: DashMap::new(),
     })
+    .custom_method("composer/packageVersions", Backend::package_versions)
+    .custom_method("composer/serverStatus", Backend::server_status)
+    .custom_method("composer/analysisExport", Backend::analysis_export)
+    .custom_method("textDocument/inlayHint", Backend::inlay_hint)
+    .custom_method("textDocument/diagnostic", Backend::document_diagnostic)
     .finish();
-    Server::new(stdin, stdout, socket).serve(service).await;
+    let service = pull::PullDiagnostics::new(service, pull_support);
+    // Debounced changes wait without blocking the other requests.
+    Server::new(stdin, stdout, socket)
+        .concurrency_level(16)
+        .serve(service)
+        .await;
 }
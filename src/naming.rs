@@ -0,0 +1,75 @@
+use crate::edit::Edit;
+use crate::json::{Node, Span};
+
+/// A requirement whose name isn't lowercase. Composer matches names
+/// case-insensitively, but normalizes them to lowercase and warns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedCaseName {
+    /// The span of the package name, including its quotes.
+    pub span: Span,
+    pub package: String,
+}
+
+impl MixedCaseName {
+    /// The edit replacing the name with its lowercase form.
+    pub fn lowercase_edit(&self) -> Edit {
+        (self.span, format!("\"{}\"", self.package.to_lowercase()))
+    }
+}
+
+/// Finds the names in require and require-dev with uppercase characters.
+pub fn find_mixed_case(root: &Node) -> Vec<MixedCaseName> {
+    let mut names = vec![];
+    for block in ["require", "require-dev"] {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            if member.key.chars().any(|c| c.is_uppercase()) {
+                names.push(MixedCaseName {
+                    span: member.key_span,
+                    package: member.key.clone(),
+                });
+            }
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edit;
+    use crate::json;
+    use crate::naming::find_mixed_case;
+
+    #[test]
+    fn it_can_find_mixed_case_names() {
+        let text = r#"{
+            "require": { "php": "^8.1", "Monolog/Monolog": "^3.0" },
+            "require-dev": { "phpunit/phpunit": "^10.0", "ext-PDO": "*" }
+        }"#;
+        let names = find_mixed_case(&json::parse(text).unwrap());
+
+        assert_eq!(2, names.len());
+        assert_eq!("Monolog/Monolog", names[0].package);
+        assert_eq!("ext-PDO", names[1].package);
+        assert_eq!(
+            "\"Monolog/Monolog\"",
+            &text[names[0].span.start..names[0].span.end]
+        );
+    }
+
+    #[test]
+    fn it_can_lowercase_a_name() {
+        let text = r#"{ "require": { "Psr/Log": "^3.0" } }"#;
+        let names = find_mixed_case(&json::parse(text).unwrap());
+
+        assert_eq!(
+            r#"{ "require": { "psr/log": "^3.0" } }"#,
+            edit::apply(text, vec![names[0].lowercase_edit()])
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::notification::Notification;
+
+/// A package that a composer command installed, updated or removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageChange {
+    pub package: String,
+    /// "install", "upgrade", "downgrade" or "remove".
+    pub operation: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl PackageChange {
+    fn describe(&self) -> String {
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => format!("{} {} => {}", self.package, from, to),
+            (None, Some(version)) | (Some(version), None) => {
+                format!("{} {}", self.package, version)
+            }
+            (None, None) => self.package.clone(),
+        }
+    }
+}
+
+/// What a finished composer command changed, sent to the client as the
+/// "composer/commandFinished" notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOutcome {
+    pub command: String,
+    pub changes: Vec<PackageChange>,
+    pub duration_ms: u128,
+}
+
+impl CommandOutcome {
+    pub fn message(&self) -> String {
+        let seconds = self.duration_ms as f64 / 1000.0;
+        if self.changes.is_empty() {
+            return format!(
+                "{} finished in {:.1}s, nothing changed.",
+                self.command, seconds
+            );
+        }
+
+        let mut message = format!("{} finished in {:.1}s:", self.command, seconds);
+        for (operation, label) in [
+            ("install", "installed"),
+            ("upgrade", "upgraded"),
+            ("downgrade", "downgraded"),
+            ("remove", "removed"),
+        ] {
+            let changes: Vec<String> = self
+                .changes
+                .iter()
+                .filter(|change| change.operation == operation)
+                .map(|change| change.describe())
+                .collect();
+            if !changes.is_empty() {
+                message.push_str(&format!(" {} {};", label, changes.join(", ")));
+            }
+        }
+
+        message.trim_end_matches(';').to_string() + "."
+    }
+}
+
+pub enum CommandFinished {}
+
+impl Notification for CommandFinished {
+    type Params = CommandOutcome;
+    const METHOD: &'static str = "composer/commandFinished";
+}
+
+/// Reads the package operations from the output of composer. The lock file
+/// operations are preferred, as composer lists the installs again when it
+/// installs from the new lock file.
+pub fn parse_changes(output: &str) -> Vec<PackageChange> {
+    for section in ["Lock file operations:", "Package operations:"] {
+        let lines = match output.find(section) {
+            Some(start) => output[start..].lines().skip(1),
+            None => continue,
+        };
+
+        let changes: Vec<PackageChange> = lines
+            .map(|line| line.trim())
+            .take_while(|line| line.starts_with("- "))
+            .filter_map(parse_operation)
+            .collect();
+        if !changes.is_empty() {
+            return changes;
+        }
+    }
+
+    vec![]
+}
+
+/// Parses an operation line like "- Upgrading monolog/monolog (3.3.0 => 3.4.0)".
+fn parse_operation(line: &str) -> Option<PackageChange> {
+    let mut words = line.trim_start_matches("- ").splitn(2, ' ');
+    let operation = match words.next()? {
+        "Installing" | "Locking" => "install",
+        "Upgrading" => "upgrade",
+        "Downgrading" => "downgrade",
+        "Removing" => "remove",
+        _ => return None,
+    };
+
+    let rest = words.next()?;
+    let (package, versions) = match rest.split_once(" (") {
+        Some((package, versions)) => (package, versions.split(')').next().unwrap_or_default()),
+        None => (rest, ""),
+    };
+
+    let (from, to) = match (operation, versions.split_once(" => ")) {
+        (_, Some((from, to))) => (Some(from.to_string()), Some(to.to_string())),
+        (_, None) if versions.is_empty() => (None, None),
+        ("remove", None) => (Some(versions.to_string()), None),
+        (_, None) => (None, Some(versions.to_string())),
+    };
+
+    Some(PackageChange {
+        package: package.to_string(),
+        operation: operation.to_string(),
+        from,
+        to,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outcome::{parse_changes, CommandOutcome, PackageChange};
+
+    #[test]
+    fn it_can_parse_the_lock_file_operations() {
+        let output = "Loading composer repositories with package information
+Updating dependencies
+Lock file operations: 1 install, 1 update, 1 removal
+  - Removing acme/old (1.2.0)
+  - Locking psr/log (3.0.0)
+  - Upgrading monolog/monolog (3.3.0 => 3.4.0)
+Writing lock file
+Installing dependencies from lock file (including require-dev)
+Package operations: 1 install, 1 update, 1 removal
+  - Removing acme/old (1.2.0)
+  - Installing psr/log (3.0.0): Extracting archive
+  - Upgrading monolog/monolog (3.3.0 => 3.4.0): Extracting archive
+Generating autoload files";
+        let changes = parse_changes(output);
+
+        assert_eq!(3, changes.len());
+        assert_eq!(
+            PackageChange {
+                package: "monolog/monolog".to_string(),
+                operation: "upgrade".to_string(),
+                from: Some("3.3.0".to_string()),
+                to: Some("3.4.0".to_string()),
+            },
+            changes[2]
+        );
+        assert_eq!(Some("1.2.0".to_string()), changes[0].from);
+        assert_eq!(Some("3.0.0".to_string()), changes[1].to);
+    }
+
+    #[test]
+    fn it_can_parse_an_install_without_changes() {
+        let output = "Installing dependencies from lock file (including require-dev)
+Verifying lock file contents can be installed on current platform.
+Nothing to install, update or remove
+Generating autoload files";
+
+        assert!(parse_changes(output).is_empty());
+    }
+
+    #[test]
+    fn it_can_describe_the_outcome() {
+        let mut outcome = CommandOutcome {
+            command: "composer update".to_string(),
+            changes: parse_changes(
+                "Package operations: 1 install, 1 update, 0 removals
+  - Installing psr/log (3.0.0): Extracting archive
+  - Upgrading monolog/monolog (3.3.0 => 3.4.0): Extracting archive",
+            ),
+            duration_ms: 3240,
+        };
+
+        assert_eq!(
+            "composer update finished in 3.2s: installed psr/log 3.0.0; upgraded monolog/monolog 3.3.0 => 3.4.0.",
+            outcome.message()
+        );
+
+        outcome.changes.clear();
+        assert_eq!(
+            "composer update finished in 3.2s, nothing changed.",
+            outcome.message()
+        );
+    }
+}
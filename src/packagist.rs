@@ -1,15 +1,38 @@
+use crate::audit::Advisory;
+use crate::auth;
+use crate::cache;
 use crate::composer::ComposerDependency;
-use futures::future;
+use crate::constraint::{self, Branch, Constraint, Stability};
+use crate::http;
+use crate::repository::{self, Repositories};
+use crate::runner::Progress;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures::stream::{self, StreamExt};
 // 0.3.4
-use reqwest::Client; // 0.10.6
-use semver::{Version, VersionReq};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode; // 0.10.6
 use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
 use std::{collections::HashMap, vec};
 
 use serde::Deserialize;
 
-const PACKAGIST_API_URL: &str = "https://repo.packagist.org/p2";
-const PACKAGIST_REPO_URL: &str = "https://packagist.org/packages";
+const PACKAGIST_METADATA_URL: &str = "https://repo.packagist.org";
+const PACKAGIST_URL: &str = "https://packagist.org";
+
+/// The key the list of all package names is cached under.
+const PACKAGE_LIST_KEY: &str = "package-list";
+
+/// How many packages are fetched at the same time.
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+type SharedFetch = Shared<BoxFuture<'static, Result<String, String>>>;
+
+/// The fetches in progress by cache key, so concurrent requests for the same
+/// metadata, e.g. from a hover during the checks on save, share one.
+static IN_FLIGHT: Mutex<Vec<(String, SharedFetch)>> = Mutex::new(Vec::new());
 
 #[derive(Debug, Clone)]
 pub struct Package {
@@ -21,11 +44,74 @@ impl Package {
     pub fn new(name: String, versions: Vec<PackageVersion>) -> Package {
         Package { name, versions }
     }
+
+    /// Whether the latest version marks the package as abandoned.
+    pub fn is_abandoned(&self) -> bool {
+        match self
+            .versions
+            .first()
+            .and_then(|latest| latest.abandoned.as_ref())
+        {
+            Some(Abandoned::Flag(abandoned)) => *abandoned,
+            Some(Abandoned::Replacement(_)) => true,
+            None => false,
+        }
+    }
+
+    /// Explains that the package is abandoned, with its replacement if any.
+    pub fn abandoned_notice(&self) -> Option<String> {
+        if !self.is_abandoned() {
+            return None;
+        }
+
+        Some(match self.replacement() {
+            Some(replacement) => {
+                format!("{} is abandoned, use {} instead.", self.name, replacement)
+            }
+            None => format!("{} is abandoned and no longer maintained.", self.name),
+        })
+    }
+
+    /// The suggested replacement, if the latest version marks the package as
+    /// abandoned in favour of another package.
+    pub fn replacement(&self) -> Option<String> {
+        match self.versions.first()?.abandoned.as_ref()? {
+            Abandoned::Replacement(name) if !name.is_empty() => Some(name.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The newest stable version and its release date, e.g.
+    /// "**Latest:** `3.5.0`, released 2023-10-27".
+    pub fn latest_release(&self) -> Option<String> {
+        let latest = self.latest_stable_version()?;
+        let released = self
+            .versions
+            .iter()
+            .find(|item| item.version.as_deref() == Some(latest.as_str()))
+            .and_then(|item| item.time.as_ref())
+            .and_then(|time| time.get(..10));
+
+        Some(match released {
+            Some(date) => format!("**Latest:** `{}`, released {}", latest, date),
+            None => format!("**Latest:** `{}`", latest),
+        })
+    }
+
+    /// The newest version without a dev, alpha, beta or RC stability.
+    pub fn latest_stable_version(&self) -> Option<String> {
+        self.versions
+            .iter()
+            .filter_map(|item| item.version.as_ref())
+            .filter_map(|version| Some((version, constraint::Version::parse(version)?)))
+            .filter(|(_, parsed)| parsed.stability() >= constraint::Stability::Stable)
+            .max_by_key(|(_, parsed)| *parsed)
+            .map(|(version, _)| version.to_string())
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone)]
 pub struct PackageVersion {
-    pub name: Option<String>,
     pub description: Option<String>,
     #[serde(default)]
     pub keywords: Option<Vec<String>>,
@@ -37,9 +123,212 @@ pub struct PackageVersion {
     pub license: Option<Vec<String>>,
     #[serde(default)]
     pub authors: Option<Vec<PackageAuthorField>>,
+    pub time: Option<String>,
+    pub source: Option<PackageSource>,
+    pub abandoned: Option<Abandoned>,
+    #[serde(default)]
+    pub support: Option<PackageSupport>,
+    /// The requirements of the version, e.g. "php" => ">=8.1".
+    #[serde(default, deserialize_with = "deserialize_requirements")]
+    pub require: HashMap<String, String>,
     pub packagist_url: Option<String>,
 }
 
+/// Reads the requirements of a version, which PHP encodes as an empty list
+/// when there are none.
+fn deserialize_requirements<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(value
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, constraint)| Some((name.clone(), constraint.as_str()?.to_string())))
+        .collect())
+}
+
+/// What composer.json allows updates to be: the minimum-stability,
+/// prefer-stable and the platform packages of config.platform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatePolicy {
+    pub minimum_stability: Stability,
+    pub prefer_stable: bool,
+    /// The platform package versions, e.g. "php" => "8.1.0".
+    pub platform: HashMap<String, String>,
+}
+
+impl Default for UpdatePolicy {
+    fn default() -> UpdatePolicy {
+        UpdatePolicy {
+            minimum_stability: Stability::Stable,
+            prefer_stable: false,
+            platform: HashMap::new(),
+        }
+    }
+}
+
+/// Where to get help with a package, from its "support" metadata.
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
+pub struct PackageSupport {
+    pub docs: Option<String>,
+    pub source: Option<String>,
+    pub issues: Option<String>,
+    pub chat: Option<String>,
+    pub forum: Option<String>,
+    pub wiki: Option<String>,
+    pub security: Option<String>,
+}
+
+impl PackageVersion {
+    /// Whether the php and extension requirements of the version allow the
+    /// platform, e.g. "php": ">=8.2" doesn't run on php 8.1.0.
+    pub fn runs_on(&self, platform: &HashMap<String, String>) -> bool {
+        self.require.iter().all(
+            |(name, constraint)| match platform.get(&name.to_lowercase()) {
+                Some(version) => constraint::matches(constraint, version) != Some(false),
+                None => true,
+            },
+        )
+    }
+
+    /// The support links, labelled and in order of usefulness, or the
+    /// packagist page when the package has none.
+    pub fn support_links(&self) -> Vec<(&'static str, String)> {
+        let mut links = vec![];
+        if let Some(support) = &self.support {
+            let fields = [
+                ("Docs", &support.docs),
+                ("Source", &support.source),
+                ("Issues", &support.issues),
+                ("Chat", &support.chat),
+                ("Forum", &support.forum),
+                ("Wiki", &support.wiki),
+                ("Security", &support.security),
+            ];
+            for (label, url) in fields {
+                // Skip emails and other non-web links.
+                if let Some(url) = url.as_ref().filter(|url| url.starts_with("http")) {
+                    links.push((label, url.to_string()));
+                }
+            }
+        }
+
+        if links.is_empty() {
+            if let Some(url) = &self.packagist_url {
+                links.push(("Packagist", url.to_string()));
+            }
+        }
+
+        links
+    }
+
+    /// The licenses as bold inline badges, e.g. "**License:** `MIT`".
+    pub fn license_badges(&self) -> Option<String> {
+        let licenses = self
+            .license
+            .as_ref()
+            .filter(|licenses| !licenses.is_empty())?;
+        let badges: Vec<String> = licenses
+            .iter()
+            .map(|license| format!("`{}`", license))
+            .collect();
+
+        Some(format!("**License:** {}", badges.join(" ")))
+    }
+
+    /// The names of the authors, e.g. "**Authors:** Jordi Boggiano".
+    pub fn author_names(&self) -> Option<String> {
+        let names: Vec<&str> = self
+            .authors
+            .iter()
+            .flatten()
+            .filter_map(|author| author.name.as_deref())
+            .collect();
+        if names.is_empty() {
+            return None;
+        }
+
+        Some(format!("**Authors:** {}", names.join(", ")))
+    }
+
+    /// The keywords as inline tags, e.g. "`log` `psr-3`".
+    pub fn keyword_tags(&self) -> Option<String> {
+        let keywords = self
+            .keywords
+            .as_ref()
+            .filter(|keywords| !keywords.is_empty())?;
+        let tags: Vec<String> = keywords
+            .iter()
+            .map(|keyword| format!("`{}`", keyword))
+            .collect();
+
+        Some(tags.join(" "))
+    }
+}
+
+/// The popularity of a package, from the package API of packagist.org.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PackageStats {
+    pub total_downloads: u64,
+    pub monthly_downloads: u64,
+    pub github_stars: Option<u64>,
+}
+
+impl PackageStats {
+    /// The statistics on one line, e.g. "**Downloads:** 12.3M total ·
+    /// 1.2M monthly · **Stars:** 20.9k".
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "**Downloads:** {} total · {} monthly",
+            compact_count(self.total_downloads),
+            compact_count(self.monthly_downloads)
+        );
+        if let Some(stars) = self.github_stars {
+            summary.push_str(&format!(" · **Stars:** {}", compact_count(stars)));
+        }
+
+        summary
+    }
+}
+
+/// Shortens a count like packagist does, e.g. "1.2M" for 1234567.
+fn compact_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f64 / 1_000.0),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
+
+/// A package providing a virtual package, from the providers API.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Provider {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub downloads: u64,
+}
+
+/// Packagist marks abandoned packages either with `true` or with the name of
+/// the suggested replacement package.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Abandoned {
+    Flag(bool),
+    Replacement(String),
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct PackageSource {
+    #[serde(rename = "type")]
+    pub source_type: Option<String>,
+    pub url: Option<String>,
+    pub reference: Option<String>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageAuthorField {
@@ -49,258 +338,952 @@ pub struct PackageAuthorField {
     pub role: Option<String>,
 }
 
-pub async fn get_all_packages() -> Vec<String> {
-    let client = Client::new();
-    let url = format!("{}/list.json", PACKAGIST_REPO_URL);
-    let resp = client.get(url).send().await.unwrap();
-    let text = resp.text().await;
-    let mut results = vec![];
+/// Turns a repository clone url into a url that can be opened in a browser.
+pub fn repository_web_url(url: &str) -> String {
+    let url = url.trim_end_matches(".git");
 
-    let contents: Value = serde_json::from_str(&text.unwrap()).unwrap_or(Value::Null);
-    let packages = contents
-        .as_object()
-        .unwrap()
-        .get("packageNames")
-        .unwrap()
-        .as_array()
-        .unwrap();
+    match url.strip_prefix("git@") {
+        Some(ssh_url) => format!("https://{}", ssh_url.replacen(':', "/", 1)),
+        None => url.to_string(),
+    }
+}
+
+/// The page of the package on packagist.org.
+pub fn package_url(name: &str) -> String {
+    format!("{}/packages/{}", packagist_url(), name)
+}
+
+/// The page of a specific version of the package on packagist.org.
+pub fn version_url(name: &str, version: &str) -> String {
+    format!("{}/packages/{}#{}", packagist_url(), name, version)
+}
+
+/// A mirror of repo.packagist.org, from the settings.
+static MIRROR: RwLock<Option<String>> = RwLock::new(None);
+
+/// Fetches the package metadata from the mirror instead of repo.packagist.org.
+pub fn set_mirror(mirror: Option<String>) {
+    *MIRROR.write().unwrap() = mirror.filter(|mirror| !mirror.trim().is_empty());
+}
 
-    for item in packages.iter() {
-        let name = item.as_str().unwrap();
-        results.push(name.to_string());
+/// The base URL of the package metadata, repo.packagist.org or its mirror.
+fn metadata_url() -> String {
+    match MIRROR.read().unwrap().as_deref() {
+        Some(mirror) => mirror.trim_end_matches('/').to_string(),
+        None => PACKAGIST_METADATA_URL.to_string(),
     }
+}
+
+/// The packagist.org site, or a proxy of it, from the settings.
+static PACKAGIST: RwLock<Option<String>> = RwLock::new(None);
+
+/// Uses the proxy of packagist.org for the package list, providers,
+/// statistics, advisories and package pages.
+pub fn set_packagist_url(url: Option<String>) {
+    *PACKAGIST.write().unwrap() = url.filter(|url| !url.trim().is_empty());
+}
+
+/// The base URL of packagist.org or its proxy.
+fn packagist_url() -> String {
+    match PACKAGIST.read().unwrap().as_deref() {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => PACKAGIST_URL.to_string(),
+    }
+}
+
+/// The names of all packages on packagist, for completion. The list is
+/// cached on disk, so it's only downloaded again once it's outdated.
+pub async fn get_all_packages() -> Result<Vec<String>, String> {
+    let policy = cache::policy();
+    let text = fetch_cached(
+        format!("{}/packages/list.json", packagist_url()),
+        PACKAGE_LIST_KEY,
+        cache::read_entry(PACKAGE_LIST_KEY, policy.package_list_ttl),
+        None,
+    )
+    .await?;
+
+    parse_package_names(&text)
+}
+
+fn parse_package_names(text: &str) -> Result<Vec<String>, String> {
+    let contents: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let names = contents
+        .get("packageNames")
+        .and_then(|names| names.as_array())
+        .ok_or_else(|| "the response doesn't list any packages".to_string())?;
 
-    return results;
+    Ok(names
+        .iter()
+        .filter_map(|name| name.as_str())
+        .map(|name| name.to_string())
+        .collect())
 }
 
-pub async fn get_packages_info(packages: Vec<ComposerDependency>) -> HashMap<String, Package> {
+/// Whether the packagist repository answers within a few seconds.
+pub async fn is_reachable() -> bool {
+    let url = format!("{}/packages.json", metadata_url());
+    match auth::authorize(http::client().get(&url), &url, None)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+    {
+        Ok(response) => response.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+/// Fetches the metadata of all packages, reporting how many have been
+/// checked so far to the progress. Packages that couldn't be fetched are
+/// returned separately, so one failure doesn't prevent checking the others.
+pub async fn get_packages_info(
+    packages: Vec<ComposerDependency>,
+    repositories: &Repositories,
+    progress: Option<&Progress>,
+) -> (HashMap<String, Package>, Vec<String>) {
     let mut result = HashMap::new();
+    let mut failed = vec![];
+
+    // A package in both require and require-dev is only fetched once.
+    let mut unique: Vec<ComposerDependency> = vec![];
+    for package in packages {
+        if !is_platform_package(&package.name)
+            && !unique
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&package.name))
+        {
+            unique.push(package);
+        }
+    }
+    let total = unique.len();
+
+    let mut fetches = stream::iter(unique)
+        .map(|package| async move {
+            let include_dev = requires_dev_branch(&package.version);
+            let package_data =
+                repository::try_get_package_info(repositories, package.clone().name, include_dev)
+                    .await;
+            (package.name, package_data)
+        })
+        .buffer_unordered(MAX_CONCURRENT_FETCHES);
+
+    let mut checked = 0;
+    while let Some((name, package_data)) = fetches.next().await {
+        checked += 1;
+        if let Some(progress) = progress {
+            progress
+                .report(
+                    format!("Checked {}/{} packages", checked, total),
+                    Some((checked * 100 / total) as u32),
+                )
+                .await;
+        }
 
-    let bodies = future::join_all(packages.into_iter().map(|package| async move {
-        let package_data = get_package_info(package.clone().name).await;
         match package_data {
-            Some(data) => {
-                return Some(data);
+            Ok(data) => {
+                result.insert(data.clone().name, data);
             }
-            None => {
-                log::info!("Can't get packagist data for {}", package.clone().name);
-                return None;
+            Err(error) => {
+                log::info!("Can't get packagist data for {}: {}", name, error);
+                failed.push(name);
             }
         }
-    }))
-    .await;
+    }
 
-    for item in bodies {
-        if item.is_some() {
-            let data = item.unwrap();
-            result.insert(data.clone().name, data.clone());
+    failed.sort();
+    (result, failed)
+}
+
+/// Fetches the security advisories affecting the installed versions, given
+/// as package name and version, from the packagist advisories API. The
+/// credentials are looked up from the directory of composer.json.
+pub async fn get_security_advisories(
+    installed: &HashMap<String, String>,
+    project_dir: Option<&Path>,
+) -> Result<Vec<Advisory>, String> {
+    if installed.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let query: Vec<(&str, &str)> = installed
+        .keys()
+        .map(|name| ("packages[]", name.as_str()))
+        .collect();
+    let url = format!("{}/api/security-advisories/", packagist_url());
+    let request = auth::authorize(http::client().get(&url), &url, project_dir).query(&query);
+    let response = http::send(request)
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("packagist responded with {}", response.status()));
+    }
+
+    let text = response.text().await.map_err(|error| error.to_string())?;
+    parse_security_advisories(&text, installed)
+}
+
+/// Reads the advisories of the packagist API whose affected versions, like
+/// ">=1.0,<1.4.2|>=2.0,<2.1.1", include the installed version.
+fn parse_security_advisories(
+    text: &str,
+    installed: &HashMap<String, String>,
+) -> Result<Vec<Advisory>, String> {
+    let contents: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let packages = match contents
+        .get("advisories")
+        .and_then(|advisories| advisories.as_object())
+    {
+        Some(packages) => packages,
+        // An empty result is a list.
+        None => return Ok(vec![]),
+    };
+
+    let mut advisories = vec![];
+    for (package, entries) in packages {
+        let version = match installed.get(package) {
+            Some(version) => version,
+            None => continue,
+        };
+
+        for entry in entries.as_array().into_iter().flatten() {
+            let field = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(|value| value.as_str())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+
+            let is_affected = field("affectedVersions")
+                .and_then(|affected| Constraint::parse(&affected))
+                .map(|affected| affected.matches(version))
+                .unwrap_or(false);
+            if !is_affected {
+                continue;
+            }
+
+            advisories.push(Advisory {
+                package: package.to_string(),
+                title: field("title").unwrap_or_else(|| "Security advisory".to_string()),
+                cve: field("cve"),
+                link: field("link"),
+            });
         }
     }
 
-    return result;
+    Ok(advisories)
 }
 
+/// The newest version the constraint allows that is newer than the
+/// installed one, or any version when nothing is installed. Unstable versions
+/// only count when the constraint, the installed version or minimum-stability
+/// asks for them, and versions that don't run on the platform are skipped.
+/// With prefer-stable, the most stable candidates win like in composer.
 pub fn check_for_package_update(
     package: &Package,
     constraint: String,
     installed: String,
+    policy: &UpdatePolicy,
 ) -> Option<String> {
-    let version_constraint = VersionReq::parse(&constraint[..]);
+    let constraint = Constraint::parse(&constraint)?;
+    let installed = constraint::Version::parse(&installed);
+    let stability = match installed {
+        Some(installed) => constraint.stability.min(installed.stability()),
+        None => constraint.stability,
+    }
+    .min(policy.minimum_stability);
 
-    match version_constraint {
-        Ok(req) => {
-            let mut matching_versions = vec![];
+    package
+        .versions
+        .iter()
+        .filter(|item| item.runs_on(&policy.platform))
+        .filter_map(|item| item.version.as_ref())
+        .filter_map(|version| Some((version, constraint::Version::parse(version)?)))
+        .filter(|(version, parsed)| parsed.stability() >= stability && constraint.matches(version))
+        .filter(|(_, parsed)| match installed {
+            Some(installed) => *parsed > installed,
+            None => true,
+        })
+        .max_by_key(|(_, parsed)| {
+            // Patch releases are as stable as stable ones.
+            let preference = match policy.prefer_stable {
+                true => parsed.stability().min(Stability::Stable),
+                false => Stability::Dev,
+            };
+            (preference, *parsed)
+        })
+        .map(|(version, _)| version.to_string())
+}
 
-            for item in package.versions.iter() {
-                let ver = item.clone().version.unwrap();
-                let parsed_version = &Version::parse(&ver);
+/// A branch with commits newer than the installed or pinned one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewCommits {
+    /// The branch version, e.g. "dev-main".
+    pub branch: String,
+    pub installed: String,
+    pub head: String,
+}
 
-                match parsed_version {
-                    Ok(parsed_version) => {
-                        if req.matches(parsed_version) {
-                            matching_versions.push(ver);
-                        }
-                    }
-                    Err(_error) => {}
-                }
-            }
+impl NewCommits {
+    pub fn message(&self) -> String {
+        format!(
+            "New commits available on {}: {} -> {}",
+            self.branch,
+            short_reference(&self.installed),
+            short_reference(&self.head)
+        )
+    }
+}
 
-            if matching_versions.len() <= 0 {
-                return None;
-            }
+/// Shortens a commit hash like git does.
+fn short_reference(reference: &str) -> &str {
+    reference.get(..7).unwrap_or(reference)
+}
 
-            if installed == "" {
-                return Some(matching_versions.first().unwrap().to_string());
-            }
+/// Compares the commit a branch constraint is installed from, or pinned to
+/// with "#commit", with the head of the branch in the metadata. The lock
+/// file's reference wins, since the pinned commit may be abbreviated.
+pub fn check_for_new_commits(
+    package: &Package,
+    branch: &Branch,
+    installed_reference: Option<&str>,
+) -> Option<NewCommits> {
+    let installed = installed_reference
+        .map(|reference| reference.to_string())
+        .or_else(|| branch.commit.clone())?;
+    let head = package
+        .versions
+        .iter()
+        .find(|item| {
+            item.version
+                .as_ref()
+                .is_some_and(|version| version.eq_ignore_ascii_case(&branch.name))
+        })?
+        .source
+        .as_ref()?
+        .reference
+        .clone()?;
 
-            let installed_normalized = installed.replace(".", "");
-            let installed_as_int = installed_normalized.parse::<i32>().unwrap();
-            let mut matching = vec![];
+    // Short hashes are prefixes of the full one.
+    if head.starts_with(&installed) || installed.starts_with(&head) {
+        return None;
+    }
 
-            for i in matching_versions.into_iter() {
-                let i_normalized = i.replace(".", "");
-                let i_as_int = i_normalized.parse::<i32>().unwrap();
+    Some(NewCommits {
+        branch: branch.name.clone(),
+        installed,
+        head,
+    })
+}
 
-                if i_as_int > installed_as_int {
-                    matching.push(i);
-                }
-            }
+/// Whether the constraint asks for a development branch, e.g. "dev-main" or
+/// "2.x-dev", whose versions packagist serves from a separate endpoint.
+pub fn requires_dev_branch(constraint: &str) -> bool {
+    constraint
+        .split(|c: char| c == '|' || c == ',' || c.is_whitespace())
+        .map(|part| part.split(" as ").next().unwrap_or_default())
+        .any(|part| part.starts_with("dev-") || part.ends_with("-dev") || part.ends_with("@dev"))
+}
 
-            if matching.len() <= 0 {
-                return None;
-            }
+/// Fetches the package metadata from packagist, including the development
+/// branches from the `~dev` endpoint when `include_dev` is set. The
+/// credentials of a mirror are looked up from the directory of composer.json.
+pub async fn try_get_package_info(
+    name: String,
+    include_dev: bool,
+    project_dir: Option<&Path>,
+) -> Result<Package, String> {
+    // Packagist only knows the lowercase names.
+    let lowercase_name = name.to_lowercase();
+    let url = format!("{}/p2/{}.json", metadata_url(), lowercase_name);
+    let mut package = Package::new(
+        name.clone(),
+        fetch_versions(url, &lowercase_name, false, project_dir).await?,
+    );
 
-            return Some(matching.first().unwrap().to_string());
+    if include_dev {
+        let dev_url = format!("{}/p2/{}~dev.json", metadata_url(), lowercase_name);
+        match fetch_versions(dev_url, &lowercase_name, true, project_dir).await {
+            Ok(dev_versions) => package.versions.extend(dev_versions),
+            Err(error) => log::info!("Can't get the dev versions of {}: {}", name, error),
         }
-        Err(_error) => None,
     }
+
+    Ok(package)
+}
+
+/// Whether the requirement is on the platform, like php or an extension,
+/// rather than on a package from packagist.
+pub fn is_platform_package(name: &str) -> bool {
+    !name.contains('/') || name.starts_with("ext-") || name.starts_with("lib-")
 }
 
-pub async fn get_package_info(name: String) -> Option<Package> {
-    let client = Client::new();
-    let url = format!("{}/{}.json", PACKAGIST_API_URL, name);
-    let resp = client.get(url).send().await.unwrap();
-    let text = resp.text().await;
+/// Whether the name follows the convention for virtual packages, like
+/// "psr/log-implementation", which only exist through what others provide.
+pub fn is_virtual_package(name: &str) -> bool {
+    name.ends_with("-implementation")
+}
 
-    let contents: Value = serde_json::from_str(&text.unwrap()).unwrap_or(Value::Null);
+/// The packages providing the virtual package, most downloaded first. They
+/// are cached like the package metadata, and read from the cache only when
+/// offline.
+pub async fn get_providers(
+    name: &str,
+    project_dir: Option<&Path>,
+) -> Result<Vec<Provider>, String> {
+    let name = name.to_lowercase();
+    let url = format!("{}/providers/{}.json", packagist_url(), name);
+    let key = cache::providers_key(&name);
+    let cached = cache::read_entry(&key, cache::policy().metadata_ttl);
+    let text = fetch_cached(url, &key, cached, project_dir).await?;
 
-    if contents.is_null() {
-        return None;
+    parse_providers(&text)
+}
+
+/// Fetches the download and star statistics of a package from packagist.org,
+/// which other repositories don't have.
+pub async fn get_package_stats(name: &str) -> Result<PackageStats, String> {
+    let name = name.to_lowercase();
+    let url = format!("{}/packages/{}.json", packagist_url(), name);
+    let key = cache::stats_key(&name);
+    let cached = cache::read_entry(&key, cache::policy().metadata_ttl);
+    let text = fetch_cached(url, &key, cached, None).await?;
+
+    parse_package_stats(&text)
+}
+
+fn parse_package_stats(text: &str) -> Result<PackageStats, String> {
+    let contents: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let package = contents
+        .get("package")
+        .ok_or_else(|| "the response doesn't describe a package".to_string())?;
+    let downloads = |period: &str| {
+        package
+            .get("downloads")
+            .and_then(|downloads| downloads.get(period))
+            .and_then(|count| count.as_u64())
+            .unwrap_or_default()
+    };
+
+    Ok(PackageStats {
+        total_downloads: downloads("total"),
+        monthly_downloads: downloads("monthly"),
+        github_stars: package.get("github_stars").and_then(|stars| stars.as_u64()),
+    })
+}
+
+fn parse_providers(text: &str) -> Result<Vec<Provider>, String> {
+    let contents: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let providers = contents
+        .get("providers")
+        .cloned()
+        .ok_or_else(|| "the response doesn't list any providers".to_string())?;
+
+    let mut providers: Vec<Provider> =
+        serde_json::from_value(providers).map_err(|error| error.to_string())?;
+    providers.sort_by_key(|provider| std::cmp::Reverse(provider.downloads));
+
+    Ok(providers)
+}
+
+/// Fetches the versions of a package, reading the metadata the composer CLI
+/// cached instead when it's recent or packagist says it didn't change.
+async fn fetch_versions(
+    url: String,
+    name: &str,
+    dev: bool,
+    project_dir: Option<&Path>,
+) -> Result<Vec<PackageVersion>, String> {
+    let key = cache::metadata_key(name, dev);
+    let cached = cache::read_entry(&key, cache::policy().metadata_ttl)
+        .or_else(|| cache::read_metadata(name, dev, project_dir));
+    let text = fetch_cached(url, &key, cached, project_dir).await?;
+
+    parse_versions(&text, name)
+}
+
+/// Fetches the url through the cache. Fresh responses are used as they are,
+/// outdated ones are revalidated, and used as long as packagist can't be
+/// reached. Concurrent fetches of the same key share one request.
+async fn fetch_cached(
+    url: String,
+    key: &str,
+    cached: Option<cache::CachedMetadata>,
+    project_dir: Option<&Path>,
+) -> Result<String, String> {
+    let fetch = {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        match in_flight
+            .iter()
+            .find(|(in_flight_key, _)| in_flight_key == key)
+        {
+            Some((_, fetch)) => fetch.clone(),
+            None => {
+                let project_dir = project_dir.map(|dir| dir.to_path_buf());
+                let fetch = fetch_uncached(url, key.to_string(), cached, project_dir)
+                    .boxed()
+                    .shared();
+                in_flight.push((key.to_string(), fetch.clone()));
+                fetch
+            }
+        }
+    };
+
+    let result = fetch.await;
+    IN_FLIGHT
+        .lock()
+        .unwrap()
+        .retain(|(in_flight_key, _)| in_flight_key != key);
+
+    result
+}
+
+async fn fetch_uncached(
+    url: String,
+    key: String,
+    cached: Option<cache::CachedMetadata>,
+    project_dir: Option<PathBuf>,
+) -> Result<String, String> {
+    let offline = cache::policy().offline;
+    if let Some(cached) = cached.as_ref().filter(|cached| cached.is_fresh || offline) {
+        return Ok(cached.text.clone());
+    }
+    if offline {
+        return Err(format!("{} isn't cached for the offline mode", url));
     }
 
-    match contents.as_object() {
-        Some(contents_data) => {
-            let contents_packages_object = contents_data.get("packages");
-            match contents_packages_object {
-                Some(contents_packages) => {
-                    let package_data = contents_packages.get(name.clone());
-                    match package_data {
-                        Some(versions) => {
-                            let mut package = Package::new(name.clone(), vec![]);
-                            let all_versions = versions.as_array().unwrap().to_owned();
-                            for item in all_versions.into_iter() {
-                                let mut package_version: PackageVersion =
-                                    serde_json::from_value(item.clone()).unwrap();
-
-                                package_version.packagist_url = Some(format!(
-                                    "{}/{}",
-                                    PACKAGIST_REPO_URL,
-                                    name.replace("\"", "")
-                                ));
-
-                                package.versions.push(package_version);
-                            }
-
-                            return Some(package);
-                        }
-                        None => {}
-                    }
+    // Mirrors in corporate networks often need credentials.
+    let mut request = auth::authorize(http::client().get(&url), &url, project_dir.as_deref());
+    if let Some(etag) = cached.as_ref().and_then(|cached| cached.etag.as_ref()) {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = cached
+        .as_ref()
+        .and_then(|cached| cached.last_modified.as_ref())
+    {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let resp = match http::send(request).await {
+        Ok(resp) => resp,
+        Err(error) => {
+            return match cached {
+                Some(cached) => {
+                    log::info!("Using the cached {}, it can't be fetched: {}", url, error);
+                    Ok(cached.text)
                 }
-                None => {}
+                None => Err(error.to_string()),
             }
         }
-        None => {}
+    };
+
+    match (resp.status(), cached) {
+        (StatusCode::NOT_MODIFIED, Some(cached)) => {
+            // Mark the response as fresh again.
+            cache::write_entry(
+                &key,
+                &cached.text,
+                cached.etag.as_deref(),
+                cached.last_modified.as_deref(),
+            );
+            return Ok(cached.text);
+        }
+        (status, Some(cached)) if status.is_server_error() => {
+            log::info!(
+                "Using the cached {}, packagist responded with {}",
+                url,
+                status
+            );
+            return Ok(cached.text);
+        }
+        (status, _) if !status.is_success() => {
+            return Err(format!("packagist responded with {}", status));
+        }
+        _ => {}
     }
 
-    return None;
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let text = resp.text().await.map_err(|error| error.to_string())?;
+    cache::write_entry(&key, &text, etag.as_deref(), last_modified.as_deref());
+
+    Ok(text)
+}
+
+pub fn parse_versions(text: &str, name: &str) -> Result<Vec<PackageVersion>, String> {
+    let contents: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+
+    let versions = contents
+        .get("packages")
+        .and_then(|packages| packages.get(name))
+        .and_then(|versions| versions.as_array())
+        .ok_or_else(|| "the response doesn't list any versions".to_string())?;
+
+    let mut package_versions = vec![];
+    let mut previous = serde_json::Map::new();
+    for item in versions.iter() {
+        // The metadata is minified, every version only lists the fields that
+        // changed since the previous one and "__unset" for removed fields.
+        let mut expanded = previous.clone();
+        if let Some(fields) = item.as_object() {
+            for (key, value) in fields {
+                match value.as_str() {
+                    Some("__unset") => expanded.remove(key),
+                    _ => expanded.insert(key.to_string(), value.clone()),
+                };
+            }
+        }
+        previous = expanded.clone();
+
+        // Skip malformed versions instead of failing the whole package.
+        let mut package_version: PackageVersion =
+            match serde_json::from_value(Value::Object(expanded)) {
+                Ok(package_version) => package_version,
+                Err(error) => {
+                    log::warn!("Skipping a malformed version of {}: {}", name, error);
+                    continue;
+                }
+            };
+
+        package_version.packagist_url = Some(package_url(&name.replace('"', "")));
+
+        package_versions.push(package_version);
+    }
+
+    Ok(package_versions)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::packagist::{check_for_package_update, Package, PackageVersion};
+    use std::collections::HashMap;
+
+    use crate::audit::Advisory;
+    use crate::constraint::{parse_branch, Stability};
+    use crate::packagist::{
+        check_for_new_commits, check_for_package_update, is_platform_package, parse_package_names,
+        parse_package_stats, parse_providers, parse_security_advisories, parse_versions,
+        repository_web_url, requires_dev_branch, version_url, Abandoned, Package,
+        PackageAuthorField, PackageSource, PackageStats, PackageSupport, PackageVersion,
+        UpdatePolicy,
+    };
 
     fn get_package_mock() -> Package {
-        let package_data = Package {
+        let version = |version: &str, normalized: &str| PackageVersion {
+            version: Some(version.to_string()),
+            version_normalized: Some(normalized.to_string()),
+            ..PackageVersion::default()
+        };
+
+        Package {
             name: "Test".to_string(),
             versions: vec![
+                version("2.2.1", "221"),
+                version("2.1.1", "211"),
+                version("2.1.0", "210"),
+                version("2.0.0", "200"),
+                version("1.9.0", "190"),
+                version("1.8.1", "181"),
+                version("1.8.0", "180"),
+            ],
+        }
+    }
+
+    #[test]
+    fn it_can_get_the_replacement_of_an_abandoned_package() {
+        let mut package = get_package_mock();
+        assert_eq!(None, package.replacement());
+
+        package.versions[0].abandoned = Some(Abandoned::Flag(true));
+        assert_eq!(None, package.replacement());
+
+        package.versions[0].abandoned = Some(Abandoned::Replacement("new/test".to_string()));
+        assert_eq!(Some("new/test".to_string()), package.replacement());
+    }
+
+    #[test]
+    fn it_can_explain_that_a_package_is_abandoned() {
+        let mut package = get_package_mock();
+        assert!(!package.is_abandoned());
+        assert_eq!(None, package.abandoned_notice());
+
+        package.versions[0].abandoned = Some(Abandoned::Flag(false));
+        assert!(!package.is_abandoned());
+
+        package.versions[0].abandoned = Some(Abandoned::Flag(true));
+        assert!(package.is_abandoned());
+        assert_eq!(
+            Some(format!(
+                "{} is abandoned and no longer maintained.",
+                package.name
+            )),
+            package.abandoned_notice()
+        );
+
+        package.versions[0].abandoned = Some(Abandoned::Replacement("new/test".to_string()));
+        assert_eq!(
+            Some(format!(
+                "{} is abandoned, use new/test instead.",
+                package.name
+            )),
+            package.abandoned_notice()
+        );
+    }
+
+    #[test]
+    fn it_can_parse_the_package_names() {
+        assert_eq!(
+            Ok(vec!["psr/log".to_string(), "monolog/monolog".to_string()]),
+            parse_package_names(r#"{"packageNames": ["psr/log", "monolog/monolog"]}"#)
+        );
+        assert!(parse_package_names("<html>Maintenance</html>").is_err());
+        assert!(parse_package_names("{}").is_err());
+    }
+
+    #[test]
+    fn it_can_get_the_latest_stable_version() {
+        assert_eq!(
+            Some("2.2.1".to_string()),
+            get_package_mock().latest_stable_version()
+        );
+    }
+
+    #[test]
+    fn it_can_detect_dev_branch_requirements() {
+        assert!(requires_dev_branch("dev-main"));
+        assert!(requires_dev_branch("2.x-dev"));
+        assert!(requires_dev_branch("^1.0 || dev-main"));
+        assert!(requires_dev_branch("dev-main as 1.0.x-dev"));
+        assert!(!requires_dev_branch("^1.0"));
+        assert!(!requires_dev_branch("@stable"));
+    }
+
+    #[test]
+    fn it_skips_malformed_versions() {
+        let text = r#"{"packages": {"acme/lib": [
+            {"version": "1.0.0"},
+            {"version": ["not", "a", "string"]}
+        ]}}"#;
+
+        let versions = parse_versions(text, "acme/lib").unwrap();
+
+        assert_eq!(1, versions.len());
+        assert!(parse_versions("<html>", "acme/lib").is_err());
+        assert!(parse_versions(text, "acme/other").is_err());
+    }
+
+    #[test]
+    fn it_can_read_the_package_stats() {
+        let text = r#"{"package": {
+            "name": "monolog/monolog",
+            "downloads": {"total": 812345678, "monthly": 12345678, "daily": 412345},
+            "github_stars": 20912
+        }}"#;
+        let stats = parse_package_stats(text).unwrap();
+
+        assert_eq!(
+            PackageStats {
+                total_downloads: 812345678,
+                monthly_downloads: 12345678,
+                github_stars: Some(20912),
+            },
+            stats
+        );
+        assert_eq!(
+            "**Downloads:** 812.3M total · 12.3M monthly · **Stars:** 20.9k",
+            stats.summary()
+        );
+        assert!(parse_package_stats("{}").is_err());
+    }
+
+    #[test]
+    fn it_can_describe_the_latest_release_and_authors() {
+        let package = Package::new(
+            "acme/lib".to_string(),
+            vec![
                 PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("2.2.1".to_string()),
-                    version_normalized: Some("221".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
-                },
-                PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("2.1.1".to_string()),
-                    version_normalized: Some("211".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
-                },
-                PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("2.1.0".to_string()),
-                    version_normalized: Some("210".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
-                },
-                PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("2.0.0".to_string()),
-                    version_normalized: Some("200".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
-                },
-                PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("1.9.0".to_string()),
-                    version_normalized: Some("190".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
-                },
-                PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("1.8.1".to_string()),
-                    version_normalized: Some("181".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
+                    version: Some("2.0.0-beta1".to_string()),
+                    ..PackageVersion::default()
                 },
                 PackageVersion {
-                    name: Some("Test".to_string()),
-                    description: None,
-                    keywords: None,
-                    homepage: None,
-                    version: Some("1.8.0".to_string()),
-                    version_normalized: Some("180".to_string()),
-                    license: None,
-                    authors: None,
-                    packagist_url: None,
+                    version: Some("1.4.0".to_string()),
+                    time: Some("2023-10-27T09:43:54+00:00".to_string()),
+                    authors: Some(vec![PackageAuthorField {
+                        name: Some("Jane Doe".to_string()),
+                        ..PackageAuthorField::default()
+                    }]),
+                    ..PackageVersion::default()
                 },
             ],
-        };
+        );
+
+        assert_eq!(
+            Some("**Latest:** `1.4.0`, released 2023-10-27".to_string()),
+            package.latest_release()
+        );
+        assert_eq!(
+            Some("**Authors:** Jane Doe".to_string()),
+            package.versions[1].author_names()
+        );
+        assert_eq!(None, package.versions[0].author_names());
+    }
+
+    #[test]
+    fn it_can_expand_minified_versions() {
+        let text = r#"{"packages": {"acme/lib": [
+            {"version": "2.0.0", "description": "A lib", "keywords": ["log"], "license": ["MIT"]},
+            {"version": "1.0.0", "keywords": "__unset"}
+        ]}}"#;
+
+        let versions = parse_versions(text, "acme/lib").unwrap();
+
+        assert_eq!(Some("A lib".to_string()), versions[1].description);
+        assert_eq!(Some(vec!["MIT".to_string()]), versions[1].license);
+        assert_eq!(None, versions[1].keywords);
+    }
+
+    #[test]
+    fn it_can_get_the_support_links() {
+        let mut package_version = get_package_mock().versions[0].clone();
+        package_version.packagist_url = Some("https://packagist.org/packages/acme/lib".to_string());
+
+        assert_eq!(
+            vec![(
+                "Packagist",
+                "https://packagist.org/packages/acme/lib".to_string()
+            )],
+            package_version.support_links()
+        );
 
-        package_data
+        package_version.support = Some(PackageSupport {
+            source: Some("https://github.com/acme/lib/tree/1.0.0".to_string()),
+            issues: Some("https://github.com/acme/lib/issues".to_string()),
+            chat: Some("irc://irc.libera.chat/acme".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            vec![
+                (
+                    "Source",
+                    "https://github.com/acme/lib/tree/1.0.0".to_string()
+                ),
+                ("Issues", "https://github.com/acme/lib/issues".to_string())
+            ],
+            package_version.support_links()
+        );
+    }
+
+    #[test]
+    fn it_can_render_license_and_keyword_badges() {
+        let mut package_version = get_package_mock().versions[0].clone();
+        package_version.license = Some(vec!["MIT".to_string(), "GPL-2.0".to_string()]);
+        package_version.keywords = Some(vec!["log".to_string(), "psr-3".to_string()]);
+
+        assert_eq!(
+            Some("**License:** `MIT` `GPL-2.0`".to_string()),
+            package_version.license_badges()
+        );
+        assert_eq!(
+            Some("`log` `psr-3`".to_string()),
+            package_version.keyword_tags()
+        );
+
+        package_version.keywords = Some(vec![]);
+        assert_eq!(None, package_version.keyword_tags());
+    }
+
+    #[test]
+    fn it_can_parse_the_advisories_affecting_installed_versions() {
+        let text = r#"{
+            "advisories": {
+                "guzzlehttp/psr7": [
+                    {
+                        "packageName": "guzzlehttp/psr7",
+                        "title": "Improper header validation",
+                        "link": "https://github.com/advisories/GHSA-wxmh-65f7-jcvw",
+                        "cve": "CVE-2023-29197",
+                        "affectedVersions": ">=1.0.0,<1.9.1|>=2.0.0,<2.4.5"
+                    },
+                    {
+                        "packageName": "guzzlehttp/psr7",
+                        "title": "CRLF injection",
+                        "link": "",
+                        "cve": null,
+                        "affectedVersions": "<1.8.4"
+                    }
+                ]
+            }
+        }"#;
+        let installed = HashMap::from([("guzzlehttp/psr7".to_string(), "2.4.1".to_string())]);
+        let advisories = parse_security_advisories(text, &installed).unwrap();
+
+        assert_eq!(
+            vec![Advisory {
+                package: "guzzlehttp/psr7".to_string(),
+                title: "Improper header validation".to_string(),
+                cve: Some("CVE-2023-29197".to_string()),
+                link: Some("https://github.com/advisories/GHSA-wxmh-65f7-jcvw".to_string()),
+            }],
+            advisories
+        );
+    }
+
+    #[test]
+    fn it_ignores_an_empty_advisories_response() {
+        assert_eq!(
+            Ok(vec![]),
+            parse_security_advisories(r#"{"advisories": []}"#, &HashMap::new())
+        );
+        assert!(parse_security_advisories("not json", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_providers_by_downloads() {
+        let text = r#"{"providers": [
+            {"name": "acme/logger", "description": "A logger", "downloads": 10},
+            {"name": "monolog/monolog", "description": "Sends your logs", "downloads": 900}
+        ]}"#;
+        let providers = parse_providers(text).unwrap();
+
+        assert_eq!("monolog/monolog", providers[0].name);
+        assert_eq!(Some("A logger".to_string()), providers[1].description);
+        assert!(parse_providers("{}").is_err());
+    }
+
+    #[test]
+    fn it_can_detect_platform_packages() {
+        assert!(is_platform_package("php"));
+        assert!(is_platform_package("ext-json"));
+        assert!(!is_platform_package("psr/log"));
+    }
+
+    #[test]
+    fn it_can_get_a_repository_web_url() {
+        assert_eq!(
+            "https://github.com/Seldaek/monolog",
+            repository_web_url("https://github.com/Seldaek/monolog.git")
+        );
+        assert_eq!(
+            "https://github.com/Seldaek/monolog",
+            repository_web_url("git@github.com:Seldaek/monolog.git")
+        );
     }
 
     #[test]
     fn it_can_get_a_correct_caret_version() {
         assert_eq!(
             Some("1.9.0".to_string()),
-            check_for_package_update(&get_package_mock(), "^1.0".to_string(), "".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "^1.0".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
@@ -308,7 +1291,12 @@ mod tests {
     fn it_can_get_a_correct_higher_version() {
         assert_eq!(
             Some("2.2.1".to_string()),
-            check_for_package_update(&get_package_mock(), ">2.0".to_string(), "".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                ">2.0".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
@@ -316,7 +1304,12 @@ mod tests {
     fn it_can_get_a_correct_higher_or_equal_version() {
         assert_eq!(
             Some("2.2.1".to_string()),
-            check_for_package_update(&get_package_mock(), ">=2.0".to_string(), "".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                ">=2.0".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
@@ -324,15 +1317,25 @@ mod tests {
     fn it_can_get_a_correct_lower_or_equal_version() {
         assert_eq!(
             Some("2.0.0".to_string()),
-            check_for_package_update(&get_package_mock(), "<=2.0".to_string(), "".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "<=2.0".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
     #[test]
     fn it_can_get_a_correct_lower_version() {
         assert_eq!(
-            Some("2.1.1".to_string()),
-            check_for_package_update(&get_package_mock(), "<=2.1".to_string(), "".to_string())
+            Some("2.1.0".to_string()),
+            check_for_package_update(
+                &get_package_mock(),
+                "<=2.1".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
@@ -340,15 +1343,34 @@ mod tests {
     fn it_can_get_a_correct_latest_version() {
         assert_eq!(
             Some("2.2.1".to_string()),
-            check_for_package_update(&get_package_mock(), "*".to_string(), "".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "*".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
     #[test]
     fn it_can_get_a_correct_tilde_version() {
+        assert_eq!(
+            Some("1.9.0".to_string()),
+            check_for_package_update(
+                &get_package_mock(),
+                "~1.8".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
+        );
         assert_eq!(
             Some("1.8.1".to_string()),
-            check_for_package_update(&get_package_mock(), "~1.8".to_string(), "".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "~1.8.0".to_string(),
+                "".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
@@ -356,7 +1378,12 @@ mod tests {
     fn it_can_get_a_correct_latest_version_with_installed_lower_version() {
         assert_eq!(
             Some("2.2.1".to_string()),
-            check_for_package_update(&get_package_mock(), "^2.0".to_string(), "2.1.0".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "^2.0".to_string(),
+                "2.1.0".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
@@ -364,28 +1391,132 @@ mod tests {
     fn it_wont_get_anything_if_latest_is_installed_and_major_is_lower() {
         assert_eq!(
             None,
-            check_for_package_update(&get_package_mock(), "^1.0".to_string(), "2.2.0".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "^1.0".to_string(),
+                "2.2.0".to_string(),
+                &UpdatePolicy::default()
+            )
         );
     }
 
-    // @todo Not yet working.
-    // #[test]
-    // fn it_can_get_a_correct_version_if_and_constraint_is_used() {
-    //     assert_eq!(
-    //         Some("2.2.0"),
-    //         check_for_package_update(
-    //             &get_package_mock(),
-    //             "^2.1.0 || ^2.2.0".to_string(),
-    //             "2.1.0".to_string()
-    //         )
-    //     );
-    // }
+    #[test]
+    fn it_can_get_a_correct_version_if_or_constraint_is_used() {
+        assert_eq!(
+            Some("2.2.1".to_string()),
+            check_for_package_update(
+                &get_package_mock(),
+                "^2.1.0 || ^2.2.0".to_string(),
+                "2.1.0".to_string(),
+                &UpdatePolicy::default()
+            )
+        );
+    }
 
     #[test]
     fn it_wont_get_anything_if_latest_is_installed() {
         assert_eq!(
             None,
-            check_for_package_update(&get_package_mock(), "^2.0".to_string(), "2.2.1".to_string())
+            check_for_package_update(
+                &get_package_mock(),
+                "^2.0".to_string(),
+                "2.2.1".to_string(),
+                &UpdatePolicy::default()
+            )
+        );
+    }
+
+    #[test]
+    fn it_can_respect_the_update_policy() {
+        let version = |version: &str, php: &str| PackageVersion {
+            version: Some(version.to_string()),
+            require: HashMap::from([("php".to_string(), php.to_string())]),
+            ..PackageVersion::default()
+        };
+        let package = Package::new(
+            "acme/lib".to_string(),
+            vec![
+                version("3.1.0-beta1", ">=8.1"),
+                version("3.0.0", ">=8.2"),
+                version("2.5.0", ">=8.1"),
+            ],
+        );
+        let check = |policy: &UpdatePolicy| {
+            check_for_package_update(&package, "*".to_string(), "".to_string(), policy)
+        };
+
+        assert_eq!(Some("3.0.0".to_string()), check(&UpdatePolicy::default()));
+
+        let mut policy = UpdatePolicy {
+            minimum_stability: Stability::Beta,
+            ..UpdatePolicy::default()
+        };
+        assert_eq!(Some("3.1.0-beta1".to_string()), check(&policy));
+
+        policy.prefer_stable = true;
+        assert_eq!(Some("3.0.0".to_string()), check(&policy));
+
+        policy.platform = HashMap::from([("php".to_string(), "8.1.0".to_string())]);
+        assert_eq!(Some("2.5.0".to_string()), check(&policy));
+    }
+
+    #[test]
+    fn it_can_read_the_requirements_of_a_version() {
+        let text = r#"{"packages": {"acme/lib": [
+            {"version": "2.0.0", "require": {"php": ">=8.1", "ext-json": "*"}},
+            {"version": "1.0.0", "require": []}
+        ]}}"#;
+
+        let versions = parse_versions(text, "acme/lib").unwrap();
+        assert_eq!(Some(&">=8.1".to_string()), versions[0].require.get("php"));
+        assert!(versions[1].require.is_empty());
+    }
+
+    #[test]
+    fn it_can_link_to_a_package_version() {
+        assert_eq!(
+            "https://packagist.org/packages/psr/log#3.0.0",
+            version_url("psr/log", "3.0.0")
+        );
+    }
+
+    #[test]
+    fn it_can_detect_new_commits_on_a_branch() {
+        let package = Package::new(
+            "acme/lib".to_string(),
+            vec![
+                PackageVersion {
+                    version: Some("dev-main".to_string()),
+                    source: Some(PackageSource {
+                        reference: Some("def4567890".to_string()),
+                        ..PackageSource::default()
+                    }),
+                    ..PackageVersion::default()
+                },
+                PackageVersion {
+                    version: Some("1.0.0".to_string()),
+                    ..PackageVersion::default()
+                },
+            ],
         );
+
+        let check = |constraint: &str, installed: Option<&str>| {
+            check_for_new_commits(&package, &parse_branch(constraint)?, installed)
+        };
+
+        let new_commits = check("dev-main", Some("abc1234567")).unwrap();
+        assert_eq!("dev-main", new_commits.branch);
+        assert_eq!(
+            "New commits available on dev-main: abc1234 -> def4567",
+            new_commits.message()
+        );
+        assert!(check("dev-main as 1.x-dev", Some("abc1234")).is_some());
+        assert!(check("dev-main#abc123", None).is_some());
+
+        assert_eq!(None, check("dev-main#def456", None));
+        assert_eq!(None, check("dev-main", Some("def4567890")));
+        assert_eq!(None, check("dev-main", None));
+        assert_eq!(None, check("dev-other", Some("abc")));
+        assert_eq!(None, check("^1.0", Some("abc")));
     }
 }
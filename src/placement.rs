@@ -0,0 +1,190 @@
+use crate::edit::{self, Edit, Style};
+use crate::json::{Node, Span};
+
+pub const REQUIRE: &str = "require";
+pub const REQUIRE_DEV: &str = "require-dev";
+
+/// Well-known packages that are only used during development.
+pub const DEV_PACKAGES: &[&str] = &[
+    "barryvdh/laravel-debugbar",
+    "behat/behat",
+    "codeception/codeception",
+    "fakerphp/faker",
+    "friendsofphp/php-cs-fixer",
+    "infection/infection",
+    "laravel/pint",
+    "mockery/mockery",
+    "nunomaduro/collision",
+    "pestphp/pest",
+    "phpmd/phpmd",
+    "phpspec/phpspec",
+    "phpspec/prophecy",
+    "phpstan/phpstan",
+    "phpunit/phpunit",
+    "rector/rector",
+    "roave/security-advisories",
+    "squizlabs/php_codesniffer",
+    "symfony/phpunit-bridge",
+    "vimeo/psalm",
+];
+
+/// Well-known packages an application can't run without.
+pub const PRODUCTION_PACKAGES: &[&str] = &[
+    "cakephp/cakephp",
+    "doctrine/orm",
+    "drupal/core",
+    "drupal/core-recommended",
+    "laminas/laminas-mvc",
+    "laravel/framework",
+    "laravel/lumen-framework",
+    "slim/slim",
+    "symfony/framework-bundle",
+    "symfony/runtime",
+    "yiisoft/yii2",
+];
+
+/// A dependency in the wrong block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Misplacement {
+    /// The span of the package name.
+    pub span: Span,
+    pub package: String,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+impl Misplacement {
+    pub fn message(&self) -> String {
+        match self.to {
+            REQUIRE_DEV => format!(
+                "{} is a development tool, it usually belongs in \"require-dev\".",
+                self.package
+            ),
+            _ => format!(
+                "{} is needed in production, it usually belongs in \"require\".",
+                self.package
+            ),
+        }
+    }
+}
+
+/// Finds dev tools in "require" and production packages that are only in
+/// "require-dev".
+pub fn find_misplaced(root: &Node) -> Vec<Misplacement> {
+    let members = |block: &str| {
+        root.get(block)
+            .and_then(|block| block.value.as_object())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let require = members(REQUIRE);
+    let require_dev = members(REQUIRE_DEV);
+
+    let mut misplaced = vec![];
+    for member in require.iter() {
+        if DEV_PACKAGES.contains(&member.key.as_str()) {
+            misplaced.push(Misplacement {
+                span: member.key_span,
+                package: member.key.clone(),
+                from: REQUIRE,
+                to: REQUIRE_DEV,
+            });
+        }
+    }
+
+    for member in require_dev.iter() {
+        let in_require = require.iter().any(|required| required.key == member.key);
+        if PRODUCTION_PACKAGES.contains(&member.key.as_str()) && !in_require {
+            misplaced.push(Misplacement {
+                span: member.key_span,
+                package: member.key.clone(),
+                from: REQUIRE_DEV,
+                to: REQUIRE,
+            });
+        }
+    }
+
+    misplaced
+}
+
+/// The edits moving the package to the other block, creating the block when
+/// it doesn't exist yet.
+pub fn move_edits(text: &str, root: &Node, misplacement: &Misplacement) -> Option<Vec<Edit>> {
+    let from = root.get(misplacement.from)?;
+    let member = from
+        .value
+        .as_object()?
+        .iter()
+        .find(|member| member.key == misplacement.package)?;
+    let value = &text[member.value.span.start..member.value.span.end];
+
+    let style = Style::detect(text);
+    let insertion = match root.get(misplacement.to) {
+        Some(to) => edit::insert_member(text, &style, to, &misplacement.package, value)?,
+        None => edit::insert_block_after(
+            text,
+            &style,
+            from,
+            misplacement.to,
+            &misplacement.package,
+            value,
+        ),
+    };
+
+    Some(vec![
+        edit::remove_member(from, &misplacement.package)?,
+        insertion,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edit;
+    use crate::json;
+    use crate::placement::{find_misplaced, move_edits, REQUIRE, REQUIRE_DEV};
+
+    #[test]
+    fn it_can_find_misplaced_dependencies() {
+        let text = r#"{
+            "require": { "phpunit/phpunit": "^10.0", "symfony/runtime": "^6.0" },
+            "require-dev": { "laravel/framework": "^10.0", "symfony/runtime": "^6.0" }
+        }"#;
+        let misplaced = find_misplaced(&json::parse(text).unwrap());
+
+        assert_eq!(2, misplaced.len());
+        assert_eq!("phpunit/phpunit", misplaced[0].package);
+        assert_eq!(REQUIRE_DEV, misplaced[0].to);
+        assert_eq!("laravel/framework", misplaced[1].package);
+        assert_eq!(REQUIRE, misplaced[1].to);
+        assert_eq!(
+            "\"phpunit/phpunit\"",
+            &text[misplaced[0].span.start..misplaced[0].span.end]
+        );
+    }
+
+    #[test]
+    fn it_can_move_a_dependency_to_a_new_block() {
+        let text = "{\n    \"require\": {\n        \"psr/log\": \"^3.0\",\n        \"phpunit/phpunit\": \"^10.0\"\n    }\n}";
+        let root = json::parse(text).unwrap();
+        let misplaced = find_misplaced(&root);
+        let edits = move_edits(text, &root, &misplaced[0]).unwrap();
+
+        assert_eq!(
+            "{\n    \"require\": {\n        \"psr/log\": \"^3.0\"\n    },\n    \"require-dev\": {\n        \"phpunit/phpunit\": \"^10.0\"\n    }\n}",
+            edit::apply(text, edits)
+        );
+    }
+
+    #[test]
+    fn it_can_move_a_dependency_to_an_existing_block() {
+        let text = "{\n    \"require\": {\n        \"psr/log\": \"^3.0\"\n    },\n    \"require-dev\": {\n        \"laravel/framework\": \"^10.0\"\n    }\n}";
+        let root = json::parse(text).unwrap();
+        let misplaced = find_misplaced(&root);
+        let edits = move_edits(text, &root, &misplaced[0]).unwrap();
+
+        assert_eq!(
+            "{\n    \"require\": {\n        \"laravel/framework\": \"^10.0\",\n        \"psr/log\": \"^3.0\"\n    },\n    \"require-dev\": {}\n}",
+            edit::apply(text, edits)
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+use crate::constraint;
+use crate::edit::{self, Edit, Style};
+use crate::json::Node;
+
+/// The version of the local php interpreter, e.g. "8.1.2".
+pub async fn local_php_version() -> Option<String> {
+    let output = Command::new("php")
+        .arg("-r")
+        .arg("echo PHP_VERSION;")
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "" => None,
+        version => Some(version.to_string()),
+    }
+}
+
+/// The local interpreter, detected once as it rarely changes while editing.
+static LOCAL_PLATFORM: OnceCell<LocalPlatform> = OnceCell::const_new();
+
+/// The version and the extensions of the local php interpreter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalPlatform {
+    pub php: Option<String>,
+    /// The loaded extensions, named like composer does, e.g. "intl" or
+    /// "zend-opcache". None when php wasn't found.
+    pub extensions: Option<Vec<String>>,
+}
+
+impl LocalPlatform {
+    pub fn has_extension(&self, extension: &str) -> Option<bool> {
+        let extension = extension.to_lowercase();
+
+        Some(self.extensions.as_ref()?.contains(&extension))
+    }
+}
+
+/// The local interpreter, from `php -v` and `php -m`.
+pub async fn local_platform() -> LocalPlatform {
+    LOCAL_PLATFORM
+        .get_or_init(|| async {
+            LocalPlatform {
+                php: local_php_version().await,
+                extensions: local_extensions().await,
+            }
+        })
+        .await
+        .clone()
+}
+
+async fn local_extensions() -> Option<Vec<String>> {
+    let output = Command::new("php").arg("-m").output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse_extensions(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The extensions `php -m` lists, without its "[PHP Modules]" and "[Zend
+/// Modules]" headings.
+pub fn parse_extensions(output: &str) -> Vec<String> {
+    let mut extensions: Vec<String> = output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('['))
+        .map(|line| line.to_lowercase().replace(' ', "-"))
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+
+    extensions
+}
+
+/// A platform requirement the local interpreter or the platform overrides
+/// don't satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformProblem {
+    pub package: String,
+    pub message: String,
+}
+
+/// Checks the php and extension requirements, given as name and constraint,
+/// against the platform overrides, or else the local interpreter. Other
+/// platform packages, like libraries, aren't checked.
+pub fn platform_problems(
+    requirements: &[(&str, &str)],
+    local: &LocalPlatform,
+    overrides: &HashMap<String, String>,
+) -> Vec<PlatformProblem> {
+    let mut problems = vec![];
+    for (name, constraint) in requirements {
+        let package = name.to_lowercase();
+        let problem = |message: String| PlatformProblem {
+            package: name.to_string(),
+            message,
+        };
+
+        if let Some(version) = overrides.get(&package) {
+            if constraint::matches(constraint, version) == Some(false) {
+                problems.push(problem(format!(
+                    "config.platform.{} `{}` doesn't satisfy `{}`.",
+                    package, version, constraint
+                )));
+            }
+            continue;
+        }
+
+        if package == "php" {
+            let version = match &local.php {
+                Some(version) => version,
+                None => continue,
+            };
+            if constraint::matches(constraint, version) == Some(false) {
+                problems.push(problem(format!(
+                    "The local PHP `{}` doesn't satisfy `{}`.",
+                    version, constraint
+                )));
+            }
+        } else if let Some(extension) = package.strip_prefix("ext-") {
+            if local.has_extension(extension) == Some(false) {
+                problems.push(problem(format!(
+                    "The {} extension isn't loaded by the local PHP.",
+                    extension
+                )));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Describes a platform requirement other than php, and whether the local
+/// interpreter or the platform override provide it, as markdown.
+pub fn platform_hover(
+    name: &str,
+    constraint: &str,
+    local: &LocalPlatform,
+    platform: Option<&str>,
+) -> String {
+    let package = name.to_lowercase();
+    let mut lines = vec![];
+
+    if let Some(extension) = package.strip_prefix("ext-") {
+        lines.push(format!(
+            "Platform requirement: the `{}` PHP extension, `{}`",
+            extension, constraint
+        ));
+        lines.push(match local.has_extension(extension) {
+            Some(true) => format!("Local PHP: `{}` is loaded.", extension),
+            Some(false) => format!("Local PHP: `{}` is not loaded.", extension),
+            None => "Local PHP: not found".to_string(),
+        });
+    } else if let Some(library) = package.strip_prefix("lib-") {
+        lines.push(format!(
+            "Platform requirement: the `{}` library, `{}`",
+            library, constraint
+        ));
+        lines.push("Its version is the one the PHP extensions were built with.".to_string());
+    } else {
+        lines.push(format!(
+            "Platform requirement: `{}`, `{}`",
+            name, constraint
+        ));
+        lines.push("It's provided by PHP or composer itself, not by a package.".to_string());
+    }
+
+    if let Some(version) = platform {
+        lines.push(format!(
+            "Platform override (config.platform.{}): `{}` {}",
+            package,
+            version,
+            satisfaction(constraint, version)
+        ));
+    }
+
+    lines.join("\n\n")
+}
+
+/// Describes how the local interpreter and the platform override compare to
+/// the php requirement, as markdown.
+pub fn php_hover(constraint: &str, local: Option<&str>, platform: Option<&str>) -> String {
+    let mut lines = vec![format!("PHP requirement: `{}`", constraint)];
+
+    match local {
+        Some(version) => lines.push(format!(
+            "Local PHP: `{}` {}",
+            version,
+            satisfaction(constraint, version)
+        )),
+        None => lines.push("Local PHP: not found".to_string()),
+    }
+
+    if let Some(version) = platform {
+        lines.push(format!(
+            "Platform override (config.platform.php): `{}` {}",
+            version,
+            satisfaction(constraint, version)
+        ));
+    }
+
+    lines.join("\n\n")
+}
+
+/// A caret requirement on the major and minor of the version, e.g. "^8.1"
+/// for "8.1.27".
+pub fn caret_requirement(version: &str) -> Option<String> {
+    let is_number = |part: &&str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next().filter(is_number)?;
+    let minor = parts.next().filter(is_number).unwrap_or("0");
+
+    Some(format!("^{}.{}", major, minor))
+}
+
+/// Whether the "require" block exists but doesn't constrain the php version.
+pub fn is_missing_php_requirement(root: &Node) -> bool {
+    match root
+        .get("require")
+        .and_then(|require| require.value.as_object())
+    {
+        Some(members) => !members.iter().any(|member| member.key == "php"),
+        None => false,
+    }
+}
+
+/// The edit adding the php requirement at the top of "require".
+pub fn php_requirement_edit(text: &str, root: &Node, requirement: &str) -> Option<Edit> {
+    let require = root.get("require")?;
+
+    edit::insert_member(
+        text,
+        &Style::detect(text),
+        require,
+        "php",
+        &format!("\"{}\"", requirement),
+    )
+}
+
+fn satisfaction(constraint: &str, version: &str) -> &'static str {
+    match constraint::matches(constraint, version) {
+        Some(true) => "satisfies the requirement.",
+        Some(false) => "does not satisfy the requirement.",
+        None => "can't be compared with the requirement.",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::platform::{
+        caret_requirement, is_missing_php_requirement, parse_extensions, php_hover,
+        php_requirement_edit, platform_hover, platform_problems, LocalPlatform, PlatformProblem,
+    };
+    use std::collections::HashMap;
+
+    fn local_platform() -> LocalPlatform {
+        LocalPlatform {
+            php: Some("8.0.30".to_string()),
+            extensions: Some(parse_extensions(
+                "[PHP Modules]\nintl\njson\nZend OPcache\n\n[Zend Modules]\nZend OPcache\n",
+            )),
+        }
+    }
+
+    #[test]
+    fn it_can_compare_the_local_php_version() {
+        assert_eq!(
+            "PHP requirement: `^8.1`\n\nLocal PHP: `8.0.30` does not satisfy the requirement.",
+            php_hover("^8.1", Some("8.0.30"), None)
+        );
+    }
+
+    #[test]
+    fn it_can_parse_the_local_extensions() {
+        assert_eq!(
+            Some(vec![
+                "intl".to_string(),
+                "json".to_string(),
+                "zend-opcache".to_string()
+            ]),
+            local_platform().extensions
+        );
+        assert_eq!(Some(true), local_platform().has_extension("Intl"));
+        assert_eq!(None, LocalPlatform::default().has_extension("intl"));
+    }
+
+    #[test]
+    fn it_can_find_unsatisfied_platform_requirements() {
+        let requirements = [
+            ("php", "^8.1"),
+            ("ext-intl", "*"),
+            ("ext-gd", "*"),
+            ("ext-redis", "^5.0"),
+            ("lib-icu", ">=60"),
+        ];
+        let overrides = HashMap::from([("ext-redis".to_string(), "4.0.0".to_string())]);
+
+        assert_eq!(
+            vec![
+                PlatformProblem {
+                    package: "php".to_string(),
+                    message: "The local PHP `8.0.30` doesn't satisfy `^8.1`.".to_string(),
+                },
+                PlatformProblem {
+                    package: "ext-gd".to_string(),
+                    message: "The gd extension isn't loaded by the local PHP.".to_string(),
+                },
+                PlatformProblem {
+                    package: "ext-redis".to_string(),
+                    message: "config.platform.ext-redis `4.0.0` doesn't satisfy `^5.0`."
+                        .to_string(),
+                },
+            ],
+            platform_problems(&requirements, &local_platform(), &overrides)
+        );
+        assert!(platform_problems(
+            &requirements,
+            &LocalPlatform::default(),
+            &HashMap::from([("php".to_string(), "8.2.0".to_string())])
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn it_can_describe_an_extension_requirement() {
+        assert_eq!(
+            "Platform requirement: the `gd` PHP extension, `*`\n\nLocal PHP: `gd` is not loaded.",
+            platform_hover("ext-gd", "*", &local_platform(), None)
+        );
+        assert!(platform_hover("lib-icu", ">=60", &local_platform(), None)
+            .starts_with("Platform requirement: the `icu` library, `>=60`"));
+    }
+
+    #[test]
+    fn it_can_show_the_platform_override() {
+        let hover = php_hover("^8.1", None, Some("8.1.0"));
+
+        assert!(hover.contains("Local PHP: not found"));
+        assert!(hover.contains("(config.platform.php): `8.1.0` satisfies the requirement."));
+    }
+
+    #[test]
+    fn it_can_build_a_caret_requirement() {
+        assert_eq!(Some("^8.1".to_string()), caret_requirement("8.1.27"));
+        assert_eq!(Some("^8.0".to_string()), caret_requirement("8"));
+        assert_eq!(None, caret_requirement("latest"));
+    }
+
+    #[test]
+    fn it_can_add_the_php_requirement() {
+        let text = "{\n    \"require\": {\n        \"psr/log\": \"^3.0\"\n    }\n}";
+        let root = json::parse(text).unwrap();
+        assert!(is_missing_php_requirement(&root));
+
+        let (span, insertion) = php_requirement_edit(text, &root, "^8.1").unwrap();
+        let edited = format!("{}{}{}", &text[..span.start], insertion, &text[span.end..]);
+
+        assert_eq!(
+            "{\n    \"require\": {\n        \"php\": \"^8.1\",\n        \"psr/log\": \"^3.0\"\n    }\n}",
+            edited
+        );
+        assert!(!is_missing_php_requirement(&json::parse(&edited).unwrap()));
+    }
+
+    #[test]
+    fn it_can_add_the_php_requirement_to_an_empty_block() {
+        let text = "{\n    \"require\": { }\n}";
+        let root = json::parse(text).unwrap();
+
+        let (span, insertion) = php_requirement_edit(text, &root, "^8.1").unwrap();
+        let edited = format!("{}{}{}", &text[..span.start], insertion, &text[span.end..]);
+
+        assert_eq!(
+            "{\n    \"require\": {\n        \"php\": \"^8.1\"\n    }\n}",
+            edited
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::json::{Node, NodeKind, Span};
+
+/// A required plugin that composer won't run without asking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnallowedPlugin {
+    /// The span of the package name in require or require-dev.
+    pub span: Span,
+    pub package: String,
+}
+
+/// Whether "config.allow-plugins" lets the plugin run, or explicitly
+/// blocks it. Keys can contain "*" wildcards, like "acme/*".
+fn is_decided(allow_plugins: Option<&Node>, name: &str) -> bool {
+    let allow_plugins = match allow_plugins {
+        Some(allow_plugins) => allow_plugins,
+        None => return false,
+    };
+
+    match &allow_plugins.kind {
+        NodeKind::Bool(_) => true,
+        NodeKind::Object(members) => members
+            .iter()
+            .any(|member| matches_pattern(&member.key, name)),
+        _ => false,
+    }
+}
+
+/// Finds the plugins in require and require-dev that are missing from
+/// "config.allow-plugins", which makes composer prompt, or fail in CI.
+pub fn find_unallowed(root: &Node, plugins: &[String]) -> Vec<UnallowedPlugin> {
+    let allow_plugins = root
+        .get("config")
+        .and_then(|config| config.value.get("allow-plugins"))
+        .map(|allow_plugins| &allow_plugins.value);
+
+    let mut unallowed: Vec<UnallowedPlugin> = vec![];
+    for block in ["require", "require-dev"] {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            if !plugins.contains(&member.key) || is_decided(allow_plugins, &member.key) {
+                continue;
+            }
+
+            unallowed.push(UnallowedPlugin {
+                span: member.key_span,
+                package: member.key.clone(),
+            });
+        }
+    }
+
+    unallowed
+}
+
+/// Whether the byte offset is on a key of the "allow-plugins" object. The
+/// text is scanned instead of parsed, because it's usually incomplete while
+/// typing.
+pub fn is_in_allow_plugins_key(text: &str, offset: usize) -> bool {
+    let before = &text[..offset.min(text.len())];
+
+    // Keys follow the opening brace or a comma, values follow a colon.
+    let before_key = match before.rfind('"') {
+        Some(quote) => before[..quote].trim_end(),
+        None => return false,
+    };
+    if !before_key.ends_with(['{', ',']) {
+        return false;
+    }
+
+    let mut depth = 0;
+    for (index, c) in before.char_indices().rev() {
+        match c {
+            ']' | '}' => depth += 1,
+            '[' if depth == 0 => return false,
+            '{' if depth == 0 => {
+                let before_brace = before[..index].trim_end();
+                return match before_brace.strip_suffix(':') {
+                    Some(before_colon) => before_colon.trim_end().ends_with("\"allow-plugins\""),
+                    None => false,
+                };
+            }
+            '[' | '{' => depth -= 1,
+            _ => continue,
+        }
+    }
+
+    false
+}
+
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (index, part) in parts.iter().enumerate() {
+        // The last part has to match the end of the name.
+        if index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+
+        match rest.find(part) {
+            Some(found) => rest = &rest[found + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::plugins::{find_unallowed, is_in_allow_plugins_key, matches_pattern};
+
+    #[test]
+    fn it_can_match_wildcard_patterns() {
+        assert!(matches_pattern(
+            "composer/installers",
+            "composer/installers"
+        ));
+        assert!(matches_pattern("drupal/*", "drupal/core-vendor-hardening"));
+        assert!(matches_pattern("*/installers", "composer/installers"));
+        assert!(!matches_pattern("drupal/*", "composer/installers"));
+        assert!(!matches_pattern(
+            "composer/installer",
+            "composer/installers"
+        ));
+    }
+
+    #[test]
+    fn it_can_find_plugins_missing_from_allow_plugins() {
+        let text = r#"{
+            "require": { "composer/installers": "^2.0", "drupal/core-project-message": "^9.0" },
+            "require-dev": { "phpstan/extension-installer": "^1.0", "psr/log": "^3.0" },
+            "config": { "allow-plugins": { "drupal/*": true, "phpstan/extension-installer": false } }
+        }"#;
+        let plugins = vec![
+            "composer/installers".to_string(),
+            "drupal/core-project-message".to_string(),
+            "phpstan/extension-installer".to_string(),
+        ];
+        let unallowed = find_unallowed(&json::parse(text).unwrap(), &plugins);
+
+        assert_eq!(1, unallowed.len());
+        assert_eq!("composer/installers", unallowed[0].package);
+        assert_eq!(
+            "\"composer/installers\"",
+            &text[unallowed[0].span.start..unallowed[0].span.end]
+        );
+    }
+
+    #[test]
+    fn it_allows_all_plugins_with_true() {
+        let text = r#"{ "require": { "composer/installers": "^2.0" }, "config": { "allow-plugins": true } }"#;
+        let plugins = vec!["composer/installers".to_string()];
+
+        assert!(find_unallowed(&json::parse(text).unwrap(), &plugins).is_empty());
+    }
+
+    #[test]
+    fn it_can_detect_the_keys_of_allow_plugins() {
+        let text = "{ \"config\": { \"allow-plugins\": { \"acme/a\": true, \"comp";
+
+        assert!(is_in_allow_plugins_key(text, text.len()));
+        assert!(!is_in_allow_plugins_key(
+            "{ \"allow-plugins\": { \"acme/a\": \"",
+            32
+        ));
+        assert!(!is_in_allow_plugins_key("{ \"require\": { \"comp", 20));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::packagist::Provider;
+
+/// How many of the available providers are listed on hover.
+const LISTED_PROVIDERS: usize = 10;
+
+/// Describes the virtual package as markdown: which locked packages provide
+/// it, with their version, and which providers are available on packagist.
+pub fn virtual_hover(name: &str, installed: &[(String, String)], providers: &[Provider]) -> String {
+    let mut lines = vec![format!(
+        "`{}` is a virtual package, it's fulfilled by any package that provides it.",
+        name
+    )];
+
+    match installed.is_empty() {
+        true => lines.push("No installed package provides it.".to_string()),
+        false => {
+            let resolved: Vec<String> = installed
+                .iter()
+                .map(|(provider, version)| format!("- `{}` {}", provider, version))
+                .collect();
+            lines.push(format!(
+                "Resolved in composer.lock to:\n{}",
+                resolved.join("\n")
+            ));
+        }
+    }
+
+    if !providers.is_empty() {
+        let available: Vec<String> = providers
+            .iter()
+            .take(LISTED_PROVIDERS)
+            .map(|provider| {
+                let mut line = format!("- `{}`", provider.name);
+                if installed.iter().any(|(name, _)| name == &provider.name) {
+                    line.push_str(" (installed)");
+                }
+                if let Some(description) = provider
+                    .description
+                    .as_ref()
+                    .filter(|description| !description.is_empty())
+                {
+                    line.push_str(&format!(": {}", description));
+                }
+                line
+            })
+            .collect();
+
+        let mut section = format!("Providers on Packagist:\n{}", available.join("\n"));
+        if providers.len() > LISTED_PROVIDERS {
+            section.push_str(&format!(
+                "\n- and {} more",
+                providers.len() - LISTED_PROVIDERS
+            ));
+        }
+        lines.push(section);
+    }
+
+    lines.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packagist::Provider;
+    use crate::provide::virtual_hover;
+
+    fn provider(name: &str, description: &str) -> Provider {
+        Provider {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            downloads: 0,
+        }
+    }
+
+    #[test]
+    fn it_can_describe_the_installed_providers() {
+        let hover = virtual_hover(
+            "psr/log-implementation",
+            &[("monolog/monolog".to_string(), "3.5.0".to_string())],
+            &[],
+        );
+
+        assert!(hover.ends_with("Resolved in composer.lock to:\n- `monolog/monolog` 3.5.0"));
+        assert!(virtual_hover("psr/log-implementation", &[], &[]).contains("No installed package"));
+    }
+
+    #[test]
+    fn it_can_list_the_available_providers() {
+        let hover = virtual_hover(
+            "psr/log-implementation",
+            &[("monolog/monolog".to_string(), "3.5.0".to_string())],
+            &[
+                provider("monolog/monolog", "Sends your logs"),
+                provider("acme/logger", ""),
+            ],
+        );
+
+        assert!(hover.ends_with(
+            "Providers on Packagist:\n- `monolog/monolog` (installed): Sends your logs\n- `acme/logger`"
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tower::Service;
+use tower_lsp::jsonrpc::{Request, Response};
+use tower_lsp::lsp_types::{Diagnostic, TextDocumentIdentifier};
+
+/// The identifier of the diagnostics in the server capabilities.
+const IDENTIFIER: &str = "composer";
+
+/// What the client supports of pull diagnostics. lsp-types doesn't know the
+/// textDocument/diagnostic request yet, so it's read from the raw initialize
+/// request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PullSupport {
+    /// The client requests the diagnostics of the open documents itself.
+    pub enabled: bool,
+    /// The client can be asked to request the diagnostics again.
+    pub refresh: bool,
+}
+
+/// Reads the pull diagnostics capabilities from the initialize params.
+pub fn client_support(params: &Value) -> PullSupport {
+    let capabilities = &params["capabilities"];
+
+    PullSupport {
+        enabled: capabilities["textDocument"]["diagnostic"].is_object(),
+        refresh: capabilities["workspace"]["diagnostics"]["refreshSupport"] == json!(true),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentDiagnosticParams {
+    pub text_document: TextDocumentIdentifier,
+    /// The result ID of the previous report of the document.
+    #[serde(default)]
+    pub previous_result_id: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DocumentDiagnosticReport {
+    #[serde(rename_all = "camelCase")]
+    Full {
+        result_id: String,
+        items: Vec<Diagnostic>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Unchanged { result_id: String },
+}
+
+/// The report of the diagnostics, unchanged when the client already has them
+/// from the previous result.
+pub fn report(
+    items: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> DocumentDiagnosticReport {
+    let result_id = result_id(&items);
+
+    match previous_result_id {
+        Some(previous) if previous == result_id => {
+            DocumentDiagnosticReport::Unchanged { result_id }
+        }
+        _ => DocumentDiagnosticReport::Full { result_id, items },
+    }
+}
+
+/// Identifies the diagnostics by a hash of their contents, so the same
+/// diagnostics always get the same ID.
+fn result_id(items: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(items)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+
+    format!("{:x}", hasher.finish())
+}
+
+/// Asks the client to request the diagnostics of the open documents again.
+pub enum WorkspaceDiagnosticRefresh {}
+
+impl tower_lsp::lsp_types::request::Request for WorkspaceDiagnosticRefresh {
+    type Params = ();
+    type Result = ();
+    const METHOD: &'static str = "workspace/diagnostic/refresh";
+}
+
+/// Adds the diagnostic provider to the capabilities in the result of
+/// initialize.
+fn add_diagnostic_provider(mut result: Value) -> Value {
+    if let Some(capabilities) = result
+        .get_mut("capabilities")
+        .and_then(|capabilities| capabilities.as_object_mut())
+    {
+        capabilities.insert(
+            "diagnosticProvider".to_string(),
+            json!({
+                "identifier": IDENTIFIER,
+                "interFileDependencies": false,
+                "workspaceDiagnostics": false,
+            }),
+        );
+    }
+
+    result
+}
+
+/// Wraps the language server to read the pull diagnostics support of the
+/// client and advertise the diagnostic provider to clients supporting it.
+pub struct PullDiagnostics<S> {
+    inner: S,
+    support: Arc<RwLock<PullSupport>>,
+}
+
+impl<S> PullDiagnostics<S> {
+    pub fn new(inner: S, support: Arc<RwLock<PullSupport>>) -> PullDiagnostics<S> {
+        PullDiagnostics { inner, support }
+    }
+}
+
+impl<S> Service<Request> for PullDiagnostics<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if request.method() != "initialize" {
+            return self.inner.call(request).boxed();
+        }
+
+        let support = request.params().map(client_support).unwrap_or_default();
+        *self.support.write().unwrap() = support;
+
+        let response = self.inner.call(request);
+        async move {
+            let response = response.await?;
+            if !support.enabled {
+                return Ok(response);
+            }
+
+            Ok(response.map(|response| {
+                let (id, result) = response.into_parts();
+                Response::from_parts(id, result.map(add_diagnostic_provider))
+            }))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower_lsp::lsp_types::{Diagnostic, Position, Range};
+
+    use crate::pull::{
+        add_diagnostic_provider, client_support, report, DocumentDiagnosticReport, PullSupport,
+    };
+
+    #[test]
+    fn it_can_read_the_pull_support_of_the_client() {
+        assert_eq!(
+            PullSupport {
+                enabled: true,
+                refresh: true,
+            },
+            client_support(&json!({
+                "capabilities": {
+                    "textDocument": { "diagnostic": { "dynamicRegistration": false } },
+                    "workspace": { "diagnostics": { "refreshSupport": true } }
+                }
+            }))
+        );
+        assert_eq!(
+            PullSupport::default(),
+            client_support(&json!({ "capabilities": {} }))
+        );
+    }
+
+    #[test]
+    fn it_can_report_unchanged_diagnostics() {
+        let diagnostic = Diagnostic::new_simple(
+            Range::new(Position::new(1, 4), Position::new(1, 13)),
+            "Update available".to_string(),
+        );
+
+        let result_id = match report(vec![diagnostic.clone()], None) {
+            DocumentDiagnosticReport::Full { result_id, items } => {
+                assert_eq!(vec![diagnostic.clone()], items);
+                result_id
+            }
+            unchanged => panic!("expected a full report, got {:?}", unchanged),
+        };
+        assert_eq!(
+            DocumentDiagnosticReport::Unchanged {
+                result_id: result_id.clone()
+            },
+            report(vec![diagnostic], Some(&result_id))
+        );
+        assert!(matches!(
+            report(vec![], Some(&result_id)),
+            DocumentDiagnosticReport::Full { .. }
+        ));
+    }
+
+    #[test]
+    fn it_can_serialize_a_report() {
+        let report = report(vec![], Some("previous"));
+
+        assert_eq!(
+            json!("full"),
+            serde_json::to_value(&report).unwrap()["kind"]
+        );
+        assert!(serde_json::to_value(&report).unwrap()["resultId"].is_string());
+    }
+
+    #[test]
+    fn it_can_advertise_the_diagnostic_provider() {
+        let result = add_diagnostic_provider(json!({ "capabilities": { "hoverProvider": true } }));
+
+        assert_eq!(
+            json!("composer"),
+            result["capabilities"]["diagnosticProvider"]["identifier"]
+        );
+        assert_eq!(json!(true), result["capabilities"]["hoverProvider"]);
+    }
+}
@@ -0,0 +1,288 @@
+use tower_lsp::lsp_types::{
+    ClientCapabilities, CodeLensOptions, DidChangeWatchedFilesRegistrationOptions, DocumentFilter,
+    FileSystemWatcher, InlayHintOptions, InlayHintRegistrationOptions, Registration,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensRegistrationOptions,
+    StaticRegistrationOptions, TextDocumentRegistrationOptions, Unregistration,
+};
+
+use crate::config::FeatureSettings;
+use crate::tokens;
+
+/// The features that can be turned off in the settings. They are registered
+/// with the client dynamically, so it only asks for the enabled ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    CodeLens,
+    InlayHints,
+    SemanticTokens,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [
+        Feature::CodeLens,
+        Feature::InlayHints,
+        Feature::SemanticTokens,
+    ];
+
+    /// The identifier of the registration, used to unregister it again.
+    pub fn id(self) -> &'static str {
+        match self {
+            Feature::CodeLens => "composer.codeLens",
+            Feature::InlayHints => "composer.inlayHints",
+            Feature::SemanticTokens => "composer.semanticTokens",
+        }
+    }
+
+    pub fn method(self) -> &'static str {
+        match self {
+            Feature::CodeLens => "textDocument/codeLens",
+            Feature::InlayHints => "textDocument/inlayHint",
+            Feature::SemanticTokens => "textDocument/semanticTokens",
+        }
+    }
+
+    pub fn is_enabled(self, settings: &FeatureSettings) -> bool {
+        match self {
+            Feature::CodeLens => settings.code_lens,
+            Feature::InlayHints => settings.inlay_hints,
+            Feature::SemanticTokens => settings.semantic_tokens,
+        }
+    }
+
+    /// Whether the client can be asked to request the feature again, once
+    /// the checks found new package data.
+    pub fn supports_refresh(self, capabilities: &ClientCapabilities) -> bool {
+        let workspace = match &capabilities.workspace {
+            Some(workspace) => workspace,
+            None => return false,
+        };
+
+        let refresh_support = match self {
+            Feature::CodeLens => workspace
+                .code_lens
+                .as_ref()
+                .and_then(|code_lens| code_lens.refresh_support),
+            Feature::InlayHints => workspace
+                .inlay_hint
+                .as_ref()
+                .and_then(|inlay_hint| inlay_hint.refresh_support),
+            _ => None,
+        };
+        refresh_support.unwrap_or(false)
+    }
+
+    /// Whether the client supports registering the feature dynamically.
+    pub fn is_dynamic(self, capabilities: &ClientCapabilities) -> bool {
+        let text_document = match &capabilities.text_document {
+            Some(text_document) => text_document,
+            None => return false,
+        };
+
+        let dynamic_registration = match self {
+            Feature::CodeLens => text_document
+                .code_lens
+                .as_ref()
+                .and_then(|code_lens| code_lens.dynamic_registration),
+            Feature::InlayHints => text_document
+                .inlay_hint
+                .as_ref()
+                .and_then(|inlay_hint| inlay_hint.dynamic_registration),
+            Feature::SemanticTokens => text_document
+                .semantic_tokens
+                .as_ref()
+                .and_then(|semantic_tokens| semantic_tokens.dynamic_registration),
+        };
+
+        dynamic_registration.unwrap_or(false)
+    }
+
+    pub fn registration(self) -> Registration {
+        let text_document = TextDocumentRegistrationOptions {
+            document_selector: Some(vec![DocumentFilter {
+                language: None,
+                scheme: None,
+                pattern: Some("**/composer.json".to_string()),
+            }]),
+        };
+
+        let options = match self {
+            Feature::CodeLens => serde_json::to_value(CodeLensRegistration {
+                text_document,
+                code_lens: code_lens_options(),
+            }),
+            Feature::InlayHints => serde_json::to_value(InlayHintRegistrationOptions {
+                inlay_hint_options: inlay_hint_options(),
+                text_document_registration_options: text_document,
+                static_registration_options: StaticRegistrationOptions { id: None },
+            }),
+            Feature::SemanticTokens => serde_json::to_value(SemanticTokensRegistrationOptions {
+                text_document_registration_options: text_document,
+                semantic_tokens_options: semantic_tokens_options(),
+                static_registration_options: StaticRegistrationOptions { id: None },
+            }),
+        };
+
+        Registration {
+            id: self.id().to_string(),
+            method: self.method().to_string(),
+            register_options: options.ok(),
+        }
+    }
+
+    pub fn unregistration(self) -> Unregistration {
+        Unregistration {
+            id: self.id().to_string(),
+            method: self.method().to_string(),
+        }
+    }
+}
+
+/// lsp-types has no registration options for code lenses.
+#[derive(serde::Serialize)]
+struct CodeLensRegistration {
+    #[serde(flatten)]
+    text_document: TextDocumentRegistrationOptions,
+    #[serde(flatten)]
+    code_lens: CodeLensOptions,
+}
+
+pub fn code_lens_options() -> CodeLensOptions {
+    CodeLensOptions {
+        resolve_provider: Some(false),
+    }
+}
+
+pub fn inlay_hint_options() -> InlayHintOptions {
+    InlayHintOptions {
+        work_done_progress_options: Default::default(),
+        resolve_provider: Some(false),
+    }
+}
+
+pub fn semantic_tokens_options() -> SemanticTokensOptions {
+    SemanticTokensOptions {
+        work_done_progress_options: Default::default(),
+        legend: tokens::legend(),
+        range: Some(false),
+        full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+    }
+}
+
+/// Whether the client can be asked to watch files.
+pub fn supports_file_watching(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+        .and_then(|watched_files| watched_files.dynamic_registration)
+        .unwrap_or(false)
+}
+
+/// Asks the client to report changes of composer.json and composer.lock on
+/// disk, like the ones of a composer update in a terminal.
+pub fn file_watcher_registration() -> Registration {
+    let watchers = ["**/composer.json", "**/composer.lock"]
+        .into_iter()
+        .map(|pattern| FileSystemWatcher {
+            glob_pattern: pattern.to_string(),
+            kind: None,
+        })
+        .collect();
+
+    Registration {
+        id: "composer.watchedFiles".to_string(),
+        method: "workspace/didChangeWatchedFiles".to_string(),
+        register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+            watchers,
+        })
+        .ok(),
+    }
+}
+
+/// The features to register and to unregister, to go from the registered
+/// features to the ones enabled in the settings.
+pub fn changes(registered: &[Feature], settings: &FeatureSettings) -> (Vec<Feature>, Vec<Feature>) {
+    let register = Feature::ALL
+        .into_iter()
+        .filter(|feature| feature.is_enabled(settings) && !registered.contains(feature))
+        .collect();
+    let unregister = registered
+        .iter()
+        .copied()
+        .filter(|feature| !feature.is_enabled(settings))
+        .collect();
+
+    (register, unregister)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::ClientCapabilities;
+
+    use crate::config::FeatureSettings;
+    use crate::registration::{
+        changes, file_watcher_registration, supports_file_watching, Feature,
+    };
+
+    #[test]
+    fn it_can_compute_the_registration_changes() {
+        let settings = FeatureSettings {
+            code_lens: false,
+            inlay_hints: true,
+            semantic_tokens: false,
+        };
+
+        assert_eq!(
+            (
+                vec![Feature::InlayHints],
+                vec![Feature::CodeLens, Feature::SemanticTokens]
+            ),
+            changes(&[Feature::CodeLens, Feature::SemanticTokens], &settings)
+        );
+        assert_eq!((vec![], vec![]), changes(&[Feature::InlayHints], &settings));
+    }
+
+    #[test]
+    fn it_can_detect_the_refresh_support() {
+        let capabilities: ClientCapabilities = serde_json::from_value(serde_json::json!({
+            "workspace": {
+                "codeLens": { "refreshSupport": false },
+                "inlayHint": { "refreshSupport": true }
+            }
+        }))
+        .unwrap();
+
+        assert!(Feature::InlayHints.supports_refresh(&capabilities));
+        assert!(!Feature::CodeLens.supports_refresh(&capabilities));
+        assert!(!Feature::SemanticTokens.supports_refresh(&capabilities));
+        assert!(!Feature::InlayHints.supports_refresh(&ClientCapabilities::default()));
+    }
+
+    #[test]
+    fn it_can_register_a_feature_for_composer_json() {
+        let registration = Feature::SemanticTokens.registration();
+        let options = registration.register_options.unwrap();
+
+        assert_eq!("textDocument/semanticTokens", registration.method);
+        assert_eq!(
+            "**/composer.json",
+            options["documentSelector"][0]["pattern"]
+        );
+        assert_eq!(true, options["full"]["delta"]);
+    }
+
+    #[test]
+    fn it_can_watch_the_composer_files() {
+        let capabilities: ClientCapabilities = serde_json::from_value(serde_json::json!({
+            "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } }
+        }))
+        .unwrap();
+        let registration = file_watcher_registration();
+        let options = registration.register_options.unwrap();
+
+        assert!(supports_file_watching(&capabilities));
+        assert!(!supports_file_watching(&ClientCapabilities::default()));
+        assert_eq!("workspace/didChangeWatchedFiles", registration.method);
+        assert_eq!("**/composer.lock", options["watchers"][1]["globPattern"]);
+    }
+}
@@ -0,0 +1,240 @@
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+use crate::completion;
+use crate::json::{Node, NodeKind, Span};
+
+/// The repository types composer knows about.
+pub const REPOSITORY_TYPES: &[&str] = &[
+    "composer",
+    "vcs",
+    "git",
+    "github",
+    "gitlab",
+    "bitbucket",
+    "git-bitbucket",
+    "hg",
+    "fossil",
+    "svn",
+    "perforce",
+    "path",
+    "artifact",
+    "package",
+];
+
+/// A mistake in one of the "repositories" entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryProblem {
+    pub span: Span,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+impl RepositoryProblem {
+    fn new(span: Span, severity: DiagnosticSeverity, message: &str) -> RepositoryProblem {
+        RepositoryProblem {
+            span,
+            severity,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Validates the structure of every entry in "repositories", which can either
+/// be a list or an object keyed by the repository name.
+pub fn validate(root: &Node) -> Vec<RepositoryProblem> {
+    let repositories = match root.get("repositories") {
+        Some(member) => &member.value,
+        None => return vec![],
+    };
+
+    let entries: Vec<&Node> = match &repositories.kind {
+        NodeKind::Array(items) => items.iter().collect(),
+        NodeKind::Object(members) => members.iter().map(|member| &member.value).collect(),
+        _ => {
+            return vec![RepositoryProblem::new(
+                repositories.span,
+                DiagnosticSeverity::ERROR,
+                "The repositories must be a list or an object.",
+            )]
+        }
+    };
+
+    let mut problems = vec![];
+    for entry in entries {
+        validate_entry(entry, &mut problems);
+    }
+
+    problems
+}
+
+fn validate_entry(entry: &Node, problems: &mut Vec<RepositoryProblem>) {
+    // "packagist.org": false disables the default repository, either as an
+    // entry of the object or as a single member object in the list.
+    if entry.kind == NodeKind::Bool(false) {
+        return;
+    }
+
+    let members = match entry.as_object() {
+        Some(members) => members,
+        None => {
+            problems.push(RepositoryProblem::new(
+                entry.span,
+                DiagnosticSeverity::ERROR,
+                "A repository must be an object.",
+            ));
+            return;
+        }
+    };
+
+    if let [member] = members.as_slice() {
+        if member.value.kind == NodeKind::Bool(false) {
+            return;
+        }
+    }
+
+    let repository_type = match entry.get("type") {
+        Some(member) => match member.value.as_str() {
+            Some(repository_type) if REPOSITORY_TYPES.contains(&repository_type) => repository_type,
+            _ => {
+                problems.push(RepositoryProblem::new(
+                    member.value.span,
+                    DiagnosticSeverity::WARNING,
+                    &format!(
+                        "Unknown repository type, expected one of: {}.",
+                        REPOSITORY_TYPES.join(", ")
+                    ),
+                ));
+                return;
+            }
+        },
+        None => {
+            problems.push(RepositoryProblem::new(
+                entry.span,
+                DiagnosticSeverity::ERROR,
+                "The repository is missing a \"type\".",
+            ));
+            return;
+        }
+    };
+
+    if repository_type == "package" {
+        if entry.get("package").is_none() {
+            problems.push(RepositoryProblem::new(
+                entry.span,
+                DiagnosticSeverity::ERROR,
+                "A package repository needs a \"package\" definition.",
+            ));
+        }
+        return;
+    }
+
+    let url = match entry.get("url") {
+        Some(url) => url,
+        None => {
+            problems.push(RepositoryProblem::new(
+                entry.span,
+                DiagnosticSeverity::ERROR,
+                "The repository is missing a \"url\".",
+            ));
+            return;
+        }
+    };
+
+    if let Some(url_value) = url.value.as_str() {
+        if url_value.starts_with("http://") {
+            problems.push(RepositoryProblem::new(
+                url.value.span,
+                DiagnosticSeverity::WARNING,
+                "The repository is fetched over plain HTTP, use HTTPS instead.",
+            ));
+        }
+    }
+
+    if repository_type == "path" && entry.get("canonical").is_none() {
+        problems.push(RepositoryProblem::new(
+            entry.span,
+            DiagnosticSeverity::INFORMATION,
+            "Path repositories are canonical by default, so packages they provide are never installed from other repositories. Add \"canonical\": false if that's not intended.",
+        ));
+    }
+}
+
+/// Whether the byte offset is in the "type" value of a repository entry.
+pub fn is_in_type_value(text: &str, offset: usize) -> bool {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    let line = &before[line_start..];
+
+    // The cursor has to be right after `"type": "`, optionally with a prefix.
+    let before_value = match line.rfind('"') {
+        Some(quote) => line[..quote].trim_end(),
+        None => return false,
+    };
+    let before_colon = match before_value.strip_suffix(':') {
+        Some(before_colon) => before_colon.trim_end(),
+        None => return false,
+    };
+    if !before_colon.ends_with("\"type\"") {
+        return false;
+    }
+
+    match before.rfind('{') {
+        Some(object_start) => completion::is_in_array(text, object_start, "repositories"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    use crate::json;
+    use crate::repositories::{is_in_type_value, validate, RepositoryProblem};
+
+    #[test]
+    fn it_accepts_valid_repositories() {
+        let text = r#"{
+            "repositories": [
+                { "type": "vcs", "url": "https://github.com/acme/lib" },
+                { "type": "path", "url": "../lib", "canonical": false },
+                { "packagist.org": false }
+            ]
+        }"#;
+        let root = json::parse(text).unwrap();
+
+        assert_eq!(Vec::<RepositoryProblem>::new(), validate(&root));
+    }
+
+    #[test]
+    fn it_can_find_problems_in_repositories() {
+        let text = r#"{
+            "repositories": {
+                "a": { "url": "https://example.com" },
+                "b": { "type": "svm", "url": "https://example.com" },
+                "c": { "type": "composer", "url": "http://example.com" },
+                "d": { "type": "git" },
+                "e": { "type": "path", "url": "../lib" }
+            }
+        }"#;
+        let root = json::parse(text).unwrap();
+        let problems = validate(&root);
+
+        assert_eq!(5, problems.len());
+        assert_eq!(DiagnosticSeverity::ERROR, problems[0].severity);
+        assert!(problems[1].message.starts_with("Unknown repository type"));
+        assert_eq!(
+            "\"http://example.com\"",
+            &text[problems[2].span.start..problems[2].span.end]
+        );
+        assert_eq!("The repository is missing a \"url\".", problems[3].message);
+        assert_eq!(DiagnosticSeverity::INFORMATION, problems[4].severity);
+    }
+
+    #[test]
+    fn it_can_detect_the_type_value_of_a_repository() {
+        let text = "{\n    \"repositories\": [\n        { \"type\": \"v";
+
+        assert!(is_in_type_value(text, text.len()));
+        assert!(!is_in_type_value("{\n    \"type\": \"", 15));
+    }
+}
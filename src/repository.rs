@@ -0,0 +1,475 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::auth;
+use crate::constraint::Version;
+use crate::http;
+use crate::json::{Node, NodeKind};
+use crate::packagist::{self, Package, PackageVersion};
+
+/// How long the packages.json of a composer repository is reused.
+const ROOT_FRESH_FOR: Duration = Duration::from_secs(10 * 60);
+
+/// The packages.json of the composer repositories, by their URL.
+static ROOTS: RwLock<Vec<(String, Instant, RepositoryRoot)>> = RwLock::new(Vec::new());
+
+/// A repository declared in the "repositories" of composer.json.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repository {
+    /// A composer repository, like Private Packagist or Satis.
+    Composer { url: String },
+    /// A version control repository. Its packages are only known after
+    /// cloning it, so they can't be looked up.
+    Vcs { url: String },
+    /// A local directory with a package, or directories with a "*" pattern.
+    Path { path: PathBuf },
+    /// Other types, like "package" or "artifact".
+    Other,
+}
+
+/// Where the packages of a composer.json come from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Repositories {
+    pub entries: Vec<Repository>,
+    /// Whether "packagist.org": false disables the default repository.
+    pub packagist_disabled: bool,
+    /// The directory of composer.json, for relative paths and auth.json.
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+enum RepositoryRoot {
+    /// The composer 2 metadata URL, with a "%package%" placeholder.
+    MetadataUrl(String),
+    /// The packages listed in packages.json or its includes.
+    Packages(HashMap<String, Vec<PackageVersion>>),
+}
+
+/// Reads the "repositories" of composer.json, which can either be a list or
+/// an object keyed by the repository name.
+pub fn parse(root: &Node, dir: &Path) -> Repositories {
+    let mut repositories = Repositories {
+        dir: dir.to_path_buf(),
+        ..Repositories::default()
+    };
+
+    let entries: Vec<(Option<&str>, &Node)> = match root.get("repositories") {
+        Some(member) => match &member.value.kind {
+            NodeKind::Array(items) => items.iter().map(|item| (None, item)).collect(),
+            NodeKind::Object(members) => members
+                .iter()
+                .map(|member| (Some(member.key.as_str()), &member.value))
+                .collect(),
+            _ => vec![],
+        },
+        None => vec![],
+    };
+
+    for (key, entry) in entries {
+        // "packagist.org": false, either as an entry of the object or as a
+        // single member object in the list.
+        let disabled = match (key, entry.as_object().map(|members| members.as_slice())) {
+            (Some(key), _) => entry.kind == NodeKind::Bool(false) && is_packagist(key),
+            (None, Some([member])) => {
+                member.value.kind == NodeKind::Bool(false) && is_packagist(&member.key)
+            }
+            _ => false,
+        };
+        if disabled {
+            repositories.packagist_disabled = true;
+            continue;
+        }
+
+        let field = |name: &str| {
+            entry
+                .get(name)
+                .and_then(|member| member.value.as_str())
+                .map(|value| value.to_string())
+        };
+        let url = match field("url") {
+            Some(url) => url,
+            None => continue,
+        };
+
+        let repository = match field("type").as_deref() {
+            Some("composer") => Repository::Composer { url },
+            Some("path") => Repository::Path {
+                path: dir.join(url),
+            },
+            Some("vcs" | "git" | "github" | "gitlab" | "bitbucket" | "git-bitbucket" | "hg")
+            | Some("fossil" | "svn" | "perforce") => Repository::Vcs { url },
+            _ => Repository::Other,
+        };
+        repositories.entries.push(repository);
+    }
+
+    repositories
+}
+
+fn is_packagist(key: &str) -> bool {
+    key == "packagist.org" || key == "packagist"
+}
+
+/// Fetches the package from the repositories of composer.json, in their
+/// order, and then from packagist.org unless it's disabled. Like composer,
+/// the first repository with the package wins.
+pub async fn try_get_package_info(
+    repositories: &Repositories,
+    name: String,
+    include_dev: bool,
+) -> Result<Package, String> {
+    if packagist::is_platform_package(&name) {
+        return Err(format!(
+            "{} is a platform package, not in a repository",
+            name
+        ));
+    }
+    let lowercase_name = name.to_lowercase();
+
+    let mut errors = vec![];
+    for repository in repositories.entries.iter() {
+        let versions = match repository {
+            Repository::Composer { url } => {
+                composer_versions(url, &repositories.dir, &lowercase_name, include_dev).await
+            }
+            Repository::Path { path } => Ok(path_versions(path, &lowercase_name)),
+            _ => continue,
+        };
+
+        match versions {
+            Ok(Some(versions)) => return Ok(Package::new(name, versions)),
+            Ok(None) => {}
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if !repositories.packagist_disabled {
+        return packagist::try_get_package_info(name, include_dev, Some(&repositories.dir)).await;
+    }
+
+    match errors.is_empty() {
+        true => Err("the package isn't in any of the repositories".to_string()),
+        false => Err(errors.join(", ")),
+    }
+}
+
+/// Like try_get_package_info, but only logs why it couldn't be fetched.
+pub async fn get_package_info(
+    repositories: &Repositories,
+    name: String,
+    include_dev: bool,
+) -> Option<Package> {
+    match try_get_package_info(repositories, name.clone(), include_dev).await {
+        Ok(package) => Some(package),
+        Err(error) => {
+            log::info!("Can't get the package data for {}: {}", name, error);
+            None
+        }
+    }
+}
+
+/// The versions of the package in a composer repository, None when the
+/// repository doesn't have it.
+async fn composer_versions(
+    url: &str,
+    dir: &Path,
+    name: &str,
+    include_dev: bool,
+) -> Result<Option<Vec<PackageVersion>>, String> {
+    let root = repository_root(url, dir).await?;
+
+    let template = match root {
+        RepositoryRoot::Packages(packages) => return Ok(packages.get(name).cloned()),
+        RepositoryRoot::MetadataUrl(template) => template,
+    };
+
+    let mut versions = match fetch(&template.replace("%package%", name), dir).await? {
+        Some(text) => packagist::parse_versions(&text, name)?,
+        None => return Ok(None),
+    };
+    if include_dev {
+        let dev_url = template.replace("%package%", &format!("{}~dev", name));
+        match fetch(&dev_url, dir).await {
+            Ok(Some(text)) => versions.extend(packagist::parse_versions(&text, name)?),
+            Ok(None) => {}
+            Err(error) => log::info!("Can't get the dev versions of {}: {}", name, error),
+        }
+    }
+
+    // The packagist links don't apply to private packages.
+    for version in versions.iter_mut() {
+        version.packagist_url = None;
+    }
+
+    Ok(Some(versions))
+}
+
+/// The packages.json of the repository, from memory when it's recent.
+async fn repository_root(url: &str, dir: &Path) -> Result<RepositoryRoot, String> {
+    let cached = ROOTS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(root_url, fetched, _)| root_url == url && fetched.elapsed() < ROOT_FRESH_FOR)
+        .map(|(_, _, root)| root.clone());
+    if let Some(root) = cached {
+        return Ok(root);
+    }
+
+    let base = format!("{}/packages.json", url.trim_end_matches('/'));
+    let text = fetch(&base, dir)
+        .await?
+        .ok_or_else(|| format!("{} has no packages.json", url))?;
+    let contents: Value = serde_json::from_str(&text).map_err(|error| error.to_string())?;
+
+    let root = match contents
+        .get("metadata-url")
+        .and_then(|value| value.as_str())
+    {
+        Some(metadata_url) => RepositoryRoot::MetadataUrl(resolve_url(&base, metadata_url)),
+        None => {
+            let mut packages = parse_packages(&contents);
+            // Satis splits the packages into included files.
+            let includes = contents
+                .get("includes")
+                .and_then(|includes| includes.as_object())
+                .map(|includes| includes.keys().cloned().collect::<Vec<String>>())
+                .unwrap_or_default();
+            for include in includes {
+                let text = match fetch(&resolve_url(&base, &include), dir).await? {
+                    Some(text) => text,
+                    None => continue,
+                };
+                let contents: Value =
+                    serde_json::from_str(&text).map_err(|error| error.to_string())?;
+                packages.extend(parse_packages(&contents));
+            }
+
+            RepositoryRoot::Packages(packages)
+        }
+    };
+
+    let mut roots = ROOTS.write().unwrap();
+    roots.retain(|(root_url, _, _)| root_url != url);
+    roots.push((url.to_string(), Instant::now(), root.clone()));
+
+    Ok(root)
+}
+
+/// Resolves a URL of packages.json, like "/p2/%package%.json".
+fn resolve_url(base: &str, url: &str) -> String {
+    match Url::parse(base).and_then(|base| base.join(url)) {
+        // Joining encodes the placeholder.
+        Ok(resolved) => resolved.to_string().replace("%25package%25", "%package%"),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Reads the composer 1 "packages", an object of versions for every package.
+fn parse_packages(contents: &Value) -> HashMap<String, Vec<PackageVersion>> {
+    let packages = match contents
+        .get("packages")
+        .and_then(|packages| packages.as_object())
+    {
+        Some(packages) => packages,
+        None => return HashMap::new(),
+    };
+
+    let mut result = HashMap::new();
+    for (name, versions) in packages {
+        let mut versions: Vec<PackageVersion> = versions
+            .as_object()
+            .into_iter()
+            .flat_map(|versions| versions.values())
+            .filter_map(|version| serde_json::from_value(version.clone()).ok())
+            .collect();
+        // Newest first, like packagist.
+        versions.sort_by_key(|version: &PackageVersion| {
+            std::cmp::Reverse(version.version.as_deref().and_then(Version::parse))
+        });
+
+        result.insert(name.to_lowercase(), versions);
+    }
+
+    result
+}
+
+/// The package of a path repository, if it's the one looked for.
+fn path_versions(path: &Path, name: &str) -> Option<Vec<PackageVersion>> {
+    // "packages/*" matches every directory in packages.
+    let dirs: Vec<PathBuf> = match path.file_name().and_then(|file| file.to_str()) {
+        Some("*") => fs::read_dir(path.parent()?)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|dir| dir.is_dir())
+            .collect(),
+        _ => vec![path.to_path_buf()],
+    };
+
+    dirs.iter().find_map(|dir| {
+        let text = fs::read_to_string(dir.join("composer.json")).ok()?;
+        let contents: Value = serde_json::from_str(&text).ok()?;
+        let field = |name: &str| {
+            contents
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+        };
+        if field("name")?.to_lowercase() != name {
+            return None;
+        }
+
+        // Composer guesses the version from git, else it's a dev version.
+        Some(vec![PackageVersion {
+            description: field("description"),
+            homepage: field("homepage"),
+            version: field("version").or_else(|| Some("dev-main".to_string())),
+            ..PackageVersion::default()
+        }])
+    })
+}
+
+/// Fetches the URL with the credentials of its host, None when it's not
+/// found.
+async fn fetch(url: &str, dir: &Path) -> Result<Option<String>, String> {
+    let request = auth::authorize(http::client().get(url), url, Some(dir));
+
+    let response = http::send(request)
+        .await
+        .map_err(|error| error.to_string())?;
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(format!("{} refused the credentials, check auth.json", url))
+        }
+        status if !status.is_success() => Err(format!("{} responded with {}", url, status)),
+        _ => response
+            .text()
+            .await
+            .map(Some)
+            .map_err(|error| error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::json;
+    use crate::repository::{parse, parse_packages, path_versions, resolve_url, Repository};
+
+    #[test]
+    fn it_can_parse_the_repositories() {
+        let text = r#"{
+            "repositories": [
+                { "type": "composer", "url": "https://repo.acme.com" },
+                { "type": "vcs", "url": "https://github.com/acme/fork" },
+                { "type": "path", "url": "../packages/*" },
+                { "type": "package", "package": {} },
+                { "packagist.org": false }
+            ]
+        }"#;
+        let (root, _) = json::parse_tolerant(text).unwrap();
+        let repositories = parse(&root, Path::new("/app"));
+
+        assert_eq!(
+            vec![
+                Repository::Composer {
+                    url: "https://repo.acme.com".to_string()
+                },
+                Repository::Vcs {
+                    url: "https://github.com/acme/fork".to_string()
+                },
+                Repository::Path {
+                    path: PathBuf::from("/app/../packages/*")
+                },
+            ],
+            repositories.entries
+        );
+        assert!(repositories.packagist_disabled);
+    }
+
+    #[test]
+    fn it_can_parse_repositories_keyed_by_name() {
+        let text = r#"{
+            "repositories": {
+                "acme": { "type": "composer", "url": "https://repo.acme.com" },
+                "packagist.org": false
+            }
+        }"#;
+        let (root, _) = json::parse_tolerant(text).unwrap();
+        let repositories = parse(&root, Path::new("/app"));
+
+        assert_eq!(1, repositories.entries.len());
+        assert!(repositories.packagist_disabled);
+    }
+
+    #[test]
+    fn it_can_resolve_the_metadata_url() {
+        assert_eq!(
+            "https://repo.acme.com/p2/%package%.json",
+            resolve_url("https://repo.acme.com/packages.json", "/p2/%package%.json")
+        );
+        assert_eq!(
+            "https://repo.acme.com/satis/include/all$1.json",
+            resolve_url(
+                "https://repo.acme.com/satis/packages.json",
+                "include/all$1.json"
+            )
+        );
+    }
+
+    #[test]
+    fn it_can_parse_composer_1_packages() {
+        let contents = json!({
+            "packages": {
+                "acme/lib": {
+                    "1.2.0": { "name": "acme/lib", "version": "1.2.0" },
+                    "1.10.0": { "name": "acme/lib", "version": "1.10.0" },
+                    "dev-main": { "name": "acme/lib", "version": "dev-main" }
+                }
+            }
+        });
+        let packages = parse_packages(&contents);
+        let versions: Vec<Option<String>> = packages["acme/lib"]
+            .iter()
+            .map(|version| version.version.clone())
+            .collect();
+
+        assert_eq!(
+            vec![
+                Some("1.10.0".to_string()),
+                Some("1.2.0".to_string()),
+                Some("dev-main".to_string())
+            ],
+            versions
+        );
+    }
+
+    #[test]
+    fn it_can_find_a_package_of_a_path_repository() {
+        let dir = std::env::temp_dir().join("composer_lsp_path_repository");
+        let package_dir = dir.join("lib");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            package_dir.join("composer.json"),
+            r#"{"name": "acme/lib", "description": "A library"}"#,
+        )
+        .unwrap();
+
+        let versions = path_versions(&dir.join("*"), "acme/lib").unwrap();
+        assert_eq!(Some("dev-main".to_string()), versions[0].version);
+        assert!(path_versions(&package_dir, "acme/other").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,451 @@
+use std::io;
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::config::CommandSettings;
+use crate::global;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tower_lsp::lsp_types::notification::Progress as ProgressNotification;
+use tower_lsp::lsp_types::request::WorkDoneProgressCreate;
+use tower_lsp::lsp_types::*;
+use tower_lsp::Client;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// A window/workDoneProgress shown in the client while a task is running.
+pub struct Progress {
+    client: Client,
+    token: NumberOrString,
+    created: bool,
+    ended: bool,
+}
+
+impl Progress {
+    pub async fn begin(client: &Client, title: &str) -> Progress {
+        let token = NumberOrString::String(format!(
+            "composer_lsp/{}",
+            NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        // Clients that don't support server initiated progress reject the token.
+        let created = client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .is_ok();
+
+        let progress = Progress {
+            client: client.clone(),
+            token,
+            created,
+            ended: false,
+        };
+
+        progress
+            .notify(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_string(),
+                cancellable: Some(false),
+                message: None,
+                percentage: None,
+            }))
+            .await;
+
+        progress
+    }
+
+    pub async fn report(&self, message: String, percentage: Option<u32>) {
+        self.notify(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(false),
+            message: Some(message),
+            percentage,
+        }))
+        .await;
+    }
+
+    pub async fn end(mut self, message: Option<String>) {
+        self.notify(WorkDoneProgress::End(WorkDoneProgressEnd { message }))
+            .await;
+        self.ended = true;
+    }
+
+    async fn notify(&self, progress: WorkDoneProgress) {
+        if !self.created {
+            return;
+        }
+
+        self.client
+            .send_notification::<ProgressNotification>(ProgressParams {
+                token: self.token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+            .await;
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        // A cancelled request drops its progress without ending it.
+        if !self.created || self.ended {
+            return;
+        }
+
+        let client = self.client.clone();
+        let token = self.token.clone();
+        tokio::spawn(async move {
+            client
+                .send_notification::<ProgressNotification>(ProgressParams {
+                    token,
+                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(
+                        WorkDoneProgressEnd {
+                            message: Some("Cancelled.".to_string()),
+                        },
+                    )),
+                })
+                .await;
+        });
+    }
+}
+
+/// Variables that are always passed to composer, as it can't run without
+/// them.
+#[cfg(not(windows))]
+const REQUIRED_VARIABLES: &[&str] = &["PATH", "HOME", "COMPOSER_HOME"];
+
+/// Variables that are always passed to composer, as it can't run without
+/// them. Windows also needs the ones to find executables, the shell and the
+/// user's profile.
+#[cfg(windows)]
+const REQUIRED_VARIABLES: &[&str] = &[
+    "PATH",
+    "HOME",
+    "COMPOSER_HOME",
+    "PATHEXT",
+    "COMSPEC",
+    "SYSTEMROOT",
+    "APPDATA",
+    "LOCALAPPDATA",
+];
+
+/// The environment composer runs in: only the required and allowed
+/// variables, and never interactive, so prompts from plugins can't hang it.
+/// Names are matched case-insensitively, like Windows does, e.g. "Path".
+pub fn composer_env(
+    settings: &CommandSettings,
+    variables: impl Iterator<Item = (String, String)>,
+    composer_home: Option<String>,
+) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = variables
+        .filter(|(name, _)| {
+            let allowed = |allowed: &str| allowed.eq_ignore_ascii_case(name);
+            REQUIRED_VARIABLES.iter().any(|required| allowed(required))
+                || settings
+                    .env_passthrough
+                    .iter()
+                    .any(|passed| allowed(passed))
+        })
+        .collect();
+
+    // Composer resolves the same home as the server, e.g. for global commands.
+    if let Some(composer_home) = composer_home {
+        env.retain(|(name, _)| !name.eq_ignore_ascii_case("COMPOSER_HOME"));
+        env.push(("COMPOSER_HOME".to_string(), composer_home));
+    }
+
+    env.push(("COMPOSER_NO_INTERACTION".to_string(), "1".to_string()));
+    if let Some(memory_limit) = &settings.memory_limit {
+        env.push(("COMPOSER_MEMORY_LIMIT".to_string(), memory_limit.clone()));
+    }
+
+    env
+}
+
+/// Runs composer with the given arguments in the working directory, without
+/// blocking the runtime, while reporting progress to the client and logging
+/// composer's output as it arrives. Composer and everything it spawned is
+/// killed when it doesn't finish within the timeout, or when the request
+/// running it is cancelled.
+pub async fn run_composer(
+    client: &Client,
+    title: &str,
+    working_dir: &str,
+    args: &[&str],
+    settings: &CommandSettings,
+) -> io::Result<Output> {
+    run(client, title, working_dir, args, settings, false).await
+}
+
+/// Runs composer like `run_composer`, but also logs what it writes to
+/// stdout as it arrives, where scripts report what they do.
+pub async fn run_script(
+    client: &Client,
+    title: &str,
+    working_dir: &str,
+    args: &[&str],
+    settings: &CommandSettings,
+) -> io::Result<Output> {
+    run(client, title, working_dir, args, settings, true).await
+}
+
+async fn run(
+    client: &Client,
+    title: &str,
+    working_dir: &str,
+    args: &[&str],
+    settings: &CommandSettings,
+    stream_stdout: bool,
+) -> io::Result<Output> {
+    let progress = Progress::begin(client, title).await;
+    let timeout = settings.timeout();
+
+    let mut command = std::process::Command::new(&settings.composer_path);
+    command
+        .arg(format!("--working-dir={}", working_dir))
+        .arg("--no-interaction")
+        .args(args)
+        .env_clear()
+        .envs(composer_env(
+            settings,
+            std::env::vars(),
+            global::home_dir().map(|dir| dir.display().to_string()),
+        ));
+
+    // Composer reports what it's doing on stderr.
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    let forward = async {
+        while let Some(line) = receiver.recv().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            client.log_message(MessageType::LOG, &line).await;
+            progress.report(line.trim().to_string(), None).await;
+        }
+    };
+    let (output, _) = tokio::join!(
+        run_with_timeout(command, timeout, Some(sender), stream_stdout),
+        forward
+    );
+
+    let output = match output {
+        Err(error) if error.kind() == io::ErrorKind::TimedOut => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("{} timed out after {} seconds.", title, timeout.as_secs()),
+        )),
+        output => output,
+    };
+
+    let message = match &output {
+        Ok(output) if output.status.success() => "Done.".to_string(),
+        Err(error) if error.kind() == io::ErrorKind::TimedOut => error.to_string(),
+        _ => "Failed.".to_string(),
+    };
+    progress.end(Some(message)).await;
+
+    output
+}
+
+/// Runs the command, sending every line it writes to stderr, and to stdout
+/// if asked to, to the sender.
+async fn run_with_timeout(
+    mut command: std::process::Command,
+    timeout: Duration,
+    lines: Option<UnboundedSender<String>>,
+    stream_stdout: bool,
+) -> io::Result<Output> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // Run in a new process group, so the processes composer starts, like git
+    // or unzip, can be killed along with it.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let mut child = Command::from(command).kill_on_drop(true).spawn()?;
+    // Until the command finished, dropping this future, on a timeout or a
+    // cancelled request, kills its process group.
+    let mut group = ProcessGroup(child.id());
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let run = async {
+        let stdout_lines = lines.as_ref().filter(|_| stream_stdout);
+        let (stdout, stderr) = tokio::try_join!(
+            read_lines(stdout, stdout_lines),
+            read_lines(stderr, lines.as_ref())
+        )?;
+        let status = child.wait().await?;
+
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    };
+
+    match tokio::time::timeout(timeout, run).await {
+        Ok(output) => {
+            group.0 = None;
+            output
+        }
+        Err(_) => Err(io::Error::from(io::ErrorKind::TimedOut)),
+    }
+}
+
+/// Reads the stream, sending every line as it arrives.
+async fn read_lines(
+    stream: Option<impl AsyncRead + Unpin>,
+    lines: Option<&UnboundedSender<String>>,
+) -> io::Result<Vec<u8>> {
+    let mut output = vec![];
+    let mut reader = match stream {
+        Some(stream) => BufReader::new(stream),
+        None => return Ok(output),
+    };
+
+    let mut line = vec![];
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        if let Some(lines) = lines {
+            let _ = lines.send(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        output.append(&mut line);
+    }
+
+    Ok(output)
+}
+
+/// The process group of a running command, killed when dropped.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            kill_process_group(pid);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    let result = std::process::Command::new("kill")
+        .arg("-KILL")
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    if let Err(error) = result {
+        log::warn!("Can't kill the process group {}: {}", pid, error);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: u32) {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use tokio::sync::mpsc;
+
+    use crate::config::CommandSettings;
+    use crate::runner::{composer_env, run_with_timeout};
+
+    #[test]
+    fn it_can_build_the_composer_environment() {
+        let settings = CommandSettings {
+            memory_limit: Some("2G".to_string()),
+            env_passthrough: vec!["HTTPS_PROXY".to_string()],
+            ..Default::default()
+        };
+        let variables = vec![
+            ("Path".to_string(), "/usr/bin".to_string()),
+            ("https_proxy".to_string(), "http://proxy:3128".to_string()),
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "secret".to_string()),
+            ("Composer_Home".to_string(), "/tmp/old".to_string()),
+        ];
+        let env = composer_env(
+            &settings,
+            variables.into_iter(),
+            Some("/home/acme/.config/composer".to_string()),
+        );
+
+        assert_eq!(
+            vec![
+                ("Path".to_string(), "/usr/bin".to_string()),
+                ("https_proxy".to_string(), "http://proxy:3128".to_string()),
+                (
+                    "COMPOSER_HOME".to_string(),
+                    "/home/acme/.config/composer".to_string()
+                ),
+                ("COMPOSER_NO_INTERACTION".to_string(), "1".to_string()),
+                ("COMPOSER_MEMORY_LIMIT".to_string(), "2G".to_string()),
+            ],
+            env
+        );
+    }
+
+    #[tokio::test]
+    async fn it_kills_a_command_that_times_out() {
+        let mut command = std::process::Command::new("sleep");
+        command.arg("5");
+
+        let started = Instant::now();
+        let result = run_with_timeout(command, Duration::from_millis(50), None, false).await;
+
+        assert_eq!(std::io::ErrorKind::TimedOut, result.unwrap_err().kind());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn it_returns_the_output_of_a_finished_command() {
+        let mut command = std::process::Command::new("echo");
+        command.arg("done");
+
+        let output = run_with_timeout(command, Duration::from_secs(5), None, false)
+            .await
+            .unwrap();
+
+        assert_eq!("done\n", String::from_utf8_lossy(&output.stdout));
+    }
+
+    #[tokio::test]
+    async fn it_streams_the_stderr_lines() {
+        let mut command = std::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg("echo Loading >&2; echo Installing >&2");
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let output = run_with_timeout(command, Duration::from_secs(5), Some(sender), false)
+            .await
+            .unwrap();
+
+        assert_eq!(Some("Loading".to_string()), receiver.recv().await);
+        assert_eq!(Some("Installing".to_string()), receiver.recv().await);
+        assert_eq!(None, receiver.recv().await);
+        assert_eq!(
+            "Loading\nInstalling\n",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[tokio::test]
+    async fn it_can_stream_the_stdout_lines() {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg("echo Running tests");
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let output = run_with_timeout(command, Duration::from_secs(5), Some(sender), true)
+            .await
+            .unwrap();
+
+        assert_eq!(Some("Running tests".to_string()), receiver.recv().await);
+        assert_eq!("Running tests\n", String::from_utf8_lossy(&output.stdout));
+    }
+}
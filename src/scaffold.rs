@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use crate::json::{self, NodeKind};
+
+/// Whether the composer.json has no content worth keeping, i.e. it's empty
+/// or an empty object.
+pub fn is_skeletal(text: &str) -> bool {
+    if text.trim().is_empty() {
+        return true;
+    }
+
+    match json::parse_tolerant(text) {
+        Ok((root, _)) => matches!(root.kind, NodeKind::Object(ref members) if members.is_empty()),
+        Err(_) => false,
+    }
+}
+
+/// The package name for a project in the given directory, e.g. "acme/my-app".
+pub fn package_name(vendor: &str, dir: &Path) -> String {
+    let project = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    format!("{}/{}", slug(vendor), slug(&project))
+}
+
+/// The PSR-4 namespace derived from a package name, e.g. "Acme\MyApp\".
+pub fn namespace(package_name: &str) -> String {
+    let mut namespace = String::new();
+    for part in package_name.split('/') {
+        for word in part.split(|c: char| !c.is_ascii_alphanumeric()) {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                namespace.push(first.to_ascii_uppercase());
+                namespace.push_str(chars.as_str());
+            }
+        }
+
+        namespace.push('\\');
+    }
+
+    namespace
+}
+
+/// A composer.json scaffold for a new project in the given directory.
+pub fn composer_json(vendor: &str, dir: &Path) -> String {
+    let name = package_name(vendor, dir);
+    let namespace = namespace(&name).replace('\\', "\\\\");
+
+    format!(
+        r#"{{
+    "name": "{}",
+    "description": "",
+    "type": "project",
+    "license": "MIT",
+    "autoload": {{
+        "psr-4": {{
+            "{}": "src/"
+        }}
+    }},
+    "require": {{}}
+}}
+"#,
+        name, namespace
+    )
+}
+
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '-',
+        })
+        .collect();
+
+    match slug.trim_matches('-') {
+        "" => "project".to_string(),
+        slug => slug.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::scaffold::{composer_json, is_skeletal, namespace, package_name};
+
+    #[test]
+    fn it_can_detect_skeletal_files() {
+        assert!(is_skeletal(""));
+        assert!(is_skeletal("{\n}\n"));
+        assert!(!is_skeletal("{ \"name\": \"acme/app\" }"));
+        assert!(!is_skeletal("{ \"name\": "));
+    }
+
+    #[test]
+    fn it_can_derive_the_name_and_namespace_from_the_folder() {
+        let name = package_name("Acme", Path::new("/home/acme/My App"));
+
+        assert_eq!("acme/my-app", name);
+        assert_eq!("Acme\\MyApp\\", namespace(&name));
+    }
+
+    #[test]
+    fn it_can_generate_a_valid_scaffold() {
+        let text = composer_json("acme", Path::new("/home/acme/app"));
+        let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+
+        assert_eq!("acme/app", parsed["name"]);
+        assert_eq!("src/", parsed["autoload"]["psr-4"]["Acme\\App\\"]);
+    }
+}
@@ -0,0 +1,1084 @@
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+use crate::completion;
+use crate::json::{Member, Node, NodeKind, Span};
+use crate::stability::STABILITIES;
+
+/// The JSON types a property of composer.json can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    String,
+    Bool,
+    Number,
+    Object,
+    Array,
+}
+
+impl Type {
+    fn of(node: &Node) -> Option<Type> {
+        match node.kind {
+            NodeKind::String(_) => Some(Type::String),
+            NodeKind::Bool(_) => Some(Type::Bool),
+            NodeKind::Number(_) => Some(Type::Number),
+            NodeKind::Object(_) => Some(Type::Object),
+            NodeKind::Array(_) => Some(Type::Array),
+            NodeKind::Null => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::String => "a string",
+            Type::Bool => "a boolean",
+            Type::Number => "a number",
+            Type::Object => "an object",
+            Type::Array => "an array",
+        }
+    }
+}
+
+/// A property of composer.json, from the composer schema.
+#[derive(Debug, Clone, Copy)]
+pub struct Property {
+    pub name: &'static str,
+    /// The types the value may have, any type when empty.
+    pub types: &'static [Type],
+    pub description: &'static str,
+    /// The values composer accepts, or suggests unless they are strict.
+    pub values: &'static [&'static str],
+    pub strict_values: bool,
+    /// The known keys of an object value.
+    pub properties: &'static [Property],
+    /// Whether other keys than the known ones are mistakes.
+    pub closed: bool,
+}
+
+impl Property {
+    const EMPTY: Property = Property {
+        name: "",
+        types: &[],
+        description: "",
+        values: &[],
+        strict_values: false,
+        properties: &[],
+        closed: false,
+    };
+
+    /// The snippet completing the value after the key.
+    pub fn value_snippet(&self) -> &'static str {
+        match self.types.first() {
+            Some(Type::Object) => "{$1}",
+            Some(Type::Array) => "[$1]",
+            Some(Type::Bool) => "${1:true}",
+            Some(Type::Number) => "${1:0}",
+            Some(Type::String) | None => "\"$1\"",
+        }
+    }
+}
+
+const STRING: &[Type] = &[Type::String];
+const BOOL: &[Type] = &[Type::Bool];
+const OBJECT: &[Type] = &[Type::Object];
+const ARRAY: &[Type] = &[Type::Array];
+
+/// The most used license identifiers of packagist.
+pub const LICENSES: &[&str] = &[
+    "MIT",
+    "Apache-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "MPL-2.0",
+    "ISC",
+    "EPL-2.0",
+    "CC0-1.0",
+    "Unlicense",
+    "proprietary",
+];
+
+const SCRIPT: &[Type] = &[Type::String, Type::Array];
+
+/// The events composer runs the scripts of the root package on.
+const SCRIPTS: &[Property] = &[
+    Property {
+        name: "pre-install-cmd",
+        types: SCRIPT,
+        description: "Runs before `composer install` with a lock file.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-install-cmd",
+        types: SCRIPT,
+        description: "Runs after `composer install` with a lock file.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-update-cmd",
+        types: SCRIPT,
+        description: "Runs before `composer update`, or install without a lock file.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-update-cmd",
+        types: SCRIPT,
+        description: "Runs after `composer update`, or install without a lock file.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-status-cmd",
+        types: SCRIPT,
+        description: "Runs before `composer status`.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-status-cmd",
+        types: SCRIPT,
+        description: "Runs after `composer status`.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-archive-cmd",
+        types: SCRIPT,
+        description: "Runs before `composer archive`.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-archive-cmd",
+        types: SCRIPT,
+        description: "Runs after `composer archive`.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-autoload-dump",
+        types: SCRIPT,
+        description: "Runs before the autoloader is dumped, on install, update and dump-autoload.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-autoload-dump",
+        types: SCRIPT,
+        description: "Runs after the autoloader is dumped, on install, update and dump-autoload.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-root-package-install",
+        types: SCRIPT,
+        description: "Runs after the root package was installed by `composer create-project`.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-create-project-cmd",
+        types: SCRIPT,
+        description: "Runs after `composer create-project`.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-operations-exec",
+        types: SCRIPT,
+        description: "Runs before the packages are installed, updated or removed.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-package-install",
+        types: SCRIPT,
+        description: "Runs before a package is installed.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-package-install",
+        types: SCRIPT,
+        description: "Runs after a package was installed.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-package-update",
+        types: SCRIPT,
+        description: "Runs before a package is updated.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-package-update",
+        types: SCRIPT,
+        description: "Runs after a package was updated.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "pre-package-uninstall",
+        types: SCRIPT,
+        description: "Runs before a package is uninstalled.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "post-package-uninstall",
+        types: SCRIPT,
+        description: "Runs after a package was uninstalled.",
+        ..Property::EMPTY
+    },
+];
+
+const AUTOLOAD: &[Property] = &[
+    Property {
+        name: "psr-4",
+        types: OBJECT,
+        description: "Namespace prefixes mapped to the directories of their classes.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "psr-0",
+        types: OBJECT,
+        description: "Namespaces mapped to directories, the legacy PSR-0 standard.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "classmap",
+        types: ARRAY,
+        description: "Directories and files scanned for classes.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "files",
+        types: ARRAY,
+        description: "Files included on every request.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "exclude-from-classmap",
+        types: ARRAY,
+        description: "Paths left out of the classmap.",
+        ..Property::EMPTY
+    },
+];
+
+const SUPPORT: &[Property] = &[
+    Property {
+        name: "email",
+        types: STRING,
+        description: "Email address for support.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "issues",
+        types: STRING,
+        description: "URL to the issue tracker.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "forum",
+        types: STRING,
+        description: "URL to the forum.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "wiki",
+        types: STRING,
+        description: "URL to the wiki.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "irc",
+        types: STRING,
+        description: "IRC channel for support, as irc://server/channel.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "source",
+        types: STRING,
+        description: "URL to browse or download the sources.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "docs",
+        types: STRING,
+        description: "URL to the documentation.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "rss",
+        types: STRING,
+        description: "URL to the RSS feed.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "chat",
+        types: STRING,
+        description: "URL to the chat channel.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "security",
+        types: STRING,
+        description: "URL to the vulnerability disclosure policy.",
+        ..Property::EMPTY
+    },
+];
+
+const ARCHIVE: &[Property] = &[
+    Property {
+        name: "name",
+        types: STRING,
+        description: "The base name of the archive.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "exclude",
+        types: ARRAY,
+        description: "Patterns of the paths left out of the archive.",
+        ..Property::EMPTY
+    },
+];
+
+const CONFIG: &[Property] = &[
+    Property {
+        name: "platform",
+        types: OBJECT,
+        description: "Platform packages to fake, like the php version of production.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "allow-plugins",
+        types: &[Type::Object, Type::Bool],
+        description: "The plugins allowed to run code during composer commands.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "sort-packages",
+        types: BOOL,
+        description: "Keep the required packages sorted by name.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "optimize-autoloader",
+        types: BOOL,
+        description: "Always optimize the autoloader when dumping it.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "classmap-authoritative",
+        types: BOOL,
+        description: "Only load classes from the classmap.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "apcu-autoloader",
+        types: BOOL,
+        description: "Cache found and missing classes in APCu.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "autoloader-suffix",
+        types: STRING,
+        description: "The suffix of the generated autoloader class.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "preferred-install",
+        types: &[Type::String, Type::Object],
+        description: "Install packages from dist or source by default.",
+        values: &["dist", "source", "auto"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "platform-check",
+        types: &[Type::Bool, Type::String],
+        description: "Check the platform requirements when the autoloader is loaded.",
+        values: &["php-only"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "process-timeout",
+        types: &[Type::Number],
+        description: "Seconds after which processes like git clones are killed.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "vendor-dir",
+        types: STRING,
+        description: "The directory packages are installed in.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "bin-dir",
+        types: STRING,
+        description: "The directory binaries are linked in.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "bin-compat",
+        types: STRING,
+        description: "How binaries are linked.",
+        values: &["auto", "full", "proxy", "symlink"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "cache-dir",
+        types: STRING,
+        description: "The directory of all caches.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "data-dir",
+        types: STRING,
+        description: "The directory of data like the past composer versions.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "cache-files-ttl",
+        types: &[Type::Number],
+        description: "Seconds cached dist files are kept.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "secure-http",
+        types: BOOL,
+        description: "Only allow https and ssh urls.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "disable-tls",
+        types: BOOL,
+        description: "Fetch packagist over http instead of https.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "cafile",
+        types: STRING,
+        description: "The certificate authority file to verify https with.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "capath",
+        types: STRING,
+        description: "The directory of certificate authorities.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "github-protocols",
+        types: ARRAY,
+        description: "The protocols to clone github repositories with.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "github-domains",
+        types: ARRAY,
+        description: "Domains of GitHub Enterprise installations.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "gitlab-domains",
+        types: ARRAY,
+        description: "Domains of GitLab installations.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "use-github-api",
+        types: BOOL,
+        description: "Use the GitHub API instead of cloning github repositories.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "github-oauth",
+        types: OBJECT,
+        description: "OAuth tokens by GitHub domain, better kept in auth.json.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "gitlab-oauth",
+        types: OBJECT,
+        description: "OAuth tokens by GitLab domain, better kept in auth.json.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "gitlab-token",
+        types: OBJECT,
+        description: "Private tokens by GitLab domain, better kept in auth.json.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "bitbucket-oauth",
+        types: OBJECT,
+        description: "OAuth consumers by Bitbucket domain, better kept in auth.json.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "http-basic",
+        types: OBJECT,
+        description: "Credentials by domain, better kept in auth.json.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "bearer",
+        types: OBJECT,
+        description: "Bearer tokens by domain, better kept in auth.json.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "store-auths",
+        types: &[Type::Bool, Type::String],
+        description: "Whether to store the credentials composer asks for.",
+        values: &["prompt"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "discard-changes",
+        types: &[Type::Bool, Type::String],
+        description: "What to do with local changes of source installs on update.",
+        values: &["stash"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "lock",
+        types: BOOL,
+        description: "Write composer.lock.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "notify-on-install",
+        types: BOOL,
+        description: "Notify repositories about installs.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "prepend-autoloader",
+        types: BOOL,
+        description: "Prepend the autoloader to the existing ones.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "use-include-path",
+        types: BOOL,
+        description: "Look for classes in the PHP include path.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "htaccess-protect",
+        types: BOOL,
+        description: "Create .htaccess files in the composer directories.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "archive-format",
+        types: STRING,
+        description: "The default format of composer archive.",
+        values: &["zip", "tar", "tar.gz", "tar.bz2"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "archive-dir",
+        types: STRING,
+        description: "The default directory of composer archive.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "audit",
+        types: OBJECT,
+        description: "Options of composer audit, like ignored advisories.",
+        ..Property::EMPTY
+    },
+];
+
+/// The top-level properties of composer.json.
+pub const ROOT: &[Property] = &[
+    Property {
+        name: "$schema",
+        types: STRING,
+        description: "The JSON schema of the file.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "name",
+        types: STRING,
+        description: "The package name, as vendor/package.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "description",
+        types: STRING,
+        description: "A short description of the package.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "version",
+        types: STRING,
+        description: "The version, better inferred from the VCS tags.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "type",
+        types: STRING,
+        description: "The package type, library by default.",
+        values: &["library", "project", "metapackage", "composer-plugin"],
+        ..Property::EMPTY
+    },
+    Property {
+        name: "keywords",
+        types: ARRAY,
+        description: "Keywords to find the package with.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "homepage",
+        types: STRING,
+        description: "URL of the project website.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "readme",
+        types: STRING,
+        description: "Path to the readme.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "time",
+        types: STRING,
+        description: "The release date, as YYYY-MM-DD or YYYY-MM-DD HH:MM:SS.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "license",
+        types: &[Type::String, Type::Array],
+        description: "The SPDX license identifier, or a list of them.",
+        values: LICENSES,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "authors",
+        types: ARRAY,
+        description: "The authors of the package.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "support",
+        types: OBJECT,
+        description: "Where to get support.",
+        properties: SUPPORT,
+        closed: true,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "funding",
+        types: ARRAY,
+        description: "Ways to fund the maintenance.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "require",
+        types: OBJECT,
+        description: "The packages required in production.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "require-dev",
+        types: OBJECT,
+        description: "The packages only required for development.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "conflict",
+        types: OBJECT,
+        description: "Package versions that can't be installed with this package.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "replace",
+        types: OBJECT,
+        description: "Packages this package replaces.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "provide",
+        types: OBJECT,
+        description: "Packages, or virtual packages, this package implements.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "suggest",
+        types: OBJECT,
+        description: "Packages that work well with this package, with the reason.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "autoload",
+        types: OBJECT,
+        description: "How the classes and files of the package are autoloaded.",
+        properties: AUTOLOAD,
+        closed: true,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "autoload-dev",
+        types: OBJECT,
+        description: "The autoloading only needed for development, like of tests.",
+        properties: AUTOLOAD,
+        closed: true,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "include-path",
+        types: ARRAY,
+        description: "Paths appended to the PHP include path, for legacy code.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "target-dir",
+        types: STRING,
+        description: "Deprecated installation target for PSR-0 packages.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "minimum-stability",
+        types: STRING,
+        description: "The lowest stability of the installed packages, stable by default.",
+        values: STABILITIES,
+        strict_values: true,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "prefer-stable",
+        types: BOOL,
+        description: "Prefer stable versions, when the minimum stability allows others.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "repositories",
+        description: "Package repositories besides packagist.org.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "config",
+        types: OBJECT,
+        description: "Options of composer for this project.",
+        properties: CONFIG,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "scripts",
+        types: OBJECT,
+        description: "Commands run by composer events or composer run-script.",
+        properties: SCRIPTS,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "scripts-descriptions",
+        types: OBJECT,
+        description: "Descriptions of the custom scripts.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "scripts-aliases",
+        types: OBJECT,
+        description: "Aliases of the custom scripts.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "extra",
+        types: &[Type::Object, Type::Array],
+        description: "Data for plugins and scripts.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "bin",
+        types: &[Type::String, Type::Array],
+        description: "Files linked into the bin directory of projects.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "archive",
+        types: OBJECT,
+        description: "Options of composer archive.",
+        properties: ARCHIVE,
+        closed: true,
+        ..Property::EMPTY
+    },
+    Property {
+        name: "abandoned",
+        types: &[Type::Bool, Type::String],
+        description: "Marks the package as abandoned, optionally with its replacement.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "non-feature-branches",
+        types: ARRAY,
+        description: "Branch patterns that are no feature branches.",
+        ..Property::EMPTY
+    },
+    Property {
+        name: "default-branch",
+        types: BOOL,
+        description: "Marks the branch as the default one of a root package.",
+        ..Property::EMPTY
+    },
+];
+
+/// The known keys of the object at the path, e.g. ["config"], or None when
+/// composer allows any key there, like in "require".
+pub fn properties(path: &[String]) -> Option<&'static [Property]> {
+    let mut properties = ROOT;
+    for key in path {
+        let property = properties.iter().find(|property| property.name == key)?;
+        properties = property.properties;
+    }
+
+    match properties.is_empty() {
+        true => None,
+        false => Some(properties),
+    }
+}
+
+/// The property of a key of the object at the path.
+pub fn property(path: &[String], key: &str) -> Option<&'static Property> {
+    properties(path)?
+        .iter()
+        .find(|property| property.name == key)
+}
+
+/// A value or key that doesn't fit the composer schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaProblem {
+    pub span: Span,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// Checks the keys and value types of composer.json against the schema.
+pub fn validate(root: &Node) -> Vec<SchemaProblem> {
+    let mut problems = vec![];
+    match root.as_object() {
+        Some(members) => validate_members(members, ROOT, true, None, &mut problems),
+        None => problems.push(SchemaProblem {
+            span: root.span,
+            severity: DiagnosticSeverity::ERROR,
+            message: "composer.json must contain an object.".to_string(),
+        }),
+    }
+
+    problems
+}
+
+fn validate_members(
+    members: &[Member],
+    properties: &[Property],
+    closed: bool,
+    parent: Option<&str>,
+    problems: &mut Vec<SchemaProblem>,
+) {
+    let location = match parent {
+        Some(parent) => format!("in \"{}\"", parent),
+        None => "in composer.json".to_string(),
+    };
+
+    for member in members {
+        let property = match properties
+            .iter()
+            .find(|property| property.name == member.key)
+        {
+            Some(property) => property,
+            None => {
+                if closed {
+                    problems.push(SchemaProblem {
+                        span: member.key_span,
+                        severity: DiagnosticSeverity::WARNING,
+                        message: format!(
+                            "Unknown key \"{}\" {}, composer ignores it.",
+                            member.key, location
+                        ),
+                    });
+                }
+                continue;
+            }
+        };
+
+        let value_type = Type::of(&member.value);
+        let has_valid_type = property.types.is_empty()
+            || value_type.is_some_and(|value_type| property.types.contains(&value_type));
+        if !has_valid_type {
+            let expected: Vec<&str> = property.types.iter().map(|types| types.name()).collect();
+            problems.push(SchemaProblem {
+                span: member.value.span,
+                severity: DiagnosticSeverity::ERROR,
+                message: format!("\"{}\" must be {}.", member.key, expected.join(" or ")),
+            });
+            continue;
+        }
+
+        if let Some(value) = member.value.as_str().filter(|_| property.strict_values) {
+            if !property
+                .values
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(value))
+            {
+                problems.push(SchemaProblem {
+                    span: member.value.span,
+                    severity: DiagnosticSeverity::ERROR,
+                    message: format!(
+                        "\"{}\" must be one of {}.",
+                        member.key,
+                        property.values.join(", ")
+                    ),
+                });
+            }
+        }
+
+        if let Some(members) = member
+            .value
+            .as_object()
+            .filter(|_| !property.properties.is_empty())
+        {
+            validate_members(
+                members,
+                property.properties,
+                property.closed,
+                Some(property.name),
+                problems,
+            );
+        }
+    }
+}
+
+/// The keys of the objects enclosing the byte offset, from the outermost
+/// one, e.g. ["config", "platform"]. None when the offset is in an array or
+/// outside of the document. The text is scanned instead of parsed, like in
+/// completion::is_in_object, because it's usually incomplete while typing.
+pub fn object_path_at(text: &str, offset: usize) -> Option<Vec<String>> {
+    let mut path = vec![];
+    let mut end = offset.min(text.len());
+
+    loop {
+        let before = &text[..end];
+        let mut depth = 0;
+        let mut open = None;
+        for (index, c) in before.char_indices().rev() {
+            match c {
+                ']' | '}' => depth += 1,
+                '[' if depth == 0 => return None,
+                '{' if depth == 0 => {
+                    open = Some(index);
+                    break;
+                }
+                '[' | '{' => depth -= 1,
+                _ => continue,
+            }
+        }
+
+        let before_brace = before[..open?].trim_end();
+        if before_brace.is_empty() {
+            path.reverse();
+            return Some(path);
+        }
+
+        // The object is the value of a key, find that key.
+        let before_colon = before_brace.strip_suffix(':')?.trim_end();
+        let key_end = before_colon.strip_suffix('"')?;
+        let key_start = key_end.rfind('"')?;
+        path.push(key_end[key_start + 1..].to_string());
+        end = key_start;
+    }
+}
+
+/// The key of the value the cursor is in, on a `"key": "value"` line.
+pub fn value_key_at(line_text: &str, character: u32) -> Option<String> {
+    let cursor = completion::character_to_byte(line_text, character);
+    let before_cursor = &line_text[..cursor];
+
+    // The cursor is inside the quotes of the value.
+    let value_start = before_cursor.rfind('"')?;
+    let before_colon = before_cursor[..value_start]
+        .trim_end()
+        .strip_suffix(':')?
+        .trim_end();
+    let key_end = before_colon.strip_suffix('"')?;
+    let key_start = key_end.rfind('"')?;
+
+    Some(key_end[key_start + 1..].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    use crate::json;
+    use crate::schema::{object_path_at, properties, property, validate, value_key_at};
+
+    #[test]
+    fn it_can_find_the_object_path_at_an_offset() {
+        let text = "{\n    \"config\": {\n        \"platform\": {\n            \"\n";
+
+        assert_eq!(
+            Some(vec!["config".to_string(), "platform".to_string()]),
+            object_path_at(text, text.len() - 1)
+        );
+        assert_eq!(Some(vec![]), object_path_at("{\n    \"na", 9));
+        assert_eq!(
+            Some(vec!["autoload".to_string()]),
+            object_path_at("{ \"name\": \"a/b\", \"autoload\": { \"ps", 34)
+        );
+        assert_eq!(None, object_path_at("{ \"keywords\": [\"", 16));
+    }
+
+    #[test]
+    fn it_can_look_up_the_known_keys() {
+        assert!(properties(&[])
+            .unwrap()
+            .iter()
+            .any(|p| p.name == "autoload"));
+        assert!(properties(&["autoload".to_string()])
+            .unwrap()
+            .iter()
+            .any(|p| p.name == "psr-4"));
+        assert!(properties(&["require".to_string()]).is_none());
+        assert_eq!(5, property(&[], "minimum-stability").unwrap().values.len());
+        assert_eq!(
+            Some("minimum-stability".to_string()),
+            value_key_at("    \"minimum-stability\": \"be", 29)
+        );
+        assert_eq!(None, value_key_at("    \"minimum-st", 15));
+    }
+
+    #[test]
+    fn it_can_validate_the_keys_and_types() {
+        let text = r#"{
+            "name": "acme/app",
+            "requires": {},
+            "prefer-stable": "yes",
+            "minimum-stability": "beta",
+            "autoload": { "psr4": {} },
+            "config": { "custom": 1, "sort-packages": 1 },
+            "repositories": [],
+            "scripts": { "test": "phpunit" }
+        }"#;
+        let problems = validate(&json::parse(text).unwrap());
+        let messages: Vec<&str> = problems
+            .iter()
+            .map(|problem| problem.message.as_str())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "Unknown key \"requires\" in composer.json, composer ignores it.",
+                "\"prefer-stable\" must be a boolean.",
+                "Unknown key \"psr4\" in \"autoload\", composer ignores it.",
+                "\"sort-packages\" must be a boolean.",
+            ],
+            messages
+        );
+        assert_eq!(DiagnosticSeverity::WARNING, problems[0].severity);
+        assert_eq!(
+            "\"requires\"",
+            &text[problems[0].span.start..problems[0].span.end]
+        );
+
+        let problems = validate(&json::parse(r#"{ "minimum-stability": "nightly" }"#).unwrap());
+        assert_eq!(
+            "\"minimum-stability\" must be one of dev, alpha, beta, RC, stable.",
+            problems[0].message
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::path::{Path, PathBuf};
+
+use crate::completion;
+use crate::json::{Node, NodeKind, Span};
+use crate::schema;
+
+/// The "@" prefixes composer handles itself, rather than referring to a
+/// script.
+const BUILTIN_REFERENCES: &[&str] = &["@php", "@composer", "@putenv"];
+
+/// A command of a script, which is either the script's value or one of the
+/// items of its list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptCommand {
+    pub span: Span,
+    pub script: String,
+    pub command: String,
+}
+
+/// All commands in "scripts".
+pub fn find_commands(root: &Node) -> Vec<ScriptCommand> {
+    let scripts = match root
+        .get("scripts")
+        .and_then(|scripts| scripts.value.as_object())
+    {
+        Some(scripts) => scripts,
+        None => return vec![],
+    };
+
+    let mut commands = vec![];
+    for script in scripts {
+        let values: Vec<&Node> = match &script.value.kind {
+            NodeKind::Array(items) => items.iter().collect(),
+            _ => vec![&script.value],
+        };
+
+        for value in values {
+            if let Some(command) = value.as_str() {
+                commands.push(ScriptCommand {
+                    span: value.span,
+                    script: script.key.clone(),
+                    command: command.to_string(),
+                });
+            }
+        }
+    }
+
+    commands
+}
+
+/// The names of the scripts, including the ones of events.
+pub fn script_names(root: &Node) -> Vec<String> {
+    match root
+        .get("scripts")
+        .and_then(|scripts| scripts.value.as_object())
+    {
+        Some(scripts) => scripts.iter().map(|script| script.key.clone()).collect(),
+        None => vec![],
+    }
+}
+
+/// The script whose key or commands contain the offset.
+pub fn script_member_at(root: &Node, offset: usize) -> Option<String> {
+    root.get("scripts")?
+        .value
+        .as_object()?
+        .iter()
+        .find(|script| script.span.contains(offset))
+        .map(|script| script.key.clone())
+}
+
+/// Whether the script runs on a composer event, like "post-install-cmd",
+/// rather than with `composer run-script`.
+pub fn is_event(name: &str) -> bool {
+    schema::property(&["scripts".to_string()], name).is_some()
+}
+
+/// The script a command refers to, e.g. "test" for "@test --filter=a".
+pub fn reference(command: &str) -> Option<&str> {
+    let reference = command.split_whitespace().next()?;
+    if BUILTIN_REFERENCES.contains(&reference) {
+        return None;
+    }
+
+    reference.strip_prefix('@').filter(|name| !name.is_empty())
+}
+
+/// The commands referring to a script that doesn't exist.
+pub fn find_undefined_references(root: &Node) -> Vec<ScriptCommand> {
+    let names = script_names(root);
+
+    find_commands(root)
+        .into_iter()
+        .filter(|command| match reference(&command.command) {
+            Some(name) => !names.iter().any(|script| script == name),
+            None => false,
+        })
+        .collect()
+}
+
+/// The script whose command the cursor is in, going by the text before it.
+/// The text is scanned instead of parsed, as it's usually incomplete while
+/// typing.
+pub fn script_at(text: &str, offset: usize) -> Option<String> {
+    let before = &text[..offset.min(text.len())];
+    let quote = before.rfind('"')?;
+
+    let mut depth = 0;
+    for (index, c) in before[..quote].char_indices().rev() {
+        let value_start = match c {
+            ']' | '}' => {
+                depth += 1;
+                continue;
+            }
+            // One of the commands of a list, `"name": ["@`.
+            '[' if depth == 0 => index,
+            // The only command, `"name": "@`.
+            '{' if depth == 0 => quote,
+            '[' | '{' => {
+                depth -= 1;
+                continue;
+            }
+            _ => continue,
+        };
+
+        let key = key_before(&before[..value_start])?;
+        return match schema::object_path_at(text, value_start)?.as_slice() {
+            [scripts] if scripts == "scripts" => Some(key),
+            _ => None,
+        };
+    }
+
+    None
+}
+
+/// The key of `"key":` at the end of the text.
+fn key_before(text: &str) -> Option<String> {
+    let key_end = text
+        .trim_end()
+        .strip_suffix(':')?
+        .trim_end()
+        .strip_suffix('"')?;
+    let key_start = key_end.rfind('"')?;
+
+    Some(key_end[key_start + 1..].to_string())
+}
+
+/// The typed script reference before the cursor, and the character it
+/// starts at, while it's the first word of the command.
+pub fn typed_reference(line_text: &str, character: u32) -> Option<(u32, String)> {
+    let cursor = completion::character_to_byte(line_text, character);
+    let before_cursor = &line_text[..cursor];
+    let start = before_cursor.rfind('"')? + 1;
+    let typed = &before_cursor[start..];
+
+    if typed.contains(char::is_whitespace) || !(typed.is_empty() || typed.starts_with('@')) {
+        return None;
+    }
+
+    Some((
+        completion::byte_to_character(line_text, start),
+        typed.to_string(),
+    ))
+}
+
+/// The references a command of the script can start with: the other
+/// scripts, and "@php", "@composer" and "@putenv".
+pub fn reference_completions(root: &Node, script: &str) -> Vec<String> {
+    let mut references: Vec<String> = BUILTIN_REFERENCES
+        .iter()
+        .map(|reference| reference.to_string())
+        .collect();
+    references.extend(
+        script_names(root)
+            .into_iter()
+            .filter(|name| name != script)
+            .map(|name| format!("@{}", name)),
+    );
+
+    references
+}
+
+/// The file a "@php" command runs, e.g. "bin/setup.php" for
+/// "@php bin/setup.php --force".
+pub fn php_script(command: &str) -> Option<&str> {
+    let arguments = command.strip_prefix("@php ")?;
+
+    arguments
+        .split_whitespace()
+        .next()
+        .filter(|argument| !argument.starts_with('-'))
+}
+
+/// The command with the "@php" and "@composer" prefixes expanded to the
+/// binaries that will run it. Other "@" prefixes refer to scripts.
+pub fn expand(command: &str, php: Option<&Path>, composer: Option<&Path>) -> String {
+    let binary = |path: Option<&Path>, name: &str| match path {
+        Some(path) => path.display().to_string(),
+        None => name.to_string(),
+    };
+
+    if let Some(arguments) = command.strip_prefix("@php ") {
+        return format!("{} {}", binary(php, "php"), arguments);
+    }
+
+    // Composer runs itself through the same php binary.
+    if let Some(arguments) = command.strip_prefix("@composer ") {
+        return format!(
+            "{} {} {}",
+            binary(php, "php"),
+            binary(composer, "composer"),
+            arguments
+        );
+    }
+
+    command.to_string()
+}
+
+/// Describes what the command runs, as markdown.
+pub fn command_hover(command: &str, php: Option<&Path>, composer: Option<&Path>) -> String {
+    if let Some(script) = command
+        .strip_prefix('@')
+        .filter(|_| !command.starts_with("@php ") && !command.starts_with("@composer "))
+    {
+        let name = script.split_whitespace().next().unwrap_or_default();
+        return format!("Runs the `{}` script.", name);
+    }
+
+    format!("```shell\n{}\n```", expand(command, php, composer))
+}
+
+/// The "@php" commands whose script doesn't exist in the project. Scripts in
+/// the vendor directory are skipped, as they only exist after an install.
+pub fn find_missing_php_scripts(
+    root: &Node,
+    dir: &Path,
+    vendor_dir: &Path,
+) -> Vec<(ScriptCommand, PathBuf)> {
+    let mut missing = vec![];
+    for command in find_commands(root) {
+        let script = match php_script(&command.command) {
+            Some(script) => dir.join(script),
+            None => continue,
+        };
+
+        if !script.starts_with(vendor_dir) && !script.exists() {
+            missing.push((command, script));
+        }
+    }
+
+    missing
+}
+
+/// Finds the binary on the PATH.
+pub fn find_binary(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::json;
+    use crate::scripts::{
+        command_hover, expand, find_commands, find_missing_php_scripts, find_undefined_references,
+        is_event, php_script, reference, reference_completions, script_at, script_member_at,
+        typed_reference,
+    };
+
+    #[test]
+    fn it_can_find_script_commands() {
+        let text = r#"{ "scripts": { "test": "@php vendor/bin/phpunit", "setup": ["@composer install", "@test"] } }"#;
+        let commands = find_commands(&json::parse(text).unwrap());
+
+        assert_eq!(3, commands.len());
+        assert_eq!("setup", commands[1].script);
+        assert_eq!(
+            "\"@composer install\"",
+            &text[commands[1].span.start..commands[1].span.end]
+        );
+    }
+
+    #[test]
+    fn it_can_find_undefined_script_references() {
+        let text = r#"{ "scripts": {
+            "test": "@php vendor/bin/phpunit",
+            "ci": ["@test --coverage", "@lint", "@putenv CI=1"],
+            "post-install-cmd": "@ci"
+        } }"#;
+        let root = json::parse(text).unwrap();
+
+        assert_eq!(Some("test"), reference("@test --coverage"));
+        assert_eq!(None, reference("@composer install"));
+        assert_eq!(None, reference("phpunit"));
+
+        let undefined = find_undefined_references(&root);
+        assert_eq!(1, undefined.len());
+        assert_eq!("@lint", undefined[0].command);
+        assert!(is_event("post-install-cmd"));
+        assert!(!is_event("test"));
+
+        let offset = text.find("@lint").unwrap();
+        assert_eq!(Some("ci".to_string()), script_member_at(&root, offset));
+        assert_eq!(None, script_member_at(&root, 0));
+    }
+
+    #[test]
+    fn it_can_complete_script_references() {
+        let text = "{\n    \"scripts\": {\n        \"test\": \"phpunit\",\n        \"ci\": [\"@t";
+        assert_eq!(Some("ci".to_string()), script_at(text, text.len()));
+        assert_eq!(
+            Some("ci".to_string()),
+            script_at("{ \"scripts\": { \"ci\": \"@", 25)
+        );
+        assert_eq!(None, script_at("{ \"scripts\": { \"c", 17));
+        assert_eq!(None, script_at("{ \"extra\": { \"ci\": \"@", 23));
+
+        let line = "        \"ci\": [\"@t";
+        assert_eq!(Some((16, "@t".to_string())), typed_reference(line, 18));
+        assert_eq!(None, typed_reference("        \"ci\": \"phpunit --", 26));
+
+        let root = json::parse(r#"{ "scripts": { "test": "phpunit", "ci": "@test" } }"#).unwrap();
+        assert_eq!(
+            vec!["@php", "@composer", "@putenv", "@test"],
+            reference_completions(&root, "ci")
+        );
+    }
+
+    #[test]
+    fn it_can_expand_the_prefixes() {
+        let php = Path::new("/usr/bin/php");
+        let composer = Path::new("/usr/local/bin/composer");
+
+        assert_eq!(
+            "/usr/bin/php bin/setup.php",
+            expand("@php bin/setup.php", Some(php), Some(composer))
+        );
+        assert_eq!(
+            "/usr/bin/php /usr/local/bin/composer install",
+            expand("@composer install", Some(php), Some(composer))
+        );
+        assert_eq!(
+            "phpcs --standard=x",
+            expand("phpcs --standard=x", None, None)
+        );
+        assert_eq!(
+            "Runs the `test` script.",
+            command_hover("@test --filter=a", None, None)
+        );
+    }
+
+    #[test]
+    fn it_can_find_missing_php_scripts() {
+        assert_eq!(
+            Some("bin/setup.php"),
+            php_script("@php bin/setup.php --force")
+        );
+        assert_eq!(None, php_script("@php -r 'echo 1;'"));
+
+        let text = r#"{ "scripts": {
+            "a": "@php Cargo.toml",
+            "b": "@php bin/missing.php",
+            "c": "@php vendor/bin/phpunit"
+        } }"#;
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let missing =
+            find_missing_php_scripts(&json::parse(text).unwrap(), dir, &dir.join("vendor"));
+
+        assert_eq!(1, missing.len());
+        assert_eq!("b", missing[0].0.script);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::constraint::{self, Stability};
+use crate::json::{Node, Span};
+
+/// The stabilities from the least to the most stable.
+pub const STABILITIES: &[&str] = &["dev", "alpha", "beta", "RC", "stable"];
+
+fn rank(stability: &str) -> usize {
+    STABILITIES
+        .iter()
+        .position(|known| known.eq_ignore_ascii_case(stability))
+        .unwrap_or(STABILITIES.len() - 1)
+}
+
+/// A requirement asking for a less stable version than the project allows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StabilityProblem {
+    /// The span of the constraint.
+    pub span: Span,
+    pub package: String,
+    pub needed: &'static str,
+    pub minimum: String,
+}
+
+impl StabilityProblem {
+    pub fn message(&self) -> String {
+        format!(
+            "{} asks for a {} version, but minimum-stability is \"{}\". Add the @{} flag or lower minimum-stability.",
+            self.package, self.needed, self.minimum, self.needed
+        )
+    }
+}
+
+/// The stability a constraint explicitly asks for, e.g. "beta" for
+/// "4.0.0-beta1" or "dev" for "dev-main". Constraints with a stability flag,
+/// like "^4.0@beta", already allow it.
+pub fn needed_stability(constraint: &str) -> Option<&'static str> {
+    let constraint = match constraint::parse_alias(constraint) {
+        Some(alias) => alias.constraint,
+        None => constraint.to_string(),
+    };
+    if constraint.contains('@') {
+        return None;
+    }
+
+    constraint
+        .split(|c: char| c == '|' || c == ',' || c.is_whitespace())
+        .map(|part| part.trim_start_matches(['^', '~', '<', '>', '=', '!']))
+        .filter(|part| !part.is_empty() && !part.contains('*'))
+        .filter_map(Stability::of)
+        .filter(|stability| *stability < Stability::Stable)
+        .min()
+        .map(|stability| stability.as_str())
+}
+
+/// Whether composer infers the stability flag from the constraint itself,
+/// which it does for root requirements on an exact version or a branch,
+/// like "4.0.0-beta1" or "dev-main".
+fn implies_stability(constraint: &str) -> bool {
+    if constraint::parse_branch(constraint).is_some() {
+        return true;
+    }
+
+    let constraint = match constraint::parse_alias(constraint) {
+        Some(alias) => alias.constraint,
+        None => constraint.trim().to_string(),
+    };
+    let version = constraint.trim_start_matches('=');
+    !version.is_empty()
+        && !version.starts_with(['^', '~', '<', '>', '!'])
+        && !version.contains(|c: char| c == '|' || c == ',' || c.is_whitespace())
+        && constraint::Version::parse(version).is_some()
+}
+
+/// Finds the requirements that need a lower stability than
+/// "minimum-stability", which defaults to stable. Exact versions and
+/// branches are left out, composer allows their stability anyway.
+pub fn find_problems(root: &Node) -> Vec<StabilityProblem> {
+    let minimum = root
+        .get("minimum-stability")
+        .and_then(|minimum| minimum.value.as_str())
+        .unwrap_or("stable")
+        .to_string();
+
+    let mut problems = vec![];
+    for block in ["require", "require-dev"] {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            let constraint = match member.value.as_str() {
+                Some(constraint) if !implies_stability(constraint) => constraint,
+                _ => continue,
+            };
+            let needed = match needed_stability(constraint) {
+                Some(needed) => needed,
+                None => continue,
+            };
+
+            if rank(needed) < rank(&minimum) {
+                problems.push(StabilityProblem {
+                    span: member.value.span,
+                    package: member.key.clone(),
+                    needed,
+                    minimum: minimum.clone(),
+                });
+            }
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::stability::{find_problems, needed_stability};
+
+    #[test]
+    fn it_can_detect_the_needed_stability() {
+        assert_eq!(Some("beta"), needed_stability("4.0.0-beta1"));
+        assert_eq!(Some("dev"), needed_stability("dev-main as 1.2.0"));
+        assert_eq!(Some("RC"), needed_stability("^2.0 || 3.0.0-RC2"));
+        assert_eq!(None, needed_stability("^4.0@beta"));
+        assert_eq!(None, needed_stability("^4.0"));
+        assert_eq!(None, needed_stability("*"));
+    }
+
+    #[test]
+    fn it_can_find_requirements_below_the_minimum_stability() {
+        let text = r#"{
+            "minimum-stability": "beta",
+            "require": {
+                "acme/a": "^4.0.0-beta1",
+                "acme/b": ">=2.0.0-alpha1",
+                "acme/c": "1.0.0-alpha2@alpha"
+            }
+        }"#;
+        let problems = find_problems(&json::parse(text).unwrap());
+
+        assert_eq!(1, problems.len());
+        assert_eq!("acme/b", problems[0].package);
+        assert_eq!("alpha", problems[0].needed);
+        assert_eq!(
+            "\">=2.0.0-alpha1\"",
+            &text[problems[0].span.start..problems[0].span.end]
+        );
+    }
+
+    #[test]
+    fn it_ignores_exact_prerelease_versions() {
+        let text = r#"{
+            "require": { "acme/a": "4.0.0-beta1", "acme/b": "=2.0.0-RC1" }
+        }"#;
+
+        assert!(find_problems(&json::parse(text).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn it_ignores_branches() {
+        let text = r#"{
+            "require": { "acme/a": "dev-main", "acme/b": "dev-feature/x as 1.2.0" },
+            "require-dev": { "acme/c": "2.x-dev#abc123" }
+        }"#;
+
+        assert!(find_problems(&json::parse(text).unwrap()).is_empty());
+    }
+}
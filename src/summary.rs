@@ -0,0 +1,34 @@
+/// Summarizes the state of the dependencies, e.g. "7 of 32 dependencies
+/// outdated, 1 security advisory".
+pub fn message(outdated: usize, total: usize, advisories: usize) -> String {
+    let mut message = match outdated {
+        0 => format!("All {} dependencies up to date", total),
+        _ => format!("{} of {} dependencies outdated", outdated, total),
+    };
+
+    match advisories {
+        0 => {}
+        1 => message.push_str(", 1 security advisory"),
+        _ => message.push_str(&format!(", {} security advisories", advisories)),
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::summary::message;
+
+    #[test]
+    fn it_can_summarize_the_dependencies() {
+        assert_eq!(
+            "7 of 32 dependencies outdated, 1 security advisory",
+            message(7, 32, 1)
+        );
+        assert_eq!("All 3 dependencies up to date", message(0, 3, 0));
+        assert_eq!(
+            "1 of 3 dependencies outdated, 2 security advisories",
+            message(1, 3, 2)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use tower_lsp::lsp_types::SymbolKind;
+
+use crate::json::{Member, Node, NodeKind, Span};
+
+/// The blocks whose keys are package names.
+const PACKAGE_BLOCKS: &[&str] = &[
+    "require",
+    "require-dev",
+    "conflict",
+    "replace",
+    "provide",
+    "suggest",
+];
+
+/// An entry of the outline of composer.json.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// The value, e.g. the constraint of a dependency.
+    pub detail: Option<String>,
+    pub kind: SymbolKind,
+    /// The span of the whole member or array item.
+    pub span: Span,
+    /// The span revealed when the symbol is picked, e.g. the key.
+    pub selection_span: Span,
+    pub children: Vec<Symbol>,
+}
+
+/// The outline of composer.json: its sections, with their dependencies,
+/// scripts and the other values as children.
+pub fn find_symbols(root: &Node) -> Vec<Symbol> {
+    match root.as_object() {
+        Some(members) => members
+            .iter()
+            .map(|member| member_symbol(member, None))
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn member_symbol(member: &Member, parent: Option<&str>) -> Symbol {
+    let kind = match (parent, &member.value.kind) {
+        (Some(parent), _) if PACKAGE_BLOCKS.contains(&parent) => SymbolKind::PACKAGE,
+        (Some("scripts"), _) => SymbolKind::FUNCTION,
+        (None, NodeKind::Object(_)) => SymbolKind::NAMESPACE,
+        (None, _) => SymbolKind::PROPERTY,
+        (Some(_), kind) => value_kind(kind),
+    };
+
+    Symbol {
+        name: member.key.to_string(),
+        detail: detail(&member.value),
+        kind,
+        span: member.span,
+        selection_span: member.key_span,
+        children: children(&member.value, &member.key),
+    }
+}
+
+fn value_kind(kind: &NodeKind) -> SymbolKind {
+    match kind {
+        NodeKind::Object(_) => SymbolKind::OBJECT,
+        NodeKind::Array(_) => SymbolKind::ARRAY,
+        NodeKind::String(_) => SymbolKind::STRING,
+        NodeKind::Number(_) => SymbolKind::NUMBER,
+        NodeKind::Bool(_) => SymbolKind::BOOLEAN,
+        NodeKind::Null => SymbolKind::NULL,
+    }
+}
+
+/// The scalar value, or the strings of an array like the commands of a
+/// script.
+fn detail(value: &Node) -> Option<String> {
+    match &value.kind {
+        NodeKind::String(value) | NodeKind::Number(value) => Some(value.to_string()),
+        NodeKind::Bool(value) => Some(value.to_string()),
+        NodeKind::Null => Some("null".to_string()),
+        NodeKind::Array(items) => {
+            let strings: Vec<&str> = items.iter().filter_map(|item| item.as_str()).collect();
+            match strings.len() == items.len() && !strings.is_empty() {
+                true => Some(strings.join(", ")),
+                false => None,
+            }
+        }
+        NodeKind::Object(_) => None,
+    }
+}
+
+fn children(value: &Node, key: &str) -> Vec<Symbol> {
+    match &value.kind {
+        NodeKind::Object(members) => members
+            .iter()
+            .map(|member| member_symbol(member, Some(key)))
+            .collect(),
+        // Arrays of strings are shown as the detail.
+        NodeKind::Array(items) if detail(value).is_none() => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| item_symbol(index, item))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// An array item, named after what identifies it, like the url of a
+/// repository or the name of an author.
+fn item_symbol(index: usize, item: &Node) -> Symbol {
+    let name = ["name", "url", "package", "type"]
+        .iter()
+        .find_map(|key| item.get(key)?.value.as_str())
+        .map(|name| name.to_string())
+        .or_else(|| item.as_str().map(|value| value.to_string()))
+        .unwrap_or_else(|| index.to_string());
+
+    Symbol {
+        name,
+        detail: item
+            .get("type")
+            .and_then(|member| member.value.as_str())
+            .map(|value| value.to_string()),
+        kind: value_kind(&item.kind),
+        span: item.span,
+        selection_span: item.span,
+        children: children(item, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::SymbolKind;
+
+    use crate::json;
+    use crate::symbols::find_symbols;
+
+    const COMPOSER_JSON: &str = r#"{
+    "name": "acme/app",
+    "require": {
+        "php": ">=8.1",
+        "monolog/monolog": "^3.0"
+    },
+    "scripts": {
+        "test": ["@php vendor/bin/phpunit", "@php vendor/bin/phpstan"]
+    },
+    "repositories": [
+        { "type": "composer", "url": "https://packages.example.com" }
+    ]
+}"#;
+
+    #[test]
+    fn it_can_outline_the_sections() {
+        let symbols = find_symbols(&json::parse(COMPOSER_JSON).unwrap());
+
+        let names: Vec<&str> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+        assert_eq!(vec!["name", "require", "scripts", "repositories"], names);
+        assert_eq!(SymbolKind::PROPERTY, symbols[0].kind);
+        assert_eq!(Some("acme/app".to_string()), symbols[0].detail);
+        assert_eq!(SymbolKind::NAMESPACE, symbols[1].kind);
+        assert_eq!(
+            "\"require\"",
+            &COMPOSER_JSON[symbols[1].selection_span.start..symbols[1].selection_span.end]
+        );
+    }
+
+    #[test]
+    fn it_can_outline_the_dependencies_and_scripts() {
+        let symbols = find_symbols(&json::parse(COMPOSER_JSON).unwrap());
+
+        let monolog = &symbols[1].children[1];
+        assert_eq!("monolog/monolog", monolog.name);
+        assert_eq!(SymbolKind::PACKAGE, monolog.kind);
+        assert_eq!(Some("^3.0".to_string()), monolog.detail);
+        assert_eq!(
+            "\"monolog/monolog\": \"^3.0\"",
+            &COMPOSER_JSON[monolog.span.start..monolog.span.end]
+        );
+
+        let test = &symbols[2].children[0];
+        assert_eq!(SymbolKind::FUNCTION, test.kind);
+        assert_eq!(
+            Some("@php vendor/bin/phpunit, @php vendor/bin/phpstan".to_string()),
+            test.detail
+        );
+        assert!(test.children.is_empty());
+
+        let repository = &symbols[3].children[0];
+        assert_eq!("https://packages.example.com", repository.name);
+        assert_eq!(Some("composer".to_string()), repository.detail);
+        assert_eq!(2, repository.children.len());
+    }
+}
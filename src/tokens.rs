@@ -0,0 +1,221 @@
+use tower_lsp::lsp_types::{
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensEdit,
+    SemanticTokensLegend,
+};
+
+use crate::json::{self, Node};
+use crate::packagist;
+
+/// The blocks whose keys are package names.
+const PACKAGE_BLOCKS: &[&str] = &[
+    "require",
+    "require-dev",
+    "conflict",
+    "replace",
+    "provide",
+    "suggest",
+];
+
+/// The blocks whose constraints can be outdated.
+const REQUIRE_BLOCKS: &[&str] = &["require", "require-dev"];
+
+// Indices into the legend.
+const NAMESPACE: u32 = 0;
+const TYPE: u32 = 1;
+const STRING: u32 = 2;
+const DEFAULT_LIBRARY: u32 = 0;
+const OUTDATED: u32 = 1;
+
+/// The numbers every token is encoded to.
+const TOKEN_SIZE: u32 = 5;
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::NAMESPACE,
+            SemanticTokenType::TYPE,
+            SemanticTokenType::STRING,
+        ],
+        token_modifiers: vec![
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+            SemanticTokenModifier::new("outdated"),
+        ],
+    }
+}
+
+/// Highlights the package names of the package blocks: the vendor as a
+/// namespace and the package as a type. Platform packages, like php or
+/// ext-json, are a single type marked as default library. Version
+/// constraints are strings, marked as outdated when a newer version than the
+/// installed one matches them, going by `is_outdated` with the package name
+/// and constraint.
+pub fn semantic_tokens(
+    text: &str,
+    root: &Node,
+    is_outdated: impl Fn(&str, &str) -> bool,
+) -> Vec<SemanticToken> {
+    let mut spans = vec![];
+    for block in PACKAGE_BLOCKS {
+        let members = match root.get(block).and_then(|block| block.value.as_object()) {
+            Some(members) => members,
+            None => continue,
+        };
+
+        for member in members {
+            // Skip the quotes.
+            let start = member.key_span.start + 1;
+            let end = member.key_span.end - 1;
+            if start >= end {
+                continue;
+            }
+
+            match member.key.split_once('/') {
+                _ if packagist::is_platform_package(&member.key) => {
+                    spans.push((start, end, TYPE, 1 << DEFAULT_LIBRARY))
+                }
+                Some((vendor, _)) => {
+                    spans.push((start, start + vendor.len(), NAMESPACE, 0));
+                    spans.push((start + vendor.len() + 1, end, TYPE, 0));
+                }
+                None => spans.push((start, end, TYPE, 0)),
+            }
+
+            // The values of "suggest" are descriptions, not constraints.
+            let constraint = match member.value.as_str() {
+                Some(constraint) if *block != "suggest" && !constraint.is_empty() => constraint,
+                _ => continue,
+            };
+            // Constraints are single-line strings, so the quotes are one
+            // byte each.
+            let span = member.value.span;
+            let modifiers = match REQUIRE_BLOCKS.contains(block)
+                && !packagist::is_platform_package(&member.key)
+                && is_outdated(&member.key, constraint)
+            {
+                true => 1 << OUTDATED,
+                false => 0,
+            };
+            spans.push((span.start + 1, span.end - 1, STRING, modifiers));
+        }
+    }
+    spans.sort_by_key(|(start, ..)| *start);
+
+    // Every token is relative to the previous one.
+    let mut tokens = vec![];
+    let mut previous = json::offset_to_position(text, 0);
+    for (start, end, token_type, modifiers) in spans {
+        let position = json::offset_to_position(text, start);
+        let delta_start = match position.line == previous.line {
+            true => position.character - previous.character,
+            false => position.character,
+        };
+        tokens.push(SemanticToken {
+            delta_line: position.line - previous.line,
+            delta_start,
+            length: text[start..end].encode_utf16().count() as u32,
+            token_type,
+            token_modifiers_bitset: modifiers,
+        });
+        previous = position;
+    }
+
+    tokens
+}
+
+/// The edit turning the previous tokens into the current ones, replacing
+/// what lies between their common start and end. Large files usually only
+/// change in a few lines, so this is much smaller than all tokens.
+pub fn edits(previous: &[SemanticToken], current: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(previous, current)| previous == current)
+        .count();
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(previous, current)| previous == current)
+        .count();
+
+    let deleted = previous.len() - prefix - suffix;
+    let inserted = &current[prefix..current.len() - suffix];
+    if deleted == 0 && inserted.is_empty() {
+        return vec![];
+    }
+
+    vec![SemanticTokensEdit {
+        start: prefix as u32 * TOKEN_SIZE,
+        delete_count: deleted as u32 * TOKEN_SIZE,
+        data: Some(inserted.to_vec()),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::SemanticToken;
+
+    use crate::json;
+    use crate::tokens::{edits, semantic_tokens};
+
+    fn encode(tokens: &[SemanticToken]) -> Vec<(u32, u32, u32, u32, u32)> {
+        tokens
+            .iter()
+            .map(|token| {
+                (
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_can_highlight_package_names() {
+        let text = "{\n    \"require\": {\n        \"php\": \"^8.1\",\n        \"psr/log\": \"^3.0\"\n    }\n}";
+        let tokens = semantic_tokens(text, &json::parse(text).unwrap(), |name, _| {
+            name == "psr/log"
+        });
+
+        assert_eq!(
+            vec![
+                (2, 9, 3, 1, 1),
+                (0, 7, 4, 2, 0),
+                (1, 9, 3, 0, 0),
+                (0, 4, 3, 1, 0),
+                (0, 7, 4, 2, 2)
+            ],
+            encode(&tokens)
+        );
+    }
+
+    #[test]
+    fn it_can_skip_the_descriptions_of_suggestions() {
+        let text = "{ \"suggest\": { \"ext-intl\": \"For translations\" } }";
+        let tokens = semantic_tokens(text, &json::parse(text).unwrap(), |_, _| true);
+
+        assert_eq!(vec![(0, 16, 8, 1, 1)], encode(&tokens));
+    }
+
+    #[test]
+    fn it_can_compute_the_edits_between_tokens() {
+        let before = "{\n    \"require\": {\n        \"php\": \"^8.1\",\n        \"psr/log\": \"^3.0\"\n    }\n}";
+        let after = before.replace("\"^8.1\"", "\"^8.2 || ^8.3\"");
+        let previous = semantic_tokens(before, &json::parse(before).unwrap(), |_, _| false);
+        let current = semantic_tokens(&after, &json::parse(&after).unwrap(), |_, _| false);
+
+        let changes = edits(&previous, &current);
+        assert_eq!(1, changes.len());
+        assert_eq!(5, changes[0].start);
+        assert_eq!(5, changes[0].delete_count);
+        assert_eq!(
+            vec![(0, 7, 12, 2, 0)],
+            encode(changes[0].data.as_ref().unwrap())
+        );
+
+        assert!(edits(&current, &current).is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+use crate::constraint::Constraint;
+use crate::json::{Node, Span};
+use crate::packagist;
+
+/// How serious a problem found by `composer validate` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem of a composer.json, like `composer validate` reports it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// The dependency whose composer.json has the problem, None for the
+    /// project's own.
+    pub package: Option<String>,
+    /// The property the problem is about, e.g. "license" or
+    /// "require.monolog/monolog".
+    pub property: Option<String>,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl Problem {
+    fn new(property: &str, message: String) -> Problem {
+        Problem {
+            package: None,
+            property: Some(property.to_string()),
+            message,
+            severity: Severity::Warning,
+        }
+    }
+
+    /// The span of the key the problem is about. Missing properties and
+    /// problems of the whole file are reported on the opening brace, and
+    /// problems of keys that have been removed since are dropped.
+    pub fn span(&self, root: &Node) -> Option<Span> {
+        let whole_file = Span::new(root.span.start, root.span.start + 1);
+
+        let property = match &self.property {
+            Some(property) => property,
+            None => return Some(whole_file),
+        };
+        match find_property(root, property) {
+            Some(span) => Some(span),
+            None if self.message.ends_with("is required") => Some(whole_file),
+            None => None,
+        }
+    }
+}
+
+/// Finds the key of a property path like "require.monolog/monolog" or
+/// "authors[0].email". Package names can contain dots, so a member matching
+/// the whole rest of the path wins.
+fn find_property(node: &Node, path: &str) -> Option<Span> {
+    let members = node.as_object()?;
+    if let Some(member) = members.iter().find(|member| member.key == path) {
+        return Some(member.key_span);
+    }
+
+    members.iter().find_map(|member| {
+        let rest = path.strip_prefix(member.key.as_str())?;
+        if let Some(rest) = rest.strip_prefix('.') {
+            return find_property(&member.value, rest);
+        }
+
+        // Array items, e.g. "[0].email".
+        let (index, rest) = rest.strip_prefix('[')?.split_once(']')?;
+        let item = member.value.as_array()?.get(index.parse::<usize>().ok()?)?;
+        find_property(item, rest.strip_prefix('.')?)
+    })
+}
+
+/// The property and the message of a problem line, e.g. "name" for
+/// "name : The property name is required".
+fn split_property(line: &str) -> (Option<String>, String) {
+    if let Some((property, message)) = line.split_once(" : ") {
+        if !property.is_empty() && !property.contains(char::is_whitespace) {
+            return (Some(property.to_string()), message.to_string());
+        }
+    }
+
+    let property = if line.starts_with("License ") {
+        Some("license")
+    } else if line.starts_with("The version field is present") {
+        Some("version")
+    } else {
+        None
+    };
+
+    (
+        property.map(|property| property.to_string()),
+        line.to_string(),
+    )
+}
+
+/// Parses the output of `composer validate`, with the problems of the
+/// dependencies when it ran with --with-dependencies. Every file starts with
+/// a line like "./composer.json is valid, but with a few warnings", followed
+/// by "# General errors" or "# Publish warnings" style sections listing the
+/// problems, some of which continue on the next lines.
+pub fn parse_output(output: &str) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = vec![];
+    let mut package = None;
+    let mut severity = Severity::Warning;
+    let mut in_problem = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            in_problem = false;
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix("# ") {
+            severity = match section.ends_with("errors") {
+                true => Severity::Error,
+                false => Severity::Warning,
+            };
+            in_problem = false;
+            continue;
+        }
+
+        if let Some(problem) = line.strip_prefix("- ") {
+            let (property, message) = split_property(problem);
+            problems.push(Problem {
+                package: package.clone(),
+                property,
+                message,
+                severity,
+            });
+            in_problem = true;
+            continue;
+        }
+
+        if let Some((subject, _)) = line
+            .split_once(" is valid")
+            .or_else(|| line.split_once(" is invalid"))
+        {
+            // The project is named by its file, the dependencies by name.
+            package = match subject.ends_with(".json") {
+                true => None,
+                false => Some(subject.to_string()),
+            };
+            in_problem = false;
+            continue;
+        }
+
+        if in_problem {
+            if let Some(problem) = problems.last_mut() {
+                problem.message.push('\n');
+                problem.message.push_str(line);
+            }
+        }
+    }
+
+    problems
+}
+
+/// The checks of `composer validate --no-check-publish` that don't need
+/// composer: requirements without an upper bound break as soon as a new
+/// major version is released. License identifiers need the SPDX list, so
+/// they're left to composer, like the publish checks are skipped by both.
+pub fn check(root: &Node) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    let require = root
+        .get("require")
+        .and_then(|require| require.value.as_object())
+        .map(|members| members.as_slice())
+        .unwrap_or_default();
+    for member in require {
+        if packagist::is_platform_package(&member.key) {
+            continue;
+        }
+        let constraint = match member.value.as_str() {
+            Some(constraint) => constraint,
+            None => continue,
+        };
+
+        // Unbound constraints allow any future major version.
+        let unbound = Constraint::parse(constraint)
+            .map(|parsed| parsed.matches("99999.0.0"))
+            .unwrap_or(false);
+        if unbound {
+            problems.push(Problem::new(
+                &format!("require.{}", member.key),
+                format!(
+                    "unbound version constraints ({}) should be avoided",
+                    constraint
+                ),
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json;
+    use crate::validate::{check, parse_output, Problem, Severity};
+
+    #[test]
+    fn it_can_parse_the_output_of_composer_validate() {
+        let output = r#"./composer.json is invalid, the following errors/warnings were found:
+# General errors
+- require.acme/lib : invalid version constraint (Could not parse version constraint ^^1.0)
+# General warnings
+- License "MITT" is not a valid SPDX license identifier, see https://spdx.org/licenses/ if you use an open license.
+If the software is closed-source, you may use "proprietary" as license.
+- require.monolog/monolog : unbound version constraints (>=1.0) should be avoided
+# Publish warnings
+- description : The property description is required
+acme/lib is valid, but with a few warnings
+See https://getcomposer.org/doc/04-schema.md for details on the schema
+# General warnings
+- No license specified, it is recommended to do so. For closed-source software you may use "proprietary" as license.
+psr/log is valid
+"#;
+        let problems = parse_output(output);
+
+        assert_eq!(5, problems.len());
+        assert_eq!(
+            Problem {
+                package: None,
+                property: Some("require.acme/lib".to_string()),
+                message: "invalid version constraint (Could not parse version constraint ^^1.0)"
+                    .to_string(),
+                severity: Severity::Error,
+            },
+            problems[0]
+        );
+        assert_eq!(Some("license".to_string()), problems[1].property);
+        assert_eq!(Severity::Warning, problems[1].severity);
+        assert_eq!(2, problems[1].message.lines().count());
+        assert_eq!(
+            Some("require.monolog/monolog".to_string()),
+            problems[2].property
+        );
+        assert_eq!(Some("description".to_string()), problems[3].property);
+        assert_eq!(Some("acme/lib".to_string()), problems[4].package);
+        assert_eq!(None, problems[4].property);
+    }
+
+    #[test]
+    fn it_can_check_a_library_natively() {
+        let root = json::parse(
+            r#"{
+                "name": "acme/app",
+                "license": "MIT",
+                "require": {
+                    "php": ">=8.1",
+                    "psr/log": "^3.0",
+                    "monolog/monolog": ">=2.0",
+                    "acme/any": "*",
+                    "acme/branch": "dev-main"
+                }
+            }"#,
+        )
+        .unwrap();
+        let problems = check(&root);
+
+        let properties: Vec<&str> = problems
+            .iter()
+            .filter_map(|problem| problem.property.as_deref())
+            .collect();
+        assert_eq!(
+            vec!["require.monolog/monolog", "require.acme/any"],
+            properties
+        );
+
+        // Publishing needs a name and a description, which isn't checked.
+        let library = json::parse(r#"{ "type": "library" }"#).unwrap();
+        assert!(check(&library).is_empty());
+    }
+
+    #[test]
+    fn it_can_find_the_key_of_a_problem() {
+        let text = r#"{
+            "license": "MITT",
+            "require": { "symfony/polyfill-php8.0": ">=1.0" },
+            "authors": [{ "name": "Acme", "email": "nope" }]
+        }"#;
+        let root = json::parse(text).unwrap();
+        let key = |property: &str, message: &str| {
+            let problem = Problem {
+                package: None,
+                property: Some(property.to_string()),
+                message: message.to_string(),
+                severity: Severity::Warning,
+            };
+            problem.span(&root).map(|span| &text[span.start..span.end])
+        };
+
+        assert_eq!(Some("\"license\""), key("license", "License \"MITT\""));
+        assert_eq!(
+            Some("\"symfony/polyfill-php8.0\""),
+            key("require.symfony/polyfill-php8.0", "unbound")
+        );
+        assert_eq!(Some("\"email\""), key("authors[0].email", "Invalid email"));
+        assert_eq!(Some("{"), key("name", "The property name is required"));
+        assert_eq!(None, key("require.acme/removed", "unbound"));
+    }
+}
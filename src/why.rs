@@ -0,0 +1,176 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::composer::{ComposerLockFile, Dependent};
+
+/// How many dependents the hover lists before summarizing the rest.
+const HOVER_DEPENDENTS: usize = 5;
+
+/// The locked packages requiring the package, sorted by name.
+pub fn dependents(lock: &ComposerLockFile, package: &str) -> Vec<Dependent> {
+    let mut dependents = lock
+        .required_by
+        .get(&package.to_lowercase())
+        .cloned()
+        .unwrap_or_default();
+    dependents.sort_by(|a, b| a.name.cmp(&b.name));
+
+    dependents
+}
+
+/// The shortest chain from a requirement of composer.json down to the
+/// package, e.g. ["laravel/framework", "symfony/console", "psr/log"].
+pub fn chain(
+    lock: &ComposerLockFile,
+    requirements: &[String],
+    package: &str,
+) -> Option<Vec<String>> {
+    let package = package.to_lowercase();
+    let requirements: Vec<String> = requirements
+        .iter()
+        .map(|requirement| requirement.to_lowercase())
+        .collect();
+
+    // Walk up through the dependents, breadth first.
+    let mut next: HashMap<String, String> = HashMap::new();
+    let mut queue = VecDeque::from([package.clone()]);
+    while let Some(current) = queue.pop_front() {
+        if requirements.contains(&current) {
+            let mut chain = vec![current.clone()];
+            let mut link = &current;
+            while let Some(required) = next.get(link) {
+                chain.push(required.clone());
+                link = required;
+            }
+            return Some(chain);
+        }
+
+        for dependent in lock.required_by.get(&current).into_iter().flatten() {
+            let name = dependent.name.to_lowercase();
+            if name != package && !next.contains_key(&name) {
+                next.insert(name.clone(), current.clone());
+                queue.push_back(name);
+            }
+        }
+    }
+
+    None
+}
+
+/// Explains why the package is installed, like `composer why` does, with
+/// one line per dependent and the chain from composer.json.
+pub fn explain(lock: &ComposerLockFile, requirements: &[String], package: &str) -> String {
+    let mut lines: Vec<String> = dependents(lock, package)
+        .iter()
+        .map(|dependent| {
+            format!(
+                "{} requires {} ({})",
+                dependent.name, package, dependent.constraint
+            )
+        })
+        .collect();
+
+    match chain(lock, requirements, package) {
+        Some(chain) if chain.len() > 1 => {
+            lines.push(format!("composer.json requires {}", chain.join(" → ")))
+        }
+        Some(_) => lines.push(format!("composer.json requires {}", package)),
+        None if lines.is_empty() => lines.push(format!("Nothing requires {}.", package)),
+        None => {}
+    }
+
+    lines.join("\n")
+}
+
+/// The short "Required by" line of the hover, e.g. "a/b, c/d and 2 more".
+pub fn required_by_summary(lock: &ComposerLockFile, package: &str) -> Option<String> {
+    let names: Vec<String> = dependents(lock, package)
+        .into_iter()
+        .map(|dependent| format!("`{}`", dependent.name))
+        .collect();
+
+    match names.len() {
+        0 => None,
+        count if count <= HOVER_DEPENDENTS => Some(names.join(", ")),
+        count => Some(format!(
+            "{} and {} more",
+            names[..HOVER_DEPENDENTS].join(", "),
+            count - HOVER_DEPENDENTS
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::composer::{ComposerLockFile, Dependent, LockFormat};
+    use crate::why::{chain, explain, required_by_summary};
+
+    fn lock() -> ComposerLockFile {
+        let dependent = |name: &str, constraint: &str| Dependent {
+            name: name.to_string(),
+            constraint: constraint.to_string(),
+        };
+
+        ComposerLockFile {
+            versions: HashMap::new(),
+            provides: HashMap::new(),
+            plugins: vec![],
+            packages: vec![],
+            replaced: HashSet::new(),
+            required_by: HashMap::from([
+                (
+                    "psr/log".to_string(),
+                    vec![
+                        dependent("symfony/console", "^1|^2|^3"),
+                        dependent("monolog/monolog", "^2.0 || ^3.0"),
+                    ],
+                ),
+                (
+                    "symfony/console".to_string(),
+                    vec![dependent("laravel/framework", "^6.2")],
+                ),
+            ]),
+            format: LockFormat::Composer2,
+            content_hash: None,
+        }
+    }
+
+    #[test]
+    fn it_can_find_the_chain_from_composer_json() {
+        assert_eq!(
+            Some(vec![
+                "laravel/framework".to_string(),
+                "symfony/console".to_string(),
+                "psr/log".to_string()
+            ]),
+            chain(&lock(), &["laravel/framework".to_string()], "psr/log")
+        );
+        assert_eq!(
+            Some(vec!["psr/log".to_string()]),
+            chain(&lock(), &["psr/log".to_string()], "psr/log")
+        );
+        assert_eq!(None, chain(&lock(), &[], "psr/log"));
+    }
+
+    #[test]
+    fn it_can_explain_why_a_package_is_installed() {
+        assert_eq!(
+            "monolog/monolog requires psr/log (^2.0 || ^3.0)\nsymfony/console requires psr/log (^1|^2|^3)\ncomposer.json requires laravel/framework → symfony/console → psr/log",
+            explain(&lock(), &["laravel/framework".to_string()], "psr/log")
+        );
+        assert_eq!(
+            "Nothing requires acme/orphan.",
+            explain(&lock(), &[], "acme/orphan")
+        );
+    }
+
+    #[test]
+    fn it_can_summarize_the_dependents() {
+        assert_eq!(
+            Some("`monolog/monolog`, `symfony/console`".to_string()),
+            required_by_summary(&lock(), "psr/log")
+        );
+        assert_eq!(None, required_by_summary(&lock(), "laravel/framework"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories that never hold a composer.json of the workspace, whatever
+/// .gitignore says.
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "vendor"];
+
+/// A pattern of a .gitignore file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoreRule {
+    pattern: String,
+    /// A "!" pattern, that includes the path again.
+    negated: bool,
+    /// A pattern with a trailing "/", that only matches directories.
+    dir_only: bool,
+    /// A pattern with a "/" before its end, relative to the .gitignore
+    /// directory instead of matching at any level.
+    anchored: bool,
+}
+
+/// The patterns of a .gitignore file, skipping blank lines and comments.
+pub fn parse_gitignore(text: &str) -> Vec<IgnoreRule> {
+    text.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+
+            IgnoreRule {
+                pattern: line.trim_start_matches('/').to_string(),
+                negated,
+                dir_only,
+                anchored: line.contains('/'),
+            }
+        })
+        .collect()
+}
+
+/// Whether the path, relative to the directory of the .gitignore, is
+/// ignored by its rules. Later rules win, like in git.
+pub fn is_ignored(rules: &[IgnoreRule], relative_path: &str, is_dir: bool) -> bool {
+    let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+
+    let mut ignored = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+
+        let matched = match rule.anchored {
+            true => glob_match(&rule.pattern, relative_path),
+            false => glob_match(&rule.pattern, name),
+        };
+        if matched {
+            ignored = !rule.negated;
+        }
+    }
+
+    ignored
+}
+
+/// Matches a gitignore glob, where "*" and "?" don't match a "/" and "**"
+/// matches anything.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            [b'*', b'*', rest @ ..] => {
+                let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+                (0..=text.len()).any(|skipped| matches(rest, &text[skipped..]))
+            }
+            [b'*', rest @ ..] => (0..=text.len())
+                .take_while(|skipped| !text[..*skipped].contains(&b'/'))
+                .any(|skipped| matches(rest, &text[skipped..])),
+            [b'?', rest @ ..] => match text {
+                [c, text @ ..] if *c != b'/' => matches(rest, text),
+                _ => false,
+            },
+            [c, rest @ ..] => match text {
+                [t, text @ ..] if t == c => matches(rest, text),
+                _ => false,
+            },
+        }
+    }
+
+    matches(pattern, text)
+}
+
+/// The composer.json files below the directory, skipping the vendor
+/// directories and what the .gitignore files ignore.
+pub fn find_composer_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    walk(root, &mut vec![], &mut files);
+    files.sort();
+
+    files
+}
+
+fn walk(dir: &Path, ignores: &mut Vec<(PathBuf, Vec<IgnoreRule>)>, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let gitignore = fs::read_to_string(dir.join(".gitignore"))
+        .map(|text| parse_gitignore(&text))
+        .unwrap_or_default();
+    let has_gitignore = !gitignore.is_empty();
+    if has_gitignore {
+        ignores.push((dir.to_path_buf(), gitignore));
+    }
+
+    let mut dirs = vec![];
+    for entry in entries.flatten() {
+        // Symlinks aren't followed, so they can't loop.
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        let path = entry.path();
+        let is_dir = file_type.is_dir();
+
+        let ignored = ignores.iter().any(|(base, rules)| {
+            let relative = match path.strip_prefix(base) {
+                Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+                Err(_) => return false,
+            };
+            is_ignored(rules, &relative, is_dir)
+        });
+        if ignored {
+            continue;
+        }
+
+        let name = entry.file_name();
+        if is_dir && !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()) {
+            dirs.push(path);
+        } else if file_type.is_file() && name == "composer.json" {
+            files.push(path);
+        }
+    }
+
+    for dir in dirs {
+        walk(&dir, ignores, files);
+    }
+
+    if has_gitignore {
+        ignores.pop();
+    }
+}
+
+/// Whether the changed file is one the workspace scan would find, going by
+/// its path alone.
+pub fn is_workspace_path(path: &str) -> bool {
+    !path
+        .split('/')
+        .any(|component| SKIPPED_DIRS.contains(&component))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workspace::{
+        find_composer_files, glob_match, is_ignored, is_workspace_path, parse_gitignore,
+    };
+    use std::fs;
+
+    #[test]
+    fn it_can_match_gitignore_globs() {
+        assert!(glob_match("*.json", "composer.json"));
+        assert!(!glob_match("*.json", "build/composer.json"));
+        assert!(glob_match(
+            "build/**/composer.json",
+            "build/a/b/composer.json"
+        ));
+        assert!(glob_match("**/fixtures", "tests/fixtures"));
+        assert!(glob_match("fixture?", "fixtures"));
+        assert!(!glob_match("fixture?", "fixture"));
+    }
+
+    #[test]
+    fn it_can_apply_gitignore_rules() {
+        let rules = parse_gitignore("# build output\n/build/\nfixtures\n!fixtures/keep\n\n");
+
+        assert!(is_ignored(&rules, "build", true));
+        assert!(!is_ignored(&rules, "build", false));
+        assert!(!is_ignored(&rules, "packages/build", true));
+        assert!(is_ignored(&rules, "tests/fixtures", true));
+        assert!(!is_ignored(&rules, "fixtures/keep", true));
+    }
+
+    #[test]
+    fn it_can_find_the_composer_files_of_the_workspace() {
+        let root = std::env::temp_dir().join("composer_lsp_workspace_scan");
+        let _ = fs::remove_dir_all(&root);
+        for dir in [
+            "packages/api",
+            "packages/web/vendor/psr/log",
+            "build/tmp",
+            "tests/fixtures",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("composer.json"), "{}").unwrap();
+        }
+        fs::write(root.join("composer.json"), "{}").unwrap();
+        fs::write(root.join(".gitignore"), "/build/\n").unwrap();
+        fs::write(root.join("tests/.gitignore"), "fixtures/\n").unwrap();
+
+        let files = find_composer_files(&root);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(
+            vec![
+                root.join("composer.json"),
+                root.join("packages/api/composer.json"),
+            ],
+            files
+        );
+        assert!(is_workspace_path("/app/packages/api/composer.json"));
+        assert!(!is_workspace_path("/app/vendor/psr/log/composer.json"));
+    }
+}